@@ -0,0 +1,322 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::db::{id_column, parse_id, unknown_value};
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::Olog;
+use crate::split::edge_sentence;
+
+// Characters of the document shown to the model; the rest of a longer
+// document is left out of the map
+const DOCUMENT_CHARS: usize = 24_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimKind {
+    // A conclusion the text argues for or against
+    Claim,
+    // A statement offered as a reason for or against another
+    Premise,
+}
+
+impl ClaimKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ClaimKind::Claim => "claim",
+            ClaimKind::Premise => "premise",
+        }
+    }
+
+    fn parse(name: &str) -> Option<ClaimKind> {
+        [ClaimKind::Claim, ClaimKind::Premise].into_iter().find(|kind| kind.name() == name.trim().to_lowercase())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelationKind {
+    Supports,
+    Attacks,
+}
+
+impl RelationKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RelationKind::Supports => "supports",
+            RelationKind::Attacks => "attacks",
+        }
+    }
+
+    fn parse(name: &str) -> Option<RelationKind> {
+        [RelationKind::Supports, RelationKind::Attacks].into_iter().find(|kind| kind.name() == name.trim().to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Claim {
+    pub id: Uuid,
+    pub kind: ClaimKind,
+    pub text: String,
+    // Hyperedges of the linked olog that state the claim or its grounds
+    pub hyperedges: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Relation {
+    pub source: Uuid,
+    pub target: Uuid,
+    pub kind: RelationKind,
+}
+
+#[derive(Debug)]
+pub struct ArgumentMap {
+    pub id: Uuid,
+    pub title: String,
+    // The olog whose hyperedges the claims are linked to, if any
+    pub olog_id: Option<Uuid>,
+    pub model: String,
+    pub claims: Vec<Claim>,
+    pub relations: Vec<Relation>,
+    pub created_at: Option<String>,
+}
+
+impl ArgumentMap {
+    pub fn claim(&self, id: Uuid) -> Option<&Claim> {
+        self.claims.iter().find(|claim| claim.id == id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MapResponse {
+    claims: Vec<ClaimResponse>,
+    #[serde(default)]
+    relations: Vec<RelationResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaimResponse {
+    id: String,
+    kind: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelationResponse {
+    from: String,
+    to: String,
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkResponse {
+    links: Vec<ClaimLinks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaimLinks {
+    claim: usize,
+    #[serde(default)]
+    facts: Vec<usize>,
+}
+
+// Asks the model for the document's claims and premises and the support and
+// attack relations between them
+pub fn generate_argmap(provider: &dyn LlmProvider, text: &str, title: &str, model: &str) -> Result<ArgumentMap, OlogError> {
+    let excerpt: String = text.chars().take(DOCUMENT_CHARS).collect();
+    let prompt = format!(
+        "Extract the argument map of the document below. List the claims it argues for or against and the premises \
+         offered as reasons, each as one self-contained sentence in the document's own terms, and say which of them \
+         supports or attacks which. A claim is a conclusion; a premise is a statement given as a reason for or \
+         against another. Include the objections the text raises and its answers to them. Respond with JSON of the \
+         form {{\"claims\": [{{\"id\": \"c1\", \"kind\": \"claim\", \"text\": \"...\"}}, {{\"id\": \"c2\", \"kind\": \
+         \"premise\", \"text\": \"...\"}}], \"relations\": [{{\"from\": \"c2\", \"to\": \"c1\", \"kind\": \"supports\"}}]}}, \
+         where kind is \"claim\" or \"premise\" for a claim and \"supports\" or \"attacks\" for a relation.\n\n\
+         Document: {}\n{}",
+        title, excerpt.trim()
+    );
+    let response: MapResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed argument map: {}", e)))?;
+
+    let mut ids: Vec<(String, Uuid)> = Vec::new();
+    let mut claims = Vec::new();
+    for claim in response.claims.into_iter().filter(|claim| !claim.text.trim().is_empty()) {
+        if ids.iter().any(|(id, _)| *id == claim.id) {
+            return Err(OlogError::Schema(format!("Argument map uses the claim id `{}` twice", claim.id)));
+        }
+        let kind = ClaimKind::parse(&claim.kind)
+            .ok_or_else(|| OlogError::Schema(format!("Unknown claim kind `{}`", claim.kind)))?;
+        let id = Uuid::new_v4();
+        ids.push((claim.id, id));
+        claims.push(Claim { id, kind, text: claim.text.trim().to_string(), hyperedges: Vec::new() });
+    }
+    let lookup = |id: &str| {
+        ids.iter()
+            .find(|(local, _)| local == id)
+            .map(|(_, id)| *id)
+            .ok_or_else(|| OlogError::Schema(format!("Argument map relation refers to unknown claim `{}`", id)))
+    };
+    let mut relations: Vec<Relation> = Vec::new();
+    for relation in response.relations {
+        let kind = RelationKind::parse(&relation.kind)
+            .ok_or_else(|| OlogError::Schema(format!("Unknown relation kind `{}`", relation.kind)))?;
+        let (source, target) = (lookup(&relation.from)?, lookup(&relation.to)?);
+        let duplicate = relations.iter().any(|known| (known.source, known.target, known.kind) == (source, target, kind));
+        if source != target && !duplicate {
+            relations.push(Relation { source, target, kind });
+        }
+    }
+
+    Ok(ArgumentMap {
+        id: Uuid::new_v4(),
+        title: title.to_string(),
+        olog_id: None,
+        model: model.to_string(),
+        claims,
+        relations,
+        created_at: None,
+    })
+}
+
+// Asks the model which of the olog's hyperedges state each claim or its
+// grounds, replacing any earlier links; returns how many links were made
+pub fn link_claims(provider: &dyn LlmProvider, map: &mut ArgumentMap, olog: &Olog, model: &str) -> Result<usize, OlogError> {
+    let claims = map.claims.iter().enumerate()
+        .map(|(i, claim)| format!("{}: ({}) {}", i, claim.kind.name(), claim.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let facts = olog.hyperedges.iter().enumerate()
+        .map(|(i, hyperedge)| format!("{}: {}", i, edge_sentence(hyperedge)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Below are the numbered claims of an argument map and the numbered facts of an ontology log about \"{}\". \
+         For each claim, list the facts that state it, or that state something it rests on or is attacked by. A claim \
+         no fact bears on gets an empty list. Respond with JSON of the form {{\"links\": [{{\"claim\": 0, \"facts\": \
+         [2, 5]}}]}}.\n\nClaims:\n{}\n\nFacts:\n{}",
+        olog.title, claims, facts
+    );
+    let response: LinkResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed claim links: {}", e)))?;
+
+    for claim in &mut map.claims {
+        claim.hyperedges.clear();
+    }
+    for link in response.links {
+        let Some(claim) = map.claims.get_mut(link.claim) else {
+            continue;
+        };
+        for hyperedge in link.facts.into_iter().filter_map(|index| olog.hyperedges.get(index)) {
+            if !claim.hyperedges.contains(&hyperedge.id) {
+                claim.hyperedges.push(hyperedge.id);
+            }
+        }
+    }
+    map.olog_id = Some(olog.id);
+    Ok(map.claims.iter().map(|claim| claim.hyperedges.len()).sum())
+}
+
+// Stored in the current namespace
+pub fn record_argmap(conn: &Connection, map: &ArgumentMap) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO Argument_Maps (argmap_id, namespace, title, olog_id, model, created_at)
+         VALUES (?1, (SELECT namespace FROM temp.Session), ?2, ?3, ?4, CURRENT_TIMESTAMP)",
+        params![map.id.to_string(), map.title, map.olog_id.map(|id| id.to_string()), map.model],
+    )?;
+    for (position, claim) in map.claims.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO Claims (claim_id, argmap_id, position, kind, text) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![claim.id.to_string(), map.id.to_string(), position as i64, claim.kind.name(), claim.text],
+        )?;
+    }
+    for relation in &map.relations {
+        tx.execute(
+            "INSERT INTO Claim_Relations (source_claim_id, target_claim_id, kind) VALUES (?1, ?2, ?3)",
+            params![relation.source.to_string(), relation.target.to_string(), relation.kind.name()],
+        )?;
+    }
+    insert_links(&tx, map)?;
+    tx.commit()
+}
+
+// Replaces the stored links with the map's, for a map linked after it was saved
+pub fn record_links(conn: &Connection, map: &ArgumentMap) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM Claim_Links WHERE claim_id IN (SELECT claim_id FROM Claims WHERE argmap_id = ?1)",
+        params![map.id.to_string()],
+    )?;
+    tx.execute(
+        "UPDATE Argument_Maps SET olog_id = ?2 WHERE argmap_id = ?1",
+        params![map.id.to_string(), map.olog_id.map(|id| id.to_string())],
+    )?;
+    insert_links(&tx, map)?;
+    tx.commit()
+}
+
+fn insert_links(conn: &Connection, map: &ArgumentMap) -> rusqlite::Result<()> {
+    let mut insert = conn.prepare("INSERT OR IGNORE INTO Claim_Links (claim_id, hyperedge_id) VALUES (?1, ?2)")?;
+    for claim in &map.claims {
+        for hyperedge_id in &claim.hyperedges {
+            insert.execute(params![claim.id.to_string(), hyperedge_id.to_string()])?;
+        }
+    }
+    Ok(())
+}
+
+// Maps in other namespaces are reported as missing
+pub fn read_argmap(conn: &Connection, argmap_id: Uuid) -> rusqlite::Result<Option<ArgumentMap>> {
+    let header = conn.query_row(
+        "SELECT title, olog_id, model, created_at FROM Argument_Maps
+         WHERE argmap_id = ?1 AND namespace = (SELECT namespace FROM temp.Session)",
+        params![argmap_id.to_string()],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?, row.get(3)?)),
+    ).optional()?;
+    let Some((title, olog_id, model, created_at)) = header else {
+        return Ok(None);
+    };
+
+    let mut links_stmt = conn.prepare("SELECT hyperedge_id FROM Claim_Links WHERE claim_id = ?1 ORDER BY rowid")?;
+    let mut stmt = conn.prepare("SELECT claim_id, kind, text FROM Claims WHERE argmap_id = ?1 ORDER BY position")?;
+    let rows = stmt
+        .query_map(params![argmap_id.to_string()], |row| {
+            Ok((id_column(row, 0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut claims = Vec::new();
+    for (id, kind, text) in rows {
+        let hyperedges = links_stmt
+            .query_map(params![id.to_string()], |row| id_column(row, 0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        claims.push(Claim { id, kind: ClaimKind::parse(&kind).ok_or_else(|| unknown_value(1, &kind))?, text, hyperedges });
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT Claim_Relations.source_claim_id, Claim_Relations.target_claim_id, Claim_Relations.kind
+         FROM Claim_Relations
+         JOIN Claims ON Claims.claim_id = Claim_Relations.source_claim_id
+         WHERE Claims.argmap_id = ?1
+         ORDER BY Claim_Relations.rowid",
+    )?;
+    let relations = stmt
+        .query_map(params![argmap_id.to_string()], |row| {
+            let kind: String = row.get(2)?;
+            Ok(Relation {
+                source: id_column(row, 0)?,
+                target: id_column(row, 1)?,
+                kind: RelationKind::parse(&kind).ok_or_else(|| unknown_value(2, &kind))?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Some(ArgumentMap {
+        id: argmap_id,
+        title,
+        olog_id: olog_id.map(|id| parse_id(1, &id)).transpose()?,
+        model,
+        claims,
+        relations,
+        created_at,
+    }))
+}
@@ -0,0 +1,187 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::config;
+use crate::error::OlogError;
+
+// A line repeated this many times is a running header or footer
+const MIN_REPEATS: usize = 3;
+// Longer lines are prose, even when they mention copyright
+const MAX_BOILERPLATE_LINE_CHARS: usize = 300;
+
+const REFERENCES_HEADING: &str = r"(?i)^(references|bibliography|works cited|literature cited|citations)$";
+const ACKNOWLEDGMENTS_HEADING: &str = r"(?i)^(acknowledge?ments?|funding|funding information|author contributions|conflicts? of interest|competing interests)$";
+const COPYRIGHT_LINE: &str = r"(?i)©|\(c\)\s*\d{4}|\bcopyright\b|all rights reserved|permission to make digital or hard copies|licensed under a creative commons|this article is an open access article";
+const PAGE_NUMBER_LINE: &str = r"(?i)^(page\s+)?\d{1,4}(\s*(of|/)\s*\d{1,4})?$";
+const NUMBERED_HEADING: &str = r"^(\d+(\.\d+)*)\.?\s+\S";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Filter {
+    /// References or bibliography section
+    References,
+    /// Acknowledgments, funding and author-contribution sections
+    Acknowledgments,
+    /// Copyright and license notices
+    Copyright,
+    /// Running headers, footers and page numbers repeated on every page
+    HeadersFooters,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 4] = [Filter::References, Filter::Acknowledgments, Filter::Copyright, Filter::HeadersFooters];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::References => "references",
+            Filter::Acknowledgments => "acknowledgments",
+            Filter::Copyright => "copyright",
+            Filter::HeadersFooters => "headers/footers",
+        }
+    }
+}
+
+// Set under [filters] in olog.toml; every filter is on unless turned off
+#[derive(Debug, Default, Deserialize)]
+struct FilterConfig {
+    references: Option<bool>,
+    acknowledgments: Option<bool>,
+    copyright: Option<bool>,
+    headers_footers: Option<bool>,
+}
+
+// Filters enabled in olog.toml, minus those the command line keeps
+pub fn enabled(keep: &[Filter]) -> Result<Vec<Filter>, OlogError> {
+    let config = config::project_setting::<FilterConfig>("filters")?.unwrap_or_default();
+    Ok(Filter::ALL.into_iter()
+        .filter(|filter| {
+            let on = match filter {
+                Filter::References => config.references,
+                Filter::Acknowledgments => config.acknowledgments,
+                Filter::Copyright => config.copyright,
+                Filter::HeadersFooters => config.headers_footers,
+            };
+            on.unwrap_or(true) && !keep.contains(filter)
+        })
+        .collect())
+}
+
+#[derive(Debug)]
+pub struct Filtered {
+    pub text: String,
+    // Lines dropped by each filter that removed anything
+    pub removed: Vec<(Filter, usize)>,
+}
+
+pub fn strip_boilerplate(text: &str, filters: &[Filter]) -> Filtered {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut dropped: Vec<Option<Filter>> = vec![None; lines.len()];
+    let references = Regex::new(REFERENCES_HEADING).unwrap();
+    let acknowledgments = Regex::new(ACKNOWLEDGMENTS_HEADING).unwrap();
+    let copyright = Regex::new(COPYRIGHT_LINE).unwrap();
+
+    for &filter in filters {
+        let matches = match filter {
+            Filter::References => section_lines(&lines, &references, true),
+            Filter::Acknowledgments => section_lines(&lines, &acknowledgments, false),
+            Filter::Copyright => (0..lines.len())
+                .filter(|&i| lines[i].chars().count() <= MAX_BOILERPLATE_LINE_CHARS && copyright.is_match(lines[i]))
+                .collect(),
+            Filter::HeadersFooters => repeated_lines(&lines),
+        };
+        for i in matches {
+            dropped[i].get_or_insert(filter);
+        }
+    }
+
+    let removed = filters.iter()
+        .map(|&filter| (filter, dropped.iter().filter(|d| **d == Some(filter)).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    let text = lines.iter().zip(&dropped)
+        .filter(|(_, dropped)| dropped.is_none())
+        .map(|(line, _)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Filtered { text, removed }
+}
+
+// Heading level of a line: markdown #'s, or numbered sections by depth
+fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim();
+    if trimmed.starts_with('#') {
+        return Some(trimmed.chars().take_while(|c| *c == '#').count());
+    }
+    if let Some(captures) = Regex::new(NUMBERED_HEADING).unwrap().captures(trimmed) {
+        if trimmed.chars().count() < 80 && !trimmed.ends_with('.') {
+            return Some(captures[1].split('.').count());
+        }
+    }
+    None
+}
+
+// The heading text without markdown markers, numbering or emphasis
+fn heading_title(line: &str) -> String {
+    let title = line.trim().trim_start_matches('#').trim();
+    let title = Regex::new(r"^(\d+(\.\d+)*\.?|[IVX]+\.)\s+").unwrap().replace(title, "");
+    title.trim_matches(|c: char| c == '*' || c == '_' || c == ':' || c.is_whitespace()).to_string()
+}
+
+// Lines from a matching heading up to the next heading at the same or a
+// higher level. Sections under a bare heading run to the next heading of any
+// kind, which for a trailing references list is the end of the document.
+// Reference lists are often numbered, so only markdown headings and
+// appendices end those.
+fn section_lines(lines: &[&str], heading: &Regex, numbered_entries: bool) -> Vec<usize> {
+    let appendix = Regex::new(r"(?i)^(appendix|appendices|supplementary)\b").unwrap();
+    let ends_section = |line: &str, level: usize| {
+        if numbered_entries && !line.trim_start().starts_with('#') {
+            return appendix.is_match(&heading_title(line));
+        }
+        heading_level(line).is_some_and(|other| other <= level)
+    };
+
+    let mut matched = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !heading.is_match(&heading_title(lines[i])) {
+            i += 1;
+            continue;
+        }
+        let level = heading_level(lines[i]).unwrap_or(usize::MAX);
+        let end = (i + 1..lines.len())
+            .find(|&j| ends_section(lines[j], level))
+            .unwrap_or(lines.len());
+        matched.extend(i..end);
+        i = end;
+    }
+    matched
+}
+
+// Short lines that recur across pages once page numbers are ignored, and
+// lines holding nothing but a page number. Markdown headings and tables
+// legitimately repeat, so they are left alone.
+fn repeated_lines(lines: &[&str]) -> Vec<usize> {
+    let page_number = Regex::new(PAGE_NUMBER_LINE).unwrap();
+    let digits = Regex::new(r"\d+").unwrap();
+    let candidate = |line: &str| {
+        let trimmed = line.trim();
+        !trimmed.is_empty()
+            && trimmed.chars().count() <= 100
+            && !trimmed.starts_with(['#', '|', '-', '*', '>', '`'])
+            && trimmed.chars().any(char::is_alphanumeric)
+    };
+    let key = |line: &str| digits.replace_all(line.trim(), "#").to_lowercase();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for line in lines.iter().filter(|line| candidate(line)) {
+        *counts.entry(key(line)).or_insert(0) += 1;
+    }
+    (0..lines.len())
+        .filter(|&i| {
+            let trimmed = lines[i].trim();
+            page_number.is_match(trimmed) || (candidate(lines[i]) && counts[&key(lines[i])] >= MIN_REPEATS)
+        })
+        .collect()
+}
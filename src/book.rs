@@ -0,0 +1,213 @@
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::chunk::split_adaptive;
+use crate::db::{
+    create_olog_tables, document_key, load_olog, read_olog_from_db, record_ingestion, replace_olog_in_db, set_olog_kind, write_olog_to_db,
+    OlogKind,
+};
+use crate::error::OlogError;
+use crate::events::{self, Event};
+use crate::gitrepo::Operation;
+use crate::llm::{generate_merged_olog, redact_document, GenerationOptions, Generated};
+use crate::metadata::record_metadata;
+use crate::olog::{merge_ologs, reassign_ids, Olog};
+use crate::provenance::{read_provenance, record_provenance};
+use crate::provider::LlmProvider;
+use crate::redact::record_redactions;
+use crate::unify::{flag_for_review, record_aliases};
+use crate::usage;
+
+// Sections shorter than this are tables of contents, part dividers and the
+// like; they are folded into the chapter that follows
+const MIN_CHAPTER_CHARS: usize = 1500;
+// Books without recognizable headings are cut at paragraph breaks into
+// sections of roughly this many tokens, fewer where equations and tables pile up
+const FALLBACK_SECTION_TOKENS: usize = 7_500;
+
+// Tried in order; the first that finds at least two chapters wins
+const CHAPTER_PATTERNS: &[&str] = &[
+    r"(?im)^[ \t]*(?:#+[ \t]*)?(?:chapter|part)[ \t]+(?:\d+|[ivxlc]+)\b.*$",
+    r"(?m)^#[ \t]+\S.*$",
+    r"(?m)^##[ \t]+\S.*$",
+];
+
+#[derive(Debug)]
+pub struct Chapter {
+    pub heading: String,
+    pub text: String,
+}
+
+pub fn split_chapters(text: &str) -> Vec<Chapter> {
+    for pattern in CHAPTER_PATTERNS {
+        let re = Regex::new(pattern).unwrap();
+        let starts: Vec<(usize, String)> = re.find_iter(text)
+            .map(|m| (m.start(), m.as_str().trim().trim_start_matches('#').trim().to_string()))
+            .collect();
+        if starts.len() >= 2 {
+            return fold_short(split_at_headings(text, &starts));
+        }
+    }
+    split_by_size(text)
+}
+
+fn split_at_headings(text: &str, starts: &[(usize, String)]) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let preamble = &text[..starts[0].0];
+    if !preamble.trim().is_empty() {
+        chapters.push(Chapter { heading: "Front matter".to_string(), text: preamble.to_string() });
+    }
+    for (i, (start, heading)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(text.len(), |(next, _)| *next);
+        chapters.push(Chapter { heading: heading.clone(), text: text[*start..end].to_string() });
+    }
+    chapters
+}
+
+fn fold_short(chapters: Vec<Chapter>) -> Vec<Chapter> {
+    let count = chapters.len();
+    let mut folded = Vec::new();
+    let mut pending = String::new();
+    for (i, mut chapter) in chapters.into_iter().enumerate() {
+        if !pending.is_empty() {
+            chapter.text = format!("{}\n\n{}", pending, chapter.text);
+            pending.clear();
+        }
+        if chapter.text.trim().chars().count() < MIN_CHAPTER_CHARS && i + 1 < count {
+            pending = chapter.text;
+        } else {
+            folded.push(chapter);
+        }
+    }
+    folded
+}
+
+fn split_by_size(text: &str) -> Vec<Chapter> {
+    split_adaptive(text, FALLBACK_SECTION_TOKENS).into_iter().enumerate()
+        .map(|(i, chunk)| Chapter { heading: format!("Section {}", i + 1), text: chunk.text })
+        .collect()
+}
+
+fn parse_olog_id(id: &str) -> Result<Uuid, OlogError> {
+    Uuid::parse_str(id).map_err(|e| OlogError::Schema(format!("Malformed olog id {}: {}", id, e)))
+}
+
+// Stores the book as an empty olog with one child olog per chapter. Running it
+// again on the same book picks up after the last chapter that was written.
+pub fn ingest_book(conn: &Connection, provider: &dyn LlmProvider, text: &str, title: &str, options: &GenerationOptions) -> Result<Uuid, OlogError> {
+    create_olog_tables(conn).map_err(|e| OlogError::from(e).context("Error creating tables"))?;
+
+    let key = document_key(text);
+    let existing: Option<String> = conn.query_row(
+        "SELECT Ingestions.olog_id FROM Ingestions
+         JOIN Books ON Books.olog_id = Ingestions.olog_id
+         JOIN Ologs ON Ologs.olog_id = Ingestions.olog_id
+         WHERE Ingestions.content_hash = ?1
+           AND Ologs.namespace = (SELECT namespace FROM temp.Session)
+         ORDER BY Ingestions.rowid DESC LIMIT 1",
+        params![key.content_hash],
+        |row| row.get(0),
+    ).optional()?;
+
+    let book_id = match existing {
+        Some(id) => {
+            let book_id = parse_olog_id(&id)?;
+            println!("Book already started as Olog {}, resuming.", book_id);
+            book_id
+        },
+        None => {
+            let book = Olog { id: Uuid::new_v4(), title: title.to_string(), nodes: Vec::new(), hyperedges: Vec::new() };
+            write_olog_to_db(conn, &book, Operation::Generate).map_err(|e| OlogError::from(e).context("Error writing book Olog to database"))?;
+            record_ingestion(conn, book.id, &key, None)?;
+            conn.execute("INSERT INTO Books (olog_id) VALUES (?1)", params![book.id.to_string()])?;
+            // Empty until book-map fills it with the merge of its chapters
+            set_olog_kind(conn, book.id, OlogKind::CorpusMerge)?;
+            book.id
+        },
+    };
+
+    // Redacted as a whole, so a name found in one chapter is masked in all of them
+    let (text, options, redactions) = redact_document(text, options);
+    let options = &options;
+    let chapters = split_chapters(&text);
+    for (position, chapter) in chapters.iter().enumerate() {
+        let done: Option<String> = conn.query_row(
+            "SELECT chapter_olog_id FROM Book_Chapters WHERE book_olog_id = ?1 AND position = ?2",
+            params![book_id.to_string(), position as i64],
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(chapter_id) = done {
+            println!("Chapter {}/{} ({}) already stored as Olog {}", position + 1, chapters.len(), chapter.heading, chapter_id);
+            continue;
+        }
+
+        println!("Processing chapter {}/{}: {}", position + 1, chapters.len(), chapter.heading);
+        events::emit(Event::ChapterStarted { chapter: position + 1, chapters: chapters.len(), heading: chapter.heading.clone() });
+        let Generated { mut olog, mut provenance, unified, flagged, metadata, .. } = generate_merged_olog(provider, &chapter.text, options)
+            .map_err(|e| e.context(format!("Chapter {} ({})", position + 1, chapter.heading)))?;
+        olog.title = chapter.heading.clone();
+        write_olog_to_db(conn, &olog, Operation::Generate).map_err(|e| OlogError::from(e).context("Error writing chapter Olog to database"))?;
+        usage::attribute(olog.id);
+        for entry in &mut provenance {
+            entry.section = Some(chapter.heading.clone());
+        }
+        record_provenance(conn, &olog, &provenance)?;
+        record_aliases(conn, &olog, &unified)?;
+        flag_for_review(conn, olog.id, &flagged)?;
+        record_metadata(conn, &metadata)?;
+        record_redactions(conn, &olog, &redactions)?;
+
+        conn.execute(
+            "INSERT INTO Book_Chapters (book_olog_id, position, chapter_olog_id, heading) VALUES (?1, ?2, ?3, ?4)",
+            params![book_id.to_string(), position as i64, olog.id.to_string(), chapter.heading],
+        )?;
+        conn.execute("UPDATE Books SET map_current = 0 WHERE olog_id = ?1", params![book_id.to_string()])?;
+    }
+
+    Ok(book_id)
+}
+
+// Merges the chapter ologs into the book olog. The result is kept until a
+// chapter is added, so repeated calls are cheap.
+pub fn book_map(conn: &Connection, book_id: Uuid, rebuild: bool) -> Result<Olog, OlogError> {
+    let book = load_olog(conn, book_id)?;
+    let map_current: bool = conn.query_row(
+        "SELECT map_current FROM Books WHERE olog_id = ?1",
+        params![book_id.to_string()],
+        |row| row.get(0),
+    ).optional()?.ok_or_else(|| OlogError::NotFound(format!("Olog {} is not a book", book_id)))?;
+
+    if map_current && !rebuild {
+        return Ok(book);
+    }
+
+    let mut stmt = conn.prepare("SELECT chapter_olog_id FROM Book_Chapters WHERE book_olog_id = ?1 ORDER BY position")?;
+    let chapter_ids = stmt
+        .query_map(params![book_id.to_string()], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut merged: Option<Olog> = None;
+    let mut provenance = Vec::new();
+    for id in chapter_ids {
+        let chapter_id = parse_olog_id(&id)?;
+        let chapter = read_olog_from_db(conn, chapter_id)
+            .map_err(|e| OlogError::from(e).context(format!("Error reading chapter Olog {}", chapter_id)))?;
+        provenance.extend(read_provenance(conn, chapter_id)?);
+        merged = Some(match merged {
+            Some(merged) => merge_ologs(merged, chapter),
+            None => chapter,
+        });
+    }
+    let merged = merged.ok_or_else(|| OlogError::Usage(format!("Book {} has no chapters yet", book_id)))?;
+
+    // The merge reuses chapter node ids, which the chapters still own
+    let mut map = reassign_ids(merged);
+    map.id = book_id;
+    map.title = book.title;
+    replace_olog_in_db(conn, &map, Operation::Merge).map_err(|e| OlogError::from(e).context("Error writing book map to database"))?;
+    record_provenance(conn, &map, &provenance)?;
+    conn.execute("UPDATE Books SET map_current = 1 WHERE olog_id = ?1", params![book_id.to_string()])?;
+
+    Ok(map)
+}
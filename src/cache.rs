@@ -0,0 +1,136 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::db::{citation_text, stored_citation_text};
+use crate::pdf::Ocr;
+use crate::sanitize;
+
+pub const DEFAULT_RESPONSE_TTL_HOURS: u64 = 7 * 24;
+
+static RESPONSES_ENABLED: AtomicBool = AtomicBool::new(true);
+static RESPONSE_TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_RESPONSE_TTL_HOURS * 3600);
+// How often each prompt has been sent so far in this run
+static SENT: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+
+// Text extracted from PDFs, so running the same document again skips the
+// extraction. Entries are keyed by the PDF's bytes and, for downloads, also
+// by URL, which lets a repeat run skip the download too; a document that
+// changed behind the same URL needs --no-cache.
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn text_for_hash(conn: &Connection, content_hash: &str, ocr: Ocr) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT text FROM Ocr_Cache WHERE content_hash = ?1 AND ocr = ?2",
+        params![content_hash, ocr.name()],
+        |row| citation_text(row, 0),
+    ).optional().map(Option::flatten)
+}
+
+// The most recent text cached for the URL
+pub fn text_for_url(conn: &Connection, url: &str, ocr: Ocr) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT text FROM Ocr_Cache WHERE url = ?1 AND ocr = ?2 ORDER BY cached_at DESC, rowid DESC LIMIT 1",
+        params![url, ocr.name()],
+        |row| citation_text(row, 0),
+    ).optional().map(Option::flatten)
+}
+
+pub fn store(conn: &Connection, content_hash: &str, url: Option<&str>, ocr: Ocr, text: &str) -> Result<()> {
+    // A local file doesn't forget the URL an earlier download recorded
+    conn.execute(
+        "INSERT INTO Ocr_Cache (content_hash, ocr, url, text, cached_at) VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+         ON CONFLICT (content_hash, ocr) DO UPDATE SET
+            url = COALESCE(excluded.url, url), text = excluded.text, cached_at = excluded.cached_at",
+        params![content_hash, ocr.name(), url, stored_citation_text(text)?],
+    )?;
+    Ok(())
+}
+
+// Returns how many documents were dropped
+pub fn clear(conn: &Connection) -> Result<usize> {
+    conn.execute("DELETE FROM Ocr_Cache", [])
+}
+
+// Model replies, one file per prompt under the XDG cache directory, so a rerun
+// over the same text doesn't pay for the same prompts again. Generating
+// several times sends the same prompt several times on purpose, so the nth
+// sending of a prompt in a run replays the nth reply an earlier run got: a
+// rerun reproduces the earlier run while its generations still differ.
+
+pub fn disable_responses() {
+    RESPONSES_ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn set_response_ttl(hours: u64) {
+    RESPONSE_TTL_SECS.store(hours.saturating_mul(3600), Ordering::Relaxed);
+}
+
+// `$XDG_CACHE_HOME/olog/responses`, or `~/.cache/olog/responses`
+pub fn responses_dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_home.join("olog").join("responses"))
+}
+
+// Where the reply to this sending of the prompt is kept, or None with the
+// cache turned off. `provider` tells apart the same model name served by
+// different providers.
+pub fn response_path(provider: &str, model: &str, prompt: &str, json: bool) -> Option<PathBuf> {
+    if !RESPONSES_ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let dir = responses_dir()?;
+    // The guardrail goes out with every prompt, so changing it changes the reply
+    let key = [provider, model, if json { "json" } else { "text" }, sanitize::GUARDRAIL, prompt].join("\0");
+    let hash = content_hash(key.as_bytes());
+    let mut sent = SENT.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let count = sent.entry(hash.clone()).or_default();
+    *count += 1;
+    Some(dir.join(format!("{}-{}.txt", hash, count)))
+}
+
+// The reply, if it was cached within the TTL
+pub fn cached_response(path: &Path) -> Option<String> {
+    let ttl = Duration::from_secs(RESPONSE_TTL_SECS.load(Ordering::Relaxed));
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    if SystemTime::now().duration_since(modified).unwrap_or_default() > ttl {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+pub fn store_response(path: &Path, reply: &str) -> io::Result<()> {
+    let dir = path.parent().ok_or_else(|| io::Error::other("no cache directory"))?;
+    fs::create_dir_all(dir)?;
+    // Written aside and renamed so a concurrent run never reads half a reply
+    let partial = dir.join(format!(".{}.partial", Uuid::new_v4()));
+    fs::write(&partial, reply)?;
+    fs::rename(&partial, path)
+}
+
+// Returns how many replies were dropped
+pub fn clear_responses() -> io::Result<usize> {
+    let Some(dir) = responses_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(0);
+    };
+    let mut cleared = 0;
+    for entry in fs::read_dir(dir)? {
+        fs::remove_file(entry?.path())?;
+        cleared += 1;
+    }
+    Ok(cleared)
+}
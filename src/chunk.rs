@@ -0,0 +1,152 @@
+use crate::estimate::CHARS_PER_TOKEN;
+
+// Equations, tables and numeric data tokenize at about half the rate of prose
+const DENSE_CHARS_PER_TOKEN: f64 = 2.0;
+// Share of dense characters at which a passage counts as fully dense;
+// ordinary prose sits well below a tenth
+const FULL_DENSITY: f64 = 0.35;
+// Chunk budgets relative to the requested size, for prose and for fully
+// dense passages. Dense passages pack more facts per token, so they get less
+// text per call; prose gets more.
+const PROSE_SCALE: f64 = 1.25;
+const DENSE_SCALE: f64 = 0.5;
+
+// Tokens each chunk repeats from the end of the one before
+pub const DEFAULT_OVERLAP: usize = 200;
+
+// Splitting of long documents for generation: each chunk is generated on its
+// own and the ologs merged
+#[derive(Debug, Clone)]
+pub struct Chunking {
+    // Target tokens per chunk, before adjusting for density
+    pub size: usize,
+    pub overlap: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub text: String,
+    pub tokens: usize,
+    // 0 for plain prose up to 1 for equation or table heavy text
+    pub density: f64,
+}
+
+fn is_dense(c: char) -> bool {
+    c.is_ascii_digit() || "=+-*/^_<>|\\{}[]$%&~".contains(c) || (!c.is_ascii() && !c.is_alphabetic())
+}
+
+// How much of the text is math, tables or numbers, from 0 to 1
+pub fn density(text: &str) -> f64 {
+    let (dense, total) = text.chars()
+        .filter(|c| !c.is_whitespace())
+        .fold((0usize, 0usize), |(dense, total), c| (dense + is_dense(c) as usize, total + 1));
+    if total == 0 {
+        return 0.0;
+    }
+    (dense as f64 / total as f64 / FULL_DENSITY).min(1.0)
+}
+
+// Token count estimate that accounts for dense text splitting into more tokens
+pub fn estimate_tokens(text: &str) -> usize {
+    let chars_per_token = CHARS_PER_TOKEN - (CHARS_PER_TOKEN - DENSE_CHARS_PER_TOKEN) * density(text);
+    (text.chars().count() as f64 / chars_per_token).ceil() as usize
+}
+
+// Tokens a chunk of the given density may hold
+fn budget(target_tokens: usize, density: f64) -> usize {
+    ((target_tokens as f64 * (PROSE_SCALE - (PROSE_SCALE - DENSE_SCALE) * density)) as usize).max(1)
+}
+
+// Cuts text at paragraph breaks into chunks of about `target_tokens` each,
+// scaled down for equation and table heavy stretches and up for prose. A
+// paragraph that alone exceeds its budget is cut at line breaks, then between
+// words.
+pub fn split_adaptive(text: &str, target_tokens: usize) -> Vec<Chunk> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    let flush = |current: &mut Vec<&str>, chunks: &mut Vec<Chunk>| {
+        if !current.is_empty() {
+            let text = current.join("\n\n");
+            chunks.push(Chunk { tokens: estimate_tokens(&text), density: density(&text), text });
+            current.clear();
+        }
+    };
+
+    for paragraph in text.split("\n\n").filter(|paragraph| !paragraph.trim().is_empty()) {
+        for piece in pieces(paragraph, budget(target_tokens, density(paragraph))) {
+            current.push(piece);
+            let joined = current.join("\n\n");
+            if current.len() > 1 && estimate_tokens(&joined) > budget(target_tokens, density(&joined)) {
+                current.pop();
+                flush(&mut current, &mut chunks);
+                current.push(piece);
+            }
+        }
+    }
+    flush(&mut current, &mut chunks);
+    chunks
+}
+
+// Chunks of the document for one generation call each. Every chunk after the
+// first starts with about the last `overlap` tokens of the one before, so a
+// fact stated across a boundary is seen whole at least once.
+pub fn chunk_document(text: &str, chunking: &Chunking) -> Vec<String> {
+    let chunks = split_adaptive(text, chunking.size);
+    let mut documents = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        match i.checked_sub(1).map(|previous| tail(&chunks[previous].text, chunking.overlap)) {
+            Some(repeated) if !repeated.is_empty() => documents.push(format!("{}\n\n{}", repeated, chunk.text)),
+            _ => documents.push(chunk.text.clone()),
+        }
+    }
+    documents
+}
+
+// About the last `tokens` tokens of the text, starting at a word boundary
+fn tail(text: &str, tokens: usize) -> &str {
+    let chars = (tokens as f64 * CHARS_PER_TOKEN) as usize;
+    let Some((start, _)) = text.char_indices().rev().nth(chars) else {
+        return text;
+    };
+    let rest = &text[start..];
+    match rest.find(char::is_whitespace) {
+        Some(space) => rest[space..].trim_start(),
+        None => "",
+    }
+}
+
+// The paragraph itself if it fits, otherwise runs of its lines, or of its
+// words for a single overlong line
+fn pieces(paragraph: &str, budget: usize) -> Vec<&str> {
+    if estimate_tokens(paragraph) <= budget {
+        return vec![paragraph];
+    }
+    let separator = if paragraph.contains('\n') { '\n' } else { ' ' };
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for (index, _) in paragraph.match_indices(separator).chain([(paragraph.len(), "")]) {
+        if end > start && estimate_tokens(&paragraph[start..index]) > budget {
+            pieces.push(&paragraph[start..end]);
+            start = end + separator.len_utf8();
+        }
+        end = index;
+    }
+    if start < paragraph.len() {
+        pieces.push(&paragraph[start..]);
+    }
+    // Lines may still be too long on their own
+    if separator == '\n' {
+        return pieces.into_iter().flat_map(|piece| pieces_of_line(piece, budget)).collect();
+    }
+    pieces
+}
+
+fn pieces_of_line(line: &str, budget: usize) -> Vec<&str> {
+    if line.contains('\n') || estimate_tokens(line) <= budget {
+        vec![line]
+    } else {
+        pieces(line, budget)
+    }
+}
@@ -0,0 +1,295 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::error::OlogError;
+use crate::provider::Provider;
+
+pub const API_KEY_ENV: &str = "OPENAI_API_KEY";
+pub const TIMEOUT_ENV: &str = "OLOG_TIMEOUT_SECS";
+pub const PARALLELISM_ENV: &str = "OLOG_PARALLELISM";
+pub const MAX_ATTEMPTS_ENV: &str = "OLOG_MAX_ATTEMPTS";
+pub const PROVIDER_ENV: &str = "OLOG_PROVIDER";
+pub const OPENAI_BASE_URL_ENV: &str = "OPENAI_BASE_URL";
+pub const ANTHROPIC_API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+pub const OLLAMA_HOST_ENV: &str = "OLLAMA_HOST";
+// Read in this order, as curl does; a proxy set for plain HTTP only is ignored
+// since every remote service olog talks to is HTTPS
+pub const PROXY_ENVS: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+pub const NO_PROXY_ENVS: [&str; 2] = ["NO_PROXY", "no_proxy"];
+pub const CA_CERT_ENV: &str = "OLOG_CA_CERT";
+pub const CONNECT_TIMEOUT_ENV: &str = "OLOG_CONNECT_TIMEOUT_SECS";
+pub const MODEL_TIMEOUT_ENV: &str = "OLOG_MODEL_TIMEOUT_SECS";
+const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+const CONFIG_DIR: &str = "olog-debate";
+const CONFIG_FILE: &str = "config.toml";
+/// Settings for the project in the working directory
+pub const PROJECT_FILE: &str = "olog.toml";
+
+// olog.toml as parsed on first use, or why it couldn't be
+static PROJECT_CONFIG: OnceLock<Result<toml::Table, String>> = OnceLock::new();
+
+/// Where an effective setting came from, highest precedence first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env,
+    Project,
+    File,
+    Default,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Flag => "flag",
+            Source::Env => "environment",
+            Source::Project => "project file",
+            Source::File => "config file",
+            Source::Default => "default",
+        }
+    }
+}
+
+/// A setting's effective value and the layer it came from
+#[derive(Debug, Clone)]
+pub struct Layered<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+/// Settings that follow the user across projects. A project's olog.toml
+/// takes precedence over the same setting here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    pub openai_api_key: Option<String>,
+    pub provider: Option<Provider>,
+    // An OpenAI-compatible server to send requests to instead of OpenAI's
+    pub openai_base_url: Option<String>,
+    pub anthropic_api_key: Option<String>,
+    pub ollama_host: Option<String>,
+    // Main and label model of every preset that doesn't name its own
+    pub model: Option<String>,
+    pub label_model: Option<String>,
+    pub database: Option<PathBuf>,
+    // Seconds before an HTTP request other than a model call gives up
+    pub timeout_secs: Option<u64>,
+    // Seconds before a model call gives up waiting for the whole reply
+    pub model_timeout_secs: Option<u64>,
+    // Proxy URL for every request, and the hosts reached without it
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    // PEM file of certificates to trust besides the bundled roots, such as a
+    // corporate proxy's
+    pub ca_cert: Option<PathBuf>,
+    // Seconds before opening any connection gives up, model calls included
+    pub connect_timeout_secs: Option<u64>,
+    pub parallelism: Option<usize>,
+    pub max_attempts: Option<u32>,
+}
+
+/// `$XDG_CONFIG_HOME/olog-debate/config.toml`, or `~/.config/olog-debate/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+// Empty when the file doesn't exist
+pub fn read_config() -> Result<UserConfig, OlogError> {
+    let Some(path) = config_path().filter(|path| path.exists()) else {
+        return Ok(UserConfig::default());
+    };
+    toml::from_str::<UserConfig>(&fs::read_to_string(&path)?)
+        .map_err(|e| OlogError::Config(format!("Error parsing {}: {}", path.display(), e)))
+}
+
+// Empty when the file doesn't exist
+fn project_config() -> Result<&'static toml::Table, OlogError> {
+    PROJECT_CONFIG
+        .get_or_init(|| {
+            if !Path::new(PROJECT_FILE).exists() {
+                return Ok(toml::Table::new());
+            }
+            let text = fs::read_to_string(PROJECT_FILE).map_err(|e| format!("Error reading {}: {}", PROJECT_FILE, e))?;
+            text.parse().map_err(|e| format!("Error parsing {}: {}", PROJECT_FILE, e))
+        })
+        .as_ref()
+        .map_err(|e| OlogError::Config(e.clone()))
+}
+
+/// A top-level setting or `[section]` of olog.toml; None when it isn't there
+pub fn project_setting<T: DeserializeOwned>(key: &str) -> Result<Option<T>, OlogError> {
+    project_config()?
+        .get(key)
+        .map(|value| {
+            value.clone().try_into().map_err(|e| OlogError::Config(format!("Invalid `{}` in {}: {}", key, PROJECT_FILE, e)))
+        })
+        .transpose()
+}
+
+/// olog.toml in the working directory, whether or not it exists
+pub fn project_path() -> PathBuf {
+    env::current_dir().map_or_else(|_| PathBuf::from(PROJECT_FILE), |dir| dir.join(PROJECT_FILE))
+}
+
+// A set, non-empty environment variable, parsed
+fn env_value<T: FromStr>(name: &str) -> Result<Option<T>, OlogError>
+where
+    T::Err: Display,
+{
+    match env::var(name).ok().filter(|value| !value.trim().is_empty()) {
+        Some(value) => value.trim().parse().map(Some).map_err(|e| OlogError::Config(format!("Invalid {}: {}", name, e))),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn layered<T>(flag: Option<T>, env: Option<T>, file: Option<T>, default: T) -> Layered<T> {
+    match (flag, env, file) {
+        (Some(value), _, _) => Layered { value, source: Source::Flag },
+        (None, Some(value), _) => Layered { value, source: Source::Env },
+        (None, None, Some(value)) => Layered { value, source: Source::File },
+        (None, None, None) => Layered { value: default, source: Source::Default },
+    }
+}
+
+/// OPENAI_API_KEY, then `openai_api_key` in the config file
+pub fn openai_api_key() -> Result<Layered<Option<String>>, OlogError> {
+    let env = env::var(API_KEY_ENV).ok().filter(|key| !key.trim().is_empty());
+    Ok(layered(None, env.map(Some), read_config()?.openai_api_key.map(Some), None))
+}
+
+/// The API key for requests to OpenAI, or why there is none
+pub fn require_api_key() -> Result<String, OlogError> {
+    openai_api_key()?.value.ok_or_else(|| {
+        OlogError::Config(format!("{} is not set and {} has no openai_api_key", API_KEY_ENV, display_path()))
+    })
+}
+
+pub fn provider(flag: Option<Provider>) -> Result<Layered<Provider>, OlogError> {
+    let env = match env::var(PROVIDER_ENV).ok().filter(|value| !value.trim().is_empty()) {
+        Some(value) => Some(
+            <Provider as clap::ValueEnum>::from_str(value.trim(), true)
+                .map_err(|_| OlogError::Config(format!("Invalid {}: unknown provider `{}`", PROVIDER_ENV, value.trim())))?,
+        ),
+        None => None,
+    };
+    Ok(layered(flag, env, read_config()?.provider, Provider::Openai))
+}
+
+/// OPENAI_BASE_URL, then `openai_base_url`; none means OpenAI itself
+pub fn openai_base_url() -> Result<Layered<Option<String>>, OlogError> {
+    let env = env::var(OPENAI_BASE_URL_ENV).ok().filter(|url| !url.trim().is_empty());
+    Ok(layered(None, env.map(Some), read_config()?.openai_base_url.map(Some), None))
+}
+
+pub fn anthropic_api_key() -> Result<Layered<Option<String>>, OlogError> {
+    let env = env::var(ANTHROPIC_API_KEY_ENV).ok().filter(|key| !key.trim().is_empty());
+    Ok(layered(None, env.map(Some), read_config()?.anthropic_api_key.map(Some), None))
+}
+
+pub fn require_anthropic_api_key() -> Result<String, OlogError> {
+    anthropic_api_key()?.value.ok_or_else(|| {
+        OlogError::Config(format!("{} is not set and {} has no anthropic_api_key", ANTHROPIC_API_KEY_ENV, display_path()))
+    })
+}
+
+/// OLLAMA_HOST, then `ollama_host`, then Ollama's own default
+pub fn ollama_host() -> Result<Layered<String>, OlogError> {
+    let env = env::var(OLLAMA_HOST_ENV).ok().filter(|host| !host.trim().is_empty());
+    let mut host = layered(None, env, read_config()?.ollama_host, DEFAULT_OLLAMA_HOST.to_string());
+    // Ollama itself accepts a bare host:port here
+    if !host.value.contains("://") {
+        host.value = format!("http://{}", host.value);
+    }
+    Ok(host)
+}
+
+/// OLOG_TIMEOUT_SECS, then `timeout_secs`, then the request's own default
+pub fn timeout(default: Duration) -> Result<Layered<Duration>, OlogError> {
+    let file = read_config()?.timeout_secs;
+    Ok(layered(None, env_value(TIMEOUT_ENV)?.map(Duration::from_secs), file.map(Duration::from_secs), default))
+}
+
+/// OLOG_MODEL_TIMEOUT_SECS, then `model_timeout_secs`, then `default`
+pub fn model_timeout(default: Duration) -> Result<Layered<Duration>, OlogError> {
+    let file = read_config()?.model_timeout_secs;
+    Ok(layered(None, env_value(MODEL_TIMEOUT_ENV)?.map(Duration::from_secs), file.map(Duration::from_secs), default))
+}
+
+// The first of `names` that is set and non-empty
+fn first_env(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+}
+
+/// HTTPS_PROXY or ALL_PROXY, then `proxy`; none means connecting directly
+pub fn proxy() -> Result<Layered<Option<String>>, OlogError> {
+    Ok(layered(None, first_env(&PROXY_ENVS).map(Some), read_config()?.proxy.map(Some), None))
+}
+
+/// NO_PROXY, then `no_proxy`: comma-separated hosts and domains reached
+/// without the proxy
+pub fn no_proxy() -> Result<Layered<Option<String>>, OlogError> {
+    Ok(layered(None, first_env(&NO_PROXY_ENVS).map(Some), read_config()?.no_proxy.map(Some), None))
+}
+
+/// OLOG_CA_CERT, then `ca_cert`
+pub fn ca_cert() -> Result<Layered<Option<PathBuf>>, OlogError> {
+    let env = first_env(&[CA_CERT_ENV]).map(PathBuf::from);
+    Ok(layered(None, env.map(Some), read_config()?.ca_cert.map(Some), None))
+}
+
+/// OLOG_CONNECT_TIMEOUT_SECS, then `connect_timeout_secs`, then `default`
+pub fn connect_timeout(default: Duration) -> Result<Layered<Duration>, OlogError> {
+    let file = read_config()?.connect_timeout_secs;
+    Ok(layered(None, env_value(CONNECT_TIMEOUT_ENV)?.map(Duration::from_secs), file.map(Duration::from_secs), default))
+}
+
+pub fn parallelism(flag: Option<usize>, default: usize) -> Result<Layered<usize>, OlogError> {
+    let resolved = layered(flag, env_value(PARALLELISM_ENV)?, read_config()?.parallelism, default);
+    if resolved.value == 0 {
+        return Err(OlogError::Config(format!("Parallelism from the {} must be at least 1", resolved.source.name())));
+    }
+    Ok(resolved)
+}
+
+pub fn max_attempts(flag: Option<u32>, default: u32) -> Result<Layered<u32>, OlogError> {
+    let resolved = layered(flag, env_value(MAX_ATTEMPTS_ENV)?, read_config()?.max_attempts, default);
+    if resolved.value == 0 {
+        return Err(OlogError::Config(format!("Max attempts from the {} must be at least 1", resolved.source.name())));
+    }
+    Ok(resolved)
+}
+
+/// A secret by its last four characters
+pub fn mask(secret: &str) -> String {
+    let tail: String = secret.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("...{}", tail)
+}
+
+// A proxy URL with its password, if any, hidden
+pub fn mask_proxy(proxy: &str) -> String {
+    let (scheme, rest) = proxy.split_once("://").map_or(("", proxy), |(scheme, rest)| (scheme, rest));
+    let Some((userinfo, host)) = rest.rsplit_once('@') else {
+        return proxy.to_string();
+    };
+    let Some((user, _)) = userinfo.split_once(':') else {
+        return proxy.to_string();
+    };
+    let scheme = if scheme.is_empty() { String::new() } else { format!("{}://", scheme) };
+    format!("{}{}:***@{}", scheme, user, host)
+}
+
+// For messages about the file, whether or not there is a home directory
+pub fn display_path() -> String {
+    config_path().map_or_else(|| format!("~/.config/{}/{}", CONFIG_DIR, CONFIG_FILE), |path| path.display().to_string())
+}
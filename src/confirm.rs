@@ -0,0 +1,31 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{CliError, ErrorCategory};
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+pub fn assume_yes() {
+    ASSUME_YES.store(true, Ordering::Relaxed);
+}
+
+// Shows what an operation is about to destroy and asks before going ahead.
+// --yes answers for the user; with no terminal to ask on, the operation is
+// refused rather than run unconfirmed.
+pub fn confirm(summary: &str) -> Result<(), CliError> {
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    eprintln!("{}", summary);
+    if !io::stdin().is_terminal() {
+        return Err(CliError::new(ErrorCategory::Usage, "Not confirmed; pass --yes to go ahead without a prompt"));
+    }
+    eprint!("Continue? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(CliError::new(ErrorCategory::Usage, "Cancelled; nothing was changed")),
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{Hyperedge, Olog};
+
+#[derive(Debug, Deserialize)]
+struct SynonymResponse {
+    groups: Vec<GroupAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GroupAnswer {
+    group: usize,
+    // Lists of label indices within the group that mean the same thing
+    synonyms: Vec<Vec<usize>>,
+}
+
+// Hyperedges joining the same sources to the same targets are candidates; the
+// ones whose labels are equivalent ("leads to" vs "causes") are merged into the
+// first of them, which keeps its label and roles and gains the others'
+// citations. Returns the olog and the number of hyperedges folded away.
+pub fn consolidate_hyperedges(provider: &dyn LlmProvider, olog: Olog, model: &str) -> Result<(Olog, usize), OlogError> {
+    let mut by_endpoints: HashMap<CandidateKey, Vec<usize>> = HashMap::new();
+    for (index, hyperedge) in olog.hyperedges.iter().enumerate() {
+        by_endpoints.entry(candidate_key(hyperedge)).or_default().push(index);
+    }
+
+    // Labels that only differ in case, spacing or punctuation need no model call
+    let mut candidates: Vec<Vec<Vec<usize>>> = Vec::new();
+    for indices in by_endpoints.into_values().filter(|indices| indices.len() > 1) {
+        let mut classes: Vec<(String, Vec<usize>)> = Vec::new();
+        for index in indices {
+            let key = normalize(&olog.hyperedges[index].label);
+            match classes.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, class)) => class.push(index),
+                None => classes.push((key, vec![index])),
+            }
+        }
+        candidates.push(classes.into_iter().map(|(_, class)| class).collect());
+    }
+
+    let ambiguous: Vec<usize> = (0..candidates.len()).filter(|&i| candidates[i].len() > 1).collect();
+    if !ambiguous.is_empty() {
+        let groups: Vec<Vec<&str>> = ambiguous.iter()
+            .map(|&i| candidates[i].iter().map(|class| olog.hyperedges[class[0]].label.as_str()).collect())
+            .collect();
+        for answer in ask_for_synonyms(provider, model, &groups)?.groups {
+            let Some(&candidate) = ambiguous.get(answer.group) else { continue };
+            candidates[candidate] = join_classes(&candidates[candidate], &answer.synonyms);
+        }
+    }
+
+    let mut keeper_of: HashMap<usize, usize> = HashMap::new();
+    for class in candidates.iter().flatten() {
+        for &index in &class[1..] {
+            keeper_of.insert(index, class[0]);
+        }
+    }
+    if keeper_of.is_empty() {
+        return Ok((olog, 0));
+    }
+
+    let mut absorbed: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (&index, &keeper) in &keeper_of {
+        absorbed.entry(keeper).or_default().push(index);
+    }
+
+    let mut hyperedges: Vec<Option<Hyperedge>> = olog.hyperedges.into_iter().map(Some).collect();
+    for (keeper, indices) in absorbed {
+        for index in indices {
+            let citations = hyperedges[index].take().map(|hyperedge| hyperedge.citations).unwrap_or_default();
+            let kept = hyperedges[keeper].as_mut().expect("keepers are never absorbed");
+            for citation in citations {
+                if !kept.citations.iter().any(|c| c.id == citation.id) {
+                    kept.citations.push(citation);
+                }
+            }
+        }
+    }
+
+    let merged = keeper_of.len();
+    Ok((
+        Olog {
+            id: olog.id,
+            title: olog.title,
+            nodes: olog.nodes,
+            hyperedges: hyperedges.into_iter().flatten().collect(),
+        },
+        merged,
+    ))
+}
+
+// Sorted sources, sorted targets and the stated quantity; hyperedges reporting
+// different values are never merged
+type CandidateKey = (Vec<Uuid>, Vec<Uuid>, Option<String>);
+
+fn candidate_key(hyperedge: &Hyperedge) -> CandidateKey {
+    let mut sources: Vec<Uuid> = hyperedge.source.iter().map(|node| node.id).collect();
+    let mut targets: Vec<Uuid> = hyperedge.target.iter().map(|node| node.id).collect();
+    sources.sort();
+    targets.sort();
+    (sources, targets, hyperedge.quantity.as_ref().map(|quantity| quantity.to_string()))
+}
+
+fn normalize(label: &str) -> String {
+    label.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Unions the classes the model grouped together; out-of-range indices are ignored
+fn join_classes(classes: &[Vec<usize>], synonyms: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..classes.len()).collect();
+    let root = |parent: &[usize], mut i: usize| {
+        while parent[i] != i {
+            i = parent[i];
+        }
+        i
+    };
+    for group in synonyms {
+        let members: Vec<usize> = group.iter().copied().filter(|&i| i < classes.len()).collect();
+        for pair in members.windows(2) {
+            let (a, b) = (root(&parent, pair[0]), root(&parent, pair[1]));
+            // The lower index stays the root so the earliest hyperedge is kept
+            if a != b {
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut joined: Vec<Vec<usize>> = vec![Vec::new(); classes.len()];
+    for (i, class) in classes.iter().enumerate() {
+        joined[root(&parent, i)].extend(class);
+    }
+    joined.into_iter().filter(|class| !class.is_empty()).collect()
+}
+
+fn ask_for_synonyms(provider: &dyn LlmProvider, model: &str, groups: &[Vec<&str>]) -> Result<SynonymResponse, OlogError> {
+    let listing = groups.iter().enumerate()
+        .map(|(i, labels)| {
+            let labels = labels.iter().enumerate()
+                .map(|(j, label)| format!("  {}: {}", j, label))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("Group {}:\n{}", i, labels)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = format!(
+        "Each group below lists relation labels from an ontology log that connect exactly the same entities in the \
+         same direction. Within each group, find the labels that express the same relationship (for example \
+         \"leads to\" and \"causes\"). Labels describing different relationships must stay apart. Respond with JSON \
+         of the form {{\"groups\": [{{\"group\": 0, \"synonyms\": [[0, 2]]}}]}}, listing only sets of two or more \
+         equivalent labels by their index.\n\n{}",
+        listing
+    );
+    let response = complete(provider, model, prompt, true)?;
+    serde_json::from_str(&response).map_err(|e| OlogError::Schema(format!("Malformed synonym response: {}", e)))
+}
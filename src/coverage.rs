@@ -0,0 +1,215 @@
+use regex::Regex;
+use std::ops::Range;
+use uuid::Uuid;
+
+use crate::olog::{distinct_citations, Citation, Hyperedge, Olog};
+
+const HEADING: &str = r"^#{1,6}[ \t]+\S";
+// Documents without headings are cut into sections of this many paragraphs
+const FALLBACK_SECTION_PARAGRAPHS: usize = 8;
+// Words of a section's first paragraph that name it when it has no heading
+const UNTITLED_HEADING_WORDS: usize = 8;
+// Shorter label words are too common to place a hyperedge by
+const MIN_ANCHOR_WORD_CHARS: usize = 4;
+// Uncovered sections shorter than this are captions and stray lines, not
+// worth regenerating
+pub const MIN_CANDIDATE_CHARS: usize = 200;
+
+#[derive(Debug)]
+pub struct SectionCoverage {
+    pub heading: String,
+    // Character offsets into the citation text
+    pub range: Range<usize>,
+    // Characters of the paragraphs some hyperedge was placed in, and of all
+    // paragraphs in the section
+    pub covered_chars: usize,
+    pub total_chars: usize,
+    pub hyperedges: usize,
+}
+
+impl SectionCoverage {
+    pub fn fraction(&self) -> f64 {
+        fraction(self.covered_chars, self.total_chars)
+    }
+}
+
+#[derive(Debug)]
+pub struct DocumentCoverage {
+    pub citation_id: Uuid,
+    pub title: String,
+    pub sections: Vec<SectionCoverage>,
+    // Hyperedges citing the document whose nodes never appear together in
+    // one of its paragraphs
+    pub unplaced: Vec<Uuid>,
+}
+
+impl DocumentCoverage {
+    pub fn covered_chars(&self) -> usize {
+        self.sections.iter().map(|section| section.covered_chars).sum()
+    }
+
+    pub fn total_chars(&self) -> usize {
+        self.sections.iter().map(|section| section.total_chars).sum()
+    }
+
+    pub fn fraction(&self) -> f64 {
+        fraction(self.covered_chars(), self.total_chars())
+    }
+
+    // Sections no hyperedge was placed in, largest first
+    pub fn candidates(&self) -> Vec<&SectionCoverage> {
+        let mut candidates: Vec<&SectionCoverage> = self.sections.iter()
+            .filter(|section| section.covered_chars == 0 && section.total_chars >= MIN_CANDIDATE_CHARS)
+            .collect();
+        candidates.sort_by_key(|section| std::cmp::Reverse(section.total_chars));
+        candidates
+    }
+}
+
+fn fraction(part: usize, whole: usize) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 / whole as f64 }
+}
+
+// Places each hyperedge in the paragraph of its cited document where most of
+// its nodes are mentioned, then measures how much of each section those
+// paragraphs make up
+pub fn coverage(olog: &Olog) -> Vec<DocumentCoverage> {
+    distinct_citations(olog).into_iter()
+        .map(|citation| {
+            let citing: Vec<&Hyperedge> = olog.hyperedges.iter()
+                .filter(|hyperedge| hyperedge.citations.iter().any(|cited| cited.id == citation.id))
+                .collect();
+            document_coverage(citation, &citing)
+        })
+        .collect()
+}
+
+fn document_coverage(citation: &Citation, hyperedges: &[&Hyperedge]) -> DocumentCoverage {
+    let text = citation.text.as_str();
+    let paragraphs = paragraphs(text);
+    let normalized: Vec<String> = paragraphs.iter().map(|range| normalize(&text[range.clone()])).collect();
+
+    let mut placed = vec![0; paragraphs.len()];
+    let mut unplaced = Vec::new();
+    // A located quote says exactly where the relation is stated
+    let quoted_in = |hyperedge: &Hyperedge| -> Option<usize> {
+        let span = hyperedge.spans.iter().find(|span| span.citation_id == citation.id)?;
+        let byte = text.char_indices().nth(span.start)?.0;
+        paragraphs.iter().position(|paragraph| byte < paragraph.end)
+    };
+    for hyperedge in hyperedges {
+        match quoted_in(hyperedge).or_else(|| place(hyperedge, &normalized)) {
+            Some(index) => placed[index] += 1,
+            None => unplaced.push(hyperedge.id),
+        }
+    }
+
+    let char_offset = |byte: usize| text[..byte].chars().count();
+    let sections = sections(text, &paragraphs).into_iter()
+        .map(|(heading, range)| {
+            let mut section = SectionCoverage {
+                heading,
+                range: char_offset(range.start)..char_offset(range.end),
+                covered_chars: 0,
+                total_chars: 0,
+                hyperedges: 0,
+            };
+            for (index, paragraph) in paragraphs.iter().enumerate().filter(|(_, paragraph)| range.contains(&paragraph.start)) {
+                let chars = text[paragraph.clone()].chars().count();
+                section.total_chars += chars;
+                if placed[index] > 0 {
+                    section.covered_chars += chars;
+                    section.hyperedges += placed[index];
+                }
+            }
+            section
+        })
+        // A heading directly followed by a subheading has nothing of its own
+        .filter(|section| section.total_chars > 0)
+        .collect();
+
+    DocumentCoverage { citation_id: citation.id, title: citation.title.trim().to_string(), sections, unplaced }
+}
+
+// Byte ranges of runs of non-blank lines, leaving out headings
+fn paragraphs(text: &str) -> Vec<Range<usize>> {
+    let heading = Regex::new(HEADING).unwrap();
+    let mut paragraphs = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset + (line.len() - line.trim_start().len());
+        let end = offset + line.trim_end().len();
+        offset += line.len();
+        if line.trim().is_empty() || heading.is_match(line.trim_start()) {
+            paragraphs.extend(current.take());
+        } else {
+            current = Some(current.map_or(start..end, |current| current.start..end));
+        }
+    }
+    paragraphs.extend(current);
+    paragraphs
+}
+
+// Markdown headings split the document; without them, runs of paragraphs
+// named after their opening words stand in
+fn sections(text: &str, paragraphs: &[Range<usize>]) -> Vec<(String, Range<usize>)> {
+    let heading = Regex::new(&format!("(?m){}.*$", HEADING)).unwrap();
+    let starts: Vec<(usize, String)> = heading.find_iter(text)
+        .map(|found| (found.start(), found.as_str().trim_start_matches('#').trim().to_string()))
+        .collect();
+
+    let mut sections = Vec::new();
+    if starts.is_empty() {
+        for group in paragraphs.chunks(FALLBACK_SECTION_PARAGRAPHS) {
+            let words: Vec<&str> = text[group[0].clone()].split_whitespace().collect();
+            let mut name = words.iter().take(UNTITLED_HEADING_WORDS).copied().collect::<Vec<_>>().join(" ");
+            if words.len() > UNTITLED_HEADING_WORDS {
+                name.push('…');
+            }
+            sections.push((name, group[0].start..group[group.len() - 1].end));
+        }
+        return sections;
+    }
+
+    if paragraphs.first().is_some_and(|first| first.start < starts[0].0) {
+        sections.push(("Front matter".to_string(), 0..starts[0].0));
+    }
+    for (i, (start, name)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(text.len(), |(next, _)| *next);
+        sections.push((name.clone(), *start..end));
+    }
+    sections
+}
+
+// The paragraph mentioning the most of the hyperedge's nodes, first on a tie.
+// One shared concept says little about where a relation is stated, so at
+// least two nodes have to appear, or the only one for a single-node hyperedge.
+fn place(hyperedge: &Hyperedge, paragraphs: &[String]) -> Option<usize> {
+    let labels: Vec<String> = hyperedge.source.iter().chain(&hyperedge.target).map(|node| normalize(&node.label)).collect();
+    let needed = labels.len().min(2);
+    let mut best: Option<(usize, usize)> = None;
+    for (index, paragraph) in paragraphs.iter().enumerate() {
+        let found = labels.iter().filter(|label| mentions(paragraph, label)).count();
+        if found >= needed.max(1) && best.is_none_or(|(_, most)| found > most) {
+            best = Some((index, found));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+// Labels are often paraphrased, so the label's longest word stands in for it
+fn mentions(paragraph: &str, label: &str) -> bool {
+    if paragraph.contains(label) {
+        return true;
+    }
+    label.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .max_by_key(|word| word.chars().count())
+        .filter(|word| word.chars().count() >= MIN_ANCHOR_WORD_CHARS)
+        .is_some_and(|word| paragraph.contains(word))
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
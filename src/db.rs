@@ -0,0 +1,985 @@
+use regex::Regex;
+use clap::ValueEnum;
+use rusqlite::types::{Type, Value, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::{self, Layered, Source};
+use crate::elastic;
+use crate::error::OlogError;
+use crate::events::{self, Event};
+use crate::gitrepo::{self, Operation};
+use crate::migrations;
+use crate::olog::{Citation, Hyperedge, Node, Olog, Quantity, Span};
+use crate::preset;
+use crate::search;
+
+/// Database file name, in the data directory or the current one
+pub const DB_FILE: &str = "olog.db";
+/// Environment variable naming the database file when `--db` isn't given
+pub const DB_PATH_ENV: &str = "OLOG_DB_PATH";
+
+/// Where the database lives: `flag` if given, then `OLOG_DB_PATH`, then
+/// `database` in olog.toml, then `database` in the user config. Otherwise an
+/// olog.db in the current directory, where earlier versions kept it, or
+/// `$XDG_DATA_HOME/olog/olog.db` (`~/.local/share/olog/olog.db` when that is unset).
+pub fn database_path(flag: Option<PathBuf>) -> std::result::Result<PathBuf, OlogError> {
+    database_location(flag).map(|location| location.value)
+}
+
+/// `database_path` together with the layer that chose it
+pub fn database_location(flag: Option<PathBuf>) -> std::result::Result<Layered<PathBuf>, OlogError> {
+    let env = env::var_os(DB_PATH_ENV).filter(|path| !path.is_empty()).map(PathBuf::from);
+    if flag.is_none() && env.is_none() {
+        if let Some(path) = preset::configured_database()? {
+            return Ok(Layered { value: path, source: Source::Project });
+        }
+    }
+    Ok(config::layered(flag, env, config::read_config()?.database, default_database_path()))
+}
+
+fn default_database_path() -> PathBuf {
+    if Path::new(DB_FILE).exists() {
+        return PathBuf::from(DB_FILE);
+    }
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    match data_home {
+        Some(dir) => dir.join("olog").join(DB_FILE),
+        None => PathBuf::from(DB_FILE),
+    }
+}
+
+/// Opens the database at `path`, creating its directory if needed. Foreign
+/// keys are enforced unless `foreign_keys = false` is set in olog.toml.
+pub fn open_database(path: &Path) -> std::result::Result<Connection, OlogError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let conn = Connection::open(path)?;
+    conn.pragma_update(None, "foreign_keys", preset::configured_foreign_keys()?)?;
+    Ok(conn)
+}
+
+/// Brings the schema up to date by applying every pending migration; safe to
+/// call on every start
+pub fn create_olog_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    migrations::migrate(conn).map(|_| ())
+}
+
+// Migration 1. Creates whatever tables, columns and indexes are missing, so it
+// also upgrades databases written before migrations were tracked.
+pub(crate) fn baseline_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Ologs (
+            olog_id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            namespace TEXT NOT NULL DEFAULT 'default',
+            created_at TEXT
+        )",
+        [],
+    )?;
+    add_column_if_missing(conn, "Ologs", "namespace", "TEXT NOT NULL DEFAULT 'default'")?;
+    // SQLite can't add a column defaulting to CURRENT_TIMESTAMP, so inserts set it
+    add_column_if_missing(conn, "Ologs", "created_at", "TEXT")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Nodes (
+            node_id TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            olog_id TEXT NOT NULL,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Hyperedges (
+            hyperedge_id TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            olog_id TEXT NOT NULL,
+            quantity_value REAL,
+            quantity_unit TEXT,
+            quantity_uncertainty REAL,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+    add_column_if_missing(conn, "Hyperedges", "quantity_value", "REAL")?;
+    add_column_if_missing(conn, "Hyperedges", "quantity_unit", "TEXT")?;
+    add_column_if_missing(conn, "Hyperedges", "quantity_uncertainty", "REAL")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Citations (
+            citation_id TEXT PRIMARY KEY,
+            title TEXT,
+            label TEXT,
+            text TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Hyperedge_Links (
+            hyperedge_id TEXT NOT NULL,
+            node_id TEXT NOT NULL,
+            type TEXT NOT NULL,
+            role TEXT,
+            position INTEGER,
+            FOREIGN KEY(hyperedge_id) REFERENCES Hyperedges(hyperedge_id),
+            FOREIGN KEY(node_id) REFERENCES Nodes(node_id)
+        )",
+        [],
+    )?;
+    add_column_if_missing(conn, "Hyperedge_Links", "role", "TEXT")?;
+    add_column_if_missing(conn, "Hyperedge_Links", "position", "INTEGER")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Citation_Links (
+            hyperedge_id TEXT NOT NULL,
+            citation_id TEXT NOT NULL,
+            FOREIGN KEY(hyperedge_id) REFERENCES Hyperedges(hyperedge_id),
+            FOREIGN KEY(citation_id) REFERENCES Citations(citation_id)
+        )",
+        [],
+    )?;
+
+    // Databases written before these indexes existed may hold duplicate links
+    // from retried writes; they are dropped before the indexes go in
+    if !has_index(conn, "Hyperedge_Links_Slot")? {
+        conn.execute(
+            "DELETE FROM Hyperedge_Links WHERE rowid NOT IN
+             (SELECT MIN(rowid) FROM Hyperedge_Links GROUP BY hyperedge_id, node_id, type, position)",
+            [],
+        )?;
+        conn.execute("CREATE UNIQUE INDEX Hyperedge_Links_Slot ON Hyperedge_Links (hyperedge_id, type, position)", [])?;
+    }
+    if !has_index(conn, "Citation_Links_Pair")? {
+        conn.execute(
+            "DELETE FROM Citation_Links WHERE rowid NOT IN
+             (SELECT MIN(rowid) FROM Citation_Links GROUP BY hyperedge_id, citation_id)",
+            [],
+        )?;
+        conn.execute("CREATE UNIQUE INDEX Citation_Links_Pair ON Citation_Links (hyperedge_id, citation_id)", [])?;
+    }
+    // Let store::Store page through one olog's rows in id order
+    conn.execute("CREATE INDEX IF NOT EXISTS Nodes_Olog ON Nodes (olog_id, node_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Hyperedges_Olog ON Hyperedges (olog_id, hyperedge_id)", [])?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Ingestions (
+            olog_id TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            doi TEXT,
+            arxiv_id TEXT,
+            ingested_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Olog_Versions (
+            olog_id TEXT PRIMARY KEY,
+            parent_olog_id TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id),
+            FOREIGN KEY(parent_olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Node_Provenance (
+            node_id TEXT NOT NULL,
+            citation_id TEXT NOT NULL,
+            pass INTEGER NOT NULL,
+            section TEXT,
+            PRIMARY KEY(node_id, citation_id, pass),
+            FOREIGN KEY(node_id) REFERENCES Nodes(node_id),
+            FOREIGN KEY(citation_id) REFERENCES Citations(citation_id)
+        )",
+        [],
+    )?;
+
+    // Labels a semantic merge folded into a node
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Node_Aliases (
+            node_id TEXT NOT NULL,
+            alias TEXT NOT NULL,
+            PRIMARY KEY(node_id, alias),
+            FOREIGN KEY(node_id) REFERENCES Nodes(node_id)
+        )",
+        [],
+    )?;
+
+    // Debates keep the hyperedges each turn cited by id; a later edit of the
+    // olog can leave some of them dangling
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Debates (
+            debate_id TEXT PRIMARY KEY,
+            olog_id TEXT NOT NULL,
+            claim TEXT NOT NULL,
+            model TEXT NOT NULL,
+            created_at TEXT,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Debate_Turns (
+            debate_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            round INTEGER NOT NULL,
+            side TEXT NOT NULL,
+            argument TEXT NOT NULL,
+            PRIMARY KEY(debate_id, position),
+            FOREIGN KEY(debate_id) REFERENCES Debates(debate_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Debate_Evidence (
+            debate_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            hyperedge_id TEXT NOT NULL,
+            PRIMARY KEY(debate_id, position, hyperedge_id),
+            FOREIGN KEY(debate_id, position) REFERENCES Debate_Turns(debate_id, position)
+        )",
+        [],
+    )?;
+
+    // One vector per node, with the model and label it was computed from so
+    // switching models or relabelling marks it stale
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Embeddings (
+            node_id TEXT PRIMARY KEY,
+            model TEXT NOT NULL,
+            label TEXT NOT NULL,
+            vector BLOB NOT NULL,
+            embedded_at TEXT NOT NULL,
+            FOREIGN KEY(node_id) REFERENCES Nodes(node_id)
+        )",
+        [],
+    )?;
+
+    // A book is an olog of its own that holds the merged concept map of its
+    // chapters once one has been requested
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Books (
+            olog_id TEXT PRIMARY KEY,
+            map_current INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Book_Chapters (
+            book_olog_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            chapter_olog_id TEXT NOT NULL,
+            heading TEXT NOT NULL,
+            PRIMARY KEY(book_olog_id, position),
+            FOREIGN KEY(book_olog_id) REFERENCES Books(olog_id),
+            FOREIGN KEY(chapter_olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn has_table(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get::<_, i64>(0),
+    ).map(|count| count > 0)
+}
+
+// Citation text at least this long is stored zstd-compressed, as a BLOB in
+// the same column; shorter text stays plain TEXT, where compression saves little
+const COMPRESS_TEXT_BYTES: usize = 2048;
+const ZSTD_LEVEL: i32 = 9;
+
+/// Cited text as it is stored in `Documents.text`
+pub(crate) fn stored_citation_text(text: &str) -> Result<Value> {
+    if text.len() < COMPRESS_TEXT_BYTES {
+        return Ok(Value::Text(text.to_string()));
+    }
+    zstd::encode_all(text.as_bytes(), ZSTD_LEVEL)
+        .map(Value::Blob)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Reads a `Documents.text` column, or a `Citations.text` one written before
+/// documents were stored apart, back, decompressing it if it was stored
+/// compressed. Only queries that select the text pay for decompressing it.
+pub(crate) fn citation_text(row: &Row, index: usize) -> Result<Option<String>> {
+    let ValueRef::Blob(bytes) = row.get_ref(index)? else {
+        return row.get(index);
+    };
+    let corrupt = |e: Box<dyn std::error::Error + Send + Sync>| rusqlite::Error::FromSqlConversionFailure(index, Type::Blob, e);
+    let bytes = zstd::decode_all(bytes).map_err(|e| corrupt(Box::new(e)))?;
+    String::from_utf8(bytes).map(Some).map_err(|e| corrupt(Box::new(e)))
+}
+
+/// Stores a cited text once, however many citations share it, under the
+/// SHA-256 of the text, and returns that id
+pub(crate) fn store_document(conn: &Connection, text: &str) -> Result<String> {
+    let document_id = format!("{:x}", Sha256::digest(text.as_bytes()));
+    conn.execute(
+        "INSERT OR IGNORE INTO Documents (document_id, text) VALUES (?1, ?2)",
+        params![document_id, stored_citation_text(text)?],
+    )?;
+    Ok(document_id)
+}
+
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Scopes the connection to one namespace. Ologs written through it are stamped
+/// with the namespace, and lookups by id, dedup and listing only see ologs in
+/// it; queries refer to it as (SELECT namespace FROM temp.Session).
+pub fn use_namespace(conn: &Connection, namespace: &str) -> Result<()> {
+    conn.execute("CREATE TEMP TABLE IF NOT EXISTS Session (namespace TEXT NOT NULL)", [])?;
+    conn.execute("DELETE FROM temp.Session", [])?;
+    conn.execute("INSERT INTO temp.Session (namespace) VALUES (?1)", params![namespace])?;
+    Ok(())
+}
+
+/// The namespace selected with `use_namespace`
+pub fn current_namespace(conn: &Connection) -> Result<String> {
+    conn.query_row("SELECT namespace FROM temp.Session", [], |row| row.get(0))
+}
+
+/// Whether the olog exists in the current namespace
+pub fn olog_in_namespace(conn: &Connection, olog_id: Uuid) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM Ologs WHERE olog_id = ?1 AND namespace = (SELECT namespace FROM temp.Session)",
+        params![olog_id.to_string()],
+        |row| row.get::<_, i64>(0),
+    ).map(|count| count > 0)
+}
+
+/// Reads an olog the user asked for by id; ologs in other namespaces are
+/// reported as missing
+pub fn load_olog(conn: &Connection, olog_id: Uuid) -> Result<Olog, OlogError> {
+    let context = || format!("Error reading Olog {}", olog_id);
+    let visible = olog_in_namespace(conn, olog_id).map_err(|e| OlogError::from(e).context(context()))?;
+    if !visible {
+        let namespace = current_namespace(conn)?;
+        return Err(OlogError::NotFound(format!("Olog {} not found in namespace `{}`", olog_id, namespace)));
+    }
+    read_olog_from_db(conn, olog_id).map_err(|e| OlogError::from(e).context(context()))
+}
+
+/// What an olog was made from, so primary extractions can be told apart
+/// from the artifacts derived from them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OlogKind {
+    /// Generated from one document, or one chapter of a book
+    Document,
+    /// Merged from other ologs, including a book's concept map
+    CorpusMerge,
+    /// Summarized or generalized from other ologs
+    Abstraction,
+    /// Built or written by hand rather than by a pipeline
+    Manual,
+    /// Read from an olog JSON or RDF file, or synced from another database
+    Imported,
+    /// Generated from the transcript of a saved debate
+    Debate,
+}
+
+impl OlogKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            OlogKind::Document => "document",
+            OlogKind::CorpusMerge => "corpus-merge",
+            OlogKind::Abstraction => "abstraction",
+            OlogKind::Manual => "manual",
+            OlogKind::Imported => "imported",
+            OlogKind::Debate => "debate",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<OlogKind> {
+        [OlogKind::Document, OlogKind::CorpusMerge, OlogKind::Abstraction, OlogKind::Manual, OlogKind::Imported, OlogKind::Debate]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    // The kind an olog first stored by `operation` gets; a later write keeps it
+    fn of(operation: Operation) -> OlogKind {
+        match operation {
+            Operation::Generate => OlogKind::Document,
+            Operation::Merge => OlogKind::CorpusMerge,
+            Operation::Edit => OlogKind::Manual,
+            Operation::Import => OlogKind::Imported,
+        }
+    }
+}
+
+// For pipelines whose olog isn't the kind its first write implies
+pub fn set_olog_kind(conn: &Connection, olog_id: Uuid, kind: OlogKind) -> Result<()> {
+    conn.execute("UPDATE Ologs SET kind = ?1 WHERE olog_id = ?2", params![kind.name(), olog_id.to_string()])?;
+    Ok(())
+}
+
+/// One row of `list_ologs`
+#[derive(Debug, Serialize)]
+pub struct OlogSummary {
+    pub id: String,
+    pub title: String,
+    pub kind: OlogKind,
+    pub nodes: i64,
+    pub hyperedges: i64,
+    pub created_at: Option<String>,
+}
+
+/// Ologs in the current namespace, oldest first. Ologs stored before creation
+/// times were recorded fall back to their first ingestion.
+pub fn list_ologs(conn: &Connection) -> Result<Vec<OlogSummary>> {
+    list_ologs_titled(conn, "", &[])
+}
+
+/// Like `list_ologs`, keeping only ologs whose title starts with `prefix`,
+/// ignoring ASCII case, and whose kind is one of `kinds` unless it is empty
+pub fn list_ologs_titled(conn: &Connection, prefix: &str, kinds: &[OlogKind]) -> Result<Vec<OlogSummary>> {
+    let pattern = format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let kinds = kinds.iter().map(|kind| kind.name()).collect::<Vec<_>>().join(",");
+    let mut stmt = conn.prepare(
+        "SELECT olog_id, title, kind,
+                (SELECT COUNT(*) FROM Nodes WHERE Nodes.olog_id = Ologs.olog_id),
+                (SELECT COUNT(*) FROM Hyperedges WHERE Hyperedges.olog_id = Ologs.olog_id),
+                COALESCE(created_at, (SELECT MIN(ingested_at) FROM Ingestions WHERE Ingestions.olog_id = Ologs.olog_id))
+         FROM Ologs
+         WHERE namespace = (SELECT namespace FROM temp.Session)
+           AND title LIKE ?1 ESCAPE '\\'
+           AND (?2 = '' OR instr(',' || ?2 || ',', ',' || kind || ',') > 0)
+         ORDER BY 6, rowid",
+    )?;
+    let summaries = stmt.query_map(params![pattern, kinds], |row| {
+        let kind: String = row.get(2)?;
+        Ok(OlogSummary {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            kind: OlogKind::parse(&kind).ok_or_else(|| unknown_value(2, &kind))?,
+            nodes: row.get(3)?,
+            hyperedges: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+    summaries.collect()
+}
+
+fn has_index(conn: &Connection, index: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'index' AND name = ?1",
+        params![index],
+        |row| row.get(0),
+    )
+}
+
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names.iter().any(|name| name == column))
+}
+
+/// CREATE TABLE IF NOT EXISTS leaves tables from older databases untouched,
+/// so columns added later have to be bolted on explicitly.
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
+// A stored id that isn't a UUID, reported as a failed conversion of its column
+pub(crate) fn parse_id(column: usize, id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+pub(crate) fn id_column(row: &Row, column: usize) -> Result<Uuid> {
+    parse_id(column, &row.get::<_, String>(column)?)
+}
+
+// A Citation_Links span as (start, end), when one was located
+pub(crate) fn span_columns(row: &Row, start: usize) -> Result<Option<(usize, usize)>> {
+    let (Some(from), Some(to)) = (row.get::<_, Option<i64>>(start)?, row.get::<_, Option<i64>>(start + 1)?) else {
+        return Ok(None);
+    };
+    Ok(Some((from as usize, to as usize)))
+}
+
+// A stored name this build doesn't know, such as a verdict or debate side
+pub(crate) fn unknown_value(column: usize, value: &str) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, format!("unknown value `{}`", value).into())
+}
+
+/// Reads a stored olog regardless of namespace; see `load_olog` for user-facing lookups
+pub fn read_olog_from_db(conn: &Connection, olog_id: Uuid) -> Result<Olog> {
+    let mut stmt = conn.prepare("SELECT title FROM Ologs WHERE olog_id = ?1")?;
+    let olog_title: String = stmt.query_row(params![olog_id.to_string()], |row| row.get(0))?;
+
+    let mut stmt = conn.prepare("SELECT node_id, label FROM Nodes WHERE olog_id = ?1")?;
+    let nodes_iter = stmt.query_map(params![olog_id.to_string()], |row| {
+        Ok(Node { id: id_column(row, 0)?, label: row.get(1)? })
+    })?;
+
+    let nodes: Vec<Node> = nodes_iter
+        .into_iter()
+        .filter_map(|result| result.ok())  // Handle each row's result
+        .collect();
+
+    // Links and citations for every hyperedge come in one query each and are
+    // attached in memory, rather than three queries per hyperedge
+    let nodes_by_id: HashMap<Uuid, &Node> = nodes.iter().map(|node| (node.id, node)).collect();
+    let mut stmt = conn.prepare(
+        "SELECT Hyperedge_Links.hyperedge_id, Hyperedge_Links.node_id, Hyperedge_Links.role, Hyperedge_Links.type
+         FROM Hyperedge_Links
+         JOIN Hyperedges ON Hyperedges.hyperedge_id = Hyperedge_Links.hyperedge_id
+         WHERE Hyperedges.olog_id = ?1
+         ORDER BY Hyperedge_Links.position, Hyperedge_Links.rowid",
+    )?;
+    type Endpoints = Vec<(Node, Option<String>)>;
+    let mut links: HashMap<Uuid, (Endpoints, Endpoints)> = HashMap::new();
+    for row in stmt.query_map(params![olog_id.to_string()], |row| {
+        let node_id = id_column(row, 1)?;
+        let node = nodes_by_id.get(&node_id).map(|&node| node.clone()).ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        Ok((id_column(row, 0)?, node, row.get::<_, Option<String>>(2)?, row.get::<_, String>(3)?))
+    })? {
+        let (hyperedge_id, node, role, kind) = row?;
+        let (sources, targets) = links.entry(hyperedge_id).or_default();
+        match kind.as_str() {
+            "source" => sources.push((node, role)),
+            "target" => targets.push((node, role)),
+            _ => {},
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT Citation_Links.hyperedge_id, Citations.citation_id, Citations.title, Citations.label,
+                COALESCE(Citations.text, Documents.text), Citation_Links.span_start, Citation_Links.span_end
+         FROM Citation_Links
+         JOIN Hyperedges ON Hyperedges.hyperedge_id = Citation_Links.hyperedge_id
+         JOIN Citations ON Citations.citation_id = Citation_Links.citation_id
+         LEFT JOIN Documents ON Documents.document_id = Citations.document_id
+         WHERE Hyperedges.olog_id = ?1
+         ORDER BY Citation_Links.citation_id",
+    )?;
+    let mut rows = stmt.query(params![olog_id.to_string()])?;
+    // Most hyperedges cite the same few documents, so each text is decompressed once
+    let mut citations_by_id: HashMap<Uuid, Citation> = HashMap::new();
+    let mut cited: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    let mut spans: HashMap<Uuid, Vec<Span>> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let citation_id = id_column(row, 1)?;
+        if let Entry::Vacant(entry) = citations_by_id.entry(citation_id) {
+            entry.insert(Citation {
+                id: citation_id,
+                title: row.get(2)?,
+                label: row.get(3)?,
+                text: citation_text(row, 4)?.unwrap_or_default(),
+            });
+        }
+        let hyperedge_id = id_column(row, 0)?;
+        if let Some((start, end)) = span_columns(row, 5)? {
+            spans.entry(hyperedge_id).or_default().push(Span { citation_id, start, end });
+        }
+        cited.entry(hyperedge_id).or_default().push(citation_id);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT hyperedge_id, label, quantity_value, quantity_unit, quantity_uncertainty
+         FROM Hyperedges WHERE olog_id = ?1 ORDER BY hyperedge_id",
+    )?;
+    let hyperedges_iter = stmt.query_map(params![olog_id.to_string()], |row| {
+        let hyperedge_id = id_column(row, 0)?;
+        let (sources, targets) = links.remove(&hyperedge_id).unwrap_or_default();
+        let (sources, source_roles): (Vec<Node>, Vec<Option<String>>) = sources.into_iter().unzip();
+        let (targets, target_roles): (Vec<Node>, Vec<Option<String>>) = targets.into_iter().unzip();
+        let citations = cited.remove(&hyperedge_id).unwrap_or_default().iter()
+            .map(|citation_id| citations_by_id[citation_id].clone())
+            .collect();
+
+        let quantity = row.get::<_, Option<f64>>(2)?.map(|value| -> rusqlite::Result<Quantity> {
+            Ok(Quantity { value, unit: row.get(3)?, uncertainty: row.get(4)? })
+        }).transpose()?;
+
+        Ok(Hyperedge {
+            id: hyperedge_id,
+            label: row.get(1)?,
+            source: sources,
+            target: targets,
+            source_roles,
+            target_roles,
+            citations,
+            quantity,
+            spans: spans.remove(&hyperedge_id).unwrap_or_default(),
+        })
+    })?;
+
+    let hyperedges: Vec<Hyperedge> = hyperedges_iter
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Olog { id: olog_id, title: olog_title, nodes, hyperedges })
+}
+
+/// Every row is keyed by the ids assigned when the olog was generated, so
+/// writing the same olog again after a failure leaves no duplicates behind
+pub fn write_olog_to_db(conn: &Connection, olog: &Olog, operation: Operation) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    insert_olog_rows(&tx, olog, operation)?;
+    tx.commit()?;
+    mirror_olog(conn, olog, operation);
+    Ok(())
+}
+
+/// Swaps the stored rows of an olog for a new state under the same id
+pub fn replace_olog_in_db(conn: &Connection, olog: &Olog, operation: Operation) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    // The olog keeps its original creation time
+    let created_at: Option<String> = tx.query_row(
+        "SELECT created_at FROM Ologs WHERE olog_id = ?1",
+        params![olog.id.to_string()],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    prune_olog_rows(&tx, olog)?;
+    insert_olog_rows(&tx, olog, operation)?;
+    if let Some(created_at) = created_at {
+        tx.execute("UPDATE Ologs SET created_at = ?1 WHERE olog_id = ?2", params![created_at, olog.id.to_string()])?;
+    }
+    tx.commit()?;
+    mirror_olog(conn, olog, operation);
+    Ok(())
+}
+
+// Pushes a committed write to the optional search index and git repository
+fn mirror_olog(conn: &Connection, olog: &Olog, operation: Operation) {
+    events::emit(Event::OlogStored { olog_id: olog.id });
+    elastic::index_olog(conn, olog.id);
+    gitrepo::record(conn, olog, operation);
+}
+
+// Clears the links of an olog about to be rewritten, along with the nodes and
+// hyperedges its new state drops. Rows that stay keep their provenance,
+// aliases and embeddings, which deleting and reinserting them would cascade
+// away.
+fn prune_olog_rows(conn: &Connection, olog: &Olog) -> Result<()> {
+    let olog_id = olog.id.to_string();
+    conn.execute(
+        "DELETE FROM Hyperedge_Links WHERE hyperedge_id IN (SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1)",
+        params![olog_id],
+    )?;
+    conn.execute(
+        "DELETE FROM Citation_Links WHERE hyperedge_id IN (SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1)",
+        params![olog_id],
+    )?;
+
+    let kept_edges: HashSet<String> = olog.hyperedges.iter().map(|hyperedge| hyperedge.id.to_string()).collect();
+    let kept_nodes: HashSet<String> = olog.nodes.iter().map(|node| node.id.to_string()).collect();
+    for (table, column, kept) in [("Hyperedges", "hyperedge_id", &kept_edges), ("Nodes", "node_id", &kept_nodes)] {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM {} WHERE olog_id = ?1", column, table))?;
+        let stored = stmt.query_map(params![olog_id], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+        for id in stored.into_iter().filter(|id| !kept.contains(id)) {
+            conn.execute(&format!("DELETE FROM {} WHERE {} = ?1", table, column), params![id])?;
+        }
+    }
+    Ok(())
+}
+
+/// Citations are shared between ologs, so only their links are removed here
+pub fn delete_olog_rows(conn: &Connection, olog_id: Uuid) -> Result<()> {
+    let olog_id = olog_id.to_string();
+    conn.execute(
+        "DELETE FROM Hyperedge_Links WHERE hyperedge_id IN (SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1)",
+        params![olog_id],
+    )?;
+    conn.execute(
+        "DELETE FROM Citation_Links WHERE hyperedge_id IN (SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1)",
+        params![olog_id],
+    )?;
+    conn.execute("DELETE FROM Hyperedges WHERE olog_id = ?1", params![olog_id])?;
+    conn.execute("DELETE FROM Nodes WHERE olog_id = ?1", params![olog_id])?;
+    conn.execute("DELETE FROM Ologs WHERE olog_id = ?1", params![olog_id])?;
+    Ok(())
+}
+
+/// Removes an olog and everything hanging off it in one transaction. Citations
+/// are shared, so only those no hyperedge links to anymore are dropped; returns
+/// how many went.
+pub fn delete_olog(conn: &Connection, olog_id: Uuid) -> Result<usize> {
+    let id = olog_id.to_string();
+    let tx = conn.unchecked_transaction()?;
+    // A deleted chapter leaves its book's map out of date; this has to happen
+    // before the chapter rows go, by hand or by cascade
+    tx.execute(
+        "UPDATE Books SET map_current = 0 WHERE olog_id IN (SELECT book_olog_id FROM Book_Chapters WHERE chapter_olog_id = ?1)",
+        params![id],
+    )?;
+    tx.execute(
+        "DELETE FROM Node_Provenance WHERE node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)",
+        params![id],
+    )?;
+    tx.execute(
+        "DELETE FROM Node_Aliases WHERE node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)",
+        params![id],
+    )?;
+    tx.execute(
+        "DELETE FROM Embeddings WHERE node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)",
+        params![id],
+    )?;
+    for table in ["Debate_Objections", "Debate_Evidence", "Debate_Turns"] {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE debate_id IN (SELECT debate_id FROM Debates WHERE olog_id = ?1)", table),
+            params![id],
+        )?;
+    }
+    tx.execute("DELETE FROM Debates WHERE olog_id = ?1", params![id])?;
+    // Argument maps outlive the olog they were linked to, without the links
+    tx.execute(
+        "DELETE FROM Claim_Links WHERE hyperedge_id IN (SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1)",
+        params![id],
+    )?;
+    tx.execute("UPDATE Argument_Maps SET olog_id = NULL WHERE olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Merge_Reviews WHERE olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Edge_Verdicts WHERE olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Ingestions WHERE olog_id = ?1", params![id])?;
+    // Later versions lose their link to a deleted parent rather than point at nothing
+    tx.execute("DELETE FROM Olog_Versions WHERE olog_id = ?1 OR parent_olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Olog_Parents WHERE olog_id = ?1 OR parent_olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Book_Chapters WHERE book_olog_id = ?1 OR chapter_olog_id = ?1", params![id])?;
+    tx.execute("DELETE FROM Books WHERE olog_id = ?1", params![id])?;
+
+    // Only this olog's citations may go; orphans it never linked to, in this
+    // namespace or another, are left for `olog fsck`
+    let linked = {
+        let mut stmt = tx.prepare(
+            "SELECT DISTINCT Citation_Links.citation_id, Citations.document_id FROM Citation_Links
+             JOIN Hyperedges ON Hyperedges.hyperedge_id = Citation_Links.hyperedge_id
+             LEFT JOIN Citations ON Citations.citation_id = Citation_Links.citation_id
+             WHERE Hyperedges.olog_id = ?1",
+        )?;
+        let linked = stmt
+            .query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        linked
+    };
+    delete_olog_rows(&tx, olog_id)?;
+
+    let mut citations = 0;
+    for (citation_id, document_id) in linked {
+        let still_cited: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM Citation_Links WHERE citation_id = ?1)",
+            params![citation_id],
+            |row| row.get(0),
+        )?;
+        if still_cited {
+            continue;
+        }
+        tx.execute("DELETE FROM Citation_Metadata WHERE citation_id = ?1", params![citation_id])?;
+        tx.execute("DELETE FROM Redactions WHERE citation_id = ?1", params![citation_id])?;
+        citations += tx.execute("DELETE FROM Citations WHERE citation_id = ?1", params![citation_id])?;
+        if let Some(document_id) = document_id {
+            tx.execute(
+                "DELETE FROM Documents WHERE document_id = ?1 AND NOT EXISTS (SELECT 1 FROM Citations WHERE document_id = ?1)",
+                params![document_id],
+            )?;
+        }
+    }
+    search::unindex_olog(&tx, olog_id)?;
+    tx.commit()?;
+    Ok(citations)
+}
+
+// Rewriting an olog is idempotent, but a row id already used by another olog
+// fails as the primary key would without the upsert
+fn owned_elsewhere(table: &str, id: Uuid) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY),
+        Some(format!("{} {} belongs to another olog", table, id)),
+    )
+}
+
+fn insert_olog_rows(conn: &Connection, olog: &Olog, operation: Operation) -> Result<()> {
+    conn.execute(
+        "INSERT INTO Ologs (olog_id, title, namespace, created_at, kind)
+         VALUES (?1, ?2, (SELECT namespace FROM temp.Session), CURRENT_TIMESTAMP, ?3)
+         ON CONFLICT(olog_id) DO UPDATE SET title = excluded.title",
+        params![olog.id.to_string(), olog.title, OlogKind::of(operation).name()],
+    )?;
+
+    for node in &olog.nodes {
+        let written = conn.execute(
+            "INSERT INTO Nodes (node_id, label, olog_id) VALUES (?1, ?2, ?3)
+             ON CONFLICT(node_id) DO UPDATE SET label = excluded.label WHERE Nodes.olog_id = excluded.olog_id",
+            params![node.id.to_string(), node.label, olog.id.to_string()],
+        )?;
+        if written == 0 {
+            return Err(owned_elsewhere("Node", node.id));
+        }
+    }
+
+    for hyperedge in &olog.hyperedges {
+        let written = conn.execute(
+            "INSERT INTO Hyperedges (hyperedge_id, label, olog_id, quantity_value, quantity_unit, quantity_uncertainty)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(hyperedge_id) DO UPDATE SET
+                label = excluded.label,
+                quantity_value = excluded.quantity_value,
+                quantity_unit = excluded.quantity_unit,
+                quantity_uncertainty = excluded.quantity_uncertainty
+             WHERE Hyperedges.olog_id = excluded.olog_id",
+            params![
+                hyperedge.id.to_string(),
+                hyperedge.label,
+                olog.id.to_string(),
+                hyperedge.quantity.as_ref().map(|quantity| quantity.value),
+                hyperedge.quantity.as_ref().and_then(|quantity| quantity.unit.clone()),
+                hyperedge.quantity.as_ref().and_then(|quantity| quantity.uncertainty),
+            ],
+        )?;
+        if written == 0 {
+            return Err(owned_elsewhere("Hyperedge", hyperedge.id));
+        }
+
+        for citation in &hyperedge.citations {
+            let document_id = store_document(conn, &citation.text)?;
+            conn.execute(
+                "INSERT OR IGNORE INTO Citations (citation_id, title, label, document_id) VALUES (?1, ?2, ?3, ?4)",
+                params![citation.id.to_string(), citation.title, citation.label, document_id],
+            )?;
+            let span = hyperedge.spans.iter().find(|span| span.citation_id == citation.id);
+            conn.execute(
+                "INSERT INTO Citation_Links (hyperedge_id, citation_id, span_start, span_end) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(hyperedge_id, citation_id) DO UPDATE SET span_start = excluded.span_start, span_end = excluded.span_end",
+                params![
+                    hyperedge.id.to_string(),
+                    citation.id.to_string(),
+                    span.map(|span| span.start as i64),
+                    span.map(|span| span.end as i64),
+                ],
+            )?;
+        }
+
+        for (position, source) in hyperedge.source.iter().enumerate() {
+            let role = hyperedge.source_roles.get(position).cloned().flatten();
+            conn.execute(
+                "INSERT INTO Hyperedge_Links (hyperedge_id, node_id, type, role, position) VALUES (?1, ?2, 'source', ?3, ?4)
+                 ON CONFLICT(hyperedge_id, type, position) DO UPDATE SET node_id = excluded.node_id, role = excluded.role",
+                params![hyperedge.id.to_string(), source.id.to_string(), role, position as i64],
+            )?;
+        }
+
+        for (position, target) in hyperedge.target.iter().enumerate() {
+            let role = hyperedge.target_roles.get(position).cloned().flatten();
+            conn.execute(
+                "INSERT INTO Hyperedge_Links (hyperedge_id, node_id, type, role, position) VALUES (?1, ?2, 'target', ?3, ?4)
+                 ON CONFLICT(hyperedge_id, type, position) DO UPDATE SET node_id = excluded.node_id, role = excluded.role",
+                params![hyperedge.id.to_string(), target.id.to_string(), role, position as i64],
+            )?;
+        }
+    }
+
+    search::index_olog(conn, olog)
+}
+
+#[derive(Debug)]
+pub struct DocumentKey {
+    pub content_hash: String,
+    pub doi: Option<String>,
+    pub arxiv_id: Option<String>,
+}
+
+/// Content hash plus DOI and arXiv id, used to recognize a document ingested before
+pub fn document_key(text: &str) -> DocumentKey {
+    let content_hash = format!("{:x}", Sha256::digest(text.trim().as_bytes()));
+
+    // Only look at the front matter; the references section is full of other papers' ids
+    let head: String = text.chars().take(4000).collect();
+    let doi_re = Regex::new(r"\b(10\.\d{4,9}/[-._;()/:A-Za-z0-9]+[A-Za-z0-9])").unwrap();
+    let arxiv_re = Regex::new(r"(?i)(?:arxiv:\s*|arxiv\.org/abs/)(\d{4}\.\d{4,5})").unwrap();
+
+    DocumentKey {
+        content_hash,
+        doi: doi_re.captures(&head).map(|c| c[1].to_lowercase()),
+        arxiv_id: arxiv_re.captures(&head).map(|c| c[1].to_string()),
+    }
+}
+
+/// Returns the most recently ingested olog for the same document, if any
+pub fn find_ingested_olog(conn: &Connection, key: &DocumentKey) -> Result<Option<Uuid>> {
+    let olog_id: Option<String> = conn.query_row(
+        "SELECT Ingestions.olog_id FROM Ingestions
+         JOIN Ologs ON Ologs.olog_id = Ingestions.olog_id
+         WHERE Ologs.namespace = (SELECT namespace FROM temp.Session)
+           AND (content_hash = ?1
+            OR (?2 IS NOT NULL AND doi = ?2)
+            OR (?3 IS NOT NULL AND arxiv_id = ?3))
+         ORDER BY Ingestions.rowid DESC LIMIT 1",
+        params![key.content_hash, key.doi, key.arxiv_id],
+        |row| row.get(0),
+    ).optional()?;
+
+    olog_id
+        .map(|id| parse_id(0, &id))
+        .transpose()
+}
+
+/// Remembers which document an olog came from, and which olog it updates, if any
+pub fn record_ingestion(conn: &Connection, olog_id: Uuid, key: &DocumentKey, parent_olog_id: Option<Uuid>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO Ingestions (olog_id, content_hash, doi, arxiv_id) VALUES (?1, ?2, ?3, ?4)",
+        params![olog_id.to_string(), key.content_hash, key.doi, key.arxiv_id],
+    )?;
+
+    if let Some(parent_olog_id) = parent_olog_id {
+        let parent_version: i64 = conn.query_row(
+            "SELECT version FROM Olog_Versions WHERE olog_id = ?1",
+            params![parent_olog_id.to_string()],
+            |row| row.get(0),
+        ).optional()?.unwrap_or(1);
+
+        conn.execute(
+            "INSERT INTO Olog_Versions (olog_id, parent_olog_id, version) VALUES (?1, ?2, ?3)",
+            params![olog_id.to_string(), parent_olog_id.to_string(), parent_version + 1],
+        )?;
+    }
+    Ok(())
+}
+
+/// Remembers the ologs a merged olog was built from, in merge order
+pub fn record_merge_parents(conn: &Connection, olog_id: Uuid, parents: &[Uuid]) -> Result<()> {
+    for (position, parent) in parents.iter().enumerate() {
+        conn.execute(
+            "INSERT OR REPLACE INTO Olog_Parents (olog_id, parent_olog_id, position) VALUES (?1, ?2, ?3)",
+            params![olog_id.to_string(), parent.to_string(), position as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// The ologs `olog_id` was merged from, if it came from `merge-ologs`
+pub fn merge_parents(conn: &Connection, olog_id: Uuid) -> Result<Vec<Uuid>> {
+    let mut stmt = conn.prepare("SELECT parent_olog_id FROM Olog_Parents WHERE olog_id = ?1 ORDER BY position")?;
+    let parents = stmt.query_map(params![olog_id.to_string()], |row| row.get::<_, String>(0))?;
+    parents
+        .map(|id| id.and_then(|id| parse_id(0, &id)))
+        .collect()
+}
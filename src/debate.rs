@@ -0,0 +1,416 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::db::{id_column, parse_id, unknown_value};
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{distinct_citations, Olog};
+use crate::prompts;
+use crate::split::edge_sentence;
+
+// Characters of each citation's text shown to the debaters
+const CITATION_EXCERPT_CHARS: usize = 1500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Pro,
+    Con,
+}
+
+impl Side {
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Pro => "PRO",
+            Side::Con => "CON",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Side> {
+        [Side::Pro, Side::Con].into_iter().find(|side| side.name() == name)
+    }
+
+    fn stance(self) -> &'static str {
+        match self {
+            Side::Pro => "argue that the claim is true",
+            Side::Con => "argue that the claim is false",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectionKind {
+    Unquoted,
+    Misread,
+    NonSequitur,
+    Circular,
+    Contradiction,
+    Grammar,
+    Other,
+}
+
+impl ObjectionKind {
+    const ALL: [ObjectionKind; 7] = [
+        ObjectionKind::Unquoted,
+        ObjectionKind::Misread,
+        ObjectionKind::NonSequitur,
+        ObjectionKind::Circular,
+        ObjectionKind::Contradiction,
+        ObjectionKind::Grammar,
+        ObjectionKind::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectionKind::Unquoted => "unquoted",
+            ObjectionKind::Misread => "misread",
+            ObjectionKind::NonSequitur => "non-sequitur",
+            ObjectionKind::Circular => "circular",
+            ObjectionKind::Contradiction => "contradiction",
+            ObjectionKind::Grammar => "grammar",
+            ObjectionKind::Other => "other",
+        }
+    }
+
+    fn meaning(self) -> &'static str {
+        match self {
+            ObjectionKind::Unquoted => "a statement that no cited fact backs",
+            ObjectionKind::Misread => "a cited source that doesn't say what the argument claims",
+            ObjectionKind::NonSequitur => "a conclusion that doesn't follow from its facts",
+            ObjectionKind::Circular => "an argument that assumes what it sets out to prove",
+            ObjectionKind::Contradiction => "a statement at odds with itself or with facts used earlier",
+            ObjectionKind::Grammar => "a relied-on fact whose nodes or relation break olog grammar",
+            ObjectionKind::Other => "anything else",
+        }
+    }
+
+    // Kinds the model makes up are kept as `other`
+    fn parse(name: &str) -> ObjectionKind {
+        let name = name.trim().to_lowercase().replace(['_', ' '], "-");
+        ObjectionKind::ALL.into_iter().find(|kind| kind.name() == name).unwrap_or(ObjectionKind::Other)
+    }
+}
+
+// A reviewer with its own epistemic role, described by a prompt file
+#[derive(Debug, Clone)]
+pub struct Persona {
+    pub name: String,
+    pub instructions: String,
+}
+
+impl Persona {
+    pub fn load(name: &str) -> Result<Persona, OlogError> {
+        Ok(Persona { name: name.to_string(), instructions: prompts::load_persona(name)? })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Objection {
+    pub persona: String,
+    pub kind: ObjectionKind,
+    pub text: String,
+    // The hyperedge objected to, if the objection is about one fact
+    pub hyperedge: Option<Uuid>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub round: usize,
+    pub side: Side,
+    pub argument: String,
+    // Hyperedges the argument relies on
+    pub evidence: Vec<Uuid>,
+    pub objections: Vec<Objection>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Debate {
+    pub id: Uuid,
+    pub olog_id: Uuid,
+    pub claim: String,
+    pub model: String,
+    pub turns: Vec<Turn>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TurnResponse {
+    argument: String,
+    #[serde(default)]
+    evidence: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewResponse {
+    #[serde(default)]
+    objections: Vec<ObjectionResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectionResponse {
+    kind: String,
+    objection: String,
+    #[serde(default)]
+    fact: Option<usize>,
+}
+
+// The olog as numbered facts followed by the text they were cited from
+fn evidence_listing(olog: &Olog) -> String {
+    let citations = distinct_citations(olog);
+    let facts = olog.hyperedges.iter().enumerate()
+        .map(|(i, hyperedge)| {
+            let markers = hyperedge.citations.iter()
+                .filter_map(|citation| citations.iter().position(|c| c.id == citation.id))
+                .map(|index| format!("[C{}]", index + 1))
+                .collect::<String>();
+            format!("{}: {} {}", i, edge_sentence(hyperedge), markers)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let sources = citations.iter().enumerate()
+        .map(|(i, citation)| {
+            let excerpt: String = citation.text.chars().take(CITATION_EXCERPT_CHARS).collect();
+            format!("[C{}] {}\n{}", i + 1, citation.title.trim(), excerpt.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("Facts:\n{}\n\nSources:\n{}", facts, sources)
+}
+
+// Objections are part of the transcript so the debaters can answer them
+fn transcript(turns: &[Turn]) -> String {
+    turns.iter()
+        .map(|turn| {
+            let objections = turn.objections.iter()
+                .map(|objection| format!("\n  [{} objects: {}] {}", objection.persona, objection.kind.name(), objection.text.trim()))
+                .collect::<String>();
+            format!("{} (round {}): {}{}", turn.side.name(), turn.round, turn.argument.trim(), objections)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// A saved debate written out as a Markdown document, for generating an olog
+/// of its argument structure: each turn's argument, the facts it relied on
+/// and the objections raised against it
+pub fn transcript_document(debate: &Debate, olog: &Olog) -> String {
+    let fact = |hyperedge_id: Uuid| -> String {
+        olog.hyperedges.iter()
+            .find(|hyperedge| hyperedge.id == hyperedge_id)
+            .map_or_else(|| "a fact since removed from the olog".to_string(), edge_sentence)
+    };
+    let mut document = format!(
+        "# Debate: {}\n\nTwo sides argued whether the claim holds, using only the facts of the ontology log \"{}\".\n",
+        debate.claim.trim(),
+        olog.title.trim()
+    );
+    for turn in &debate.turns {
+        document.push_str(&format!("\n## Round {}, {} side\n\n{}\n", turn.round, turn.side.name(), turn.argument.trim()));
+        if !turn.evidence.is_empty() {
+            document.push_str("\nFacts relied on:\n");
+            for hyperedge_id in &turn.evidence {
+                document.push_str(&format!("- {}\n", fact(*hyperedge_id)));
+            }
+        }
+        for objection in &turn.objections {
+            document.push_str(&format!("\nObjection by the {} ({}): {}\n", objection.persona, objection.kind.name(), objection.text.trim()));
+            if let Some(hyperedge_id) = objection.hyperedge {
+                document.push_str(&format!("The objection is about the fact: {}\n", fact(hyperedge_id)));
+            }
+        }
+    }
+    document
+}
+
+// One persona's objections to the last turn
+fn review(
+    provider: &dyn LlmProvider,
+    olog: &Olog,
+    claim: &str,
+    evidence: &str,
+    turns: &[Turn],
+    persona: &Persona,
+    model: &str,
+) -> Result<Vec<Objection>, OlogError> {
+    let Some(latest) = turns.last() else {
+        return Ok(Vec::new());
+    };
+    let kinds = ObjectionKind::ALL.iter()
+        .map(|kind| format!("\"{}\" ({})", kind.name(), kind.meaning()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let prompt = format!(
+        "{}\n\nYou are reviewing a debate about the claim: \"{}\", argued only from the numbered facts and sources \
+         below, taken from an ontology log about \"{}\". Review only the latest argument, by the {} side. Object only \
+         where your role calls for it; an argument you have no objection to gets none. Each objection has a kind, one \
+         of: {}. Respond with JSON of the form {{\"objections\": [{{\"kind\": \"unquoted\", \"objection\": \"...\", \
+         \"fact\": 3}}]}}, where fact is the number of the fact the objection is about, or null.\n\n{}\n\n\
+         Debate so far:\n{}",
+        persona.instructions, claim, olog.title, latest.side.name(), kinds, evidence, transcript(turns)
+    );
+    let response: ReviewResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed review from {}: {}", persona.name, e)))?;
+    Ok(response.objections.into_iter()
+        .filter(|objection| !objection.objection.trim().is_empty())
+        .map(|objection| Objection {
+            persona: persona.name.clone(),
+            kind: ObjectionKind::parse(&objection.kind),
+            text: objection.objection.trim().to_string(),
+            hyperedge: objection.fact.and_then(|index| olog.hyperedges.get(index)).map(|hyperedge| hyperedge.id),
+        })
+        .collect())
+}
+
+// Two agents take turns, pro first, for the given number of rounds. Each
+// sees the whole transcript so far and may only argue from the olog. After
+// every argument each persona raises its objections, which the next speaker
+// sees. `on_turn` is called as each argument and its objections arrive.
+pub fn run_debate(
+    provider: &dyn LlmProvider,
+    olog: &Olog,
+    claim: &str,
+    rounds: usize,
+    model: &str,
+    personas: &[Persona],
+    mut on_turn: impl FnMut(&Turn),
+) -> Result<Vec<Turn>, OlogError> {
+    let evidence = evidence_listing(olog);
+    let mut turns: Vec<Turn> = Vec::new();
+
+    for round in 1..=rounds {
+        for side in [Side::Pro, Side::Con] {
+            let so_far = if turns.is_empty() {
+                "You speak first.".to_string()
+            } else {
+                format!("Debate so far:\n{}", transcript(&turns))
+            };
+            let prompt = format!(
+                "You are the {} side in a debate about the claim: \"{}\". Your job is to {}. This is round {} of {}.\n\n\
+                 Argue only from the numbered facts and sources below, taken from an ontology log about \"{}\"; do not \
+                 use outside knowledge. Rebut the other side's latest points where the evidence allows, and point out \
+                 where it lacks support. Keep the argument to one or two paragraphs. Respond with JSON of the form \
+                 {{\"argument\": \"...\", \"evidence\": [0, 3]}}, listing the numbers of the facts you relied on.\n\n\
+                 {}\n\n{}",
+                side.name(), claim, side.stance(), round, rounds, olog.title, evidence, so_far
+            );
+            let response: TurnResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+                .map_err(|e| OlogError::Schema(format!("Malformed debate response: {}", e)))?;
+
+            let mut cited: Vec<Uuid> = Vec::new();
+            for hyperedge in response.evidence.into_iter().filter_map(|index| olog.hyperedges.get(index)) {
+                if !cited.contains(&hyperedge.id) {
+                    cited.push(hyperedge.id);
+                }
+            }
+            turns.push(Turn { round, side, argument: response.argument, evidence: cited, objections: Vec::new() });
+            let mut objections = Vec::new();
+            for persona in personas {
+                objections.extend(review(provider, olog, claim, &evidence, &turns, persona, model)?);
+            }
+            let turn = turns.last_mut().expect("just pushed");
+            turn.objections = objections;
+            on_turn(turn);
+        }
+    }
+    Ok(turns)
+}
+
+pub fn record_debate(conn: &Connection, debate: &Debate) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO Debates (debate_id, olog_id, claim, model, created_at) VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)",
+        params![debate.id.to_string(), debate.olog_id.to_string(), debate.claim, debate.model],
+    )?;
+    for (position, turn) in debate.turns.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO Debate_Turns (debate_id, position, round, side, argument) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![debate.id.to_string(), position as i64, turn.round as i64, turn.side.name(), turn.argument],
+        )?;
+        for hyperedge_id in &turn.evidence {
+            tx.execute(
+                "INSERT OR IGNORE INTO Debate_Evidence (debate_id, position, hyperedge_id) VALUES (?1, ?2, ?3)",
+                params![debate.id.to_string(), position as i64, hyperedge_id.to_string()],
+            )?;
+        }
+        for (ordinal, objection) in turn.objections.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO Debate_Objections (debate_id, position, ordinal, persona, kind, objection, hyperedge_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    debate.id.to_string(),
+                    position as i64,
+                    ordinal as i64,
+                    objection.persona,
+                    objection.kind.name(),
+                    objection.text,
+                    objection.hyperedge.map(|id| id.to_string())
+                ],
+            )?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn read_debate(conn: &Connection, debate_id: Uuid) -> rusqlite::Result<Option<Debate>> {
+    let header = conn.query_row(
+        "SELECT olog_id, claim, model, created_at FROM Debates WHERE debate_id = ?1",
+        params![debate_id.to_string()],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get(3)?)),
+    ).optional()?;
+    let Some((olog_id, claim, model, created_at)) = header else {
+        return Ok(None);
+    };
+
+    let mut evidence_stmt = conn.prepare(
+        "SELECT hyperedge_id FROM Debate_Evidence WHERE debate_id = ?1 AND position = ?2 ORDER BY rowid",
+    )?;
+    let mut objection_stmt = conn.prepare(
+        "SELECT persona, kind, objection, hyperedge_id FROM Debate_Objections
+         WHERE debate_id = ?1 AND position = ?2 ORDER BY ordinal",
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT position, round, side, argument FROM Debate_Turns WHERE debate_id = ?1 ORDER BY position",
+    )?;
+    let rows = stmt
+        .query_map(params![debate_id.to_string()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut turns = Vec::new();
+    for (position, round, side, argument) in rows {
+        let evidence = evidence_stmt
+            .query_map(params![debate_id.to_string(), position], |row| id_column(row, 0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let objections = objection_stmt
+            .query_map(params![debate_id.to_string(), position], |row| {
+                let hyperedge: Option<String> = row.get(3)?;
+                Ok(Objection {
+                    persona: row.get(0)?,
+                    kind: ObjectionKind::parse(&row.get::<_, String>(1)?),
+                    text: row.get(2)?,
+                    hyperedge: hyperedge
+                        .map(|id| parse_id(3, &id))
+                        .transpose()?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        turns.push(Turn {
+            round: round as usize,
+            side: Side::parse(&side).ok_or_else(|| unknown_value(2, &side))?,
+            argument,
+            evidence,
+            objections,
+        });
+    }
+
+    Ok(Some(Debate {
+        id: debate_id,
+        olog_id: parse_id(0, &olog_id)?,
+        claim,
+        model,
+        turns,
+        created_at,
+    }))
+}
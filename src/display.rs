@@ -0,0 +1,578 @@
+use serde_json::json;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::argmap::ArgumentMap;
+use crate::config::Source;
+use crate::coverage::DocumentCoverage;
+use crate::db::OlogSummary;
+use crate::debate::{Debate, Side, Turn};
+use crate::factcheck::{self, EdgeVerdict, Verdict};
+use crate::format::{self, truncate, Output, OutputFormat, Table};
+use crate::olog::{distinct_citations, Citation, Node, Olog};
+use crate::provenance::NodeOrigin;
+use crate::search::OlogHits;
+use crate::split::edge_sentence;
+use crate::usage::{GroupBy, UsageTotal};
+
+// Longest a citation preview may get before being cut short
+const CITATION_PREVIEW_CHARS: usize = 160;
+
+pub fn print_olog(olog: &Olog, full: bool) {
+    format::print(&olog_output(olog, &[], full), OutputFormat::Table, full);
+}
+
+// `parents` are the ologs this one was merged from, if any
+pub fn olog_output(olog: &Olog, parents: &[Uuid], full: bool) -> Output {
+    let citations: Vec<&Citation> = distinct_citations(olog);
+    let mut output = Output::default();
+    output.field("id", olog.id.to_string());
+    output.field("title", olog.title.clone());
+
+    output.line(format!("Olog {}: {}", olog.id, olog.title));
+    output.line(format!(
+        "{} nodes, {} hyperedges, {} citations",
+        olog.nodes.len(),
+        olog.hyperedges.len(),
+        citations.len()
+    ));
+
+    output.line("\nNodes");
+    let mut nodes = Table::new("nodes", &[("id", "ID"), ("label", "LABEL")]);
+    for node in &olog.nodes {
+        nodes.push(vec![json!(node.id.to_string()), json!(node.label)]);
+    }
+    output.table(nodes);
+
+    output.line("\nHyperedges");
+    let mut shown = Table::new(
+        "hyperedges",
+        &[("sources", "SOURCES"), ("relation", "RELATION"), ("targets", "TARGETS"), ("cited", "CITED")],
+    );
+    let mut hyperedges = Table::new(
+        "hyperedges",
+        &[
+            ("id", "ID"),
+            ("label", "LABEL"),
+            ("sources", "SOURCES"),
+            ("targets", "TARGETS"),
+            ("quantity", "QUANTITY"),
+            ("citations", "CITATIONS"),
+            ("quote", "QUOTE"),
+        ],
+    );
+    for hyperedge in &olog.hyperedges {
+        let with_roles = |nodes: &[Node], roles: &[Option<String>]| -> String {
+            nodes.iter().enumerate()
+                .map(|(i, node)| match roles.get(i).cloned().flatten() {
+                    Some(role) => format!("{} ({})", node.label, role),
+                    None => node.label.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let citation_refs = hyperedge.citations.iter()
+            .filter_map(|citation| citations.iter().position(|c| c.id == citation.id))
+            .map(|index| format!("[{}]", index + 1))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let relation = match &hyperedge.quantity {
+            Some(quantity) => format!("{} = {}", hyperedge.label, quantity),
+            None => hyperedge.label.clone(),
+        };
+
+        shown.push(vec![
+            json!(with_roles(&hyperedge.source, &hyperedge.source_roles)),
+            json!(relation),
+            json!(with_roles(&hyperedge.target, &hyperedge.target_roles)),
+            json!(citation_refs),
+        ]);
+        let ids = |nodes: &[Node]| nodes.iter().map(|node| node.id.to_string()).collect::<Vec<_>>();
+        hyperedges.push(vec![
+            json!(hyperedge.id.to_string()),
+            json!(hyperedge.label),
+            json!(ids(&hyperedge.source)),
+            json!(ids(&hyperedge.target)),
+            json!(hyperedge.quantity.as_ref().map(ToString::to_string)),
+            json!(hyperedge.citations.iter().map(|citation| citation.id.to_string()).collect::<Vec<_>>()),
+            json!(hyperedge.quote()),
+        ]);
+    }
+    output.show(shown);
+    output.record(hyperedges);
+
+    output.line("\nCitations");
+    let mut cited = Table::new("citations", &[("id", "ID"), ("title", "TITLE"), ("label", "LABEL"), ("text", "TEXT")]);
+    for (index, citation) in citations.iter().enumerate() {
+        output.line(format!("  [{}] {} ({})", index + 1, citation.title.trim(), citation.id));
+        output.line(format!("      {}", preview(&citation.label, full)));
+        output.line(format!("      {}", preview(&citation.text, full)));
+        cited.push(vec![
+            json!(citation.id.to_string()),
+            json!(citation.title.trim()),
+            json!(citation.label),
+            json!(citation.text),
+        ]);
+    }
+    output.record(cited);
+
+    if !parents.is_empty() {
+        let names = parents.iter().map(|id| format!("Olog {}", id)).collect::<Vec<_>>().join(" and ");
+        output.line(format!("\nMerged from {}.", names));
+    }
+    output.field("merged_from", parents.iter().map(ToString::to_string).collect::<Vec<_>>());
+    output
+}
+
+// `empty` is printed in place of the table when there is nothing to list
+pub fn olog_list_output(summaries: &[OlogSummary], empty: &str) -> Output {
+    let mut output = Output::default();
+    let mut table = Table::new(
+        "ologs",
+        &[("id", "ID"), ("title", "TITLE"), ("kind", "KIND"), ("nodes", "NODES"), ("hyperedges", "HYPEREDGES"), ("created_at", "CREATED")],
+    );
+    for summary in summaries {
+        table.push(vec![
+            json!(summary.id),
+            json!(summary.title),
+            json!(summary.kind.name()),
+            json!(summary.nodes),
+            json!(summary.hyperedges),
+            json!(summary.created_at),
+        ]);
+    }
+    if summaries.is_empty() {
+        output.line(empty);
+        output.record(table);
+    } else {
+        output.table(table);
+    }
+    output
+}
+
+// Usage totals, then the grand total; costs are estimates at the prices
+// known when each call was recorded
+pub fn usage_output(totals: &[UsageTotal], by: GroupBy) -> Output {
+    let mut output = Output::default();
+    let key = match by {
+        GroupBy::Day => ("day", "DAY"),
+        GroupBy::Model => ("model", "MODEL"),
+        GroupBy::Olog => ("olog_id", "OLOG"),
+        GroupBy::Operation => ("operation", "COMMAND"),
+    };
+    let mut columns = vec![key];
+    if by == GroupBy::Olog {
+        columns.push(("title", "TITLE"));
+    }
+    columns.extend([
+        ("calls", "CALLS"),
+        ("prompt_tokens", "PROMPT TOKENS"),
+        ("completion_tokens", "COMPLETION TOKENS"),
+        ("cost_usd", "COST (USD)"),
+        ("unpriced_calls", "UNPRICED"),
+    ]);
+    let mut table = Table::new("usage", &columns);
+    for total in totals {
+        let mut row = vec![json!(total.key)];
+        if by == GroupBy::Olog {
+            row.push(json!(total.title));
+        }
+        row.extend([
+            json!(total.calls),
+            json!(total.prompt_tokens),
+            json!(total.completion_tokens),
+            json!(format!("{:.4}", total.cost)),
+            json!(total.unpriced_calls),
+        ]);
+        table.push(row);
+    }
+    if totals.is_empty() {
+        output.line("No model calls recorded in this namespace.");
+        output.record(table);
+        return output;
+    }
+    output.table(table);
+    let cost: f64 = totals.iter().map(|total| total.cost).sum();
+    let unpriced: i64 = totals.iter().map(|total| total.unpriced_calls).sum();
+    output.field("total_cost_usd", format!("{:.4}", cost));
+    output.line(format!(
+        "Total: {} calls, ${:.4} estimated{}",
+        totals.iter().map(|total| total.calls).sum::<i64>(),
+        cost,
+        match unpriced {
+            0 => String::new(),
+            n => format!(", not counting {} calls to models without a price (add them under [pricing] in olog.toml)", n),
+        }
+    ));
+    output
+}
+
+// Namespaces with their olog counts, the current one starred
+pub fn namespaces_output(namespaces: &[(String, i64)], current: &str) -> Output {
+    let mut output = Output::default();
+    let mut table = Table::new("namespaces", &[("namespace", "NAMESPACE"), ("ologs", "OLOGS"), ("current", "CURRENT")]);
+    for (name, count) in namespaces {
+        let marker = if name == current { "*" } else { " " };
+        output.line(format!("{} {} ({} ologs)", marker, name, count));
+        table.push(vec![json!(name), json!(count), json!(name == current)]);
+    }
+    output.record(table);
+    output
+}
+
+pub fn search_output(query: &str, found: &[OlogHits]) -> Output {
+    let mut output = Output::default();
+    output.field("query", query);
+    if found.is_empty() {
+        output.line(format!("No matches for \"{}\".", query));
+    }
+    let mut table = Table::new(
+        "hits",
+        &[("item_id", "ID"), ("kind", "KIND"), ("olog_id", "OLOG"), ("olog_title", "TITLE"), ("snippet", "SNIPPET")],
+    );
+    for ologs in found {
+        output.line(format!("{} ({})", ologs.title, ologs.olog_id));
+        for hit in &ologs.hits {
+            output.line(format!("  {:<9}  {}", hit.kind.name(), hit.snippet));
+            table.push(vec![
+                json!(hit.item_id),
+                json!(hit.kind.name()),
+                json!(ologs.olog_id.to_string()),
+                json!(ologs.title),
+                json!(hit.snippet),
+            ]);
+        }
+    }
+    output.record(table);
+    output
+}
+
+// A node's hyperedges, then each generation pass it came from
+pub fn node_output(olog: &Olog, node: &Node, aliases: &[String], origins: &[NodeOrigin]) -> Output {
+    let mut output = Output::default();
+    output.field("id", node.id.to_string());
+    output.field("label", node.label.clone());
+    output.field("olog_id", olog.id.to_string());
+    output.field("olog_title", olog.title.clone());
+    output.field("aliases", aliases.to_vec());
+
+    output.line(format!("{} ({})", node.label, node.id));
+    output.line(format!("Olog: {} ({})", olog.title, olog.id));
+    if !aliases.is_empty() {
+        output.line(format!("Also known as: {}", aliases.join(", ")));
+    }
+    let edges: Vec<_> = olog.hyperedges.iter()
+        .filter(|hyperedge| hyperedge.source.iter().chain(&hyperedge.target).any(|n| n.id == node.id))
+        .collect();
+    output.line(format!("\nHyperedges ({}):", edges.len()));
+    let mut hyperedges = Table::new("hyperedges", &[("id", "ID"), ("sentence", "SENTENCE")]);
+    for hyperedge in edges {
+        output.line(format!("  {}", edge_sentence(hyperedge)));
+        hyperedges.push(vec![json!(hyperedge.id.to_string()), json!(edge_sentence(hyperedge))]);
+    }
+    output.record(hyperedges);
+
+    if origins.is_empty() {
+        output.line("\nNo provenance recorded for this node.");
+    }
+    let mut passes = Table::new(
+        "origins",
+        &[
+            ("pass", "PASS"),
+            ("section", "SECTION"),
+            ("citation_id", "CITATION"),
+            ("document_title", "DOCUMENT"),
+            ("excerpt", "EXCERPT"),
+        ],
+    );
+    for origin in origins {
+        let section = origin.section.as_ref().map(|section| format!(", section \"{}\"", section)).unwrap_or_default();
+        output.line(format!("\nPass {}{} of \"{}\" (citation {})", origin.pass, section, origin.document_title, origin.citation_id));
+        if let Some(excerpt) = &origin.excerpt {
+            output.line(format!("  {}", excerpt));
+        }
+        passes.push(vec![
+            json!(origin.pass),
+            json!(origin.section),
+            json!(origin.citation_id),
+            json!(origin.document_title),
+            json!(origin.excerpt),
+        ]);
+    }
+    output.record(passes);
+    output
+}
+
+// Collapses whitespace so multi-line document text fits on one line
+fn preview(text: &str, full: bool) -> String {
+    if full {
+        return text.trim().to_string();
+    }
+
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let total = collapsed.chars().count();
+    if total <= CITATION_PREVIEW_CHARS {
+        collapsed
+    } else {
+        format!("{} ({} chars, use --full to show all)", truncate(&collapsed, CITATION_PREVIEW_CHARS), total)
+    }
+}
+
+// Overall share of the source text represented, then each document's
+// sections and the ones nothing was drawn from
+pub fn coverage_output(olog: &Olog, documents: &[DocumentCoverage]) -> Output {
+    let covered: usize = documents.iter().map(DocumentCoverage::covered_chars).sum();
+    let total: usize = documents.iter().map(DocumentCoverage::total_chars).sum();
+    let mut output = Output::default();
+    output.field("olog_id", olog.id.to_string());
+    output.field("title", olog.title.clone());
+    output.field("covered_chars", covered);
+    output.field("total_chars", total);
+
+    output.line(format!("Olog {}: {}", olog.id, olog.title));
+    output.line(format!(
+        "{:.0}% of the source text is represented ({} of {} characters, {} document{}).",
+        if total == 0 { 0.0 } else { covered as f64 / total as f64 * 100.0 },
+        covered,
+        total,
+        documents.len(),
+        if documents.len() == 1 { "" } else { "s" }
+    ));
+
+    let mut sections = Table::new(
+        "sections",
+        &[
+            ("citation_id", "CITATION"),
+            ("heading", "SECTION"),
+            ("start", "START"),
+            ("end", "END"),
+            ("covered_chars", "COVERED"),
+            ("total_chars", "TOTAL"),
+            ("hyperedges", "HYPEREDGES"),
+            ("candidate", "CANDIDATE"),
+        ],
+    );
+    let mut unplaced = Table::new("unplaced", &[("hyperedge_id", "HYPEREDGE"), ("citation_id", "CITATION")]);
+    for document in documents {
+        let title = if document.title.is_empty() { "Untitled" } else { &document.title };
+        output.line(format!("\n{} ({:.0}%)", title, document.fraction() * 100.0));
+        output.line(format!("  {}", document.citation_id));
+        let mut shown = Table::new(
+            "sections",
+            &[("heading", "SECTION"), ("chars", "CHARS"), ("covered", "COVERED"), ("hyperedges", "HYPEREDGES")],
+        );
+        let candidates = document.candidates();
+        for section in &document.sections {
+            shown.push(vec![
+                json!(section.heading),
+                json!(format!("{}–{}", section.range.start, section.range.end)),
+                json!(format!("{:.0}%", section.fraction() * 100.0)),
+                json!(section.hyperedges),
+            ]);
+            sections.push(vec![
+                json!(document.citation_id.to_string()),
+                json!(section.heading),
+                json!(section.range.start),
+                json!(section.range.end),
+                json!(section.covered_chars),
+                json!(section.total_chars),
+                json!(section.hyperedges),
+                json!(candidates.iter().any(|candidate| std::ptr::eq(*candidate, section))),
+            ]);
+        }
+        output.show(shown);
+        if !document.unplaced.is_empty() {
+            output.line(format!("  {} hyperedge(s) could not be placed in the text.", document.unplaced.len()));
+        }
+        for hyperedge_id in &document.unplaced {
+            unplaced.push(vec![json!(hyperedge_id.to_string()), json!(document.citation_id.to_string())]);
+        }
+        if !candidates.is_empty() {
+            output.line("  Unrepresented, candidates for regeneration:");
+            for section in candidates {
+                output.line(format!("  - {} (chars {}–{})", section.heading, section.range.start, section.range.end));
+            }
+        }
+    }
+    output.record(sections);
+    output.record(unplaced);
+    output
+}
+
+// Counts per verdict, then every hyperedge that wasn't found supported with
+// the model's reason
+pub fn print_fact_check(olog: &Olog, verdicts: &[EdgeVerdict]) {
+    let overall = factcheck::overall(verdicts);
+    let count = |verdict: Verdict| overall.iter().filter(|edge| edge.verdict == verdict).count();
+    println!(
+        "{} hyperedges checked: {} supported, {} contradicted, {} unverifiable.",
+        overall.len(),
+        count(Verdict::Supported),
+        count(Verdict::Contradicted),
+        count(Verdict::Unverifiable)
+    );
+
+    let mut doubtful: Vec<&&EdgeVerdict> = overall.iter().filter(|edge| edge.verdict != Verdict::Supported).collect();
+    // Contradicted first
+    doubtful.sort_by_key(|edge| std::cmp::Reverse(edge.verdict));
+    for edge in doubtful {
+        let sentence = olog.hyperedges.iter()
+            .find(|hyperedge| hyperedge.id == edge.hyperedge_id)
+            .map_or_else(|| format!("hyperedge {} (no longer in the olog)", edge.hyperedge_id), edge_sentence);
+        println!("\n{} ({:.0}%): {}", edge.verdict.name().to_uppercase(), edge.confidence * 100.0, sentence);
+        println!("  {}", edge.hyperedge_id);
+        if !edge.reason.is_empty() {
+            println!("  {}", edge.reason);
+        }
+    }
+}
+
+// A debate turn with the hyperedges it cited and where those came from
+pub fn print_debate_turn(olog: &Olog, turn: &Turn) {
+    let mut output = Output::default();
+    debate_turn_lines(&mut output, olog, turn);
+    format::print(&output, OutputFormat::Table, false);
+}
+
+fn debate_turn_lines(output: &mut Output, olog: &Olog, turn: &Turn) {
+    if turn.side == Side::Pro {
+        output.line(format!("\nRound {}", turn.round));
+    }
+    output.line(format!("\n{}: {}", turn.side.name(), turn.argument.trim()));
+    for hyperedge_id in &turn.evidence {
+        let Some(hyperedge) = olog.hyperedges.iter().find(|hyperedge| hyperedge.id == *hyperedge_id) else {
+            output.line(format!("  - hyperedge {} (no longer in the olog)", hyperedge_id));
+            continue;
+        };
+        output.line(format!("  - {} ({})", edge_sentence(hyperedge), hyperedge.id));
+        for citation in &hyperedge.citations {
+            output.line(format!("      cites {} ({})", citation.title.trim(), citation.id));
+        }
+    }
+    for objection in &turn.objections {
+        output.line(format!("  ! {} ({}): {}", objection.persona, objection.kind.name(), objection.text));
+        if let Some(hyperedge) = objection.hyperedge.and_then(|id| olog.hyperedges.iter().find(|hyperedge| hyperedge.id == id)) {
+            output.line(format!("      about {}", edge_sentence(hyperedge)));
+        }
+    }
+}
+
+// A saved debate replayed turn by turn
+pub fn debate_output(debate: &Debate, olog: &Olog) -> Output {
+    let mut output = Output::default();
+    output.field("id", debate.id.to_string());
+    output.field("claim", debate.claim.clone());
+    output.field("olog_id", olog.id.to_string());
+    output.field("olog_title", olog.title.clone());
+    output.field("model", debate.model.clone());
+    output.field("created_at", debate.created_at.clone());
+
+    output.line(format!("Claim: {}", debate.claim));
+    output.line(format!("Olog: {} ({})", olog.title, olog.id));
+    let created = debate.created_at.as_ref().map(|at| format!(", {}", at)).unwrap_or_default();
+    output.line(format!("Model: {}{}", debate.model, created));
+    let mut turns = Table::new(
+        "turns",
+        &[("round", "ROUND"), ("side", "SIDE"), ("argument", "ARGUMENT"), ("evidence", "EVIDENCE"), ("objections", "OBJECTIONS")],
+    );
+    for turn in &debate.turns {
+        debate_turn_lines(&mut output, olog, turn);
+        let objections: Vec<_> = turn.objections.iter()
+            .map(|objection| json!({
+                "persona": objection.persona,
+                "kind": objection.kind.name(),
+                "text": objection.text,
+                "hyperedge_id": objection.hyperedge.map(|id| id.to_string()),
+            }))
+            .collect();
+        turns.push(vec![
+            json!(turn.round),
+            json!(turn.side.name()),
+            json!(turn.argument.trim()),
+            json!(turn.evidence.iter().map(ToString::to_string).collect::<Vec<_>>()),
+            json!(objections),
+        ]);
+    }
+    output.record(turns);
+    output
+}
+
+// Claims in the order the model gave them, each followed by the relations
+// starting from it and, once linked, the hyperedges it rests on
+pub fn argmap_output(map: &ArgumentMap, olog: Option<&Olog>) -> Output {
+    let mut output = Output::default();
+    output.field("id", map.id.to_string());
+    output.field("title", map.title.clone());
+    output.field("olog_id", map.olog_id.map(|id| id.to_string()));
+    output.field("model", map.model.clone());
+    output.field("created_at", map.created_at.clone());
+
+    output.line(format!("Argument map {}: {}", map.id, map.title));
+    match (map.olog_id, olog) {
+        (Some(_), Some(olog)) => output.line(format!("Linked to Olog {} ({})", olog.title, olog.id)),
+        (Some(olog_id), None) => output.line(format!("Linked to Olog {}", olog_id)),
+        (None, _) => output.line("Not linked to an olog"),
+    }
+    output.line(format!("{} claims, {} relations", map.claims.len(), map.relations.len()));
+
+    let mut claims = Table::new("claims", &[("id", "ID"), ("kind", "KIND"), ("text", "TEXT"), ("hyperedges", "HYPEREDGES")]);
+    for (i, claim) in map.claims.iter().enumerate() {
+        output.line(format!("\n[{}] {}: {}", i + 1, claim.kind.name(), claim.text));
+        output.line(format!("    {}", claim.id));
+        for relation in map.relations.iter().filter(|relation| relation.source == claim.id) {
+            let target = map.claims.iter().position(|claim| claim.id == relation.target);
+            output.line(format!("  {} [{}]", relation.kind.name(), target.map_or_else(|| "?".to_string(), |index| (index + 1).to_string())));
+        }
+        for hyperedge_id in &claim.hyperedges {
+            match olog.and_then(|olog| olog.hyperedges.iter().find(|hyperedge| hyperedge.id == *hyperedge_id)) {
+                Some(hyperedge) => output.line(format!("  - {} ({})", edge_sentence(hyperedge), hyperedge.id)),
+                None => output.line(format!("  - hyperedge {} (no longer in the olog)", hyperedge_id)),
+            }
+        }
+        claims.push(vec![
+            json!(claim.id.to_string()),
+            json!(claim.kind.name()),
+            json!(claim.text),
+            json!(claim.hyperedges.iter().map(ToString::to_string).collect::<Vec<_>>()),
+        ]);
+    }
+    output.record(claims);
+
+    let mut relations = Table::new("relations", &[("source", "SOURCE"), ("kind", "KIND"), ("target", "TARGET")]);
+    for relation in &map.relations {
+        relations.push(vec![json!(relation.source.to_string()), json!(relation.kind.name()), json!(relation.target.to_string())]);
+    }
+    output.record(relations);
+    output
+}
+
+// Effective settings with the layer each came from; `file` is the user config
+// file and `project` the project's olog.toml, each with whether it exists
+pub fn config_output(
+    file: Option<(&Path, bool)>,
+    project: (&Path, bool),
+    settings: &[(&'static str, Option<String>, Source)],
+) -> Output {
+    let mut output = Output::default();
+    output.field("config_file", file.map(|(path, _)| path.display().to_string()));
+    output.field("config_file_exists", file.is_some_and(|(_, exists)| exists));
+    output.field("project_file", project.0.display().to_string());
+    output.field("project_file_exists", project.1);
+
+    output.line(match file {
+        Some((path, true)) => format!("Config file: {}", path.display()),
+        Some((path, false)) => format!("Config file: {} (not found)", path.display()),
+        None => "Config file: none (no home directory)".to_string(),
+    });
+    output.line(match project {
+        (path, true) => format!("Project file: {}", path.display()),
+        (path, false) => format!("Project file: {} (not found)", path.display()),
+    });
+    let mut table = Table::new("settings", &[("setting", "SETTING"), ("value", "VALUE"), ("source", "SOURCE")]);
+    for (setting, value, source) in settings {
+        table.push(vec![json!(setting), json!(value), json!(source.name())]);
+    }
+    output.table(table);
+    output
+}
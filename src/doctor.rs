@@ -0,0 +1,302 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::db::{create_olog_tables, open_database};
+use crate::error::{CliError, ErrorCategory, OlogError};
+use crate::prompts::{self, Template};
+use crate::provider::{self, Provider};
+use crate::{config, elastic, gitrepo, hooks, http, llama, migrations, preset};
+
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Status {
+    Ok(String),
+    Skipped(String),
+    // Problem and how to fix it
+    Failed(String, String),
+}
+
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn print(&mut self, name: &str, status: Status) {
+        match status {
+            Status::Ok(detail) => println!("[ok]   {}: {}", name, detail),
+            Status::Skipped(detail) => println!("[skip] {}: {}", name, detail),
+            Status::Failed(problem, fix) => {
+                self.failures += 1;
+                println!("[FAIL] {}: {}\n       -> {}", name, problem, fix);
+            },
+        }
+    }
+}
+
+// Reports every check instead of stopping at the first failure, so one run
+// lists everything a new setup is missing
+pub fn run_checks(preset_name: &str, db_path: &Path, local_model: Option<&Path>) -> Result<(), CliError> {
+    let mut report = Report { failures: 0 };
+
+    let kind = provider::current_kind();
+    let api_key = config::openai_api_key();
+    let (api_key, source) = match api_key {
+        Ok(key) => (key.value, key.source),
+        Err(e) => {
+            report.print("User config", Status::Failed(e.to_string(), format!("fix or remove {}", config::display_path())));
+            (None, config::Source::Default)
+        },
+    };
+    report.print("API key", match (&api_key, local_model) {
+        (Some(key), _) if source == config::Source::File => {
+            Status::Ok(format!("openai_api_key is set in {} ({})", config::display_path(), config::mask(key)))
+        },
+        (Some(key), _) => Status::Ok(format!("OPENAI_API_KEY is set ({})", config::mask(key))),
+        (None, _) if kind == Provider::Ollama => Status::Skipped("not needed with --provider ollama".to_string()),
+        (None, _) if kind != Provider::Openai => {
+            Status::Skipped(format!("not needed with --provider {}, except for embeddings", kind.name()))
+        },
+        (None, _) => Status::Failed(
+            "OPENAI_API_KEY is not set".to_string(),
+            format!(
+                "export OPENAI_API_KEY=sk-..., or set openai_api_key in {} (see https://platform.openai.com/api-keys)",
+                config::display_path()
+            ),
+        ),
+    });
+
+    let preset = preset::resolve(preset_name);
+    report.print("Config", match &preset {
+        Ok(preset) => Status::Ok(format!("preset `{}` uses {} with {} generations", preset_name, preset.model, preset.count)),
+        Err(e) => Status::Failed(e.to_string(), "fix the [presets] section of olog.toml or pick another --preset".to_string()),
+    });
+
+    let used = |preset: &preset::Preset| -> Vec<String> {
+        let mut used = vec![preset.model.clone()];
+        used.extend(preset.label_model.iter().filter(|model| **model != preset.model).cloned());
+        used
+    };
+    match (&api_key, local_model) {
+        _ if kind == Provider::Anthropic => {
+            report.print("Provider", match config::anthropic_api_key() {
+                Ok(key) => match key.value {
+                    Some(key) => Status::Ok(format!("sends model calls to Anthropic ({})", config::mask(&key))),
+                    None => Status::Failed(
+                        format!("{} is not set", config::ANTHROPIC_API_KEY_ENV),
+                        format!("export {}=..., or set anthropic_api_key in {}", config::ANTHROPIC_API_KEY_ENV, config::display_path()),
+                    ),
+                },
+                Err(e) => Status::Failed(e.to_string(), format!("fix or remove {}", config::display_path())),
+            });
+            report.print("Model", Status::Skipped("not checked for anthropic; models come from the preset or --model".to_string()));
+        },
+        _ if kind == Provider::Ollama => {
+            let models = config::ollama_host().map_err(|e| (e.to_string(), format!("fix or remove {}", config::display_path())))
+                .and_then(|host| list_ollama_models(&host.value).map(|models| (host.value, models)));
+            report.print("Provider", match &models {
+                Ok((host, models)) => Status::Ok(format!("reached {} ({} models pulled)", host, models.len())),
+                Err((problem, fix)) => Status::Failed(problem.clone(), fix.clone()),
+            });
+            report.print("Model", match (&models, &preset) {
+                (Ok((_, models)), Ok(preset)) => {
+                    let pulled = |model: &String| models.iter().any(|name| name == model || *name == format!("{}:latest", model));
+                    match used(preset).into_iter().find(|model| !pulled(model)) {
+                        None => Status::Ok(format!("{} pulled", used(preset).join(" and "))),
+                        Some(missing) => Status::Failed(
+                            format!("{} is not pulled", missing),
+                            format!("run `ollama pull {}`, or pick another model with --model", missing),
+                        ),
+                    }
+                },
+                _ => Status::Skipped("needs a reachable provider and a valid preset".to_string()),
+            });
+        },
+        (_, Some(model_path)) => {
+            let binary = llama::binary();
+            report.print("Provider", match Command::new(&binary).arg("--version").output() {
+                Ok(output) if output.status.success() => Status::Ok(format!("runs models locally with {}", binary)),
+                _ => Status::Failed(
+                    format!("cannot run {}", binary),
+                    format!("install llama.cpp, or point {} at its llama-cli binary", llama::LLAMA_CLI_ENV),
+                ),
+            });
+            report.print("Model", Status::Ok(format!("{} answers every preset's model calls", model_path.display())));
+        },
+        (Some(key), None) => {
+            let base_url = config::openai_base_url().ok().and_then(|url| url.value).unwrap_or_else(|| OPENAI_URL.to_string());
+            let models_url = format!("{}/models", base_url.trim_end_matches('/'));
+            let models = list_models(&models_url, key);
+            report.print("Provider", match &models {
+                Ok(models) => Status::Ok(format!("reached {} ({} models available)", models_url, models.len())),
+                Err((problem, fix)) => Status::Failed(problem.clone(), fix.clone()),
+            });
+            report.print("Model", match (&models, &preset) {
+                (Ok(models), Ok(preset)) => {
+                    let used = used(preset);
+                    match used.iter().find(|model| !models.contains(model)) {
+                        None => Status::Ok(format!(
+                            "{} {} available to this key",
+                            used.join(" and "),
+                            if used.len() > 1 { "are" } else { "is" }
+                        )),
+                        Some(missing) => Status::Failed(
+                            format!("{} is not available to this key", missing),
+                            format!("choose a model your account can use under [presets] in olog.toml, or with {}", preset::MODEL_ENV),
+                        ),
+                    }
+                },
+                _ => Status::Skipped("needs a reachable provider and a valid preset".to_string()),
+            });
+        },
+        (None, None) => {
+            report.print("Provider", Status::Skipped("needs an API key".to_string()));
+            report.print("Model", Status::Skipped("needs an API key".to_string()));
+        },
+    }
+
+    report.print("Schema", check_schema(db_path));
+    report.print("Database", match check_database(db_path) {
+        Ok(()) => Status::Ok(format!("{} is writable", db_path.display())),
+        Err(e) => Status::Failed(
+            format!("cannot write to {}: {}", db_path.display(), e),
+            "run from a directory you can write to, or fix the file's permissions".to_string(),
+        ),
+    });
+
+    for template in [Template::Olog, Template::Title, Template::Label, Template::Quantities, Template::Quotes, Template::Debate] {
+        let name = format!("Prompt {}", prompts::location(template));
+        report.print(&name, match prompts::load(template) {
+            Ok(text) if !text.trim().is_empty() => Status::Ok(format!("{} chars", text.len())),
+            Ok(_) => Status::Failed("template is empty".to_string(), "restore it, or delete it to use the built-in copy".to_string()),
+            Err(e) => Status::Failed(e.to_string(), "fix or remove the file, or drop --prompt-dev or --prompt-dir".to_string()),
+        });
+    }
+
+    for var in [hooks::ON_SUCCESS_VAR, hooks::ON_FAILURE_VAR] {
+        let status = match env::var(var) {
+            Ok(hook) if hook.starts_with("http://") || hook.starts_with("https://") => Status::Ok(format!("posts to {}", hook)),
+            Ok(hook) if !hook.trim().is_empty() => Status::Ok(format!("runs `{}`", hook)),
+            _ => Status::Skipped("not configured".to_string()),
+        };
+        report.print(var, status);
+    }
+
+    report.print("Elasticsearch", match elastic::config() {
+        Ok(Some(config)) => match elastic::request("GET", &config.url, &config).and_then(|request| Ok(request.call()?)) {
+            Ok(_) => Status::Ok(format!("reached {} (index `{}`)", config.url, config.index)),
+            Err(e) => Status::Failed(
+                format!("cannot reach the cluster: {}", e),
+                "check [elasticsearch] url and api_key in olog.toml".to_string(),
+            ),
+        },
+        Ok(None) => Status::Skipped("not configured".to_string()),
+        Err(e) => Status::Failed(e.to_string(), "fix the [elasticsearch] section of olog.toml".to_string()),
+    });
+
+    report.print("Git repository", match gitrepo::config() {
+        Ok(Some(config)) => match Command::new("git").arg("--version").output() {
+            Ok(output) if output.status.success() => Status::Ok(format!("mirrors ologs to {}", config.path.display())),
+            _ => Status::Failed("git is not installed".to_string(), "install git or remove [git] from olog.toml".to_string()),
+        },
+        Ok(None) => Status::Skipped("not configured".to_string()),
+        Err(e) => Status::Failed(e.to_string(), "fix the [git] section of olog.toml".to_string()),
+    });
+
+    if report.failures > 0 {
+        let noun = if report.failures == 1 { "check" } else { "checks" };
+        return Err(CliError::new(ErrorCategory::Config, format!("{} {} failed", report.failures, noun)));
+    }
+    println!("All checks passed.");
+    Ok(())
+}
+
+// Failures come back as (problem, fix)
+fn list_models(models_url: &str, api_key: &str) -> Result<Vec<String>, (String, String)> {
+    let response = http::get(models_url)
+        .map_err(|e| (e.to_string(), format!("fix the proxy settings in {}", config::display_path())))?
+        .set("Authorization", &format!("Bearer {}", api_key))
+        .timeout(REQUEST_TIMEOUT)
+        .call();
+
+    let body = match response {
+        Ok(response) => response.into_string()
+            .map_err(|e| (format!("unreadable response: {}", e), "retry later".to_string()))?,
+        Err(ureq::Error::Status(401, _)) => {
+            return Err(("the API key was rejected".to_string(), "check OPENAI_API_KEY for typos or revocation".to_string()))
+        },
+        Err(ureq::Error::Status(code, response)) => {
+            return Err((
+                format!("{} answered {} {}", models_url, code, response.status_text()),
+                "check the account's status and quota".to_string(),
+            ))
+        },
+        Err(ureq::Error::Transport(e)) => {
+            return Err((format!("cannot reach the provider: {}", e), "check network access and proxy settings".to_string()))
+        },
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| (format!("unexpected model list: {}", e), "retry later".to_string()))?;
+    Ok(json["data"].as_array().into_iter().flatten()
+        .filter_map(|model| model["id"].as_str().map(str::to_string))
+        .collect())
+}
+
+// Schema version of an existing database, without upgrading it
+fn check_schema(db_path: &Path) -> Status {
+    if !db_path.exists() {
+        return Status::Skipped(format!("{} will be created on first use", db_path.display()));
+    }
+    let conn = match open_database(db_path) {
+        Ok(conn) => conn,
+        Err(e) => return Status::Skipped(format!("cannot open {}: {}", db_path.display(), e)),
+    };
+    let (current, latest) = match migrations::current_version(&conn) {
+        Ok(current) => (current, migrations::latest_version()),
+        Err(e) => return Status::Skipped(format!("cannot read the schema version: {}", e)),
+    };
+    match migrations::schema_problem(&conn) {
+        Ok(None) => Status::Ok(format!("version {}", latest.max(current))),
+        Ok(Some(_)) if current > latest => Status::Failed(
+            format!("{} uses schema version {}; this build knows up to {}", db_path.display(), current, latest),
+            "upgrade olog".to_string(),
+        ),
+        Ok(Some(_)) => Status::Failed(
+            format!("{} is at schema version {}; this build needs {}", db_path.display(), current, latest),
+            "run `olog migrate`, which backs the database up first".to_string(),
+        ),
+        Err(e) => Status::Skipped(format!("cannot read the schema version: {}", e)),
+    }
+}
+
+// Creates any missing tables and makes a throwaway write. A database that
+// needs migrating is left alone; the schema check reports it.
+fn check_database(db_path: &Path) -> Result<(), OlogError> {
+    let conn = open_database(db_path)?;
+    if migrations::schema_problem(&conn)?.is_none() {
+        create_olog_tables(&conn)?;
+    }
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("CREATE TABLE Doctor_Probe (id INTEGER)", [])?;
+    tx.rollback()?;
+    Ok(())
+}
+
+fn list_ollama_models(host: &str) -> Result<Vec<String>, (String, String)> {
+    let url = format!("{}/api/tags", host.trim_end_matches('/'));
+    let body = http::get(&url)
+        .map_err(|e| (e.to_string(), format!("fix the proxy settings in {}", config::display_path())))?
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+        .map_err(|e| (format!("cannot reach {}: {}", host, e), format!("start `ollama serve`, or set {}", config::OLLAMA_HOST_ENV)))?
+        .into_string()
+        .map_err(|e| (format!("unreadable response: {}", e), "retry later".to_string()))?;
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| (format!("unexpected model list: {}", e), "check that the host is an Ollama server".to_string()))?;
+    Ok(json["models"].as_array().into_iter().flatten()
+        .filter_map(|model| model["name"].as_str().map(str::to_string))
+        .collect())
+}
@@ -0,0 +1,116 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::db::{current_namespace, has_table, olog_in_namespace};
+use crate::error::OlogError;
+
+const OLOG_HYPEREDGES: &str = "SELECT hyperedge_id FROM Hyperedges WHERE olog_id = ?1";
+
+// Rows belonging to one olog, table by table, in an order that keeps
+// references pointing at rows inserted earlier
+fn selections() -> Vec<(&'static str, String)> {
+    vec![
+        ("Ologs", "olog_id = ?1".to_string()),
+        ("Nodes", "olog_id = ?1".to_string()),
+        ("Hyperedges", "olog_id = ?1".to_string()),
+        (
+            "Documents",
+            format!(
+                "document_id IN (SELECT document_id FROM Citations WHERE citation_id IN
+                 (SELECT citation_id FROM Citation_Links WHERE hyperedge_id IN ({})))",
+                OLOG_HYPEREDGES
+            ),
+        ),
+        (
+            "Citations",
+            format!("citation_id IN (SELECT citation_id FROM Citation_Links WHERE hyperedge_id IN ({}))", OLOG_HYPEREDGES),
+        ),
+        (
+            "Citation_Metadata",
+            format!("citation_id IN (SELECT citation_id FROM Citation_Links WHERE hyperedge_id IN ({}))", OLOG_HYPEREDGES),
+        ),
+        (
+            "Redactions",
+            format!("citation_id IN (SELECT citation_id FROM Citation_Links WHERE hyperedge_id IN ({}))", OLOG_HYPEREDGES),
+        ),
+        ("Hyperedge_Links", format!("hyperedge_id IN ({})", OLOG_HYPEREDGES)),
+        ("Citation_Links", format!("hyperedge_id IN ({})", OLOG_HYPEREDGES)),
+        ("Node_Provenance", "node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)".to_string()),
+        ("Node_Aliases", "node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)".to_string()),
+        ("Embeddings", "node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)".to_string()),
+        ("Debates", "olog_id = ?1".to_string()),
+        ("Debate_Turns", "debate_id IN (SELECT debate_id FROM Debates WHERE olog_id = ?1)".to_string()),
+        ("Debate_Evidence", "debate_id IN (SELECT debate_id FROM Debates WHERE olog_id = ?1)".to_string()),
+        ("Debate_Objections", "debate_id IN (SELECT debate_id FROM Debates WHERE olog_id = ?1)".to_string()),
+        ("Argument_Maps", "olog_id = ?1".to_string()),
+        ("Claims", "argmap_id IN (SELECT argmap_id FROM Argument_Maps WHERE olog_id = ?1)".to_string()),
+        (
+            "Claim_Relations",
+            "source_claim_id IN (SELECT claim_id FROM Claims WHERE argmap_id IN (SELECT argmap_id FROM Argument_Maps WHERE olog_id = ?1))"
+                .to_string(),
+        ),
+        (
+            "Claim_Links",
+            "claim_id IN (SELECT claim_id FROM Claims WHERE argmap_id IN (SELECT argmap_id FROM Argument_Maps WHERE olog_id = ?1))"
+                .to_string(),
+        ),
+        ("Merge_Reviews", "olog_id = ?1".to_string()),
+        ("Edge_Verdicts", "olog_id = ?1".to_string()),
+        ("Ingestions", "olog_id = ?1".to_string()),
+        ("Olog_Versions", "olog_id = ?1".to_string()),
+        ("Olog_Parents", "olog_id = ?1".to_string()),
+        ("Books", "olog_id = ?1".to_string()),
+        ("Book_Chapters", "book_olog_id = ?1".to_string()),
+    ]
+}
+
+// A script that creates any missing tables and inserts the olog's rows in one
+// transaction. Documents, citations, their metadata and redactions may already exist in
+// the target database since they are shared between ologs, so only those are
+// inserted with OR IGNORE.
+pub fn olog_sql_dump(conn: &Connection, olog_id: Uuid) -> Result<String, OlogError> {
+    let id = olog_id.to_string();
+    if !olog_in_namespace(conn, olog_id)? {
+        return Err(OlogError::NotFound(format!("Olog {} not found in namespace `{}`", olog_id, current_namespace(conn)?)));
+    }
+
+    let mut out = format!("-- Olog {}\nBEGIN TRANSACTION;\n", olog_id);
+
+    for (table, filter) in selections() {
+        if !has_table(conn, table)? {
+            continue;
+        }
+        let schema: String = conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![table],
+            |row| row.get(0),
+        )?;
+        // sqlite_master keeps the statement without its IF NOT EXISTS
+        out.push_str(&format!("{};\n", schema.replacen("CREATE TABLE ", "CREATE TABLE IF NOT EXISTS ", 1)));
+
+        let verb = if matches!(table, "Documents" | "Citations" | "Citation_Metadata" | "Redactions") { "INSERT OR IGNORE" } else { "INSERT" };
+        let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {}", table, filter))?;
+        let columns = stmt.column_names().join(", ");
+        let mut rows = stmt.query(params![id])?;
+        while let Some(row) = rows.next()? {
+            let values = (0..row.as_ref().column_count())
+                .map(|i| row.get_ref(i).map(sql_literal))
+                .collect::<Result<Vec<_>, _>>()?;
+            out.push_str(&format!("{} INTO {} ({}) VALUES ({});\n", verb, table, columns, values.join(", ")));
+        }
+    }
+
+    out.push_str("COMMIT;\n");
+    Ok(out)
+}
+
+fn sql_literal(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => format!("'{}'", String::from_utf8_lossy(text).replace('\'', "''")),
+        ValueRef::Blob(bytes) => format!("X'{}'", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>()),
+    }
+}
@@ -0,0 +1,128 @@
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config;
+use crate::error::OlogError;
+use crate::http;
+use crate::ndjson::write_ndjson;
+
+const DEFAULT_INDEX: &str = "olog";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Set under [elasticsearch] in olog.toml; works with OpenSearch too
+#[derive(Debug, Deserialize)]
+pub struct ElasticConfig {
+    pub url: String,
+    #[serde(default = "default_index")]
+    pub index: String,
+    pub api_key: Option<String>,
+    // From OLOG_TIMEOUT_SECS or the user config, not olog.toml
+    #[serde(skip, default = "default_timeout")]
+    pub timeout: Duration,
+}
+
+fn default_index() -> String {
+    DEFAULT_INDEX.to_string()
+}
+
+fn default_timeout() -> Duration {
+    REQUEST_TIMEOUT
+}
+
+pub fn config() -> Result<Option<ElasticConfig>, OlogError> {
+    let Some(mut elasticsearch) = config::project_setting::<ElasticConfig>("elasticsearch")? else {
+        return Ok(None);
+    };
+    elasticsearch.timeout = config::timeout(REQUEST_TIMEOUT)?.value;
+    Ok(Some(elasticsearch))
+}
+
+// Called after every olog write. The database stays the source of truth, so
+// an unreachable cluster only gets a warning.
+pub fn index_olog(conn: &Connection, olog_id: Uuid) {
+    let result = config().and_then(|config| match config {
+        Some(config) => push_olog(conn, &config, olog_id).map(Some),
+        None => Ok(None),
+    });
+    match result {
+        Ok(Some(count)) => println!("Indexed {} documents for Olog {} in Elasticsearch.", count, olog_id),
+        Ok(None) => {},
+        Err(e) => eprintln!("Error indexing Olog {} in Elasticsearch: {}", olog_id, e),
+    }
+}
+
+// Replaces the olog's documents in the index with its current rows. Documents
+// carry the same fields as export-ndjson, keyed by their database id.
+fn push_olog(conn: &Connection, config: &ElasticConfig, olog_id: Uuid) -> Result<usize, OlogError> {
+    // Nodes and hyperedges removed from the olog since the last write
+    delete_documents(config, olog_id)?;
+
+    let mut records = Vec::new();
+    write_ndjson(conn, Some(olog_id), &mut records)?;
+
+    let mut body = String::new();
+    let mut count = 0;
+    for line in String::from_utf8_lossy(&records).lines() {
+        let record: Value = serde_json::from_str(line)?;
+        let action = json!({ "index": { "_index": config.index, "_id": record["id"] } });
+        body.push_str(&format!("{}\n{}\n", action, line));
+        count += 1;
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+
+    let response = request("POST", &format!("{}/_bulk", config.url.trim_end_matches('/')), config)?
+        .set("Content-Type", "application/x-ndjson")
+        .send_string(&body)?
+        .into_string()?;
+
+    // _bulk answers 200 even when individual documents fail
+    let response: Value = serde_json::from_str(&response)?;
+    if response["errors"].as_bool().unwrap_or(false) {
+        let reason = response["items"].as_array().into_iter().flatten()
+            .find_map(|item| item["index"]["error"]["reason"].as_str())
+            .unwrap_or("unknown error");
+        return Err(OlogError::Schema(format!("bulk request rejected: {}", reason)));
+    }
+    Ok(count)
+}
+
+// Called after an olog is deleted
+pub fn remove_olog(olog_id: Uuid) {
+    let result = config().and_then(|config| match config {
+        Some(config) => delete_documents(&config, olog_id),
+        None => Ok(()),
+    });
+    if let Err(e) = result {
+        eprintln!("Error removing Olog {} from Elasticsearch: {}", olog_id, e);
+    }
+}
+
+// Drops the olog's own document and those of its nodes and hyperedges.
+// Citations are shared between ologs and stay.
+fn delete_documents(config: &ElasticConfig, olog_id: Uuid) -> Result<(), OlogError> {
+    let query = json!({
+        "query": { "bool": { "should": [
+            { "match_phrase": { "olog_id": olog_id.to_string() } },
+            { "match_phrase": { "id": olog_id.to_string() } },
+        ] } }
+    });
+    let url = format!("{}/{}/_delete_by_query", config.url.trim_end_matches('/'), config.index);
+    match request("POST", &url, config)?.set("Content-Type", "application/json").send_string(&query.to_string()) {
+        // Nothing to clean up in an index that doesn't exist yet
+        Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn request(method: &str, url: &str, config: &ElasticConfig) -> Result<ureq::Request, OlogError> {
+    let request = http::request(method, url)?.timeout(config.timeout);
+    Ok(match &config.api_key {
+        Some(key) => request.set("Authorization", &format!("ApiKey {}", key)),
+        None => request,
+    })
+}
@@ -0,0 +1,116 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::config;
+use crate::error::OlogError;
+use crate::http;
+use crate::retry;
+use crate::usage;
+
+const EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
+pub const DEFAULT_MODEL: &str = "text-embedding-3-small";
+const OLLAMA_EXAMPLE_MODEL: &str = "nomic-embed-text";
+// The endpoint takes at most 2048 inputs per request
+const BATCH_SIZE: usize = 512;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+    #[serde(default)]
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingUsage {
+    prompt_tokens: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddings {
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    prompt_eval_count: i64,
+}
+
+pub(crate) fn embed_openai(model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+    let api_key = config::require_api_key()?;
+    let timeout = config::timeout(REQUEST_TIMEOUT)?.value;
+    let mut vectors = Vec::with_capacity(texts.len());
+
+    for batch in texts.chunks(BATCH_SIZE) {
+        let body = json!({ "model": model, "input": batch });
+        let response = retry::with_retries("Embedding request", || {
+            Ok(http::post(EMBEDDINGS_URL)?
+                .set("Authorization", &format!("Bearer {}", api_key))
+                .set("Content-Type", "application/json")
+                .timeout(timeout)
+                .send_string(&body.to_string())?
+                .into_string()?)
+        })?;
+
+        let mut response: EmbeddingResponse = serde_json::from_str(&response)
+            .map_err(|e| OlogError::Llm(format!("Malformed embedding response: {}", e)))?;
+        if response.data.len() != batch.len() {
+            return Err(OlogError::Llm(format!("Asked for {} embeddings, got {}", batch.len(), response.data.len())));
+        }
+        if let Some(tokens) = &response.usage {
+            usage::record("openai", model, tokens.prompt_tokens, 0);
+        }
+        response.data.sort_by_key(|data| data.index);
+        vectors.extend(response.data.into_iter().map(|data| data.embedding));
+    }
+
+    Ok(vectors)
+}
+
+pub(crate) fn embed_ollama(host: &str, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+    if model == DEFAULT_MODEL {
+        return Err(OlogError::Config(format!(
+            "{} is an OpenAI model; pass an embedding model pulled into Ollama, such as {}",
+            DEFAULT_MODEL, OLLAMA_EXAMPLE_MODEL
+        )));
+    }
+    let url = format!("{}/api/embed", host.trim_end_matches('/'));
+    let timeout = config::timeout(REQUEST_TIMEOUT)?.value;
+    let mut vectors = Vec::with_capacity(texts.len());
+
+    for batch in texts.chunks(BATCH_SIZE) {
+        let body = json!({ "model": model, "input": batch });
+        let response = retry::with_retries("Embedding request", || {
+            Ok(http::post(&url)?
+                .set("Content-Type", "application/json")
+                .timeout(timeout)
+                .send_string(&body.to_string())?
+                .into_string()?)
+        })?;
+
+        let response: OllamaEmbeddings = serde_json::from_str(&response)
+            .map_err(|e| OlogError::Llm(format!("Malformed embedding response from Ollama: {}", e)))?;
+        if response.embeddings.len() != batch.len() {
+            return Err(OlogError::Llm(format!("Asked for {} embeddings, got {}", batch.len(), response.embeddings.len())));
+        }
+        usage::record(&format!("ollama:{}", host), model, response.prompt_eval_count, 0);
+        vectors.extend(response.embeddings);
+    }
+
+    Ok(vectors)
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
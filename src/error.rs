@@ -0,0 +1,185 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::env;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Other,
+    Usage,
+    Io,
+    Database,
+    Config,
+    Llm,
+    Schema,
+    Network,
+    Ocr,
+}
+
+impl ErrorCategory {
+    // 2 matches clap's own exit code for bad arguments
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorCategory::Other => 1,
+            ErrorCategory::Usage => 2,
+            ErrorCategory::Io => 3,
+            ErrorCategory::Database => 4,
+            ErrorCategory::Config => 5,
+            ErrorCategory::Llm => 6,
+            ErrorCategory::Schema => 7,
+            ErrorCategory::Network => 8,
+            ErrorCategory::Ocr => 9,
+        }
+    }
+
+    fn of(error: &(dyn Error + 'static)) -> Self {
+        if let Some(error) = error.downcast_ref::<OlogError>() {
+            error.category()
+        } else if error.is::<rusqlite::Error>() {
+            ErrorCategory::Database
+        } else if error.is::<std::io::Error>() {
+            ErrorCategory::Io
+        } else if error.is::<env::VarError>() {
+            ErrorCategory::Config
+        } else if error.is::<serde_json::Error>() {
+            ErrorCategory::Schema
+        } else if error.is::<ureq::Error>() {
+            ErrorCategory::Network
+        } else {
+            ErrorCategory::Other
+        }
+    }
+}
+
+/// Everything the library can fail with, by where the failure happened
+#[derive(Debug, thiserror::Error)]
+pub enum OlogError {
+    /// SQLite rejected a statement, or a stored row is malformed
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+    /// Reading or writing a file, or running an external program
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The model answered with nothing usable, or couldn't be run
+    #[error("{0}")]
+    Llm(String),
+    /// An HTTP request failed, to a model API or any other service
+    #[error(transparent)]
+    Network(Box<ureq::Error>),
+    /// Text couldn't be extracted from a document
+    #[error("{0}")]
+    Ocr(String),
+    /// JSON that doesn't parse
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Parsed data that isn't what was expected, such as a reply that isn't an olog
+    #[error("{0}")]
+    Schema(String),
+    /// olog.toml, an environment variable, a preset or a template is wrong or missing
+    #[error("{0}")]
+    Config(String),
+    /// The olog or other record asked for doesn't exist, or isn't visible in
+    /// the current namespace
+    #[error("{0}")]
+    NotFound(String),
+    /// The request can't be carried out as asked, such as merging no ologs
+    #[error("{0}")]
+    Usage(String),
+    /// Another error, with what was being done when it happened
+    #[error("{context}: {source}")]
+    Context { context: String, source: Box<OlogError> },
+    #[error("{0}")]
+    Other(String),
+}
+
+impl OlogError {
+    /// Prefixes the message with `context` while keeping the category
+    pub fn context(self, context: impl Into<String>) -> Self {
+        OlogError::Context { context: context.into(), source: Box::new(self) }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            OlogError::Database(_) => ErrorCategory::Database,
+            OlogError::Io(_) => ErrorCategory::Io,
+            OlogError::Llm(_) => ErrorCategory::Llm,
+            OlogError::Network(_) => ErrorCategory::Network,
+            OlogError::Ocr(_) => ErrorCategory::Ocr,
+            OlogError::Json(_) | OlogError::Schema(_) => ErrorCategory::Schema,
+            OlogError::Config(_) => ErrorCategory::Config,
+            OlogError::NotFound(_) | OlogError::Usage(_) => ErrorCategory::Usage,
+            OlogError::Context { source, .. } => source.category(),
+            OlogError::Other(_) => ErrorCategory::Other,
+        }
+    }
+}
+
+// Boxed, as a ureq error is several times the size of every other variant
+impl From<ureq::Error> for OlogError {
+    fn from(error: ureq::Error) -> Self {
+        OlogError::Network(Box::new(error))
+    }
+}
+
+impl From<env::VarError> for OlogError {
+    fn from(error: env::VarError) -> Self {
+        OlogError::Config(error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct CliError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        CliError { category, message: message.into() }
+    }
+
+    // Prefixes the message while keeping the category of the underlying error
+    pub fn context<E: Into<Box<dyn Error>>>(context: &str, error: E) -> Self {
+        let error = error.into();
+        CliError {
+            category: ErrorCategory::of(&*error),
+            message: format!("{}: {}", context, error),
+        }
+    }
+
+    pub fn report(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Text => eprintln!("{}", self.message),
+            ErrorFormat::Json => {
+                let report = serde_json::json!({
+                    "error": {
+                        "category": self.category,
+                        "code": self.category.exit_code(),
+                        "message": self.message,
+                    }
+                });
+                eprintln!("{}", report);
+            }
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl<E: Error + 'static> From<E> for CliError {
+    fn from(error: E) -> Self {
+        CliError { category: ErrorCategory::of(&error), message: error.to_string() }
+    }
+}
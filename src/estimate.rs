@@ -0,0 +1,128 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::chunk::chunk_document;
+use crate::config;
+use crate::error::OlogError;
+use crate::llm::{compose_prompt, prepare_document, GenerationOptions};
+use crate::prompts::Template;
+use crate::sanitize;
+
+// OpenAI's tokenizers average about four characters per token on English prose
+pub(crate) const CHARS_PER_TOKEN: f64 = 4.0;
+// Expected reply sizes: titles and labels are a line, the olog JSON grows with
+// the document up to the model's output limit
+const SHORT_REPLY_TOKENS: usize = 30;
+const OLOG_REPLY_RATIO: f64 = 0.3;
+const MAX_REPLY_TOKENS: usize = 4096;
+
+// USD per million prompt and completion tokens
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-3.5-turbo-1106", 1.0, 2.0),
+    ("gpt-3.5-turbo-0125", 0.5, 1.5),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("gpt-4-1106-preview", 10.0, 30.0),
+    ("gpt-4-0125-preview", 10.0, 30.0),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 5.0, 15.0),
+    ("text-embedding-3-small", 0.02, 0.0),
+    ("text-embedding-3-large", 0.13, 0.0),
+    ("text-embedding-ada-002", 0.1, 0.0),
+];
+
+#[derive(Debug, Deserialize)]
+struct Price {
+    input: f64,
+    output: f64,
+}
+
+
+#[derive(Debug)]
+pub struct Estimate {
+    pub document_chars: usize,
+    pub document_tokens: usize,
+    // Documents sent per generation: 1, or the number of chunks
+    pub chunks: usize,
+    pub calls: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    // None when a model used has no known price
+    pub cost: Option<f64>,
+    // The first model without a price, if any
+    pub unpriced_model: Option<String>,
+}
+
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.chars().count() as f64 / CHARS_PER_TOKEN).ceil() as usize
+}
+
+// Builds the same prompts a run would send, without sending them. Each
+// generation makes one olog, one title and one label call per chunk.
+pub fn estimate_run(text: &str, options: &GenerationOptions) -> Result<Estimate, OlogError> {
+    let chunks = match &options.chunking {
+        Some(chunking) => chunk_document(text, chunking),
+        None => vec![text.to_string()],
+    };
+
+    let mut document_chars = 0;
+    let mut document_tokens = 0;
+    // (model, prompt tokens, completion tokens) per generation; titles and
+    // labels may go to a different model than the olog
+    let mut calls = [(&options.model, 0, 0), (&options.label_model, 0, 0)];
+    for chunk in &chunks {
+        let sanitized = prepare_document(chunk, options);
+        let document = sanitize::delimit(&sanitized.text);
+        let chunk_tokens = estimate_tokens(&sanitized.text);
+        document_chars += sanitized.text.chars().count();
+        document_tokens += chunk_tokens;
+
+        let prompt = |template| -> Result<usize, OlogError> {
+            Ok(estimate_tokens(sanitize::GUARDRAIL) + estimate_tokens(&compose_prompt(template, &document, options)?))
+        };
+        calls[0].1 += prompt(Template::Olog)?;
+        calls[0].2 += ((chunk_tokens as f64 * OLOG_REPLY_RATIO) as usize).min(MAX_REPLY_TOKENS);
+        calls[1].1 += prompt(Template::Title)? + prompt(Template::Label)?;
+        calls[1].2 += 2 * SHORT_REPLY_TOKENS;
+    }
+
+    let mut cost = Some(0.0);
+    let mut unpriced_model = None;
+    for (model, prompt_tokens, completion_tokens) in calls {
+        match price(model)? {
+            Some((input, output)) => {
+                let call_cost = (prompt_tokens as f64 * input + completion_tokens as f64 * output) / 1_000_000.0;
+                cost = cost.map(|cost| cost + call_cost * options.count as f64);
+            },
+            None => {
+                cost = None;
+                unpriced_model.get_or_insert_with(|| model.clone());
+            },
+        }
+    }
+    let prompt_tokens = calls.iter().map(|(_, prompt_tokens, _)| prompt_tokens).sum::<usize>() * options.count;
+    let completion_tokens = calls.iter().map(|(_, _, completion_tokens)| completion_tokens).sum::<usize>() * options.count;
+
+    Ok(Estimate {
+        document_chars,
+        document_tokens,
+        chunks: chunks.len(),
+        calls: 3 * options.count * chunks.len(),
+        prompt_tokens,
+        completion_tokens,
+        cost,
+        unpriced_model,
+    })
+}
+
+pub(crate) fn price(model: &str) -> Result<Option<(f64, f64)>, OlogError> {
+    // Prices for other models, or newer ones, go under [pricing."<model>"] in olog.toml
+    let pricing = config::project_setting::<HashMap<String, Price>>("pricing")?.unwrap_or_default();
+    if let Some(price) = pricing.get(model) {
+        return Ok(Some((price.input, price.output)));
+    }
+    Ok(PRICES.iter()
+        .find(|(name, _, _)| *name == model)
+        .map(|(_, input, output)| (*input, *output)))
+}
@@ -0,0 +1,44 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Progress of a pipeline run, for frontends that show it their own way.
+/// Chunk, pass and chapter numbers count from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Text extraction from a PDF began
+    OcrStarted { ocr: &'static str, bytes: usize },
+    /// Text extraction finished with this much text
+    OcrCompleted { chars: usize },
+    /// A book chapter is about to be generated
+    ChapterStarted { chapter: usize, chapters: usize, heading: String },
+    /// A document is about to be generated, `passes` times over `chunks` chunks
+    GenerationStarted { passes: usize, chunks: usize },
+    /// One pass over one chunk produced an olog; with parallelism these
+    /// arrive in the order the calls finish
+    ChunkGenerated { pass: usize, chunk: usize, chunks: usize, nodes: usize, hyperedges: usize },
+    /// Every generated olog was merged into one
+    MergeCompleted { nodes: usize, hyperedges: usize },
+    /// The semantic merge folded these many labels into others, or was undone
+    /// by its spot check
+    NodesUnified { merged: usize, undone: bool },
+    /// Synonymous hyperedges were folded together
+    ConsolidationCompleted { merged: usize },
+    /// An olog was written to the database
+    OlogStored { olog_id: Uuid },
+}
+
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+/// Receives every event emitted from now on, from any thread. Dropping the
+/// receiver unsubscribes.
+pub fn subscribe() -> Receiver<Event> {
+    let (sender, receiver) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(sender);
+    receiver
+}
+
+pub(crate) fn emit(event: Event) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
@@ -0,0 +1,501 @@
+use clap::ValueEnum;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::importance::{self, Importance};
+use crate::olog::{distinct_citations, node_citations, Citation, Hyperedge, Node, Olog};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Graphviz graph, one color per source document
+    Dot,
+    /// Standalone page with a source legend, color-coded tables and the cited texts
+    Html,
+    /// GraphML for Gephi, yEd and other graph tools
+    Graphml,
+}
+
+// Colors cycle once a merged olog has more sources than this
+const SOURCE_COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+    "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf",
+];
+const UNSOURCED_COLOR: &str = "#cccccc";
+
+struct Sources<'a> {
+    citations: Vec<&'a Citation>,
+    by_node: HashMap<Uuid, Vec<&'a Citation>>,
+    // Raw and normalized (0..=1) importance per node when sizing is requested
+    scores: Option<(HashMap<Uuid, f64>, HashMap<Uuid, f64>)>,
+    // Prepended to every exported id
+    id_prefix: &'a str,
+}
+
+impl<'a> Sources<'a> {
+    fn new(olog: &'a Olog, size_by: Option<Importance>, id_prefix: &'a str) -> Self {
+        Sources {
+            citations: distinct_citations(olog),
+            by_node: node_citations(olog),
+            scores: size_by.map(|size_by| (importance::node_scores(olog, size_by), importance::normalized_scores(olog, size_by))),
+            id_prefix,
+        }
+    }
+
+    // The id as written to DOT and GraphML
+    fn key(&self, id: Uuid) -> String {
+        format!("{}{}", self.id_prefix, id)
+    }
+
+    fn keys(&self, citations: &[&Citation]) -> String {
+        citations.iter().map(|citation| self.key(citation.id)).collect::<Vec<_>>().join(",")
+    }
+
+    // (raw score, weight between 0 and 1)
+    fn importance(&self, id: &Uuid) -> Option<(f64, f64)> {
+        self.scores.as_ref().map(|(raw, normalized)| {
+            (raw.get(id).copied().unwrap_or(0.0), normalized.get(id).copied().unwrap_or(0.0))
+        })
+    }
+
+    fn color(&self, citation: &Citation) -> &'static str {
+        self.citations.iter()
+            .position(|c| c.id == citation.id)
+            .map_or(UNSOURCED_COLOR, |index| SOURCE_COLORS[index % SOURCE_COLORS.len()])
+    }
+
+    fn colors(&self, citations: &[&Citation]) -> Vec<&'static str> {
+        if citations.is_empty() {
+            vec![UNSOURCED_COLOR]
+        } else {
+            citations.iter().map(|citation| self.color(citation)).collect()
+        }
+    }
+
+    fn node(&self, id: &Uuid) -> &[&'a Citation] {
+        self.by_node.get(id).map_or(&[], Vec::as_slice)
+    }
+}
+
+// Where labels first appear in each citation's text, so badges can link to
+// the passage a node or hyperedge was drawn from. Matching ignores ASCII case
+// only, which keeps byte offsets valid in the original text.
+struct Anchors {
+    // (citation index, label) -> id of the mark around its first mention
+    links: HashMap<(usize, String), String>,
+    // Per citation, non-overlapping (start, end, id) in text order
+    marks: Vec<Vec<(usize, usize, String)>>,
+}
+
+impl Anchors {
+    fn new(olog: &Olog, sources: &Sources) -> Self {
+        let index = |citation: &Citation| sources.citations.iter().position(|c| c.id == citation.id);
+        let mut wanted: Vec<Vec<&str>> = vec![Vec::new(); sources.citations.len()];
+        for node in &olog.nodes {
+            for i in sources.node(&node.id).iter().filter_map(|citation| index(citation)) {
+                wanted[i].push(&node.label);
+            }
+        }
+        for hyperedge in &olog.hyperedges {
+            for i in hyperedge.citations.iter().filter_map(index) {
+                wanted[i].extend(hyperedge.source.iter().chain(&hyperedge.target).map(|node| node.label.as_str()));
+            }
+        }
+
+        let mut links = HashMap::new();
+        let mut marks = Vec::with_capacity(wanted.len());
+        for (i, mut labels) in wanted.into_iter().enumerate() {
+            labels.sort_unstable();
+            labels.dedup();
+            let lower = sources.citations[i].text.to_ascii_lowercase();
+            let mut found: Vec<(usize, usize, &str)> = labels.into_iter()
+                .filter(|label| !label.trim().is_empty())
+                .filter_map(|label| lower.find(&label.to_ascii_lowercase()).map(|start| (start, start + label.len(), label)))
+                .collect();
+            // Earliest first, and the longer label where two start together
+            found.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+            let mut citation_marks: Vec<(usize, usize, String)> = Vec::new();
+            for (start, end, label) in found {
+                let id = match citation_marks.last() {
+                    // A mention inside an earlier mark links to that mark
+                    Some((_, last_end, id)) if start < *last_end => id.clone(),
+                    _ => {
+                        let id = format!("citation-{}-{}", i + 1, start);
+                        citation_marks.push((start, end, id.clone()));
+                        id
+                    },
+                };
+                links.insert((i, label.to_string()), id);
+            }
+            marks.push(citation_marks);
+        }
+        Anchors { links, marks }
+    }
+
+    // The first of the labels found in the citation's text, or the text itself
+    fn link(&self, index: usize, labels: &[&str]) -> String {
+        labels.iter()
+            .find_map(|label| self.links.get(&(index, label.to_string())))
+            .map_or_else(|| format!("#citation-{}", index + 1), |id| format!("#{}", id))
+    }
+
+    // The citation's text with each anchored passage wrapped in a mark
+    fn marked_text(&self, index: usize, text: &str) -> String {
+        let mut out = String::new();
+        let mut at = 0;
+        for (start, end, id) in &self.marks[index] {
+            out.push_str(&html_escape(&text[at..*start]));
+            out.push_str(&format!("<mark id=\"{}\">{}</mark>", id, html_escape(&text[*start..*end])));
+            at = *end;
+        }
+        out.push_str(&html_escape(&text[at..]));
+        out
+    }
+}
+
+// `id_prefix` goes in front of every olog, node, hyperedge and citation id in
+// DOT and GraphML output, such as a base URI, so exports of ologs from several
+// databases can be loaded into one graph store without their ids colliding.
+// The HTML page only links within itself and ignores it.
+pub fn export(olog: &Olog, format: ExportFormat, size_by: Option<Importance>, id_prefix: &str) -> String {
+    let sources = Sources::new(olog, size_by, id_prefix);
+    match format {
+        ExportFormat::Dot => to_dot(olog, &sources),
+        ExportFormat::Html => to_html(olog, &sources),
+        ExportFormat::Graphml => to_graphml(olog, &sources),
+    }
+}
+
+// Hyperedges become diamond relation nodes wired from their sources to their
+// targets. Nodes backed by several documents are drawn as wedges, one slice
+// per source, and edges as parallel colored lines.
+fn to_dot(olog: &Olog, sources: &Sources) -> String {
+    let mut out = String::new();
+
+    out.push_str(&format!("digraph \"{}\" {{\n", dot_escape(&olog.title)));
+    out.push_str("  rankdir=LR;\n  node [shape=ellipse, style=wedged];\n");
+
+    for node in &olog.nodes {
+        let cited = sources.node(&node.id);
+        // Important nodes are drawn larger than the 0.75 x 0.5 inch default
+        let size = sources.importance(&node.id).map_or_else(String::new, |(score, weight)| {
+            format!(
+                ", width={:.2}, height={:.2}, fontsize={:.1}, importance=\"{}\"",
+                0.75 + 1.75 * weight,
+                0.5 + 0.75 * weight,
+                10.0 + 14.0 * weight,
+                score
+            )
+        });
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\", fillcolor=\"{}\", sources=\"{}\"{}];\n",
+            dot_escape(&sources.key(node.id)),
+            dot_escape(&node.label),
+            sources.colors(cited).join(":"),
+            dot_escape(&sources.keys(cited)),
+            size
+        ));
+    }
+
+    for hyperedge in &olog.hyperedges {
+        let cited: Vec<&Citation> = hyperedge.citations.iter().collect();
+        let color = sources.colors(&cited).join(":");
+        let hyperedge_key = dot_escape(&sources.key(hyperedge.id));
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\", shape=diamond, style=filled, fillcolor=\"white\", color=\"{}\", sources=\"{}\"{}];\n",
+            dot_escape(&sources.key(hyperedge.id)),
+            dot_escape(&relation_label(hyperedge)),
+            color,
+            dot_escape(&sources.keys(&cited)),
+            hyperedge.quantity.as_ref().map_or_else(String::new, |quantity| format!(", quantity=\"{}\"", dot_escape(&quantity.to_string())))
+        ));
+        for node in &hyperedge.source {
+            out.push_str(&format!(
+                "  \"{}\" -> \"{}\" [color=\"{}\", arrowhead=none];\n",
+                dot_escape(&sources.key(node.id)),
+                hyperedge_key,
+                color
+            ));
+        }
+        for node in &hyperedge.target {
+            out.push_str(&format!("  \"{}\" -> \"{}\" [color=\"{}\"];\n", hyperedge_key, dot_escape(&sources.key(node.id)), color));
+        }
+    }
+
+    out.push_str("  subgraph cluster_legend {\n    label=\"Sources\";\n    node [shape=box, style=filled];\n");
+    for (index, citation) in sources.citations.iter().enumerate() {
+        out.push_str(&format!(
+            "    \"legend_{}\" [label=\"[{}] {}\", fillcolor=\"{}\"];\n",
+            index,
+            index + 1,
+            dot_escape(citation.title.trim()),
+            sources.color(citation)
+        ));
+    }
+    out.push_str("  }\n}\n");
+    out
+}
+
+// Citation badges link to the cited text further down the page, at the first
+// mention of the node or of one of the hyperedge's endpoints
+fn to_html(olog: &Olog, sources: &Sources) -> String {
+    let anchors = Anchors::new(olog, sources);
+    let badges = |citations: &[&Citation], labels: &[&str]| -> String {
+        if citations.is_empty() {
+            return "<span class=\"badge none\">unsourced</span>".to_string();
+        }
+        citations.iter()
+            .map(|citation| {
+                let index = sources.citations.iter().position(|c| c.id == citation.id).unwrap_or(0);
+                format!(
+                    "<a class=\"badge\" href=\"{}\" style=\"background:{}\" title=\"{}\">{}</a>",
+                    anchors.link(index, labels),
+                    sources.color(citation),
+                    html_escape(citation.title.trim()),
+                    index + 1
+                )
+            })
+            .collect()
+    };
+    let overlap = |citations: &[&Citation]| if citations.len() > 1 { " class=\"shared\"" } else { "" };
+    let mut out = String::new();
+
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", html_escape(&olog.title)));
+    out.push_str(
+        "<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; margin-bottom: 2em; }\n\
+         td, th { border: 1px solid #ddd; padding: 4px 8px; text-align: left; vertical-align: top; }\n\
+         tr.shared { background: #f4f4f4; }\n\
+         .badge { display: inline-block; min-width: 1.4em; margin-right: 2px; border-radius: 3px; color: white; text-align: center; font-size: 0.8em; }\n\
+         .badge.none { background: #cccccc; color: black; padding: 0 4px; }\n\
+         a.badge { text-decoration: none; }\n\
+         .source-text { white-space: pre-wrap; max-width: 50em; }\n\
+         mark:target { background: #ffd54f; outline: 2px solid #f9a825; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    out.push_str(&format!("<h1>{}</h1>\n", html_escape(&olog.title)));
+
+    out.push_str("<h2>Sources</h2>\n<table>\n<tr><th></th><th>Title</th><th>Nodes</th><th>Hyperedges</th></tr>\n");
+    for citation in &sources.citations {
+        let nodes = olog.nodes.iter().filter(|node| sources.node(&node.id).iter().any(|c| c.id == citation.id)).count();
+        let hyperedges = olog.hyperedges.iter().filter(|hyperedge| hyperedge.citations.iter().any(|c| c.id == citation.id)).count();
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            badges(&[*citation], &[]),
+            html_escape(citation.title.trim()),
+            nodes,
+            hyperedges
+        ));
+    }
+    out.push_str("</table>\n");
+
+    // Shaded rows are supported by more than one document; with sizing the
+    // most important nodes come first and get larger labels
+    let mut nodes: Vec<&Node> = olog.nodes.iter().collect();
+    if sources.scores.is_some() {
+        nodes.sort_by(|a, b| {
+            let score = |node: &Node| sources.importance(&node.id).map_or(0.0, |(score, _)| score);
+            score(b).total_cmp(&score(a))
+        });
+        out.push_str("<h2>Nodes</h2>\n<table>\n<tr><th>Label</th><th>Importance</th><th>Sources</th></tr>\n");
+    } else {
+        out.push_str("<h2>Nodes</h2>\n<table>\n<tr><th>Label</th><th>Sources</th></tr>\n");
+    }
+    for node in nodes {
+        let cited = sources.node(&node.id);
+        let (label, score) = match sources.importance(&node.id) {
+            Some((score, weight)) => (
+                format!("<span style=\"font-size: {:.2}em\">{}</span>", 0.9 + 0.8 * weight, html_escape(&node.label)),
+                format!("<td>{:.3}</td>", score),
+            ),
+            None => (html_escape(&node.label), String::new()),
+        };
+        out.push_str(&format!(
+            "<tr{} id=\"{}\"><td>{}</td>{}<td>{}</td></tr>\n",
+            overlap(cited),
+            node.id,
+            label,
+            score,
+            badges(cited, &[&node.label])
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out.push_str("<h2>Hyperedges</h2>\n<table>\n<tr><th>Sources</th><th>Relation</th><th>Targets</th><th>Cited by</th></tr>\n");
+    for hyperedge in &olog.hyperedges {
+        let cited: Vec<&Citation> = hyperedge.citations.iter().collect();
+        let endpoints: Vec<&str> = hyperedge.source.iter().chain(&hyperedge.target).map(|node| node.label.as_str()).collect();
+        let labels = |nodes: &[Node]| nodes.iter().map(|node| html_escape(&node.label)).collect::<Vec<_>>().join("<br>");
+        out.push_str(&format!(
+            "<tr{} id=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            overlap(&cited),
+            hyperedge.id,
+            labels(&hyperedge.source),
+            html_escape(&relation_label(hyperedge)),
+            labels(&hyperedge.target),
+            badges(&cited, &endpoints)
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out.push_str("<h2>Source texts</h2>\n");
+    for (index, citation) in sources.citations.iter().enumerate() {
+        out.push_str(&format!(
+            "<section id=\"citation-{}\">\n<h3>{} {}</h3>\n<div class=\"source-text\">{}</div>\n</section>\n",
+            index + 1,
+            badges(&[*citation], &[]),
+            html_escape(citation.title.trim()),
+            anchors.marked_text(index, &citation.text)
+        ));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// Hyperedges become nodes of kind "relation" so tools without hyperedge
+// support can still lay the olog out
+fn to_graphml(olog: &Olog, sources: &Sources) -> String {
+    let mut out = String::new();
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"sources\" for=\"node\" attr.name=\"sources\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"color\" for=\"node\" attr.name=\"color\" attr.type=\"string\"/>\n");
+    if sources.scores.is_some() {
+        out.push_str("  <key id=\"importance\" for=\"node\" attr.name=\"importance\" attr.type=\"double\"/>\n");
+        out.push_str("  <key id=\"weight\" for=\"node\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+    }
+    out.push_str("  <key id=\"quantity_value\" for=\"node\" attr.name=\"quantity_value\" attr.type=\"double\"/>\n");
+    out.push_str("  <key id=\"quantity_unit\" for=\"node\" attr.name=\"quantity_unit\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"quantity_uncertainty\" for=\"node\" attr.name=\"quantity_uncertainty\" attr.type=\"double\"/>\n");
+    out.push_str("  <key id=\"role\" for=\"edge\" attr.name=\"role\" attr.type=\"string\"/>\n");
+    out.push_str(&format!("  <graph id=\"{}\" edgedefault=\"directed\">\n", html_escape(&sources.key(olog.id))));
+
+    let data = |key: &str, value: &str| format!("      <data key=\"{}\">{}</data>\n", key, html_escape(value));
+    for node in &olog.nodes {
+        let cited = sources.node(&node.id);
+        out.push_str(&format!("    <node id=\"{}\">\n", html_escape(&sources.key(node.id))));
+        out.push_str(&data("kind", "concept"));
+        out.push_str(&data("label", &node.label));
+        out.push_str(&data("sources", &sources.keys(cited)));
+        out.push_str(&data("color", sources.colors(cited)[0]));
+        if let Some((score, weight)) = sources.importance(&node.id) {
+            out.push_str(&data("importance", &score.to_string()));
+            out.push_str(&data("weight", &weight.to_string()));
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for hyperedge in &olog.hyperedges {
+        let cited: Vec<&Citation> = hyperedge.citations.iter().collect();
+        out.push_str(&format!("    <node id=\"{}\">\n", html_escape(&sources.key(hyperedge.id))));
+        out.push_str(&data("kind", "relation"));
+        out.push_str(&data("label", &hyperedge.label));
+        out.push_str(&data("sources", &sources.keys(&cited)));
+        out.push_str(&data("color", sources.colors(&cited)[0]));
+        if let Some(quantity) = &hyperedge.quantity {
+            out.push_str(&data("quantity_value", &quantity.value.to_string()));
+            if let Some(unit) = &quantity.unit {
+                out.push_str(&data("quantity_unit", unit));
+            }
+            if let Some(uncertainty) = quantity.uncertainty {
+                out.push_str(&data("quantity_uncertainty", &uncertainty.to_string()));
+            }
+        }
+        out.push_str("    </node>\n");
+
+        let links = hyperedge.source.iter().zip(hyperedge.source_roles.iter().chain(std::iter::repeat(&None)))
+            .map(|(node, role)| (node.id, hyperedge.id, role))
+            .chain(hyperedge.target.iter().zip(hyperedge.target_roles.iter().chain(std::iter::repeat(&None)))
+                .map(|(node, role)| (hyperedge.id, node.id, role)));
+        for (from, to, role) in links {
+            match role {
+                Some(role) => out.push_str(&format!(
+                    "    <edge source=\"{}\" target=\"{}\">\n{}    </edge>\n",
+                    html_escape(&sources.key(from)),
+                    html_escape(&sources.key(to)),
+                    data("role", role)
+                )),
+                None => out.push_str(&format!(
+                    "    <edge source=\"{}\" target=\"{}\"/>\n",
+                    html_escape(&sources.key(from)),
+                    html_escape(&sources.key(to))
+                )),
+            }
+        }
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+// Mermaid flowchart for pasting into Markdown. A hyperedge with one source and
+// one target is drawn as an arrow, labeled when `edge_labels` is set; any other
+// hyperedge needs a relation node of its own, which always shows its label.
+pub fn to_mermaid(olog: &Olog, edge_labels: bool) -> String {
+    // Mermaid ids must be plain words, so nodes are numbered in olog order
+    let keys: HashMap<Uuid, String> = olog.nodes.iter().enumerate()
+        .map(|(index, node)| (node.id, format!("n{}", index + 1)))
+        .collect();
+    let key = |node: &Node| keys.get(&node.id).cloned().unwrap_or_else(|| format!("n_{}", node.id.simple()));
+    let mut out = String::new();
+
+    // A JSON string is also a valid YAML one
+    let title = serde_json::to_string(&olog.title.replace('\n', " ")).unwrap_or_default();
+    out.push_str(&format!("---\ntitle: {}\n---\nflowchart LR\n", title));
+    for node in &olog.nodes {
+        out.push_str(&format!("    {}[\"{}\"]\n", key(node), mermaid_escape(&node.label)));
+    }
+
+    for (index, hyperedge) in olog.hyperedges.iter().enumerate() {
+        let label = mermaid_escape(&relation_label(hyperedge));
+        match (hyperedge.source.as_slice(), hyperedge.target.as_slice()) {
+            ([source], [target]) if edge_labels => {
+                out.push_str(&format!("    {} -->|\"{}\"| {}\n", key(source), label, key(target)));
+            },
+            ([source], [target]) => out.push_str(&format!("    {} --> {}\n", key(source), key(target))),
+            (sources, targets) => {
+                let relation = format!("e{}", index + 1);
+                out.push_str(&format!("    {}{{{{\"{}\"}}}}\n", relation, label));
+                for source in sources {
+                    out.push_str(&format!("    {} --- {}\n", key(source), relation));
+                }
+                for target in targets {
+                    out.push_str(&format!("    {} --> {}\n", relation, key(target)));
+                }
+            },
+        }
+    }
+    out
+}
+
+fn relation_label(hyperedge: &Hyperedge) -> String {
+    match &hyperedge.quantity {
+        Some(quantity) => format!("{} = {}", hyperedge.label, quantity),
+        None => hyperedge.label.clone(),
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Mermaid's entity codes, so quotes and brackets can't end a label early
+fn mermaid_escape(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+        .replace('\n', " ")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
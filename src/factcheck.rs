@@ -0,0 +1,156 @@
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::db::{id_column, unknown_value};
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{Citation, Hyperedge, Olog};
+use crate::split::edge_sentence;
+
+// Characters of the citation shown to the model; longer documents are cut,
+// which can only turn a verdict into unverifiable
+const CITATION_CHARS: usize = 24_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    // Ordered so that the strongest finding across a hyperedge's citations wins
+    Unverifiable,
+    Contradicted,
+    Supported,
+}
+
+impl Verdict {
+    pub fn name(self) -> &'static str {
+        match self {
+            Verdict::Supported => "supported",
+            Verdict::Contradicted => "contradicted",
+            Verdict::Unverifiable => "unverifiable",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Verdict> {
+        [Verdict::Supported, Verdict::Contradicted, Verdict::Unverifiable].into_iter()
+            .find(|verdict| verdict.name() == name.trim().to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EdgeVerdict {
+    pub hyperedge_id: Uuid,
+    pub citation_id: Uuid,
+    pub verdict: Verdict,
+    // The model's own confidence, 0 to 1
+    pub confidence: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerdictResponse {
+    verdict: String,
+    confidence: f64,
+    #[serde(default)]
+    reason: String,
+}
+
+pub fn check_hyperedge(provider: &dyn LlmProvider, hyperedge: &Hyperedge, citation: &Citation, model: &str) -> Result<EdgeVerdict, OlogError> {
+    let excerpt: String = citation.text.chars().take(CITATION_CHARS).collect();
+    let quantity = hyperedge.quantity.as_ref().map_or_else(String::new, |quantity| format!(" (stated value: {})", quantity));
+    let prompt = format!(
+        "Decide whether the source text below supports this statement extracted from it:\n\n\"{}\"{}\n\n\
+         Answer \"supported\" if the text states or directly implies it, \"contradicted\" if the text says otherwise, \
+         and \"unverifiable\" if the text doesn't address it. Judge only by the text, not by outside knowledge. \
+         Respond with JSON of the form {{\"verdict\": \"supported\", \"confidence\": 0.9, \"reason\": \"...\"}}, \
+         where confidence is between 0 and 1 and reason is one sentence.\n\nSource: {}\n{}",
+        edge_sentence(hyperedge), quantity, citation.title.trim(), excerpt.trim()
+    );
+    let response: VerdictResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed fact-check response: {}", e)))?;
+    let verdict = Verdict::parse(&response.verdict)
+        .ok_or_else(|| OlogError::Schema(format!("Unknown fact-check verdict `{}`", response.verdict)))?;
+
+    Ok(EdgeVerdict {
+        hyperedge_id: hyperedge.id,
+        citation_id: citation.id,
+        verdict,
+        confidence: if response.confidence.is_finite() { response.confidence.clamp(0.0, 1.0) } else { 0.0 },
+        reason: response.reason.trim().to_string(),
+    })
+}
+
+// Checks every hyperedge against each of its citations. `on_verdict` is
+// called as each verdict arrives.
+pub fn fact_check(
+    provider: &dyn LlmProvider,
+    olog: &Olog,
+    model: &str,
+    mut on_verdict: impl FnMut(&EdgeVerdict),
+) -> Result<Vec<EdgeVerdict>, OlogError> {
+    let mut verdicts = Vec::new();
+    for hyperedge in &olog.hyperedges {
+        for citation in &hyperedge.citations {
+            let verdict = check_hyperedge(provider, hyperedge, citation, model)?;
+            on_verdict(&verdict);
+            verdicts.push(verdict);
+        }
+    }
+    Ok(verdicts)
+}
+
+// Per hyperedge, the strongest verdict any of its citations gave, the more
+// confident one on a tie
+pub fn overall(verdicts: &[EdgeVerdict]) -> Vec<&EdgeVerdict> {
+    let mut best: Vec<&EdgeVerdict> = Vec::new();
+    for verdict in verdicts {
+        match best.iter_mut().find(|best| best.hyperedge_id == verdict.hyperedge_id) {
+            Some(best) => {
+                if (verdict.verdict, verdict.confidence) > (best.verdict, best.confidence) {
+                    *best = verdict;
+                }
+            },
+            None => best.push(verdict),
+        }
+    }
+    best
+}
+
+// Replaces the olog's earlier verdicts, including those for hyperedges that
+// have since been edited away
+pub fn record_verdicts(conn: &Connection, olog_id: Uuid, model: &str, verdicts: &[EdgeVerdict]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM Edge_Verdicts WHERE olog_id = ?1", params![olog_id.to_string()])?;
+    for verdict in verdicts {
+        tx.execute(
+            "INSERT OR REPLACE INTO Edge_Verdicts (olog_id, hyperedge_id, citation_id, verdict, confidence, reason, model, checked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, CURRENT_TIMESTAMP)",
+            params![
+                olog_id.to_string(),
+                verdict.hyperedge_id.to_string(),
+                verdict.citation_id.to_string(),
+                verdict.verdict.name(),
+                verdict.confidence,
+                verdict.reason,
+                model
+            ],
+        )?;
+    }
+    tx.commit()
+}
+
+pub fn read_verdicts(conn: &Connection, olog_id: Uuid) -> rusqlite::Result<Vec<EdgeVerdict>> {
+    let mut stmt = conn.prepare(
+        "SELECT hyperedge_id, citation_id, verdict, confidence, reason FROM Edge_Verdicts WHERE olog_id = ?1 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map(params![olog_id.to_string()], |row| {
+        let verdict: String = row.get(2)?;
+        Ok(EdgeVerdict {
+            hyperedge_id: id_column(row, 0)?,
+            citation_id: id_column(row, 1)?,
+            verdict: Verdict::parse(&verdict).ok_or_else(|| unknown_value(2, &verdict))?,
+            confidence: row.get(3)?,
+            reason: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{Hyperedge, Olog};
+use crate::split::edge_sentence;
+
+// Hyperedges sent to the model per request
+const BATCH_SIZE: usize = 40;
+
+#[derive(Debug)]
+pub struct Card {
+    pub question: String,
+    pub answer: String,
+    // Titles of the documents the hyperedge was extracted from
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardResponse {
+    cards: Vec<CardAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardAnswer {
+    edge: usize,
+    question: String,
+    answer: String,
+}
+
+// One card per hyperedge asking for its targets, without calling the model
+pub fn plain_cards(olog: &Olog) -> Vec<Card> {
+    olog.hyperedges.iter()
+        .filter(|hyperedge| !hyperedge.target.is_empty())
+        .map(|hyperedge| {
+            let sources = hyperedge.source.iter().map(|node| node.label.as_str()).collect::<Vec<_>>().join(" and ");
+            let mut answer = hyperedge.target.iter().map(|node| node.label.as_str()).collect::<Vec<_>>().join(" and ");
+            if let Some(quantity) = &hyperedge.quantity {
+                answer = format!("{} ({})", answer, quantity);
+            }
+            Card { question: format!("{} {} …?", sources, hyperedge.label), answer, sources: source_titles(hyperedge) }
+        })
+        .collect()
+}
+
+// Lets the model phrase a natural question for each hyperedge. Answers must
+// come from the hyperedge itself, so every card stays backed by its citations.
+pub fn generated_cards(provider: &dyn LlmProvider, olog: &Olog, model: &str) -> Result<Vec<Card>, OlogError> {
+    let mut cards = Vec::new();
+    for batch in olog.hyperedges.chunks(BATCH_SIZE) {
+        let listing = batch.iter().enumerate()
+            .map(|(i, hyperedge)| format!("{}: {}", i, edge_sentence(hyperedge)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "Turn each numbered fact below, taken from an ontology log about \"{}\", into a study flashcard. Write a \
+             question that can be answered from the fact alone, and a short answer that uses only what the fact \
+             states. Skip facts too vague to ask about. Respond with JSON of the form {{\"cards\": [{{\"edge\": 0, \
+             \"question\": \"...\", \"answer\": \"...\"}}]}}.\n\n{}",
+            olog.title, listing
+        );
+        let response: CardResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+            .map_err(|e| OlogError::Schema(format!("Malformed flashcard response: {}", e)))?;
+
+        for answer in response.cards {
+            let Some(hyperedge) = batch.get(answer.edge) else { continue };
+            cards.push(Card { question: answer.question, answer: answer.answer, sources: source_titles(hyperedge) });
+        }
+    }
+    Ok(cards)
+}
+
+fn source_titles(hyperedge: &Hyperedge) -> Vec<String> {
+    let mut titles: Vec<String> = Vec::new();
+    for citation in &hyperedge.citations {
+        if !titles.contains(&citation.title) {
+            titles.push(citation.title.clone());
+        }
+    }
+    titles
+}
+
+// Tab-separated notes with the header lines Anki reads on import: front, back
+// with the sources underneath, and a tag naming the olog
+pub fn to_anki_tsv(olog: &Olog, cards: &[Card]) -> String {
+    let tag = olog.title.split_whitespace().collect::<Vec<_>>().join("_");
+    let mut out = format!(
+        "#separator:tab\n#html:true\n#notetype:Basic\n#deck:{}\n#tags column:3\n",
+        field(&olog.title)
+    );
+    for card in cards {
+        let mut back = html_escape(&card.answer);
+        if !card.sources.is_empty() {
+            let sources = card.sources.iter().map(|title| html_escape(title)).collect::<Vec<_>>().join("; ");
+            back = format!("{}<br><small>Source: {}</small>", back, sources);
+        }
+        out.push_str(&format!("{}\t{}\t{}\n", field(&html_escape(&card.question)), field(&back), field(&tag)));
+    }
+    out
+}
+
+// Tabs and line breaks would start a new field or note
+fn field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
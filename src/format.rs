@@ -0,0 +1,318 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+// Longest a table cell may get before being cut short
+const MAX_CELL_WIDTH: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns with summary lines, for reading
+    Table,
+    /// One JSON document
+    Json,
+    /// The JSON document as YAML
+    Yaml,
+    /// Comma-separated rows under a header line; a blank line separates tables
+    Csv,
+    /// Only the ids in the first column, one per line, for piping into other commands
+    Quiet,
+}
+
+/// Rows under named columns. Cells are JSON values so the machine-readable
+/// formats keep numbers, nulls and lists as such.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: &'static str,
+    // Key for the machine-readable formats, then the heading the table format shows
+    pub columns: Vec<(&'static str, &'static str)>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(name: &'static str, columns: &[(&'static str, &'static str)]) -> Self {
+        Table { name, columns: columns.to_vec(), rows: Vec::new() }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    fn records(&self) -> Value {
+        let records = self.rows.iter()
+            .map(|row| {
+                let record: Map<String, Value> = self.columns.iter()
+                    .zip(row)
+                    .map(|((key, _), cell)| (key.to_string(), cell.clone()))
+                    .collect();
+                Value::Object(record)
+            })
+            .collect();
+        Value::Array(records)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Block {
+    Line(String),
+    Table(Table),
+}
+
+/// What a read-only command prints. The table format shows `blocks` in order;
+/// the other formats show `fields` and `tables`, which hold the same facts
+/// without the prose.
+#[derive(Debug, Default)]
+pub struct Output {
+    pub fields: Vec<(&'static str, Value)>,
+    pub tables: Vec<Table>,
+    pub blocks: Vec<Block>,
+}
+
+impl Output {
+    pub fn field(&mut self, key: &'static str, value: impl Into<Value>) {
+        self.fields.push((key, value.into()));
+    }
+
+    pub fn line(&mut self, line: impl Into<String>) {
+        self.blocks.push(Block::Line(line.into()));
+    }
+
+    // A table every format shows
+    pub fn table(&mut self, table: Table) {
+        self.blocks.push(Block::Table(table.clone()));
+        self.tables.push(table);
+    }
+
+    // A table only the table format shows, laid out for reading
+    pub fn show(&mut self, table: Table) {
+        self.blocks.push(Block::Table(table));
+    }
+
+    // A table only the machine-readable formats show
+    pub fn record(&mut self, table: Table) {
+        self.tables.push(table);
+    }
+
+    // One table and nothing else is a bare list of records; anything more is
+    // an object keyed by field and table names
+    fn document(&self) -> Value {
+        if let ([], [table]) = (self.fields.as_slice(), self.tables.as_slice()) {
+            return table.records();
+        }
+        let mut document: Map<String, Value> = self.fields.iter().map(|(key, value)| (key.to_string(), value.clone())).collect();
+        for table in &self.tables {
+            document.insert(table.name.to_string(), table.records());
+        }
+        Value::Object(document)
+    }
+}
+
+pub trait Formatter {
+    fn render(&self, output: &Output) -> String;
+}
+
+// `full` turns off truncating table cells
+pub fn formatter(format: OutputFormat, full: bool) -> Box<dyn Formatter> {
+    match format {
+        OutputFormat::Table => Box::new(TableFormatter { full }),
+        OutputFormat::Json => Box::new(JsonFormatter),
+        OutputFormat::Yaml => Box::new(YamlFormatter),
+        OutputFormat::Csv => Box::new(CsvFormatter),
+        OutputFormat::Quiet => Box::new(QuietFormatter),
+    }
+}
+
+pub fn print(output: &Output, format: OutputFormat, full: bool) {
+    print!("{}", formatter(format, full).render(output));
+}
+
+pub struct TableFormatter {
+    pub full: bool,
+}
+
+impl Formatter for TableFormatter {
+    fn render(&self, output: &Output) -> String {
+        let mut rendered = String::new();
+        for block in &output.blocks {
+            match block {
+                Block::Line(line) => {
+                    rendered.push_str(line);
+                    rendered.push('\n');
+                },
+                Block::Table(table) => rendered.push_str(&self.table(table)),
+            }
+        }
+        rendered
+    }
+}
+
+impl TableFormatter {
+    fn table(&self, table: &Table) -> String {
+        let cells: Vec<Vec<String>> = table.rows.iter()
+            .map(|row| row.iter()
+                .map(|cell| {
+                    let cell = plain(cell);
+                    if self.full { cell } else { truncate(&cell, MAX_CELL_WIDTH) }
+                })
+                .collect())
+            .collect();
+
+        let widths: Vec<usize> = table.columns.iter().enumerate()
+            .map(|(i, (_, header))| {
+                cells.iter()
+                    .map(|row| row[i].chars().count())
+                    .chain(std::iter::once(header.len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let format_row = |row: Vec<String>| -> String {
+            row.iter().enumerate()
+                .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        let mut rendered = format!("  {}\n", format_row(table.columns.iter().map(|(_, header)| header.to_string()).collect()));
+        for row in cells {
+            rendered.push_str(&format!("  {}\n", format_row(row)));
+        }
+        rendered
+    }
+}
+
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn render(&self, output: &Output) -> String {
+        format!("{:#}\n", output.document())
+    }
+}
+
+// JSON strings are valid double-quoted YAML scalars, so strings are written
+// with JSON escaping and never need YAML's quoting rules
+pub struct YamlFormatter;
+
+impl Formatter for YamlFormatter {
+    fn render(&self, output: &Output) -> String {
+        let mut rendered = String::new();
+        yaml(&output.document(), 0, &mut rendered);
+        rendered
+    }
+}
+
+fn yaml(value: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                match item {
+                    // The record's first key goes on the dash's line
+                    Value::Object(entries) if !entries.is_empty() => {
+                        let mut nested = String::new();
+                        yaml(item, indent + 2, &mut nested);
+                        out.push_str(&format!("{}- {}", pad, &nested[indent + 2..]));
+                    },
+                    _ => {
+                        out.push_str(&format!("{}-", pad));
+                        yaml_nested(item, indent, out);
+                    },
+                }
+            }
+        },
+        Value::Object(entries) if !entries.is_empty() => {
+            for (key, item) in entries {
+                if key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    out.push_str(&format!("{}{}:", pad, key));
+                } else {
+                    out.push_str(&format!("{}{}:", pad, Value::String(key.clone())));
+                }
+                yaml_nested(item, indent, out);
+            }
+        },
+        scalar => out.push_str(&format!("{}{}\n", pad, scalar)),
+    }
+}
+
+// The part after `-` or `key:`: scalars and empty collections stay on the
+// line, anything else goes below it
+fn yaml_nested(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            yaml(value, indent + 2, out);
+        },
+        Value::Object(entries) if !entries.is_empty() => {
+            out.push('\n');
+            yaml(value, indent + 2, out);
+        },
+        scalar => out.push_str(&format!(" {}\n", scalar)),
+    }
+}
+
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn render(&self, output: &Output) -> String {
+        output.tables.iter()
+            .map(|table| {
+                let mut rendered = csv_row(table.columns.iter().map(|(key, _)| key.to_string()));
+                for row in &table.rows {
+                    rendered.push_str(&csv_row(row.iter().map(plain)));
+                }
+                rendered
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn csv_row(cells: impl Iterator<Item = String>) -> String {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect();
+    format!("{}\n", cells.join(","))
+}
+
+pub struct QuietFormatter;
+
+impl Formatter for QuietFormatter {
+    fn render(&self, output: &Output) -> String {
+        let Some(table) = output.tables.first() else {
+            return String::new();
+        };
+        table.rows.iter()
+            .filter_map(|row| row.first())
+            .map(|cell| format!("{}\n", plain(cell)))
+            .collect()
+    }
+}
+
+// A cell as text: strings without quotes, nothing for null, lists joined by
+// spaces
+fn plain(cell: &Value) -> String {
+    match cell {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(plain).collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    }
+}
+
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let cut: String = text.chars().take(max.saturating_sub(1)).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
@@ -0,0 +1,58 @@
+use rusqlite::{params, Connection, Result};
+
+// A row whose foreign key names a row that doesn't exist
+#[derive(Debug)]
+pub struct Violation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    // What SQLite's integrity check found wrong with the file itself
+    pub corruption: Vec<String>,
+    pub violations: Vec<Violation>,
+    pub foreign_keys: bool,
+}
+
+pub fn check(conn: &Connection) -> Result<Check> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let corruption = stmt.query_map([], |row| row.get::<_, String>(0))?
+        .filter(|message| !matches!(message, Ok(message) if message == "ok"))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Check {
+        corruption,
+        violations: violations(conn)?,
+        foreign_keys: conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?,
+    })
+}
+
+fn violations(conn: &Connection) -> Result<Vec<Violation>> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let violations = stmt.query_map([], |row| {
+        Ok(Violation { table: row.get(0)?, rowid: row.get(1)?, parent: row.get(2)? })
+    })?;
+    violations.collect()
+}
+
+// Deletes every row with a dangling reference, in one transaction, and returns
+// how many went. Deleting a row can strand rows that point at it, so this
+// repeats until the check comes back clean.
+pub fn repair(conn: &Connection) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut deleted = 0;
+    loop {
+        let found: Vec<(String, i64)> = violations(&tx)?.into_iter()
+            .filter_map(|violation| Some((violation.table, violation.rowid?)))
+            .collect();
+        if found.is_empty() {
+            break;
+        }
+        for (table, rowid) in found {
+            deleted += tx.execute(&format!("DELETE FROM {} WHERE rowid = ?1", table), params![rowid])?;
+        }
+    }
+    tx.commit()?;
+    Ok(deleted)
+}
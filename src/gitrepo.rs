@@ -0,0 +1,146 @@
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+use crate::config;
+use crate::db::current_namespace;
+use crate::error::OlogError;
+use crate::olog::{olog_to_json, Olog};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Operation {
+    Generate,
+    Merge,
+    Edit,
+    Import,
+}
+
+impl Operation {
+    fn verb(self) -> &'static str {
+        match self {
+            Operation::Generate => "generate",
+            Operation::Merge => "merge",
+            Operation::Edit => "edit",
+            Operation::Import => "import",
+        }
+    }
+}
+
+// Set under [git] in olog.toml; the repository is created if it doesn't exist
+#[derive(Debug, Deserialize)]
+pub struct GitConfig {
+    pub path: PathBuf,
+}
+
+pub fn config() -> Result<Option<GitConfig>, OlogError> {
+    config::project_setting("git")
+}
+
+// Called after every olog write. Like the search index, the repository is a
+// mirror of the database, so failing to update it only gets a warning.
+pub fn record(conn: &Connection, olog: &Olog, operation: Operation) {
+    let config = match config() {
+        Ok(Some(config)) => config,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Error committing Olog {} to the git repository: {}", olog.id, e);
+            return;
+        },
+    };
+    match commit_olog(conn, &config.path, olog, operation) {
+        Ok(Some(file)) => println!("Committed {} in {}.", file.display(), config.path.display()),
+        Ok(None) => {},
+        Err(e) => eprintln!("Error committing Olog {} to the git repository: {}", olog.id, e),
+    }
+}
+
+// Called after an olog is deleted
+pub fn remove(conn: &Connection, olog_id: Uuid, title: &str) {
+    let result = config().and_then(|config| match config {
+        Some(config) if config.path.join(".git").exists() => {
+            let path = olog_file(conn, olog_id)?.to_string_lossy().to_string();
+            git(&config.path, &["rm", "--quiet", "--ignore-unmatch", "--", &path])?;
+            if !staged(&config.path, &path)? {
+                return Ok(());
+            }
+            git(&config.path, &["commit", "--quiet", "-m", &format!("delete: {}\n\nOlog {}", title, olog_id), "--", &path])
+        },
+        _ => Ok(()),
+    });
+    if let Err(e) = result {
+        eprintln!("Error removing Olog {} from the git repository: {}", olog_id, e);
+    }
+}
+
+// <namespace>/<olog id>.json within the repository
+fn olog_file(conn: &Connection, olog_id: Uuid) -> Result<PathBuf, OlogError> {
+    // Namespaces are free-form, so keep them from naming paths outside the repository
+    let directory = current_namespace(conn)?.replace(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'), "_");
+    Ok(PathBuf::from(directory).join(format!("{}.json", olog_id)))
+}
+
+fn staged(repository: &Path, path: &str) -> Result<bool, OlogError> {
+    let unchanged = Command::new("git")
+        .arg("-C").arg(repository)
+        .args(["diff", "--cached", "--quiet", "--", path])
+        .status()?
+        .success();
+    Ok(!unchanged)
+}
+
+// Writes <namespace>/<olog id>.json and commits it. Returns the file's path
+// within the repository, or None when the olog didn't change.
+fn commit_olog(conn: &Connection, repository: &Path, olog: &Olog, operation: Operation) -> Result<Option<PathBuf>, OlogError> {
+    if !repository.join(".git").exists() {
+        fs::create_dir_all(repository)?;
+        git(repository, &["init", "--quiet"])?;
+    }
+
+    let file = olog_file(conn, olog.id)?;
+    fs::create_dir_all(repository.join(file.parent().expect("file has a namespace directory")))?;
+    fs::write(repository.join(&file), canonical_json(olog)?)?;
+
+    let path = file.to_string_lossy().to_string();
+    git(repository, &["add", "--", &path])?;
+    if !staged(repository, &path)? {
+        return Ok(None);
+    }
+
+    let message = format!("{}: {}\n\nOlog {}", operation.verb(), olog.title, olog.id);
+    git(repository, &["commit", "--quiet", "-m", &message, "--", &path])?;
+    Ok(Some(file))
+}
+
+// The olog-json output with everything sorted by id, so unchanged content
+// always serializes the same way and diffs only show real edits
+fn canonical_json(olog: &Olog) -> Result<String, serde_json::Error> {
+    let mut json = olog_to_json(olog);
+    json.nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    for node in &mut json.nodes {
+        if let Some(citations) = &mut node.citations {
+            citations.sort();
+        }
+    }
+    json.hyperedges.sort_by(|a, b| a.id.cmp(&b.id));
+    for hyperedge in &mut json.hyperedges {
+        if let Some(citations) = &mut hyperedge.citations {
+            citations.sort();
+        }
+    }
+    if let Some(citations) = &mut json.citations {
+        citations.sort_by(|a, b| a.id.cmp(&b.id));
+    }
+    Ok(serde_json::to_string_pretty(&json)? + "\n")
+}
+
+fn git(repository: &Path, args: &[&str]) -> Result<(), OlogError> {
+    let output = Command::new("git").arg("-C").arg(repository).args(args).output()?;
+    if !output.status.success() {
+        let message = format!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+        return Err(OlogError::Io(std::io::Error::other(message)));
+    }
+    Ok(())
+}
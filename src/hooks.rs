@@ -0,0 +1,117 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::error::OlogError;
+use crate::http;
+use crate::olog::Olog;
+
+// Each hook is either an http(s) URL that receives the payload as a JSON POST,
+// or a shell command that receives it on stdin.
+pub const ON_SUCCESS_VAR: &str = "OLOG_ON_SUCCESS";
+pub const ON_FAILURE_VAR: &str = "OLOG_ON_FAILURE";
+
+#[derive(Debug, Serialize)]
+struct HookPayload {
+    event: &'static str,
+    olog_id: Option<String>,
+    title: Option<String>,
+    stats: Option<HookStats>,
+    error: Option<String>,
+    duration_ms: u128,
+}
+
+#[derive(Debug, Serialize)]
+struct HookStats {
+    nodes: usize,
+    hyperedges: usize,
+    citations: usize,
+}
+
+pub fn on_success(olog: &Olog, elapsed: Duration) {
+    let citations: HashSet<_> = olog.hyperedges.iter()
+        .flat_map(|hyperedge| hyperedge.citations.iter().map(|citation| citation.id))
+        .collect();
+
+    let payload = HookPayload {
+        event: "success",
+        olog_id: Some(olog.id.to_string()),
+        title: Some(olog.title.clone()),
+        stats: Some(HookStats {
+            nodes: olog.nodes.len(),
+            hyperedges: olog.hyperedges.len(),
+            citations: citations.len(),
+        }),
+        error: None,
+        duration_ms: elapsed.as_millis(),
+    };
+
+    fire(ON_SUCCESS_VAR, &payload);
+}
+
+pub fn on_failure(error: &str, elapsed: Duration) {
+    let payload = HookPayload {
+        event: "failure",
+        olog_id: None,
+        title: None,
+        stats: None,
+        error: Some(error.to_string()),
+        duration_ms: elapsed.as_millis(),
+    };
+
+    fire(ON_FAILURE_VAR, &payload);
+}
+
+fn fire(var: &str, payload: &HookPayload) {
+    let hook = match env::var(var) {
+        Ok(hook) if !hook.trim().is_empty() => hook,
+        _ => return,
+    };
+
+    let body = match serde_json::to_string(payload) {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("Error serializing {} payload: {}", var, e);
+            return;
+        }
+    };
+
+    let result = if hook.starts_with("http://") || hook.starts_with("https://") {
+        post_webhook(&hook, &body)
+    } else {
+        run_command(&hook, &body)
+    };
+
+    // A broken hook should never turn a finished run into a failed one
+    if let Err(e) = result {
+        eprintln!("Error running {} hook: {}", var, e);
+    }
+}
+
+fn post_webhook(url: &str, body: &str) -> Result<(), OlogError> {
+    http::post(url)?
+        .set("Content-Type", "application/json")
+        .send_string(body)?;
+    Ok(())
+}
+
+fn run_command(command: &str, body: &str) -> Result<(), OlogError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(OlogError::Io(std::io::Error::other(format!("command exited with {}", status))));
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::CertificateDer;
+
+use crate::config;
+use crate::error::OlogError;
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+// Local servers such as Ollama are never reached through the proxy
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+// Every request olog makes goes through one of these: `direct` for hosts the
+// proxy is bypassed for, `proxied` for the rest
+struct Agents {
+    proxied: ureq::Agent,
+    direct: ureq::Agent,
+    no_proxy: Vec<String>,
+}
+
+static AGENTS: OnceLock<Agents> = OnceLock::new();
+
+/// Sends every request olog makes, model calls and document downloads
+/// included, through `agent` instead of one built from the proxy settings.
+/// Call it before the first request; it fails once requests have been made.
+pub fn set_agent(agent: ureq::Agent) -> Result<(), OlogError> {
+    let agents = Agents { proxied: agent.clone(), direct: agent, no_proxy: Vec::new() };
+    AGENTS.set(agents).map_err(|_| OlogError::Config("An HTTP agent is already in use".to_string()))
+}
+
+fn agents() -> Result<&'static Agents, OlogError> {
+    if let Some(agents) = AGENTS.get() {
+        return Ok(agents);
+    }
+    let agents = build_agents()?;
+    // Another thread may have won the race; either agent will do
+    Ok(AGENTS.get_or_init(|| agents))
+}
+
+fn build_agents() -> Result<Agents, OlogError> {
+    let connect_timeout = config::connect_timeout(DEFAULT_CONNECT_TIMEOUT)?.value;
+    let tls = config::ca_cert()?.value.as_deref().map(tls_config).transpose()?;
+    let builder = || {
+        let builder = ureq::AgentBuilder::new().timeout_connect(connect_timeout);
+        match &tls {
+            Some(tls) => builder.tls_config(tls.clone()),
+            None => builder,
+        }
+    };
+    let proxied = match config::proxy()?.value {
+        Some(proxy) => {
+            let proxy = ureq::Proxy::new(&proxy).map_err(|e| OlogError::Config(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder().proxy(proxy).build()
+        }
+        None => builder().build(),
+    };
+    let no_proxy = config::no_proxy()?.value.unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect();
+    Ok(Agents { proxied, direct: builder().build(), no_proxy })
+}
+
+// The bundled roots plus every certificate in `path`
+fn tls_config(path: &Path) -> Result<Arc<rustls::ClientConfig>, OlogError> {
+    let invalid = |e: &dyn std::fmt::Display| OlogError::Config(format!("Invalid CA certificate {}: {}", path.display(), e));
+    let mut roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let certs = CertificateDer::pem_file_iter(path).map_err(|e| invalid(&e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(&e))?;
+    if certs.is_empty() {
+        return Err(invalid(&"no PEM certificates found"));
+    }
+    for cert in certs {
+        roots.add(cert).map_err(|e| invalid(&e))?;
+    }
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(&e))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+// The host part of `url`, without userinfo, port or IPv6 brackets
+fn host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.to_ascii_lowercase()
+}
+
+// NO_PROXY entries match the host itself or any subdomain; `*` matches all
+fn bypasses_proxy(host: &str, no_proxy: &[String]) -> bool {
+    LOOPBACK_HOSTS.contains(&host)
+        || no_proxy.iter().any(|entry| {
+            let domain = entry.trim_start_matches('*').trim_start_matches('.');
+            entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+        })
+}
+
+/// A request through the configured agent, or the one given to [`set_agent`]
+pub fn request(method: &str, url: &str) -> Result<ureq::Request, OlogError> {
+    let agents = agents()?;
+    let agent = if bypasses_proxy(&host(url), &agents.no_proxy) { &agents.direct } else { &agents.proxied };
+    Ok(agent.request(method, url))
+}
+
+pub fn get(url: &str) -> Result<ureq::Request, OlogError> {
+    request("GET", url)
+}
+
+pub fn post(url: &str) -> Result<ureq::Request, OlogError> {
+    request("POST", url)
+}
@@ -0,0 +1,88 @@
+use clap::ValueEnum;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::olog::{node_citations, Olog};
+
+const PAGERANK_DAMPING: f64 = 0.85;
+const PAGERANK_ITERATIONS: usize = 100;
+const PAGERANK_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Importance {
+    /// Number of hyperedges a node takes part in
+    Degree,
+    /// PageRank over source -> target links of every hyperedge
+    Pagerank,
+    /// Number of distinct documents supporting the node
+    Support,
+}
+
+// Raw scores for every node, including those no hyperedge touches
+pub fn node_scores(olog: &Olog, importance: Importance) -> HashMap<Uuid, f64> {
+    match importance {
+        Importance::Degree => degree(olog),
+        Importance::Pagerank => pagerank(olog),
+        Importance::Support => {
+            let by_node = node_citations(olog);
+            olog.nodes.iter()
+                .map(|node| (node.id, by_node.get(&node.id).map_or(0, Vec::len) as f64))
+                .collect()
+        },
+    }
+}
+
+// Scores scaled so the most important node is 1.0
+pub fn normalized_scores(olog: &Olog, importance: Importance) -> HashMap<Uuid, f64> {
+    let scores = node_scores(olog, importance);
+    let max = scores.values().cloned().fold(0.0, f64::max);
+    scores.into_iter()
+        .map(|(id, score)| (id, if max > 0.0 { score / max } else { 0.0 }))
+        .collect()
+}
+
+fn degree(olog: &Olog) -> HashMap<Uuid, f64> {
+    let mut scores: HashMap<Uuid, f64> = olog.nodes.iter().map(|node| (node.id, 0.0)).collect();
+    for hyperedge in &olog.hyperedges {
+        for node in hyperedge.source.iter().chain(&hyperedge.target) {
+            *scores.entry(node.id).or_default() += 1.0;
+        }
+    }
+    scores
+}
+
+fn pagerank(olog: &Olog) -> HashMap<Uuid, f64> {
+    let index: HashMap<Uuid, usize> = olog.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+    let n = index.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for hyperedge in &olog.hyperedges {
+        for source in hyperedge.source.iter().filter_map(|node| index.get(&node.id)) {
+            outgoing[*source].extend(hyperedge.target.iter().filter_map(|node| index.get(&node.id)));
+        }
+    }
+
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..PAGERANK_ITERATIONS {
+        // Nodes without outgoing links spread their rank evenly
+        let dangling: f64 = (0..n).filter(|&i| outgoing[i].is_empty()).map(|i| rank[i]).sum();
+        let base = (1.0 - PAGERANK_DAMPING) / n as f64 + PAGERANK_DAMPING * dangling / n as f64;
+        let mut next = vec![base; n];
+        for (from, targets) in outgoing.iter().enumerate() {
+            let share = PAGERANK_DAMPING * rank[from] / targets.len().max(1) as f64;
+            for &to in targets {
+                next[to] += share;
+            }
+        }
+        let delta: f64 = next.iter().zip(&rank).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if delta < PAGERANK_TOLERANCE {
+            break;
+        }
+    }
+
+    olog.nodes.iter().map(|node| (node.id, rank[index[&node.id]])).collect()
+}
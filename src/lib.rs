@@ -0,0 +1,75 @@
+//! Build ontology logs (ologs) from academic papers with a language model and
+//! keep them in SQLite.
+//!
+//! The core pieces are re-exported here: the [`Olog`] data model, generation
+//! with [`generate_olog`] against any [`LlmProvider`] and with
+//! [`generate_merged_olog`], authoring in code with
+//! [`OlogBuilder`], merging with [`merge_ologs`], and storage with
+//! [`write_olog_to_db`] and [`read_olog_from_db`], or [`Store`] to page through
+//! large ologs. Frontends can follow a run through [`events::subscribe`].
+//! Everything else the `olog` command line uses lives in the public modules.
+
+pub mod argmap;
+pub mod boilerplate;
+pub mod book;
+pub mod cache;
+pub mod chunk;
+pub mod config;
+pub mod confirm;
+pub mod consolidate;
+pub mod coverage;
+pub mod db;
+pub mod debate;
+pub mod display;
+pub mod doctor;
+pub mod dump;
+pub mod elastic;
+pub mod embedding;
+pub mod error;
+pub mod estimate;
+pub mod events;
+pub mod export;
+pub mod factcheck;
+pub mod flashcards;
+pub mod format;
+pub mod fsck;
+pub mod gitrepo;
+pub mod hooks;
+pub mod http;
+pub mod importance;
+pub mod llama;
+pub mod llm;
+pub mod metadata;
+pub mod metrics;
+pub mod migrations;
+pub mod ndjson;
+pub mod olog;
+pub mod paths;
+pub mod pdf;
+mod postprocess;
+pub mod preset;
+pub mod prompts;
+pub mod provenance;
+pub mod provider;
+pub mod rdf;
+pub mod redact;
+pub mod reembed;
+pub mod report;
+pub mod retry;
+pub mod sanitize;
+pub mod script;
+pub mod search;
+pub mod split;
+pub mod store;
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod unify;
+pub mod usage;
+
+pub use db::{create_olog_tables, delete_olog, read_olog_from_db, replace_olog_in_db, use_namespace, write_olog_to_db};
+pub use gitrepo::Operation;
+pub use llm::{generate_merged_olog, generate_olog, GenerationOptions};
+pub use provider::LlmProvider;
+pub use olog::{merge_ologs, olog_to_json, Citation, Hyperedge, Node, Olog, OlogBuilder, Quantity};
+pub use store::Store;
@@ -0,0 +1,63 @@
+use std::env;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::error::OlogError;
+use crate::sanitize;
+
+// Overrides the llama.cpp binary, for builds not on PATH or named differently
+pub const LLAMA_CLI_ENV: &str = "OLOG_LLAMA_CLI";
+const DEFAULT_LLAMA_CLI: &str = "llama-cli";
+// Tokens generated per reply; an olog for a long document runs to a few thousand
+const MAX_TOKENS: u32 = 8192;
+// llama-cli reads its prompt from a file; this one is the pipe from olog
+const STDIN: &str = "/dev/stdin";
+
+pub fn binary() -> String {
+    env::var(LLAMA_CLI_ENV).ok().filter(|binary| !binary.trim().is_empty()).unwrap_or_else(|| DEFAULT_LLAMA_CLI.to_string())
+}
+
+// Runs one prompt to completion with greedy sampling. llama.cpp sees a plain
+// completion prompt rather than chat turns, so the guardrail goes first.
+pub fn generate(model_path: &Path, prompt: &str, json: bool) -> Result<String, OlogError> {
+    let binary = binary();
+    let mut command = Command::new(&binary);
+    command
+        .arg("--model").arg(model_path)
+        // Piped rather than passed as an argument, which long documents would
+        // overflow, or written to a temporary file other users could read
+        .args(["--file", STDIN])
+        .args(["--n-predict", &MAX_TOKENS.to_string()])
+        // 0 takes the context length the model was trained with
+        .args(["--ctx-size", "0", "--temp", "0", "--no-display-prompt", "--no-conversation"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if json {
+        command.args(["--json-schema", r#"{"type": "object"}"#]);
+    }
+    let mut child = command.spawn()
+        .map_err(|e| OlogError::Llm(format!("Cannot run {} ({}); install llama.cpp or set {}", binary, e, LLAMA_CLI_ENV)))?;
+
+    // Written from another thread so a child already printing can't block on
+    // a full stdout pipe while the prompt is still going in
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = format!("{}\n\n{}\n", sanitize::GUARDRAIL, prompt);
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    // A child that exits early closes the pipe; its status says why
+    let written = writer.join().expect("prompt writer does not panic");
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default();
+        return Err(OlogError::Llm(format!("{} exited with {}: {}", binary, output.status, last.trim())));
+    }
+    written?;
+    let reply = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if reply.is_empty() {
+        return Err(OlogError::Llm(format!("No response from {}", binary)));
+    }
+    Ok(reply)
+}
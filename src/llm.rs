@@ -0,0 +1,464 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use uuid::Uuid;
+
+use crate::boilerplate;
+use crate::cache;
+use crate::chunk::{chunk_document, Chunking};
+use crate::consolidate;
+use crate::error::OlogError;
+use crate::events::{self, Event};
+use crate::metadata::{self, CitationMetadata, Extractor};
+use crate::olog::{
+    check_olog_references, convert_json_olog_to_olog, distinct_citations, merge_ologs, parse_olog_json,
+    replace_ids_with_uuids, Citation, JsonOlogSchema, Olog,
+};
+use crate::pdf;
+use crate::postprocess;
+use crate::prompts::{self, Template};
+use crate::provider::LlmProvider;
+use crate::redact::{self, Redaction};
+use crate::provenance::NodeProvenance;
+use crate::sanitize;
+use crate::script;
+use crate::unify::{self, SemanticMerge, Unification};
+
+/// One call to `provider`, with the guardrail system message; `json`
+/// constrains the reply to a JSON object. An earlier run's reply to the same
+/// prompt is replayed while it is fresh; otherwise the reply is kept for next time.
+pub fn complete(provider: &dyn LlmProvider, model: &str, prompt: String, json: bool) -> Result<String, OlogError> {
+    let cached = provider.cacheable().then(|| cache::response_path(&provider.name(), model, &prompt, json)).flatten();
+    if let Some(reply) = cached.as_deref().and_then(cache::cached_response) {
+        return Ok(reply);
+    }
+    let reply = if json { provider.complete_json(model, &prompt)? } else { provider.complete(model, &prompt)? };
+    if let Some(path) = cached {
+        if let Err(e) = cache::store_response(&path, &reply) {
+            eprintln!("Could not cache the model's reply in {}: {}", path.display(), e);
+        }
+    }
+    Ok(reply)
+}
+
+/// How a document is turned into an olog; the CLI fills these from a preset and flags
+#[derive(Debug, Clone)]
+pub struct GenerationOptions {
+    /// Model that writes the olog itself
+    pub model: String,
+    /// Model for the shorter title and label calls
+    pub label_model: String,
+    /// Number of generations merged into the stored olog
+    pub count: usize,
+    /// Show each pass after the first the labels earlier passes chose. Passes
+    /// then run one after another; without it they are independent and run
+    /// at once.
+    pub reuse_labels: bool,
+    pub strict_validation: bool,
+    /// External commands applied to each generated olog before it is stored
+    pub postprocessors: Vec<String>,
+    /// Rhai scripts run over each generated olog after the postprocessors
+    pub scripts: Vec<PathBuf>,
+    /// Vocabulary of an existing olog the model should reuse
+    pub context: Option<String>,
+    /// Strip instruction-like passages from the document before prompting
+    pub sanitize: bool,
+    /// Fold synonymous hyperedges together after merging
+    pub consolidate: bool,
+    /// Ask for structured value/unit/uncertainty on hyperedges
+    pub quantities: bool,
+    /// Ask for the passage stating each hyperedge, stored as a span of its citation
+    pub quotes: bool,
+    /// The document is a saved debate; ask for its argument structure
+    pub debate_transcript: bool,
+    /// Boilerplate stripped from the document before prompting
+    pub filters: Vec<boilerplate::Filter>,
+    /// Also merge nodes whose labels are near-duplicates by embedding similarity
+    pub semantic_merge: Option<SemanticMerge>,
+    /// Generate long documents a chunk at a time instead of in one prompt
+    pub chunking: Option<Chunking>,
+    /// Most generations, across passes and chunks, run at once
+    pub parallelism: usize,
+    /// Where titles and labels come from, tried in order
+    pub extractors: Vec<Extractor>,
+    /// Title and subject the source file itself declares, for the
+    /// pdf-metadata extractor
+    pub source_metadata: Option<pdf::Metadata>,
+    /// Mask personal details in the document before any of it reaches a model
+    pub redaction: Option<redact::Rules>,
+}
+
+/// Generations run at once unless `--parallelism` says otherwise
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// A merged olog with which pass produced each node and which labels were
+/// folded together
+#[derive(Debug)]
+pub struct Generated {
+    pub olog: Olog,
+    pub provenance: Vec<NodeProvenance>,
+    pub unified: Vec<Unification>,
+    /// Semantic merges that failed the spot check and were undone, to be
+    /// flagged for review once the olog is stored
+    pub flagged: Vec<Unification>,
+    /// How each citation's title and label were found
+    pub metadata: Vec<CitationMetadata>,
+    /// What the citations' placeholders stand for, to be recorded once the
+    /// olog is stored
+    pub redactions: Vec<Redaction>,
+}
+
+// Upper bounds on how much of a context olog ends up in the prompt
+const CONTEXT_MAX_CONCEPTS: usize = 60;
+const CONTEXT_MAX_RELATIONS: usize = 30;
+
+/// Lists an olog's most connected concepts and most common relations so a new
+/// generation can reuse the same labels instead of inventing near-duplicates
+pub fn summarize_olog_vocabulary(olog: &Olog) -> String {
+    let (concepts, relations) = vocabulary(olog);
+    format!(
+        "**Existing vocabulary**:\nThis document will be merged into an existing olog titled \"{}\". \
+         Whenever the document refers to one of the concepts or relations below, reuse the label exactly as written.\n\
+         Concepts:\n{}\nRelations:\n{}\n",
+        olog.title, concepts, relations,
+    )
+}
+
+// The labels earlier passes over the same document chose, so later passes
+// name the same things the same way and their ologs merge cleanly
+fn summarize_earlier_passes(olog: &Olog, passes: usize) -> String {
+    let (concepts, relations) = vocabulary(olog);
+    let times = if passes == 1 { "once".to_string() } else { format!("{} times", passes) };
+    format!(
+        "**Labels from earlier passes**:\nThis document was already read {} and the resulting ologs used the labels below. \
+         Reuse a label exactly as written whenever it names what you would otherwise label yourself; add new labels only for \
+         concepts and relations it doesn't cover.\n\
+         Concepts:\n{}\nRelations:\n{}\n",
+        times, concepts, relations,
+    )
+}
+
+// The most connected concepts and most common relations, one "- label" line each
+fn vocabulary(olog: &Olog) -> (String, String) {
+    let mut degree: HashMap<&str, usize> = olog.nodes.iter().map(|node| (node.label.as_str(), 0)).collect();
+    let mut relations: HashMap<&str, usize> = HashMap::new();
+    for hyperedge in &olog.hyperedges {
+        for node in hyperedge.source.iter().chain(hyperedge.target.iter()) {
+            *degree.entry(node.label.as_str()).or_insert(0) += 1;
+        }
+        *relations.entry(hyperedge.label.as_str()).or_insert(0) += 1;
+    }
+
+    let ranked = |counts: HashMap<&str, usize>, limit: usize| -> String {
+        let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.into_iter().take(limit).map(|(label, _)| format!("- {}", label)).collect::<Vec<_>>().join("\n")
+    };
+    (ranked(degree, CONTEXT_MAX_CONCEPTS), ranked(relations, CONTEXT_MAX_RELATIONS))
+}
+
+/// Strips boilerplate and, if enabled, instruction-like passages from a document
+pub fn prepare_document(text: &str, options: &GenerationOptions) -> sanitize::Sanitized {
+    let filtered = boilerplate::strip_boilerplate(text, &options.filters);
+    if !filtered.removed.is_empty() {
+        let counts = filtered.removed.iter()
+            .map(|(filter, lines)| format!("{} {}", lines, filter.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        eprintln!("Skipped boilerplate lines before prompting: {}", counts);
+    }
+    let text = filtered.text.as_str();
+
+    let sanitized = if options.sanitize {
+        sanitize::sanitize_document(text)
+    } else {
+        sanitize::Sanitized { text: text.to_string(), removed: Vec::new() }
+    };
+    if !sanitized.removed.is_empty() {
+        eprintln!("Removed {} instruction-like passages from the document before prompting", sanitized.removed.len());
+    }
+    sanitized
+}
+
+/// The user message sent for a template; document is already delimited
+pub fn compose_prompt(template: Template, document: &str, options: &GenerationOptions) -> Result<String, OlogError> {
+    let mut prompt = prompts::load(template)?;
+    if template == Template::Olog && options.quantities {
+        prompt = format!("{}\n\n{}", prompt, prompts::load(Template::Quantities)?);
+    }
+    if template == Template::Olog && options.quotes {
+        prompt = format!("{}\n\n{}", prompt, prompts::load(Template::Quotes)?);
+    }
+    if template == Template::Olog && options.debate_transcript {
+        prompt = format!("{}\n\n{}", prompt, prompts::load(Template::Debate)?);
+    }
+    let context = match (template, &options.context) {
+        (Template::Olog, Some(context)) => context.as_str(),
+        _ => "",
+    };
+    if !context.is_empty() && !prompts::has_var(&prompt, prompts::CONTEXT_VAR) {
+        prompt = format!("{}\n{}", prompt, context);
+    }
+    if !prompts::has_var(&prompt, prompts::TEXT_VAR) {
+        prompt = format!("{}\n{{{}}}", prompt, prompts::TEXT_VAR);
+    }
+    let title = metadata::title_hint(document, options.source_metadata.as_ref()).unwrap_or_default();
+    Ok(prompts::render(&prompt, &[
+        (prompts::TEXT_VAR, document),
+        (prompts::TITLE_VAR, &title),
+        (prompts::CONTEXT_VAR, context),
+    ]))
+}
+
+/// Runs one generation: the olog, title and label prompts, then validation and
+/// postprocessing. The document becomes the citation of every hyperedge.
+pub fn generate_olog(provider: &dyn LlmProvider, text: String, options: &GenerationOptions) -> Result<Olog, OlogError> {
+    generate_with_metadata(provider, text, options).map(|(olog, _)| olog)
+}
+
+// The title and label come from the first of options.extractors confident
+// enough; the model is only asked if the chain reaches it
+fn generate_with_metadata(
+    provider: &dyn LlmProvider,
+    text: String,
+    options: &GenerationOptions,
+) -> Result<(Olog, CitationMetadata), OlogError> {
+    let sanitized = prepare_document(&text, options);
+    let document = sanitize::delimit(&sanitized.text);
+    let openai_response = complete(provider, &options.model, compose_prompt(Template::Olog, &document, options)?, true)?;
+    let mut lookup = metadata::Lookup::new(&text, options.source_metadata.as_ref(), provider.is_offline());
+    let title = lookup.title(&options.extractors, || {
+        complete(provider, &options.label_model, compose_prompt(Template::Title, &document, options)?, false)
+    })?;
+    let label = lookup.label(&options.extractors, || {
+        complete(provider, &options.label_model, compose_prompt(Template::Label, &document, options)?, false)
+    })?;
+    let olog_schema: JsonOlogSchema = parse_olog_json(&openai_response)
+        .map_err(|e| OlogError::Schema(sanitize::schema_violation(e, &openai_response, &sanitized)))?;
+    if olog_schema.nodes.is_empty() {
+        return Err(OlogError::Schema(sanitize::schema_violation("no nodes were extracted", &openai_response, &sanitized)));
+    }
+    let olog_schema = postprocess::run_postprocessors(olog_schema, &options.postprocessors)?;
+    let olog_schema = script::run_scripts(olog_schema, &options.scripts)?;
+    if options.strict_validation {
+        check_olog_references(&olog_schema)?;
+    }
+    let olog_schema_uuid: JsonOlogSchema = replace_ids_with_uuids(olog_schema);
+    let citation: Citation = Citation {
+        id: Uuid::new_v4(),
+        title: title.value.clone(),
+        label: label.value.clone(),
+        text,
+    };
+    let metadata = CitationMetadata { citation_id: citation.id, title, label };
+    let olog: Olog = convert_json_olog_to_olog(olog_schema_uuid, citation);
+
+    Ok((olog, metadata))
+}
+
+/// Generates `options.count` separate ologs and folds them into one. With
+/// chunking, each pass generates one olog per chunk, each citing its chunk.
+/// Unless the passes are independent, each one is prompted with the labels
+/// of those before it.
+pub fn generate_merged_olog(provider: &dyn LlmProvider, text: &str, options: &GenerationOptions) -> Result<Generated, OlogError> {
+    if options.redaction.is_some() {
+        let (redacted, options, redactions) = redact_document(text, options);
+        if !redactions.is_empty() {
+            eprintln!("Redacted {} personal details before prompting", redactions.len());
+        }
+        let generated = generate_merged_olog(provider, &redacted, &options)?;
+        return Ok(Generated { redactions, ..generated });
+    }
+    let chunks = match &options.chunking {
+        Some(chunking) => chunk_document(text, chunking),
+        None => vec![text.to_string()],
+    };
+    if chunks.len() > 1 {
+        println!("Generating from {} chunks.", chunks.len());
+    }
+    events::emit(Event::GenerationStarted { passes: options.count, chunks: chunks.len() });
+
+    // (pass, chunk index)
+    let jobs: Vec<(usize, usize)> = (1..=options.count)
+        .flat_map(|n| (0..chunks.len()).map(move |i| (n, i)))
+        .collect();
+    // Each pass waits for the ones before it when it is to see their labels
+    let rounds: Vec<&[(usize, usize)]> = if options.reuse_labels { jobs.chunks(chunks.len()).collect() } else { vec![&jobs] };
+
+    // Merged in job order, so the result doesn't depend on which call finished first
+    let mut merged_olog: Option<Olog> = None;
+    let mut provenance = Vec::new();
+    let mut metadata = Vec::new();
+    for (passes, round) in rounds.into_iter().enumerate() {
+        let round_options = match &merged_olog {
+            Some(earlier) => {
+                let labels = summarize_earlier_passes(earlier, passes);
+                let context = options.context.as_ref().map_or(labels.clone(), |context| format!("{}\n{}", context, labels));
+                Cow::Owned(GenerationOptions { context: Some(context), ..options.clone() })
+            },
+            None => Cow::Borrowed(options),
+        };
+        let generated = run_bounded(round, options.parallelism, |&(n, i)| {
+            let generated = generate_with_metadata(provider, chunks[i].clone(), &round_options).map_err(|e| {
+                let context = match chunks.len() {
+                    1 => format!("An error occurred in generating Olog{}", n),
+                    count => format!("An error occurred in generating Olog{} from chunk {}/{}", n, i + 1, count),
+                };
+                e.context(context)
+            })?;
+            let (olog, _) = &generated;
+            events::emit(Event::ChunkGenerated {
+                pass: n,
+                chunk: i + 1,
+                chunks: chunks.len(),
+                nodes: olog.nodes.len(),
+                hyperedges: olog.hyperedges.len(),
+            });
+            Ok(generated)
+        });
+
+        for (&(n, i), generated) in round.iter().zip(generated).filter_map(|(job, generated)| Some((job, generated?))) {
+            let (olog, found) = generated?;
+            metadata.push(found);
+            if let Some(citation) = distinct_citations(&olog).first() {
+                let section = (chunks.len() > 1).then(|| format!("Chunk {}/{}", i + 1, chunks.len()));
+                provenance.extend(olog.nodes.iter().map(|node| NodeProvenance {
+                    label: node.label.clone(),
+                    citation_id: citation.id,
+                    pass: n,
+                    section: section.clone(),
+                }));
+            }
+            merged_olog = Some(match merged_olog {
+                Some(merged) => merge_ologs(merged, olog),
+                None => olog,
+            });
+        }
+    }
+    let merged_olog = merged_olog.ok_or_else(|| OlogError::Usage("At least one olog must be generated".to_string()))?;
+    events::emit(Event::MergeCompleted { nodes: merged_olog.nodes.len(), hyperedges: merged_olog.hyperedges.len() });
+    let mut flagged = Vec::new();
+    let (merged_olog, unified) = unify_similar_nodes(provider, merged_olog, &mut provenance, &mut flagged, options)?;
+
+    if !options.consolidate {
+        return Ok(Generated { olog: merged_olog, provenance, unified, flagged, metadata, redactions: Vec::new() });
+    }
+    let (olog, merged) = consolidate::consolidate_hyperedges(provider, merged_olog, &options.model)
+        .map_err(|e| e.context("Error consolidating hyperedges"))?;
+    if merged > 0 {
+        println!("Consolidated {} synonymous hyperedges.", merged);
+    }
+    events::emit(Event::ConsolidationCompleted { merged });
+    Ok(Generated { olog, provenance, unified, flagged, metadata, redactions: Vec::new() })
+}
+
+/// The document and the file's own title and subject with personal details
+/// masked, and options that generate from them without redacting again.
+/// Citations keep the masked text; the mapping stays local.
+pub fn redact_document(text: &str, options: &GenerationOptions) -> (String, GenerationOptions, Vec<Redaction>) {
+    let mut options = options.clone();
+    let Some(rules) = options.redaction.take() else {
+        return (text.to_string(), options, Vec::new());
+    };
+    let source = options.source_metadata.take();
+    let field = |field: fn(&pdf::Metadata) -> &Option<String>| source.as_ref().and_then(|source| field(source).as_deref()).unwrap_or("");
+    let texts = [text, field(|source| &source.title), field(|source| &source.subject)];
+    let (redacted, redactions) = redact::redact_all(&texts, &rules);
+    let [text, title, subject]: [String; 3] = redacted.try_into().expect("one redacted text per input");
+    options.source_metadata = source.map(|source| pdf::Metadata {
+        title: source.title.map(|_| title),
+        subject: source.subject.map(|_| subject),
+    });
+    (text, options, redactions)
+}
+
+// Runs `job` over `jobs`, up to `parallelism` at a time, and returns the
+// results in job order. Jobs are started in order and none after one fails,
+// so every job before the first failure has a result; later ones may be None.
+fn run_bounded<J: Sync, T: Send>(
+    jobs: &[J],
+    parallelism: usize,
+    job: impl Fn(&J) -> Result<T, OlogError> + Sync,
+) -> Vec<Option<Result<T, OlogError>>> {
+    if parallelism <= 1 || jobs.len() <= 1 {
+        let mut results = Vec::with_capacity(jobs.len());
+        for each in jobs {
+            let result = job(each);
+            let failed = result.is_err();
+            results.push(Some(result));
+            if failed {
+                break;
+            }
+        }
+        results.resize_with(jobs.len(), || None);
+        return results;
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<Result<T, OlogError>>>> = Mutex::new((0..jobs.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..parallelism.min(jobs.len()) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(each) = jobs.get(index) else {
+                        break;
+                    };
+                    let result = job(each);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    results.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = Some(result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Applies the semantic merge, if enabled, and points provenance entries at
+/// the labels that were kept. When the spot check finds too many wrong merges
+/// the olog is returned unmerged and the merges are added to `flagged`.
+pub fn unify_similar_nodes(
+    provider: &dyn LlmProvider,
+    olog: Olog,
+    provenance: &mut [NodeProvenance],
+    flagged: &mut Vec<Unification>,
+    options: &GenerationOptions,
+) -> Result<(Olog, Vec<Unification>), OlogError> {
+    let Some(merge) = &options.semantic_merge else {
+        return Ok((olog, Vec::new()));
+    };
+    let unmerged = olog.clone();
+    let (olog, unified) = unify::unify_nodes(provider, olog, merge)
+        .map_err(|e| e.context("Error merging similar nodes"))?;
+    if let (Some(check), false) = (&merge.check, unified.is_empty()) {
+        let grade = unify::grade_unifications(provider, &unified, check)
+            .map_err(|e| e.context("Error checking the semantic merge"))?;
+        if grade.error_rate() > check.max_error_rate {
+            let examples = grade.wrong.iter()
+                .map(|(alias, label)| format!("\"{}\" into \"{}\"", alias, label))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!(
+                "Undid the semantic merge: {} of {} sampled merges look wrong ({}).",
+                grade.wrong.len(), grade.checked, examples
+            );
+            events::emit(Event::NodesUnified { merged: unified.len(), undone: true });
+            flagged.extend(unified);
+            return Ok((unmerged, Vec::new()));
+        }
+    }
+    for unification in &unified {
+        println!("Merged \"{}\" into \"{}\".", unification.aliases.join("\", \""), unification.label);
+    }
+    events::emit(Event::NodesUnified { merged: unified.iter().map(|unification| unification.aliases.len()).sum(), undone: false });
+    for entry in provenance.iter_mut() {
+        entry.label = unify::canonical_label(&unified, &entry.label).to_string();
+    }
+    Ok((olog, unified))
+}
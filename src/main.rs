@@ -1,485 +1,1952 @@
-use openai_api_rs::v1::api::Client;
-use openai_api_rs::v1::chat_completion::{self, ChatCompletionRequest};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
 use uuid::Uuid;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use rusqlite::{params, Connection, Result};
-
-#[derive(Debug, Serialize, Deserialize)]
-struct JsonOlogSchema {
-    title: String,
-    nodes: Vec<JsonNodeSchema>,
-    hyperedges: Vec<JsonHyperedgeSchema>,
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct JsonNodeSchema {
-    id: String,
-    label: String,
+use olog::db::{
+    create_olog_tables, database_location, database_path, delete_olog, document_key, find_ingested_olog, has_table, list_ologs_titled,
+    load_olog, merge_parents, olog_in_namespace, open_database, read_olog_from_db, record_ingestion,
+    record_merge_parents, replace_olog_in_db, set_olog_kind, use_namespace, write_olog_to_db, OlogKind, DEFAULT_NAMESPACE,
+};
+use olog::error::{CliError, ErrorCategory, ErrorFormat, OlogError};
+use olog::format::OutputFormat;
+use olog::gitrepo::{self, Operation};
+use olog::llm::{
+    compose_prompt, generate_merged_olog, prepare_document, redact_document, summarize_olog_vocabulary, unify_similar_nodes,
+    GenerationOptions, Generated, DEFAULT_PARALLELISM,
+};
+use olog::olog::{merge_ologs, olog_from_json, olog_json_schema, olog_to_json, reassign_ids, Citation, Olog};
+use olog::prompts::{self, Template};
+use olog::provider::Provider;
+use olog::{
+    argmap, boilerplate, book, cache, chunk, config, confirm, consolidate, coverage, debate, display, doctor, dump, elastic,
+    embedding, estimate, export, factcheck, flashcards, format, fsck, hooks, http, importance, metadata, metrics,
+    migrations, ndjson, paths, pdf, preset, provenance, provider, rdf, redact, reembed, report, retry, sanitize, search,
+    split, sync, unify, usage,
+};
+
+#[derive(Parser)]
+#[command(name = "olog", about = "Build ologs from academic papers")]
+struct Cli {
+    /// How to report errors on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
+    /// Reload prompt templates from src/res on every call instead of using the built-in copies
+    #[arg(long, global = true)]
+    prompt_dev: bool,
+
+    /// Directory of prompt templates (olog.md, title.md, label.md, quantities.md, quotes.md, debate.md) that replace the built-in ones; defaults to OLOG_PROMPT_DIR, then `prompt_dir` in olog.toml
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "prompt_dev")]
+    prompt_dir: Option<PathBuf>,
+
+    /// Tries per model or embedding request before giving up on rate limits, server errors and dropped connections; defaults to OLOG_MAX_ATTEMPTS, then `max_attempts` in the user config, then 5
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: Option<u32>,
+
+    /// Go ahead with deletions and other destructive operations without asking first
+    #[arg(long, short = 'y', global = true, visible_alias = "force")]
+    yes: bool,
+
+    /// Ask the model again instead of replaying replies cached by earlier runs with the same prompts
+    #[arg(long, global = true)]
+    no_llm_cache: bool,
+
+    /// Hours a cached model reply stays valid; replies live under $XDG_CACHE_HOME/olog/responses
+    #[arg(long, global = true, value_name = "HOURS", default_value_t = cache::DEFAULT_RESPONSE_TTL_HOURS)]
+    llm_cache_ttl: u64,
+
+    /// Where model calls go, overriding OLOG_PROVIDER and `provider` in the user config (default openai); ollama and local-gguf send nothing to cloud services, so with the local PDF extractor a document never leaves the machine
+    #[arg(long, global = true, value_enum)]
+    provider: Option<Provider>,
+
+    /// GGUF model file for --provider local-gguf; the llama.cpp binary is OLOG_LLAMA_CLI, default llama-cli
+    #[arg(long, global = true, value_name = "PATH")]
+    model_path: Option<PathBuf>,
+
+    /// Project namespace to work in; ologs in other namespaces are invisible
+    #[arg(long, global = true, default_value = DEFAULT_NAMESPACE)]
+    namespace: String,
+
+    /// Database file; defaults to OLOG_DB_PATH, then `database` in olog.toml, then in the user config, then ./olog.db if it exists, then the XDG data directory
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct JsonHyperedgeSchema {
-    id: String,
-    label: String,
-    sources: Vec<String>,
-    targets: Vec<String>,
+// Generation settings shared by every command that calls the model
+#[derive(Args)]
+struct GenerationArgs {
+    /// Shell command that rewrites each generated olog (JSON on stdin and stdout); repeatable
+    #[arg(long = "postprocessor", value_name = "CMD")]
+    postprocessors: Vec<String>,
+    /// Rhai script that rewrites each generated olog, run after the postprocessors; repeatable (needs the scripting feature)
+    #[arg(long = "script", value_name = "FILE")]
+    scripts: Vec<PathBuf>,
+    /// Existing olog whose key concepts the model should reuse as labels
+    #[arg(long, value_name = "UUID")]
+    context_olog: Option<Uuid>,
+    /// Bundle of model, generation count and validation settings (quick, balanced, thorough, offline for --provider ollama, or one from olog.toml)
+    #[arg(long, default_value = preset::DEFAULT_PRESET)]
+    preset: String,
+    /// Model for every call, overriding the preset and OLOG_MODEL (any name the API endpoint accepts)
+    #[arg(long)]
+    model: Option<String>,
+    /// Model for the title and label calls, overriding the preset and OLOG_LABEL_MODEL
+    #[arg(long)]
+    label_model: Option<String>,
+    /// Number of ologs to generate and merge, overriding the preset
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    count: Option<u32>,
+    /// Run the generations as independent passes at once, instead of showing each pass the labels earlier ones chose
+    #[arg(long)]
+    independent_passes: bool,
+    /// Send the document as-is instead of stripping instruction-like passages
+    #[arg(long)]
+    no_sanitize: bool,
+    /// Keep hyperedges with equivalent labels separate, overriding the preset
+    #[arg(long)]
+    no_consolidate: bool,
+    /// Mask emails, phone numbers, identifiers and names (plus [redaction] in olog.toml) before the document reaches a model; local views and exports other than export-ndjson put the originals back
+    #[arg(long)]
+    redact: bool,
+    /// Extract reported numbers as structured quantities (value, unit, uncertainty) on hyperedges
+    #[arg(long)]
+    quantities: bool,
+    /// Have each hyperedge quote the passage stating it, stored as a character span of its citation
+    #[arg(long)]
+    quotes: bool,
+    /// Boilerplate to leave in the document instead of stripping it; repeatable
+    #[arg(long = "keep", value_enum, value_name = "FILTER")]
+    keep: Vec<boilerplate::Filter>,
+    /// Send the document without stripping any boilerplate
+    #[arg(long, conflicts_with = "keep")]
+    no_filters: bool,
+    /// How to get text out of PDF input
+    #[arg(long, value_enum, default_value_t = pdf::Ocr::Local)]
+    ocr: pdf::Ocr,
+    /// Extract PDF text again instead of reusing the text cached by an earlier run
+    #[arg(long)]
+    no_cache: bool,
+    /// Also merge nodes whose labels mean the same thing ("neural network" and "neural networks"), by embedding similarity
+    #[arg(long)]
+    semantic_merge: bool,
+    /// Minimum cosine similarity between labels for --semantic-merge to fold them together
+    #[arg(long, default_value_t = unify::DEFAULT_THRESHOLD, requires = "semantic_merge")]
+    merge_threshold: f32,
+    /// Embedding model for --semantic-merge; with --provider ollama, one pulled into Ollama such as nomic-embed-text
+    #[arg(long = "merge-embedding-model", default_value = embedding::DEFAULT_MODEL, requires = "semantic_merge")]
+    merge_embedding_model: String,
+    /// Merged labels the model spot-checks after --semantic-merge
+    #[arg(long, default_value_t = unify::DEFAULT_CHECK_SAMPLE, requires = "semantic_merge")]
+    merge_check_sample: usize,
+    /// Share of spot-checked merges judged wrong above which the merge is undone and flagged for review
+    #[arg(long, default_value_t = unify::DEFAULT_MAX_ERROR_RATE, requires = "semantic_merge")]
+    merge_max_error_rate: f32,
+    /// Keep every --semantic-merge without the spot check
+    #[arg(long, requires = "semantic_merge")]
+    no_merge_check: bool,
+    /// Generate from chunks of about this many tokens and merge the results, for documents too long for one prompt
+    #[arg(long, value_name = "TOKENS", value_parser = clap::value_parser!(u32).range(100..))]
+    chunk_size: Option<u32>,
+    /// Tokens each chunk repeats from the end of the previous one
+    #[arg(long, value_name = "TOKENS", default_value_t = chunk::DEFAULT_OVERLAP as u32, requires = "chunk_size")]
+    chunk_overlap: u32,
+    /// Where to take titles and labels from, in order; the first confident enough wins
+    #[arg(long = "title-from", value_enum, value_name = "EXTRACTOR", value_delimiter = ',', default_values_t = metadata::Extractor::ALL)]
+    title_from: Vec<metadata::Extractor>,
+    /// Most generations to run at once: a pass's chunks, or with --independent-passes every pass's chunks; defaults to OLOG_PARALLELISM, then `parallelism` in the user config, then 4
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    parallelism: Option<u32>,
 }
 
-#[derive(Debug, Clone)]
-struct Citation {
-    id: Uuid,
-    title: String,
-    label: String,
-    text: String,
-}
+fn generation_options(conn: &Connection, args: GenerationArgs) -> Result<GenerationOptions, CliError> {
+    let context = match args.context_olog {
+        Some(olog_id) => {
+            Some(summarize_olog_vocabulary(&load_olog(conn, olog_id)?))
+        },
+        None => None,
+    };
+    let preset = preset::resolve(&args.preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+    if args.chunk_size.is_some_and(|size| args.chunk_overlap >= size) {
+        return Err(CliError::new(ErrorCategory::Usage, "--chunk-overlap must be smaller than --chunk-size"));
+    }
+    let parallelism = config::parallelism(args.parallelism.map(|parallelism| parallelism as usize), DEFAULT_PARALLELISM)
+        .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+    let filters = if args.no_filters {
+        Vec::new()
+    } else {
+        boilerplate::enabled(&args.keep).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?
+    };
+    // --model replaces the preset's models for every call; --label-model only for titles and labels
+    let model = args.model.clone().unwrap_or(preset.model);
+    let label_model = args.label_model.or(args.model).or(preset.label_model).unwrap_or_else(|| model.clone());
+    let redaction = args.redact
+        .then(redact::rules)
+        .transpose()
+        .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+    let semantic_merge = args.semantic_merge.then(|| unify::SemanticMerge {
+        model: args.merge_embedding_model,
+        threshold: args.merge_threshold,
+        check: (!args.no_merge_check).then(|| unify::MergeCheck {
+            model: label_model.clone(),
+            sample: args.merge_check_sample,
+            max_error_rate: args.merge_max_error_rate,
+        }),
+    });
+
+    let count = args.count.map_or(preset.count, |count| count as usize);
+    // Passes that see earlier passes' labels wait for them, so only chunks can overlap
+    if count > 1 && !args.independent_passes && args.chunk_size.is_none()
+        && parallelism.value > 1 && parallelism.source != config::Source::Default
+    {
+        eprintln!(
+            "The {} passes run one after another so each can reuse earlier labels; \
+             pass --independent-passes to run them {} at a time.",
+            count, parallelism.value
+        );
+    }
 
-#[derive(Debug, Clone)]
-struct Hyperedge {
-    id: Uuid,
-    label: String,
-    source: Vec<Node>,
-    target: Vec<Node>,
-    citations: Vec<Citation>,
+    Ok(GenerationOptions {
+        model,
+        label_model,
+        count,
+        reuse_labels: !args.independent_passes,
+        strict_validation: preset.strict_validation,
+        postprocessors: args.postprocessors,
+        scripts: args.scripts,
+        context,
+        sanitize: !args.no_sanitize,
+        consolidate: preset.consolidate && !args.no_consolidate,
+        quantities: args.quantities,
+        quotes: args.quotes,
+        debate_transcript: false,
+        filters,
+        semantic_merge,
+        chunking: args.chunk_size.map(|size| chunk::Chunking { size: size as usize, overlap: args.chunk_overlap as usize }),
+        parallelism: parallelism.value,
+        extractors: args.title_from,
+        source_metadata: None,
+        redaction,
+    })
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-struct Node {
-    id: Uuid,
-    label: String,
+#[derive(Subcommand)]
+enum Commands {
+    /// Generate an olog from a paper and store it in the database
+    ProcessPaper {
+        /// Markdown, plain-text or PDF file to process (defaults to the bundled olog paper)
+        file: Option<PathBuf>,
+        /// What to do when the same document (hash, DOI or arXiv id) was ingested before
+        #[arg(long, value_enum, default_value_t = OnDuplicate::Skip)]
+        on_duplicate: OnDuplicate,
+        #[command(flatten)]
+        generation: GenerationArgs,
+    },
+    /// Estimate tokens and cost of processing a document, without calling the model
+    Estimate {
+        /// File or http(s) URL to estimate (defaults to the bundled olog paper)
+        #[arg(value_name = "FILE|URL")]
+        source: Option<String>,
+        #[command(flatten)]
+        generation: GenerationArgs,
+    },
+    /// Split a book into chapters and generate one olog per chapter
+    ProcessBook {
+        /// Markdown, plain-text or PDF file holding the whole book
+        file: PathBuf,
+        /// Title of the book olog (defaults to the file name)
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        generation: GenerationArgs,
+    },
+    /// Merge a book's chapter ologs into one concept map and show it
+    BookMap {
+        /// Id of the book olog printed by process-book
+        book_id: Uuid,
+        /// Re-merge even if no chapter changed since the last map
+        #[arg(long)]
+        rebuild: bool,
+        /// Show full labels and citation text instead of truncated previews
+        #[arg(long)]
+        full: bool,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print the exact prompt that would be sent for a document, without calling the model
+    ShowPrompt {
+        /// Markdown or plain-text file (defaults to the bundled olog paper)
+        file: Option<PathBuf>,
+        /// Which prompt to compose
+        #[arg(long, value_enum, default_value_t = Template::Olog)]
+        template: Template,
+        #[command(flatten)]
+        generation: GenerationArgs,
+    },
+    /// Show a stored olog as tables with citation previews
+    ReadDb {
+        /// Id of the olog to show
+        olog_id: Uuid,
+        /// Show full labels and citation text instead of truncated previews
+        #[arg(long)]
+        full: bool,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print a stored olog as JSON, including its citations
+    OlogJson {
+        /// Id of the olog to export
+        olog_id: Uuid,
+    },
+    /// Merge two stored ologs into a new one, joining nodes and hyperedges with the same labels
+    MergeOlogs {
+        /// Id of the first olog; its title is the default for the result
+        first: Uuid,
+        /// Id of the second olog
+        second: Uuid,
+        /// Title of the merged olog
+        #[arg(long)]
+        title: Option<String>,
+        /// Keep both input ologs and link the result back to them (the default)
+        #[arg(long, conflicts_with = "delete_sources")]
+        keep_sources: bool,
+        /// Delete both input ologs once the merged one is written
+        #[arg(long)]
+        delete_sources: bool,
+    },
+    /// Merge hyperedges of a stored olog that join the same nodes with equivalent labels
+    Consolidate {
+        /// Id of the olog to clean up
+        olog_id: Uuid,
+        /// Preset whose model judges which labels are equivalent
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+    },
+    /// Go through semantic merges that failed their spot check and apply the ones you accept
+    ReviewMerges {
+        /// Id of the olog the merges were flagged on
+        olog_id: Uuid,
+    },
+    /// Find nodes used for unrelated concepts, or split one of them apart
+    SplitNode {
+        /// Id of the olog to inspect
+        olog_id: Uuid,
+        /// Node to split; without it, overloaded nodes are only listed
+        #[arg(long)]
+        node: Option<Uuid>,
+        /// Label for each new concept, in the listed order; the model names them otherwise
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// Minimum average similarity for hyperedges to describe the same concept
+        #[arg(long, default_value_t = 0.5)]
+        threshold: f32,
+        #[arg(long, default_value = embedding::DEFAULT_MODEL)]
+        embedding_model: String,
+        /// Preset whose model names the new concepts
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+    },
+    /// Explain how one node leads to another by narrating the hyperedge paths between them
+    ExplainPath {
+        /// Id of the olog to search
+        olog_id: Uuid,
+        /// Node to start from, by id or exact label
+        from: String,
+        /// Node to reach, by id or exact label
+        to: String,
+        /// Longest path to consider, in hyperedges
+        #[arg(long, default_value_t = 4)]
+        max_hops: usize,
+        /// Number of paths to explain, shortest first
+        #[arg(long, default_value_t = 3)]
+        max_paths: usize,
+        /// Print the hyperedges as they are instead of asking the model to narrate them
+        #[arg(long)]
+        plain: bool,
+        /// Preset whose model narrates the paths
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+    },
+    /// Have two agents argue for and against a claim, using only an olog's facts and citations as evidence
+    Debate {
+        /// Id of the olog to argue from
+        olog_id: Uuid,
+        /// The claim to debate
+        claim: String,
+        /// Number of rounds; each round has one argument per side
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        rounds: u32,
+        /// Preset whose model plays both sides
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+        /// Reviewer who raises objections after every argument: empiricist, theorist, editor, or one from personas/ in the prompt directory; repeatable
+        #[arg(long = "persona", value_name = "NAME")]
+        personas: Vec<String>,
+    },
+    /// Ask a model whether each hyperedge is supported by the text it cites, and save the verdicts
+    FactCheck {
+        /// Id of the olog to check
+        olog_id: Uuid,
+        /// Preset whose model judges the hyperedges
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+        /// Print the saved verdicts from the last check instead of checking again
+        #[arg(long)]
+        report: bool,
+    },
+    /// Show how much of the source text an olog represents, section by section, and which sections it leaves out
+    Coverage {
+        /// Id of the olog to measure
+        olog_id: Uuid,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Replay a saved debate with the hyperedges and citations each turn relied on
+    ShowDebate {
+        /// Id printed when the debate finished
+        debate_id: Uuid,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Generate an olog of a saved debate's argument structure (claims, objections, the facts they rest on) and store it with kind `debate`
+    DebateOlog {
+        /// Id printed when the debate finished
+        debate_id: Uuid,
+        /// What to do when the same transcript was ingested before
+        #[arg(long, value_enum, default_value_t = OnDuplicate::Skip)]
+        on_duplicate: OnDuplicate,
+        #[command(flatten)]
+        generation: GenerationArgs,
+    },
+    /// Extract an argument map (claims, premises, and which supports or attacks which) from a document and store it
+    GenerateArgmap {
+        /// Markdown, plain-text or PDF file to map (defaults to the bundled olog paper)
+        file: Option<PathBuf>,
+        /// Title of the map (defaults to the document's own title)
+        #[arg(long)]
+        title: Option<String>,
+        /// Also link the claims to the hyperedges of this olog that state them or their grounds
+        #[arg(long = "olog", value_name = "OLOG_ID")]
+        olog_id: Option<Uuid>,
+        /// Preset whose model extracts the map
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+        /// How to get text out of PDF input
+        #[arg(long, value_enum, default_value_t = pdf::Ocr::Local)]
+        ocr: pdf::Ocr,
+        /// Extract PDF text again instead of reusing the text cached by an earlier run
+        #[arg(long)]
+        no_cache: bool,
+    },
+    /// Link a stored argument map's claims to an olog's hyperedges, replacing any earlier links
+    LinkArgmap {
+        /// Id printed by generate-argmap
+        argmap_id: Uuid,
+        /// Id of the olog to link to
+        olog_id: Uuid,
+        /// Preset whose model matches claims to hyperedges
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+    },
+    /// Show a stored argument map with its relations and the hyperedges its claims are linked to
+    ShowArgmap {
+        /// Id printed by generate-argmap
+        argmap_id: Uuid,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Render a stored olog as a Graphviz or HTML view colored by source document
+    Export {
+        /// Id of the olog to export
+        olog_id: Uuid,
+        #[arg(long, value_enum, default_value_t = export::ExportFormat::Html)]
+        format: export::ExportFormat,
+        /// Scale nodes by this importance measure
+        #[arg(long, value_enum)]
+        size_by: Option<importance::Importance>,
+        /// Prefix for exported ids, such as a base URI, so ologs from different databases don't collide downstream; defaults to `export_id_prefix` in olog.toml
+        #[arg(long)]
+        id_prefix: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Print a stored olog as Graphviz DOT, with hyperedges as diamonds between their sources and targets
+    ExportDot {
+        /// Id of the olog to export
+        olog_id: Uuid,
+        /// Scale nodes by this importance measure
+        #[arg(long, value_enum)]
+        size_by: Option<importance::Importance>,
+        /// Prefix for exported ids, such as a base URI, so ologs from different databases don't collide downstream; defaults to `export_id_prefix` in olog.toml
+        #[arg(long)]
+        id_prefix: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Print a stored olog as a Mermaid flowchart for Markdown notes and wikis
+    ExportMermaid {
+        /// Id of the olog to export
+        olog_id: Uuid,
+        /// Label arrows with their relation; hyperedges with several sources or targets are always labeled
+        #[arg(long)]
+        edge_labels: bool,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Print a stored olog as an OWL ontology for Protégé and triple stores, with citations as provenance annotations
+    ExportRdf {
+        /// Id of the olog to export
+        olog_id: Uuid,
+        /// Serialization to write
+        #[arg(long, value_enum, default_value_t = rdf::RdfFormat::Turtle)]
+        format: rdf::RdfFormat,
+        /// Base IRI for exported ids, such as https://example.org/ologs/; defaults to `export_id_prefix` in olog.toml, then urn:uuid:
+        #[arg(long)]
+        id_prefix: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Stream nodes, hyperedges and citations as newline-delimited JSON, as stored: redacted text keeps its placeholders, as in the search index
+    ExportNdjson {
+        /// Id of the olog to export
+        #[arg(required_unless_present = "all")]
+        olog_id: Option<Uuid>,
+        /// Export every olog in the namespace
+        #[arg(long, conflicts_with = "olog_id")]
+        all: bool,
+    },
+    /// Print SQL statements that recreate a single olog in another database
+    ExportSql {
+        /// Id of the olog to dump
+        olog_id: Uuid,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Turn an olog's hyperedges into question/answer cards for Anki
+    Flashcards {
+        /// Id of the olog to study
+        olog_id: Uuid,
+        /// Build questions straight from the hyperedges instead of asking the model
+        #[arg(long)]
+        plain: bool,
+        /// Preset whose model writes the questions
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Upgrade the database to the schema this build expects, after copying it aside
+    Migrate {
+        /// List the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Upgrade in place without the copy
+        #[arg(long)]
+        no_backup: bool,
+    },
+    /// Check the database file and its foreign keys, and optionally delete rows that reference missing ones
+    Fsck {
+        /// Delete rows with dangling references, after copying the database aside
+        #[arg(long)]
+        repair: bool,
+        /// Repair in place without the copy
+        #[arg(long, requires = "repair")]
+        no_backup: bool,
+    },
+    /// Manage the caches of text extracted from PDFs and of model replies
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Describe the file formats olog reads and writes
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommand,
+    },
+    /// Inspect the layered configuration: flags, then environment variables, then the user config file, then defaults
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Check API key, provider access, models, database and prompts before a real run
+    Doctor {
+        /// Preset whose model should be checked
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+    },
+    /// Delete an olog with its nodes, hyperedges, links and unreferenced citations
+    DeleteOlog {
+        /// Id of the olog to delete
+        olog_id: Uuid,
+    },
+    /// Show a node's hyperedges and the document text and generation pass that produced it
+    ShowNode {
+        /// Id of the node
+        node_id: Uuid,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Find ologs whose node labels, hyperedge labels or citation text mention the query
+    Search {
+        /// Words to look for; all of them must match
+        #[arg(required_unless_present = "reindex")]
+        query: Vec<String>,
+        /// Most matches to show
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+        limit: u32,
+        /// Pass the query to SQLite FTS5 as written, for phrases ("gradient descent"), OR, NEAR and prefix* searches
+        #[arg(long)]
+        raw: bool,
+        /// Rebuild the search index from the stored ologs, e.g. after loading a SQL dump
+        #[arg(long)]
+        reindex: bool,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Write a Markdown dossier of every hyperedge involving a concept, across all ologs in the namespace
+    ConceptReport {
+        /// Node label to report on, matched case-insensitively and against merged aliases
+        label: String,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// List the ologs in the current namespace
+    ListOlogs {
+        /// Only ologs whose title starts with this, ignoring case
+        #[arg(long, value_name = "PREFIX")]
+        title: Option<String>,
+        /// Only ologs of this kind; repeatable
+        #[arg(long, value_enum)]
+        kind: Vec<OlogKind>,
+        /// Same as --format json
+        #[arg(long, hide = true, conflicts_with = "format")]
+        json: bool,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print database-derived gauges (ologs, nodes, citations, OCR cache, database size) in the Prometheus text format
+    Metrics {
+        /// Write them to this file instead, replacing it in one step, for a node_exporter textfile collector
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Tokens and estimated cost of the model calls made so far, totalled by day, model, olog or command
+    Usage {
+        #[arg(long, value_enum, default_value_t = usage::GroupBy::Day)]
+        by: usage::GroupBy,
+        /// Only calls made on or after this day (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// List namespaces in the database with their olog counts
+    Namespaces {
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare ids for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Embed node labels with a new embedding model, in rate-limited batches that resume where a failed run stopped
+    Reembed {
+        /// Id of the olog whose nodes to embed
+        #[arg(required_unless_present = "all")]
+        olog_id: Option<Uuid>,
+        /// Embed every node in the namespace
+        #[arg(long, conflicts_with = "olog_id")]
+        all: bool,
+        /// Embedding model to switch to
+        #[arg(long, default_value = embedding::DEFAULT_MODEL)]
+        model: String,
+        /// Labels sent per request
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=2048))]
+        batch_size: u32,
+        /// Upper bound on embedding requests per minute
+        #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+        requests_per_minute: u32,
+        /// Retries per batch before the job stops; defaults to --max-attempts less one
+        #[arg(long)]
+        retries: Option<u32>,
+    },
+    /// Store a Turtle ontology's classes, properties and assertions as an olog, to seed later generations with --context-olog
+    ImportRdf {
+        /// Turtle (.ttl) or OWL-in-Turtle file
+        file: PathBuf,
+        /// Olog title; defaults to the ontology's title or the file name
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Store an olog from `olog-json` output, e.g. after editing an export by hand, under fresh ids
+    ImportJson {
+        /// JSON file as written by olog-json
+        file: PathBuf,
+        /// Text file to cite for hyperedges that cite nothing; defaults to the JSON file itself
+        #[arg(long)]
+        citation: Option<PathBuf>,
+        /// Olog title; defaults to the title in the file
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Import ologs from another olog database and reconcile divergent copies
+    Sync {
+        /// Path to the other olog.db
+        other_db: PathBuf,
+        /// What to do with ologs that exist in both databases but differ
+        #[arg(long, value_enum, default_value_t = sync::Resolution::Skip)]
+        resolve: sync::Resolution,
+    },
 }
 
-#[derive(Debug)]
-struct Olog {
-    id: Uuid,
-    title: String,
-    nodes: Vec<Node>,
-    hyperedges: Vec<Hyperedge>,
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Forget all cached text and model replies, so every PDF is extracted and every prompt sent again
+    Clear,
 }
 
-fn create_olog_tables() -> Result<(), rusqlite::Error> {
-    let conn = Connection::open("olog.db")?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Ologs (
-            olog_id TEXT PRIMARY KEY,
-            title TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Nodes (
-            node_id TEXT PRIMARY KEY,
-            label TEXT NOT NULL,
-            olog_id TEXT NOT NULL,
-            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Hyperedges (
-            hyperedge_id TEXT PRIMARY KEY,
-            label TEXT NOT NULL,
-            olog_id TEXT NOT NULL,
-            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Citations (
-            citation_id TEXT PRIMARY KEY,
-            title TEXT,
-            label TEXT,
-            text TEXT
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Hyperedge_Links (
-            hyperedge_id TEXT NOT NULL,
-            node_id TEXT NOT NULL,
-            type TEXT NOT NULL,
-            FOREIGN KEY(hyperedge_id) REFERENCES Hyperedges(hyperedge_id),
-            FOREIGN KEY(node_id) REFERENCES Nodes(node_id)
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS Citation_Links (
-            hyperedge_id TEXT NOT NULL,
-            citation_id TEXT NOT NULL,
-            FOREIGN KEY(hyperedge_id) REFERENCES Hyperedges(hyperedge_id),
-            FOREIGN KEY(citation_id) REFERENCES Citations(citation_id)
-        )",
-        [],
-    )?;
-
-    Ok(())
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print every effective setting and where it came from
+    Show {
+        /// Preset whose models to show
+        #[arg(long, default_value = preset::DEFAULT_PRESET)]
+        preset: String,
+        /// How to print the result: aligned tables, or JSON, YAML, CSV or bare setting names for scripts
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
 }
 
-fn read_olog_from_db(olog_id: Uuid) -> Result<Olog> {
-    let conn = Connection::open("olog.db")?;
-
-    let mut stmt = conn.prepare("SELECT title FROM Ologs WHERE olog_id = ?1")?;
-    let olog_title: String = stmt.query_row(params![olog_id.to_string()], |row| row.get(0))?;
-
-    let mut stmt = conn.prepare("SELECT node_id, label FROM Nodes WHERE olog_id = ?1")?;
-    let nodes_iter = stmt.query_map(params![olog_id.to_string()], |row| {
-        let id_str: String = row.get(0)?;
-        let id = Uuid::parse_str(&id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-        Ok(Node { id, label: row.get(1)? })
-    })?;
-
-    let nodes: Vec<Node> = nodes_iter
-        .into_iter()
-        .filter_map(|result| result.ok())  // Handle each row's result
-        .collect();
-
-    let mut stmt = conn.prepare("SELECT hyperedge_id, label FROM Hyperedges WHERE olog_id = ?1")?;
-    let hyperedges_iter = stmt.query_map(params![olog_id.to_string()], |row| {
-        let hyperedge_id_str: String = row.get(0)?;
-        let hyperedge_id = Uuid::parse_str(&hyperedge_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-        let mut stmt = conn.prepare("
-            SELECT c.citation_id, c.title, c.label, c.text
-            FROM Citations AS c
-            JOIN Citation_Links AS cl ON c.citation_id = cl.citation_id
-            WHERE cl.hyperedge_id = ?1
-        ")?;
-        let citations_iter = stmt.query_map(params![hyperedge_id.to_string()], |row| {
-            let citation_id_str: String = row.get(0)?;
-            let citation_id = Uuid::parse_str(&citation_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-    
-            Ok(Citation {
-                id: citation_id,
-                title: row.get(1)?,
-                label: row.get(2)?,
-                text: row.get(3)?,
-            })
-        })?;
-
-        let citations: Vec<Citation> = citations_iter
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let mut stmt = conn.prepare("SELECT node_id FROM Hyperedge_Links WHERE hyperedge_id = ?1 AND type = 'source'")?;
-        let sources_iter = stmt.query_map(params![hyperedge_id.to_string()], |row| {
-            let node_id_str: String = row.get(0)?;
-            let node_id = Uuid::parse_str(&node_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-            nodes.iter().find(|&n| n.id == node_id).cloned().ok_or(rusqlite::Error::QueryReturnedNoRows)
-        })?;
-
-        let sources: Vec<Node> = sources_iter
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let mut stmt = conn.prepare("SELECT node_id FROM Hyperedge_Links WHERE hyperedge_id = ?1 AND type = 'target'")?;
-        let targets_iter = stmt.query_map(params![hyperedge_id.to_string()], |row| {
-            let node_id_str: String = row.get(0)?;
-            let node_id = Uuid::parse_str(&node_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-            nodes.iter().find(|&n| n.id == node_id).cloned().ok_or(rusqlite::Error::QueryReturnedNoRows)
-        })?;
-
-        let targets: Vec<Node> = targets_iter
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(Hyperedge {
-            id: hyperedge_id,
-            label: row.get(1)?,
-            source: sources,
-            target: targets,
-            citations,
-        })
-    })?;
-
-    let hyperedges: Vec<Hyperedge> = hyperedges_iter
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(Olog { id: olog_id, title: olog_title, nodes, hyperedges })
+#[derive(Subcommand)]
+enum SchemaCommand {
+    /// Print the schema of olog JSON, as accepted from the model and by imports
+    Print {
+        /// Schema language to print
+        #[arg(long, value_enum, default_value_t = SchemaFormat::JsonSchema)]
+        format: SchemaFormat,
+    },
 }
 
-fn write_olog_to_db(olog: &Olog) -> Result<()> {
-    let conn = Connection::open("olog.db")?;
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SchemaFormat {
+    /// JSON Schema (draft 7), for validators and structured-output modes
+    JsonSchema,
+}
 
-    conn.execute("BEGIN TRANSACTION", [])?;
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnDuplicate {
+    /// Leave the existing olog alone
+    Skip,
+    /// Merge a fresh generation into the existing olog as its next version
+    Extend,
+    /// Store a fresh generation as the next version of the existing olog
+    Version,
+}
 
-    conn.execute(
-        "INSERT INTO Ologs (olog_id, title) VALUES (?1, ?2)",
-        params![olog.id.to_string(), olog.title],
-    )?;
 
-    for node in &olog.nodes {
-        conn.execute(
-            "INSERT INTO Nodes (node_id, label, olog_id) VALUES (?1, ?2, ?3)",
-            params![node.id.to_string(), node.label, olog.id.to_string()],
-        )?;
+fn run_pipeline(conn: &Connection, text: String, on_duplicate: OnDuplicate, options: &GenerationOptions) -> Result<Option<Olog>, CliError> {
+    // Check whether this document has been processed before
+    let key = document_key(&text);
+    let existing = find_ingested_olog(conn, &key)?;
+    if let (Some(existing_id), OnDuplicate::Skip) = (existing, on_duplicate) {
+        println!(
+            "Document already ingested as Olog {}. Use --on-duplicate extend or --on-duplicate version to update it.",
+            existing_id
+        );
+        return Ok(None);
     }
 
-    for hyperedge in &olog.hyperedges {
-        conn.execute(
-            "INSERT INTO Hyperedges (hyperedge_id, label, olog_id) VALUES (?1, ?2, ?3)",
-            params![hyperedge.id.to_string(), hyperedge.label, olog.id.to_string()],
-        )?;
-
-        for citation in &hyperedge.citations {
-            conn.execute(
-                "INSERT OR IGNORE INTO Citations (citation_id, title, label, text) VALUES (?1, ?2, ?3, ?4)",
-                params![citation.id.to_string(), citation.title, citation.label, citation.text],
-            )?;
-            conn.execute(
-                "INSERT INTO Citation_Links (hyperedge_id, citation_id) VALUES (?1, ?2)",
-                params![hyperedge.id.to_string(), citation.id.to_string()]
-            )?;
-        }
-
-        for source in &hyperedge.source {
-            conn.execute(
-                "INSERT INTO Hyperedge_Links (hyperedge_id, node_id, type) VALUES (?1, ?2, 'source')",
-                params![hyperedge.id.to_string(), source.id.to_string()],
-            )?;
+    let Generated { olog: mut merged_olog, mut provenance, mut unified, mut flagged, metadata, redactions } = generate_merged_olog(provider::current(), &text, options)?;
+
+    if let (Some(existing_id), OnDuplicate::Extend) = (existing, on_duplicate) {
+        let previous = read_olog_from_db(conn, existing_id)
+            .map_err(|e| CliError::context(&format!("Error reading existing Olog {}", existing_id), e))?;
+        provenance.extend(provenance::read_provenance(conn, existing_id)?);
+        unified.extend(unify::read_aliases(conn, existing_id)?);
+        let merged = reassign_ids(merge_ologs(merged_olog, previous));
+        let (olog, more) = unify_similar_nodes(provider::current(), merged, &mut provenance, &mut flagged, options)?;
+        // Aliases recorded earlier follow their label if it was folded too
+        for unification in &mut unified {
+            unification.label = unify::canonical_label(&more, &unification.label).to_string();
         }
+        unified.extend(more);
+        merged_olog = olog;
+    }
 
-        for target in &hyperedge.target {
-            conn.execute(
-                "INSERT INTO Hyperedge_Links (hyperedge_id, node_id, type) VALUES (?1, ?2, 'target')",
-                params![hyperedge.id.to_string(), target.id.to_string()],
-            )?;
-        }
+    // Write the merged Olog to the database
+    let operation = if existing.is_some() && on_duplicate == OnDuplicate::Extend { Operation::Merge } else { Operation::Generate };
+    write_olog_to_db(conn, &merged_olog, operation)
+        .map_err(|e| CliError::context("Error writing merged Olog to database", e))?;
+    record_ingestion(conn, merged_olog.id, &key, existing)?;
+    usage::attribute(merged_olog.id);
+    provenance::record_provenance(conn, &merged_olog, &provenance)?;
+    unify::record_aliases(conn, &merged_olog, &unified)?;
+    unify::flag_for_review(conn, merged_olog.id, &flagged)?;
+    metadata::record_metadata(conn, &metadata)?;
+    redact::record_redactions(conn, &merged_olog, &redactions)?;
+    println!("Merged Olog written to database successfully.");
+    if let Some(found) = metadata.first() {
+        println!(
+            "Title from {} ({:.0}% confident), label from {} ({:.0}% confident).",
+            found.title.extractor.name(),
+            found.title.confidence * 100.0,
+            found.label.extractor.name(),
+            found.label.confidence * 100.0
+        );
+    }
+    if !flagged.is_empty() {
+        println!("Flagged the undone merges for review; run `olog review-merges {}` to go through them.", merged_olog.id);
     }
 
-    conn.execute("COMMIT", [])?;
-    Ok(())
+    Ok(Some(merged_olog))
 }
 
-fn validate_olog_schema(json_data: &str) -> Result<(), serde_json::Error> {
-    let _olog: JsonOlogSchema = serde_json::from_str(json_data)?;
-    Ok(())
+// Text of a document, extracting it first if the bytes are a PDF. Extracted
+// text comes from the cache when `use_cache` is set and is cached either way.
+fn document_text(
+    conn: &Connection,
+    bytes: Vec<u8>,
+    name: &str,
+    url: Option<&str>,
+    ocr: pdf::Ocr,
+    use_cache: bool,
+) -> Result<String, CliError> {
+    if !pdf::is_pdf(&bytes) {
+        return String::from_utf8(bytes).map_err(|e| CliError::context(&format!("Error reading {}", name), e));
+    }
+    let hash = cache::content_hash(&bytes);
+    if use_cache {
+        if let Some(text) = cache::text_for_hash(conn, &hash, ocr).map_err(|e| CliError::context("Error reading the OCR cache", e))? {
+            eprintln!("Using the text extracted from {} on an earlier run; pass --no-cache to extract it again.", name);
+            return Ok(text);
+        }
+    }
+    let text = pdf::extract_text(&bytes, ocr).map_err(|e| CliError::context(&format!("Error extracting text from {}", name), e))?;
+    cache::store(conn, &hash, url, ocr, &text).map_err(|e| CliError::context("Error caching the extracted text", e))?;
+    Ok(text)
 }
 
-fn get_openai_response(prompt: String) -> Result<String, Box<dyn std::error::Error>> {
-    let client = Client::new(env::var("OPENAI_API_KEY")?);
-
-    let req = ChatCompletionRequest::new(
-        "gpt-4-1106-preview".to_string(),
-        vec![chat_completion::ChatCompletionMessage {
-            role: chat_completion::MessageRole::user,
-            content: prompt,
-            name: None,
-            function_call: None,
-        }],
-    );
-
-    let result = client.chat_completion(req)?;
-
-    // Handling the Option<String> with ok_or
-    result.choices.get(0)
-        .and_then(|choice| choice.message.content.clone())
-        .ok_or_else(|| "No response from OpenAI".into()) // Converting to Result
+fn read_document(conn: &Connection, file: Option<PathBuf>, ocr: pdf::Ocr, use_cache: bool) -> Result<String, CliError> {
+    match file {
+        Some(path) => {
+            let bytes = fs::read(&path).map_err(|e| CliError::context(&format!("Error reading {}", path.display()), e))?;
+            document_text(conn, bytes, &path.display().to_string(), None, ocr, use_cache)
+        },
+        None => Ok(include_str!("./res/olog-pdf.md").to_string()),
+    }
 }
 
-fn get_openai_response_json(prompt: String) -> Result<String, Box<dyn std::error::Error>> {
-    let client = Client::new(env::var("OPENAI_API_KEY")?);
+// The PDF's own title and subject, if the file is a PDF that declares them
+fn pdf_metadata(path: &Path) -> Option<pdf::Metadata> {
+    let bytes = fs::read(path).ok()?;
+    pdf::is_pdf(&bytes).then(|| pdf::metadata(&bytes))
+}
 
-    let response_format_value = serde_json::json!({ "type": "json_object" });
+// Like read_document, but also accepts an http(s) URL. A URL with cached text
+// isn't downloaded again.
+fn read_source(conn: &Connection, source: Option<String>, ocr: pdf::Ocr, use_cache: bool) -> Result<String, CliError> {
+    match source {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            if use_cache {
+                if let Some(text) = cache::text_for_url(conn, &url, ocr).map_err(|e| CliError::context("Error reading the OCR cache", e))? {
+                    eprintln!("Using the text extracted from {} on an earlier run; pass --no-cache to fetch it again.", url);
+                    return Ok(text);
+                }
+            }
+            let mut bytes = Vec::new();
+            http::get(&url)
+                .and_then(|request| Ok(request.call()?))
+                .map_err(|e| CliError::context("Error fetching document", e))?
+                .into_reader()
+                .read_to_end(&mut bytes)
+                .map_err(|e| CliError::context(&format!("Error reading {}", url), e))?;
+            document_text(conn, bytes, &url, Some(&url), ocr, use_cache)
+        },
+        source => read_document(conn, source.map(PathBuf::from), ocr, use_cache),
+    }
+}
 
-    let req = ChatCompletionRequest::new(
-        "gpt-4-1106-preview".to_string(),
-        vec![chat_completion::ChatCompletionMessage {
-            role: chat_completion::MessageRole::user,
-            content: prompt,
-            name: None,
-            function_call: None,
-        }],
-    )
-    .response_format(response_format_value); // Set the response_format here
+// One line describing what deleting an olog takes with it, for confirmation
+fn deletion_summary(conn: &Connection, olog: &Olog) -> Result<String, CliError> {
+    let debates: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM Debates WHERE olog_id = ?1",
+        params![olog.id.to_string()],
+        |row| row.get(0),
+    )?;
+    let debates = match debates {
+        0 => String::new(),
+        1 => ", 1 saved debate".to_string(),
+        n => format!(", {} saved debates", n),
+    };
+    Ok(format!(
+        "  Olog {} \"{}\": {} nodes, {} hyperedges{}, and citations no other olog uses",
+        olog.id, olog.title, olog.nodes.len(), olog.hyperedges.len(), debates
+    ))
+}
 
-    let result = client.chat_completion(req)?;
+fn read_argmap(conn: &Connection, argmap_id: Uuid) -> Result<argmap::ArgumentMap, CliError> {
+    argmap::read_argmap(conn, argmap_id)
+        .map_err(|e| CliError::context(&format!("Error reading argument map {}", argmap_id), e))?
+        .ok_or_else(|| CliError::new(ErrorCategory::Usage, format!("Argument map {} not found", argmap_id)))
+}
 
-    // Handling the Option<String> with ok_or
-    result.choices.get(0)
-        .and_then(|choice| choice.message.content.clone())
-        .ok_or_else(|| "No response from OpenAI".into()) // Converting to Result
+// Secrets are shown by their last characters only
+fn show_config(
+    db: Option<PathBuf>,
+    provider: Option<Provider>,
+    max_attempts: Option<u32>,
+    preset: &str,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let config_error = |e: OlogError| CliError::new(ErrorCategory::Config, e.to_string());
+    let database = database_location(db).map_err(config_error)?;
+    let api_key = config::openai_api_key().map_err(config_error)?;
+    let provider = config::provider(provider).map_err(config_error)?;
+    let base_url = config::openai_base_url().map_err(config_error)?;
+    let anthropic_key = config::anthropic_api_key().map_err(config_error)?;
+    let ollama_host = config::ollama_host().map_err(config_error)?;
+    let (model, label_model) = preset::models(preset).map_err(config_error)?;
+    let timeout = config::timeout(std::time::Duration::ZERO).map_err(config_error)?;
+    let model_timeout = config::model_timeout(provider::DEFAULT_MODEL_TIMEOUT).map_err(config_error)?;
+    let parallelism = config::parallelism(None, DEFAULT_PARALLELISM).map_err(config_error)?;
+    let max_attempts = config::max_attempts(max_attempts, retry::DEFAULT_MAX_ATTEMPTS).map_err(config_error)?;
+    let proxy = config::proxy().map_err(config_error)?;
+    let no_proxy = config::no_proxy().map_err(config_error)?;
+    let ca_cert = config::ca_cert().map_err(config_error)?;
+    let connect_timeout = config::connect_timeout(std::time::Duration::ZERO).map_err(config_error)?;
+
+    let settings = [
+        ("database", Some(database.value.display().to_string()), database.source),
+        ("provider", Some(provider.value.name().to_string()), provider.source),
+        ("openai_api_key", api_key.value.as_deref().map(config::mask), api_key.source),
+        ("openai_base_url", base_url.value, base_url.source),
+        ("anthropic_api_key", anthropic_key.value.as_deref().map(config::mask), anthropic_key.source),
+        ("ollama_host", Some(ollama_host.value), ollama_host.source),
+        ("model", Some(model.value), model.source),
+        ("label_model", label_model.value, label_model.source),
+        (
+            "timeout_secs",
+            (timeout.source != config::Source::Default).then(|| timeout.value.as_secs().to_string()),
+            timeout.source,
+        ),
+        ("model_timeout_secs", Some(model_timeout.value.as_secs().to_string()), model_timeout.source),
+        ("parallelism", Some(parallelism.value.to_string()), parallelism.source),
+        ("max_attempts", Some(max_attempts.value.to_string()), max_attempts.source),
+        ("proxy", proxy.value.as_deref().map(config::mask_proxy), proxy.source),
+        ("no_proxy", no_proxy.value, no_proxy.source),
+        ("ca_cert", ca_cert.value.map(|path| path.display().to_string()), ca_cert.source),
+        (
+            "connect_timeout_secs",
+            (connect_timeout.source != config::Source::Default).then(|| connect_timeout.value.as_secs().to_string()),
+            connect_timeout.source,
+        ),
+    ];
+    let path = config::config_path();
+    let file = path.as_deref().map(|path| (path, path.exists()));
+    let project = config::project_path();
+    format::print(&display::config_output(file, (&project, project.exists()), &settings), format, true);
+    Ok(())
 }
 
-fn replace_ids_with_uuids(mut olog: JsonOlogSchema) -> JsonOlogSchema {
-    let mut id_map: HashMap<String, Uuid> = HashMap::new();
+// An olog as its sources wrote it, for showing or exporting on this machine;
+// prompts and the search index keep the redaction placeholders
+fn load_restored(conn: &Connection, olog_id: Uuid) -> Result<Olog, CliError> {
+    let mut olog = load_olog(conn, olog_id)?;
+    redact::restore_olog(conn, &mut olog)?;
+    Ok(olog)
+}
 
-    // Replace node ids
-    for node in olog.nodes.iter_mut() {
-        let uuid = *id_map.entry(node.id.clone()).or_insert_with(Uuid::new_v4);
-        node.id = uuid.to_string();
+// --id-prefix, else olog.toml's export_id_prefix, else none
+fn export_id_prefix(flag: Option<String>) -> Result<String, CliError> {
+    match flag {
+        Some(prefix) => Ok(prefix),
+        None => Ok(preset::configured_id_prefix()
+            .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?
+            .unwrap_or_default()),
     }
+}
 
-    // Replace hyperedge ids and update sources and targets
-    for hyperedge in olog.hyperedges.iter_mut() {
-        let uuid = *id_map.entry(hyperedge.id.clone()).or_insert_with(Uuid::new_v4);
-        hyperedge.id = uuid.to_string();
+// Copies the database to the first free `{db}.{tag}.bak` name before a
+// change that can't be undone
+fn back_up(conn: &Connection, db_path: &Path, tag: &str) -> Result<(), CliError> {
+    let backup = (0..)
+        .map(|n| match n {
+            0 => PathBuf::from(format!("{}.{}.bak", db_path.display(), tag)),
+            n => PathBuf::from(format!("{}.{}.{}.bak", db_path.display(), tag, n)),
+        })
+        .find(|path| !path.exists())
+        .expect("some backup name is free");
+    migrations::backup(conn, &backup)
+        .map_err(|e| CliError::context(&format!("Error backing up {} to {}", db_path.display(), backup.display()), e))?;
+    println!("Copied {} to {}.", db_path.display(), backup.display());
+    Ok(())
+}
 
-        for source_id in hyperedge.sources.iter_mut() {
-            let source_uuid = *id_map.entry(source_id.clone()).or_insert_with(Uuid::new_v4);
-            *source_id = source_uuid.to_string();
+fn run(command: Commands, operation: &str, namespace: &str, db_path: &Path) -> Result<(), CliError> {
+    if let Commands::Schema { command: SchemaCommand::Print { format } } = &command {
+        match format {
+            SchemaFormat::JsonSchema => {
+                let schema = serde_json::to_string_pretty(&olog_json_schema())?;
+                println!("{}", schema);
+            },
         }
+        return Ok(());
+    }
 
-        for target_id in hyperedge.targets.iter_mut() {
-            let target_uuid = *id_map.entry(target_id.clone()).or_insert_with(Uuid::new_v4);
-            *target_id = target_uuid.to_string();
+    let conn = open_database(db_path)
+        .map_err(|e| CliError::context(&format!("Error opening {}", db_path.display()), e))?;
+    // Databases with data are only upgraded by an explicit `migrate`
+    if !matches!(command, Commands::Migrate { .. }) {
+        let problem = migrations::schema_problem(&conn).map_err(|e| CliError::context("Error reading the schema version", e))?;
+        if let Some(problem) = problem {
+            return Err(CliError::new(ErrorCategory::Config, problem));
         }
+        create_olog_tables(&conn).map_err(|e| CliError::context("Error creating tables", e))?;
     }
+    use_namespace(&conn, namespace).map_err(|e| CliError::context("Error selecting namespace", e))?;
 
-    olog
+    // Calls made before a failure were paid for too
+    let olog_id = command_olog(&command);
+    let result = run_command(&conn, command, namespace, db_path);
+    if let Err(e) = usage::store_pending(&conn, operation, olog_id) {
+        eprintln!("Could not record model usage: {}", e);
+    }
+    result
 }
 
-fn convert_json_olog_to_olog(json_olog: JsonOlogSchema, citation: Citation) -> Olog {
-    let mut id_map: HashMap<String, Uuid> = HashMap::new();
-    let mut node_map: HashMap<Uuid, Node> = HashMap::new();
-
-    // Process nodes and build a map from string IDs to Node instances
-    for json_node in &json_olog.nodes {
-        let uuid = *id_map.entry(json_node.id.clone()).or_insert_with(Uuid::new_v4);
-        let node = Node { id: uuid, label: json_node.label.clone() };
-        node_map.insert(uuid, node);
+// The olog a command works on, which its model calls are charged to
+fn command_olog(command: &Commands) -> Option<Uuid> {
+    match command {
+        Commands::Consolidate { olog_id, .. }
+        | Commands::ReviewMerges { olog_id, .. }
+        | Commands::SplitNode { olog_id, .. }
+        | Commands::ExplainPath { olog_id, .. }
+        | Commands::Debate { olog_id, .. }
+        | Commands::FactCheck { olog_id, .. }
+        | Commands::Coverage { olog_id, .. }
+        | Commands::LinkArgmap { olog_id, .. } => Some(*olog_id),
+        Commands::GenerateArgmap { olog_id, .. } => *olog_id,
+        _ => None,
     }
+}
 
-    // Convert nodes to Vec<Node>
-    let nodes: Vec<Node> = node_map.values().cloned().collect();
-
-    // Process hyperedges and convert sources and targets to Node instances
-    let hyperedges = json_olog.hyperedges.into_iter().map(|json_hyperedge| {
-        let hyperedge_id = *id_map.entry(json_hyperedge.id.clone()).or_insert_with(Uuid::new_v4);
-        let sources = json_hyperedge.sources.iter()
-            .filter_map(|source_id| {
-                id_map.get(source_id)
-                    .and_then(|&uuid| node_map.get(&uuid).cloned())
-            })
-            .collect();
-        let targets = json_hyperedge.targets.iter()
-            .filter_map(|target_id| {
-                id_map.get(target_id)
-                    .and_then(|&uuid| node_map.get(&uuid).cloned())
+fn run_command(conn: &Connection, command: Commands, namespace: &str, db_path: &Path) -> Result<(), CliError> {
+    match command {
+        Commands::ProcessPaper { file, on_duplicate, generation } => {
+            let source_metadata = file.as_deref().and_then(pdf_metadata);
+            let text = read_document(conn, file, generation.ocr, !generation.no_cache)?;
+
+            let options = GenerationOptions { source_metadata, ..generation_options(conn, generation)? };
+
+            let started = Instant::now();
+            let merged_olog = match run_pipeline(conn, text, on_duplicate, &options) {
+                Ok(Some(olog)) => {
+                    hooks::on_success(&olog, started.elapsed());
+                    olog
+                },
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    hooks::on_failure(&e.message, started.elapsed());
+                    return Err(e);
+                },
+            };
+
+            // Optionally, read the merged Olog from the database and display it
+            let mut olog_from_db = read_olog_from_db(conn, merged_olog.id)
+                .map_err(|e| CliError::context("Error reading merged Olog from database", e))?;
+            redact::restore_olog(conn, &mut olog_from_db)?;
+            display::print_olog(&olog_from_db, false);
+        },
+        Commands::Estimate { source, generation } => {
+            let text = read_source(conn, source, generation.ocr, !generation.no_cache)?;
+            let options = generation_options(conn, generation)?;
+            let estimate = estimate::estimate_run(&text, &options)
+                .map_err(|e| CliError::context("Error estimating the run", e))?;
+
+            println!("Document:          {} chars, ~{} tokens", estimate.document_chars, estimate.document_tokens);
+            let models = if options.label_model == options.model {
+                options.model.clone()
+            } else {
+                format!("{} (titles and labels: {})", options.model, options.label_model)
+            };
+            let chunks = match estimate.chunks {
+                1 => String::new(),
+                n => format!(" over {} chunks", n),
+            };
+            println!("Plan:              {} generation(s){} with {}, {} calls", options.count, chunks, models, estimate.calls);
+            println!("Prompt tokens:     ~{}", estimate.prompt_tokens);
+            println!("Completion tokens: ~{}", estimate.completion_tokens);
+            match (estimate.cost, estimate.unpriced_model) {
+                (Some(cost), _) => println!("Estimated cost:    ~${:.2}", cost),
+                (None, model) => println!(
+                    "Estimated cost:    unknown; add [pricing.\"{}\"] with input and output USD per million tokens to olog.toml",
+                    model.unwrap_or_default()
+                ),
+            }
+            if options.consolidate && options.count > 1 {
+                println!("Consolidation may add one small call with the merged relation labels.");
+            }
+        },
+        Commands::ProcessBook { file, title, generation } => {
+            let text = read_document(conn, Some(file.clone()), generation.ocr, !generation.no_cache)?;
+            let title = title.unwrap_or_else(|| {
+                file.file_stem().map_or_else(|| file.display().to_string(), |stem| stem.to_string_lossy().into_owned())
+            });
+            let options = generation_options(conn, generation)?;
+
+            let book_id = book::ingest_book(conn, provider::current(), &text, &title, &options)?;
+            println!("Book written to database as Olog {}. Run `book-map {}` to merge its chapters.", book_id, book_id);
+        },
+        Commands::BookMap { book_id, rebuild, full, format } => {
+            let olog = book::book_map(conn, book_id, rebuild)?;
+            format::print(&display::olog_output(&olog, &[], full), format, full);
+        },
+        Commands::ShowPrompt { file, template, generation } => {
+            let text = read_document(conn, file, generation.ocr, !generation.no_cache)?;
+            let (text, options, _) = redact_document(&text, &generation_options(conn, generation)?);
+            let document = sanitize::delimit(&prepare_document(&text, &options).text);
+            let prompt = compose_prompt(template, &document, &options)
+                .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+            println!("--- system ---\n{}\n--- user ---\n{}", sanitize::GUARDRAIL, prompt);
+        },
+        Commands::ReadDb { olog_id, full, format } => {
+            let olog = load_restored(conn, olog_id)?;
+            let parents = merge_parents(conn, olog_id)?;
+            format::print(&display::olog_output(&olog, &parents, full), format, full);
+        },
+        Commands::OlogJson { olog_id } => {
+            let olog = load_restored(conn, olog_id)?;
+            println!("{}", serde_json::to_string_pretty(&olog_to_json(&olog))?);
+        },
+        Commands::MergeOlogs { first, second, title, keep_sources: _, delete_sources } => {
+            if first == second {
+                return Err(CliError::new(ErrorCategory::Usage, "Give two different ologs to merge"));
+            }
+            let (olog1, olog2) = (load_olog(conn, first)?, load_olog(conn, second)?);
+            if delete_sources {
+                confirm::confirm(&format!(
+                    "Merging with --delete-sources deletes both source ologs afterwards:\n{}\n{}",
+                    deletion_summary(conn, &olog1)?,
+                    deletion_summary(conn, &olog2)?
+                ))?;
+            }
+            let mut provenance = provenance::read_provenance(conn, first)?;
+            provenance.extend(provenance::read_provenance(conn, second)?);
+            let mut aliases = unify::read_aliases(conn, first)?;
+            aliases.extend(unify::read_aliases(conn, second)?);
+
+            // The merge reuses the inputs' node ids, which they still own
+            let mut merged = reassign_ids(merge_ologs(olog1, olog2));
+            if let Some(title) = title {
+                merged.title = title;
+            }
+            write_olog_to_db(conn, &merged, Operation::Merge)
+                .map_err(|e| CliError::context("Error writing merged Olog to database", e))?;
+            provenance::record_provenance(conn, &merged, &provenance)?;
+            unify::record_aliases(conn, &merged, &aliases)?;
+            println!(
+                "Merged Olog {} and Olog {} into Olog {}: {} ({} nodes, {} hyperedges)",
+                first, second, merged.id, merged.title, merged.nodes.len(), merged.hyperedges.len()
+            );
+
+            if !delete_sources {
+                record_merge_parents(conn, merged.id, &[first, second])
+                    .map_err(|e| CliError::context("Error linking the merged Olog to its sources", e))?;
+                return Ok(());
+            }
+            // Only once the merged olog is safely stored
+            for source in [first, second] {
+                let title = read_olog_from_db(conn, source)?.title;
+                delete_olog(conn, source).map_err(|e| CliError::context(&format!("Error deleting Olog {}", source), e))?;
+                elastic::remove_olog(source);
+                gitrepo::remove(conn, source, &title);
+                println!("Deleted Olog {}.", source);
+            }
+        },
+        Commands::Consolidate { olog_id, preset } => {
+            let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+            let olog = load_olog(conn, olog_id)?;
+            let (olog, merged) = consolidate::consolidate_hyperedges(provider::current(), olog, &preset.model)
+                .map_err(|e| CliError::context("Error consolidating hyperedges", e))?;
+            if merged > 0 {
+                replace_olog_in_db(conn, &olog, Operation::Edit)
+                    .map_err(|e| CliError::context("Error writing consolidated Olog to database", e))?;
+            }
+            println!("Consolidated {} synonymous hyperedges in Olog {}.", merged, olog_id);
+        },
+        Commands::ReviewMerges { olog_id } => {
+            let olog = load_olog(conn, olog_id)?;
+            let flagged = unify::flagged_merges(conn, olog_id)?;
+            if flagged.is_empty() {
+                println!("No merges awaiting review for Olog {}.", olog_id);
+                return Ok(());
+            }
+
+            let (mut accepted, mut decided) = (Vec::new(), Vec::new());
+            for (i, unification) in flagged.iter().enumerate() {
+                print!(
+                    "[{}/{}] Merge \"{}\" into \"{}\"? [y/n/q] ",
+                    i + 1, flagged.len(), unification.aliases.join("\", \""), unification.label
+                );
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                if io::stdin().read_line(&mut answer)? == 0 {
+                    break;
+                }
+                match answer.trim().to_lowercase().as_str() {
+                    "q" | "quit" => break,
+                    "y" | "yes" => accepted.push(unification.clone()),
+                    _ => {},
+                }
+                decided.push(unification);
+            }
+
+            // Labels edited away since the merge was flagged are skipped
+            let has_label = |label: &str| olog.nodes.iter().any(|node| node.label == label);
+            let accepted: Vec<unify::Unification> = accepted.into_iter()
+                .filter(|unification| has_label(&unification.label))
+                .map(|mut unification| {
+                    unification.aliases.retain(|alias| has_label(alias));
+                    unification
+                })
+                .filter(|unification| !unification.aliases.is_empty())
+                .collect();
+            if !accepted.is_empty() {
+                let merged = unify::apply_unifications(olog, &accepted);
+                replace_olog_in_db(conn, &merged, Operation::Edit)
+                    .map_err(|e| CliError::context(&format!("Error updating Olog {}", olog_id), e))?;
+                unify::record_aliases(conn, &merged, &accepted)?;
+            }
+            for unification in &decided {
+                unify::clear_review(conn, olog_id, unification)?;
+            }
+            println!("Applied {} merges; {} left for review.", accepted.len(), flagged.len() - decided.len());
+        },
+        Commands::SplitNode { olog_id, node, labels, threshold, embedding_model, preset } => {
+            let olog = load_olog(conn, olog_id)?;
+            if let Some(node_id) = node {
+                if !olog.nodes.iter().any(|node| node.id == node_id) {
+                    return Err(CliError::new(ErrorCategory::Usage, format!("Node {} is not part of Olog {}", node_id, olog_id)));
+                }
+            }
+            let overloaded = split::find_overloaded(provider::current(), &olog, &embedding_model, threshold, node)
+                .map_err(|e| CliError::context("Error looking for overloaded nodes", e))?;
+
+            let Some(node_id) = node else {
+                for overload in &overloaded {
+                    println!("{} \"{}\" covers {} unrelated groups:", overload.node.id, overload.node.label, overload.groups.len());
+                    for (i, group) in overload.groups.iter().enumerate() {
+                        for &index in group {
+                            println!("  {}. {}", i + 1, split::edge_sentence(&olog.hyperedges[index]));
+                        }
+                    }
+                }
+                if overloaded.is_empty() {
+                    println!("No overloaded nodes found in Olog {}.", olog_id);
+                } else {
+                    println!("Run `olog split-node {} --node <NODE_ID>` to split one.", olog_id);
+                }
+                return Ok(());
+            };
+
+            let overload = overloaded.into_iter().next().ok_or_else(|| CliError::new(
+                ErrorCategory::Usage,
+                format!("Node {} doesn't take part in unrelated groups of hyperedges (a higher --threshold splits more eagerly)", node_id),
+            ))?;
+            let labels = if labels.is_empty() {
+                let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+                split::name_concepts(provider::current(), &olog, &overload, &preset.model)
+                    .map_err(|e| CliError::context("Error naming the split concepts", e))?
+            } else if labels.len() == overload.groups.len() {
+                labels
+            } else {
+                return Err(CliError::new(
+                    ErrorCategory::Usage,
+                    format!("Node {} splits into {} concepts, but {} --label values were given", node_id, overload.groups.len(), labels.len()),
+                ));
+            };
+
+            let (olog, concepts) = split::split_node(olog, &overload, &labels);
+            replace_olog_in_db(conn, &olog, Operation::Edit)
+                .map_err(|e| CliError::context("Error writing split Olog to database", e))?;
+            println!("Split \"{}\" into:", overload.node.label);
+            for (concept, group) in concepts.iter().zip(&overload.groups) {
+                println!("  {} \"{}\" ({} hyperedges)", concept.id, concept.label, group.len());
+            }
+        },
+        Commands::ExplainPath { olog_id, from, to, max_hops, max_paths, plain, preset } => {
+            let olog = load_olog(conn, olog_id)?;
+            let node = |given: &str| paths::resolve_node(&olog, given).ok_or_else(|| {
+                CliError::new(ErrorCategory::Usage, format!("No node `{}` in Olog {}", given, olog_id))
+            });
+            let (from_id, to_id) = (node(&from)?, node(&to)?);
+            let label = |id: Uuid| olog.nodes.iter().find(|node| node.id == id).map_or("", |node| node.label.as_str());
+
+            let found = paths::find_paths(&olog, from_id, to_id, max_hops, max_paths);
+            if found.is_empty() {
+                println!("No path from \"{}\" to \"{}\" within {} hyperedges.", label(from_id), label(to_id), max_hops);
+                return Ok(());
+            }
+            let model = if plain {
+                None
+            } else {
+                Some(preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?.model)
+            };
+
+            for (n, path) in found.iter().enumerate() {
+                let sentences = match &model {
+                    Some(model) => paths::narrate_path(provider::current(), &olog, path, model)
+                        .map_err(|e| CliError::context("Error narrating the path", e))?,
+                    None => path.iter().map(|step| split::edge_sentence(&olog.hyperedges[step.hyperedge])).collect(),
+                };
+                let citations = paths::path_citations(&olog, path);
+                let hops = path.iter().map(|step| label(step.to)).collect::<Vec<_>>().join(" -> ");
+                println!("Path {}: {} -> {}", n + 1, label(from_id), hops);
+                for (step, sentence) in path.iter().zip(&sentences) {
+                    let markers = olog.hyperedges[step.hyperedge].citations.iter()
+                        .filter_map(|citation| citations.iter().position(|c| c.id == citation.id))
+                        .map(|index| format!("[{}]", index + 1))
+                        .collect::<String>();
+                    println!("  {} {}", sentence.trim(), markers);
+                }
+                for (index, citation) in citations.iter().enumerate() {
+                    println!("  [{}] {} ({})", index + 1, citation.title.trim(), citation.id);
+                }
+                println!();
+            }
+        },
+        Commands::Export { olog_id, format, size_by, id_prefix, output } => {
+            let olog = load_restored(conn, olog_id)?;
+            let rendered = export::export(&olog, format, size_by, &export_id_prefix(id_prefix)?);
+            match output {
+                Some(path) => fs::write(&path, rendered)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", rendered),
+            }
+        },
+        Commands::ExportDot { olog_id, size_by, id_prefix, output } => {
+            let olog = load_restored(conn, olog_id)?;
+            let rendered = export::export(&olog, export::ExportFormat::Dot, size_by, &export_id_prefix(id_prefix)?);
+            match output {
+                Some(path) => fs::write(&path, rendered)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", rendered),
+            }
+        },
+        Commands::ExportMermaid { olog_id, edge_labels, output } => {
+            let olog = load_restored(conn, olog_id)?;
+            let rendered = export::to_mermaid(&olog, edge_labels);
+            match output {
+                Some(path) => fs::write(&path, rendered)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", rendered),
+            }
+        },
+        Commands::ExportRdf { olog_id, format, id_prefix, output } => {
+            let olog = load_restored(conn, olog_id)?;
+            let rendered = rdf::export_rdf(&olog, format, &export_id_prefix(id_prefix)?);
+            match output {
+                Some(path) => fs::write(&path, rendered)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", rendered),
+            }
+        },
+        Commands::ExportNdjson { olog_id, .. } => {
+            if let Some(olog_id) = olog_id {
+                if !olog_in_namespace(conn, olog_id)? {
+                    return Err(CliError::new(ErrorCategory::Usage, format!("Olog {} not found in namespace `{}`", olog_id, namespace)));
+                }
+            }
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            match ndjson::write_ndjson(conn, olog_id, &mut out) {
+                Ok(()) => {},
+                // The reader went away (e.g. `| head`); that's not a failure
+                Err(OlogError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => {},
+                Err(e) => return Err(CliError::context("Error exporting NDJSON", e)),
+            }
+        },
+        Commands::ExportSql { olog_id, output } => {
+            let sql = dump::olog_sql_dump(conn, olog_id)
+                .map_err(|e| CliError::context(&format!("Error dumping Olog {}", olog_id), e))?;
+            match output {
+                Some(path) => fs::write(&path, sql)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", sql),
+            }
+        },
+        Commands::Debate { olog_id, claim, rounds, preset, personas } => {
+            let olog = load_olog(conn, olog_id)?;
+            if olog.hyperedges.is_empty() {
+                return Err(CliError::new(ErrorCategory::Usage, format!("Olog {} has no hyperedges to argue from", olog_id)));
+            }
+            let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+            let personas = personas.iter()
+                .map(|name| debate::Persona::load(name))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+
+            println!("Claim: {}", claim);
+            let turns = debate::run_debate(provider::current(), &olog, &claim, rounds as usize, &preset.model, &personas, |turn| {
+                display::print_debate_turn(&olog, turn);
             })
-            .collect();
-
-        Hyperedge {
-            id: hyperedge_id,
-            label: json_hyperedge.label,
-            source: sources,
-            target: targets,
-            citations: vec![citation.clone()],
-        }
-    }).collect();
+            .map_err(|e| CliError::context("Error running the debate", e))?;
 
-    Olog {
-        id: Uuid::new_v4(),
-        title: json_olog.title,
-        nodes,
-        hyperedges,
+            let debate = debate::Debate { id: Uuid::new_v4(), olog_id, claim, model: preset.model, turns, created_at: None };
+            debate::record_debate(conn, &debate).map_err(|e| CliError::context("Error saving the debate", e))?;
+            println!("\nDebate saved as {}.", debate.id);
+        },
+        Commands::FactCheck { olog_id, preset, report } => {
+            let olog = load_olog(conn, olog_id)?;
+            let verdicts = if report {
+                factcheck::read_verdicts(conn, olog_id).map_err(|e| CliError::context("Error reading verdicts", e))?
+            } else {
+                let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+                let checks: usize = olog.hyperedges.iter().map(|hyperedge| hyperedge.citations.len()).sum();
+                let mut done = 0;
+                let verdicts = factcheck::fact_check(provider::current(), &olog, &preset.model, |_| {
+                    done += 1;
+                    eprint!("\rChecked {}/{}", done, checks);
+                })
+                .map_err(|e| CliError::context("Error fact-checking", e))?;
+                eprintln!();
+                factcheck::record_verdicts(conn, olog_id, &preset.model, &verdicts)
+                    .map_err(|e| CliError::context("Error saving verdicts", e))?;
+                verdicts
+            };
+            if verdicts.is_empty() {
+                println!("No verdicts for Olog {}{}.", olog_id, if report { "; run fact-check without --report first" } else { "" });
+            } else {
+                display::print_fact_check(&olog, &verdicts);
+            }
+        },
+        Commands::Cache { command: CacheCommand::Clear } => {
+            confirm::confirm("Clearing forgets all text extracted from PDFs and every cached model reply.")?;
+            let documents = cache::clear(conn).map_err(|e| CliError::context("Error clearing the OCR cache", e))?;
+            let replies = cache::clear_responses().map_err(|e| CliError::context("Error clearing cached model replies", e))?;
+            println!(
+                "Cleared {} cached document{} and {} cached model repl{}.",
+                documents,
+                if documents == 1 { "" } else { "s" },
+                replies,
+                if replies == 1 { "y" } else { "ies" }
+            );
+        },
+        Commands::Coverage { olog_id, format } => {
+            let olog = load_restored(conn, olog_id)?;
+            format::print(&display::coverage_output(&olog, &coverage::coverage(&olog)), format, false);
+        },
+        Commands::ShowDebate { debate_id, format } => {
+            let debate = debate::read_debate(conn, debate_id)
+                .map_err(|e| CliError::context(&format!("Error reading debate {}", debate_id), e))?
+                .ok_or_else(|| CliError::new(ErrorCategory::Usage, format!("Debate {} not found", debate_id)))?;
+            let olog = load_restored(conn, debate.olog_id)?;
+            format::print(&display::debate_output(&debate, &olog), format, false);
+        },
+        Commands::DebateOlog { debate_id, on_duplicate, generation } => {
+            let debate = debate::read_debate(conn, debate_id)
+                .map_err(|e| CliError::context(&format!("Error reading debate {}", debate_id), e))?
+                .ok_or_else(|| CliError::new(ErrorCategory::Usage, format!("Debate {} not found", debate_id)))?;
+            let olog = load_olog(conn, debate.olog_id)?;
+            let text = debate::transcript_document(&debate, &olog);
+
+            // Cited under the debate it came from, whatever the model makes of the transcript
+            let source_metadata = Some(pdf::Metadata {
+                title: Some(format!("Debate: {}", debate.claim.trim())),
+                subject: Some(format!("Debate {} over Olog {}", debate.id, olog.id)),
+            });
+            let options = GenerationOptions { source_metadata, debate_transcript: true, ..generation_options(conn, generation)? };
+            let Some(meta_olog) = run_pipeline(conn, text, on_duplicate, &options)? else {
+                return Ok(());
+            };
+            set_olog_kind(conn, meta_olog.id, OlogKind::Debate)
+                .map_err(|e| CliError::context("Error recording the olog's kind", e))?;
+
+            let olog_from_db = read_olog_from_db(conn, meta_olog.id)
+                .map_err(|e| CliError::context("Error reading the debate olog from the database", e))?;
+            display::print_olog(&olog_from_db, false);
+        },
+        Commands::GenerateArgmap { file, title, olog_id, preset, ocr, no_cache } => {
+            let olog = olog_id.map(|olog_id| load_olog(conn, olog_id)).transpose()?;
+            let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+            let source_metadata = file.as_deref().and_then(pdf_metadata);
+            let name = file.as_ref().and_then(|path| path.file_stem()).map(|stem| stem.to_string_lossy().into_owned());
+            let text = read_document(conn, file, ocr, !no_cache)?;
+            let title = title
+                .or_else(|| metadata::title_hint(&text, source_metadata.as_ref()))
+                .or(name)
+                .unwrap_or_else(|| "Untitled".to_string());
+
+            let mut map = argmap::generate_argmap(provider::current(), &text, &title, &preset.model)
+                .map_err(|e| CliError::context("Error generating the argument map", e))?;
+            if let Some(olog) = &olog {
+                argmap::link_claims(provider::current(), &mut map, olog, &preset.model)
+                    .map_err(|e| CliError::context("Error linking claims to hyperedges", e))?;
+            }
+            argmap::record_argmap(conn, &map).map_err(|e| CliError::context("Error saving the argument map", e))?;
+            format::print(&display::argmap_output(&map, olog.as_ref()), OutputFormat::Table, false);
+            println!("\nArgument map saved as {}.", map.id);
+        },
+        Commands::LinkArgmap { argmap_id, olog_id, preset } => {
+            let mut map = read_argmap(conn, argmap_id)?;
+            let olog = load_olog(conn, olog_id)?;
+            let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+            let links = argmap::link_claims(provider::current(), &mut map, &olog, &preset.model)
+                .map_err(|e| CliError::context("Error linking claims to hyperedges", e))?;
+            argmap::record_links(conn, &map).map_err(|e| CliError::context("Error saving the links", e))?;
+            let linked = map.claims.iter().filter(|claim| !claim.hyperedges.is_empty()).count();
+            println!(
+                "Linked {} claim{} to Olog {} with {} link{}.",
+                linked,
+                if linked == 1 { "" } else { "s" },
+                olog_id,
+                links,
+                if links == 1 { "" } else { "s" }
+            );
+        },
+        Commands::ShowArgmap { argmap_id, format } => {
+            let map = read_argmap(conn, argmap_id)?;
+            let olog = map.olog_id.map(|olog_id| load_olog(conn, olog_id)).transpose()?;
+            format::print(&display::argmap_output(&map, olog.as_ref()), format, false);
+        },
+        Commands::Flashcards { olog_id, plain, preset, output } => {
+            let olog = load_olog(conn, olog_id)?;
+            let cards = if plain {
+                flashcards::plain_cards(&olog)
+            } else {
+                let preset = preset::resolve(&preset).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))?;
+                flashcards::generated_cards(provider::current(), &olog, &preset.model)
+                    .map_err(|e| CliError::context("Error writing flashcards", e))?
+            };
+            let tsv = flashcards::to_anki_tsv(&olog, &cards);
+            match output {
+                Some(path) => {
+                    fs::write(&path, tsv).map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?;
+                    println!("Wrote {} cards to {}.", cards.len(), path.display());
+                },
+                None => print!("{}", tsv),
+            }
+        },
+        Commands::Doctor { .. } | Commands::Schema { .. } | Commands::Config { .. } => {
+            unreachable!("handled before opening the database")
+        },
+        Commands::Migrate { dry_run, no_backup } => {
+            let read_error = |e| CliError::context("Error reading the schema version", e);
+            let current = migrations::current_version(conn).map_err(read_error)?;
+            let latest = migrations::latest_version();
+            if current > latest {
+                return Err(CliError::new(
+                    ErrorCategory::Config,
+                    format!(
+                        "{} uses schema version {}, newer than the {} this build knows; upgrade olog",
+                        db_path.display(), current, latest
+                    ),
+                ));
+            }
+            let pending = migrations::pending(conn).map_err(read_error)?;
+            if pending.is_empty() {
+                println!("Schema is up to date (version {}).", current);
+                return Ok(());
+            }
+            println!("Schema version {}; {} pending:", current, pending.len());
+            for migration in &pending {
+                println!("  {}: {}", migration.version, migration.description);
+            }
+            if dry_run {
+                return Ok(());
+            }
+
+            if !no_backup && has_table(conn, "Ologs")? {
+                back_up(conn, db_path, &format!("v{}", current))?;
+            }
+            migrations::migrate(conn)
+                .map_err(|e| CliError::context(&format!("Error migrating {}", db_path.display()), e))?;
+            println!("Migrated to schema version {}.", latest);
+        },
+        Commands::Fsck { repair, no_backup } => {
+            let check = fsck::check(conn).map_err(|e| CliError::context(&format!("Error checking {}", db_path.display()), e))?;
+            if check.corruption.is_empty() {
+                println!("Integrity check: ok");
+            } else {
+                println!("Integrity check found {} problems:", check.corruption.len());
+                for problem in &check.corruption {
+                    println!("  {}", problem);
+                }
+            }
+            if !check.foreign_keys {
+                println!("Foreign keys are not enforced (foreign_keys = false in {}).", config::PROJECT_FILE);
+            }
+            if check.violations.is_empty() {
+                println!("Foreign keys: ok");
+            } else {
+                println!("{} rows reference rows that don't exist:", check.violations.len());
+                let mut counts: Vec<(&str, &str, usize)> = Vec::new();
+                for violation in &check.violations {
+                    match counts.iter_mut().find(|(table, parent, _)| *table == violation.table && *parent == violation.parent) {
+                        Some((_, _, count)) => *count += 1,
+                        None => counts.push((&violation.table, &violation.parent, 1)),
+                    }
+                }
+                for (table, parent, count) in counts {
+                    println!("  {} → {}: {}", table, parent, count);
+                }
+            }
+
+            if !check.corruption.is_empty() {
+                return Err(CliError::new(
+                    ErrorCategory::Database,
+                    format!("{} is damaged; restore it from a backup", db_path.display()),
+                ));
+            }
+            if check.violations.is_empty() {
+                return Ok(());
+            }
+            if !repair {
+                return Err(CliError::new(
+                    ErrorCategory::Database,
+                    "Found dangling references; run `olog fsck --repair` to delete the rows holding them",
+                ));
+            }
+            confirm::confirm(&format!(
+                "Repairing deletes these {} rows, and any rows that depend on them{}.",
+                check.violations.len(),
+                if no_backup { ", without a backup" } else { "" }
+            ))?;
+            if !no_backup {
+                back_up(conn, db_path, "fsck")?;
+            }
+            let deleted = fsck::repair(conn)
+                .map_err(|e| CliError::context(&format!("Error repairing {}", db_path.display()), e))?;
+            println!("Deleted {} rows with dangling references, along with the rows that depended on them.", deleted);
+        },
+        Commands::DeleteOlog { olog_id } => {
+            let olog = load_olog(conn, olog_id)?;
+            confirm::confirm(&format!("Deleting:\n{}", deletion_summary(conn, &olog)?))?;
+            let citations = delete_olog(conn, olog_id)
+                .map_err(|e| CliError::context(&format!("Error deleting Olog {}", olog_id), e))?;
+            elastic::remove_olog(olog_id);
+            gitrepo::remove(conn, olog_id, &olog.title);
+            println!(
+                "Deleted Olog {}: {} nodes, {} hyperedges and {} citations no other olog uses.",
+                olog_id, olog.nodes.len(), olog.hyperedges.len(), citations
+            );
+        },
+        Commands::ShowNode { node_id, format } => {
+            let olog_id: Option<String> = conn
+                .query_row("SELECT olog_id FROM Nodes WHERE node_id = ?1", params![node_id.to_string()], |row| row.get(0))
+                .optional()?;
+            let Some(olog_id) = olog_id else {
+                return Err(CliError::new(ErrorCategory::Usage, format!("Node {} not found", node_id)));
+            };
+            let mut olog = load_olog(conn, Uuid::parse_str(&olog_id)?)?;
+            let Some(label) = olog.nodes.iter().find(|node| node.id == node_id).map(|node| node.label.clone()) else {
+                return Err(CliError::new(ErrorCategory::Usage, format!("Node {} not found", node_id)));
+            };
+
+            // Excerpts are found by the stored label, then shown with the originals back
+            let redactions = redact::read_redactions(conn, &olog)?;
+            let aliases: Vec<String> = unify::node_aliases(conn, node_id)?.iter()
+                .map(|alias| redact::restore(alias, &redactions))
+                .collect();
+            let mut origins = provenance::node_origins(conn, node_id, &label)
+                .map_err(|e| CliError::context(&format!("Error reading provenance of node {}", node_id), e))?;
+            for origin in &mut origins {
+                origin.document_title = redact::restore(&origin.document_title, &redactions);
+                origin.excerpt = origin.excerpt.as_deref().map(|excerpt| redact::restore(excerpt, &redactions));
+            }
+            redact::restore_olog(conn, &mut olog)?;
+            let node = olog.nodes.iter().find(|node| node.id == node_id).expect("the node was found above");
+            format::print(&display::node_output(&olog, node, &aliases, &origins), format, false);
+        },
+        Commands::Search { query, limit, raw, reindex, format } => {
+            if reindex {
+                let indexed = search::reindex(conn).map_err(|e| CliError::context("Error rebuilding the search index", e))?;
+                // Kept off stdout in the other formats so it doesn't corrupt their output
+                match format {
+                    OutputFormat::Table => println!("Indexed {} labels and citations.", indexed),
+                    _ => eprintln!("Indexed {} labels and citations.", indexed),
+                }
+            }
+            if query.is_empty() {
+                return Ok(());
+            }
+            let query = query.join(" ");
+            let fts_query = if raw { query.clone() } else { search::plain_query(&query) };
+            let (open, close) = if format == OutputFormat::Table && io::stdout().is_terminal() { ("\x1b[1m", "\x1b[0m") } else { ("**", "**") };
+            let found = search::search(conn, &fts_query, limit as usize, open, close).map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(_, Some(message)) if message.starts_with("fts5") => {
+                    CliError::new(ErrorCategory::Usage, format!("Invalid search query: {}", message))
+                },
+                e => CliError::context("Error searching", e),
+            })?;
+            format::print(&display::search_output(&query, &found), format, false);
+        },
+        Commands::ConceptReport { label, output } => {
+            let report = report::gather(conn, &label)
+                .map_err(|e| CliError::context(&format!("Error gathering hyperedges for \"{}\"", label), e))?;
+            if report.mentions.is_empty() {
+                return Err(CliError::new(ErrorCategory::Usage, format!("No hyperedges involve a concept named \"{}\"", label)));
+            }
+            let markdown = report::to_markdown(&report);
+            match output {
+                Some(path) => {
+                    fs::write(&path, markdown).map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?;
+                    println!("Wrote a report on {} hyperedges to {}.", report.mentions.len(), path.display());
+                },
+                None => print!("{}", markdown),
+            }
+        },
+        Commands::ListOlogs { title, kind, json, format } => {
+            let summaries = list_ologs_titled(conn, title.as_deref().unwrap_or_default(), &kind)
+                .map_err(|e| CliError::context("Error listing ologs", e))?;
+            let mut empty = format!("No ologs in namespace `{}`", namespace);
+            if let Some(prefix) = title {
+                empty.push_str(&format!(" with a title starting `{}`", prefix));
+            }
+            if !kind.is_empty() {
+                empty.push_str(&format!(" of kind {}", kind.iter().map(|kind| kind.name()).collect::<Vec<_>>().join(" or ")));
+            }
+            empty.push('.');
+            let format = if json { OutputFormat::Json } else { format };
+            format::print(&display::olog_list_output(&summaries, &empty), format, false);
+        },
+        Commands::Usage { by, since, format } => {
+            let totals = usage::totals(conn, by, since.as_deref()).map_err(|e| CliError::context("Error reading usage", e))?;
+            format::print(&display::usage_output(&totals, by), format, false);
+        },
+        Commands::Metrics { output } => {
+            let rendered = metrics::render(&metrics::database_metrics(conn)?);
+            match output {
+                Some(path) => metrics::write_textfile(&path, &rendered)
+                    .map_err(|e| CliError::context(&format!("Error writing {}", path.display()), e))?,
+                None => print!("{}", rendered),
+            }
+        },
+        Commands::Namespaces { format } => {
+            let mut stmt = conn.prepare("SELECT namespace, COUNT(*) FROM Ologs GROUP BY namespace ORDER BY namespace")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            format::print(&display::namespaces_output(&rows, namespace), format, false);
+        },
+        Commands::Reembed { olog_id, model, batch_size, requests_per_minute, retries, .. } => {
+            if let Some(olog_id) = olog_id {
+                if !olog_in_namespace(conn, olog_id)? {
+                    return Err(CliError::new(ErrorCategory::Usage, format!("Olog {} not found in namespace `{}`", olog_id, namespace)));
+                }
+            }
+            if let Some(retries) = retries {
+                retry::set_max_attempts(retries.saturating_add(1));
+            }
+            let options = reembed::ReembedOptions { model, batch_size: batch_size as usize, requests_per_minute };
+            let report = reembed::reembed_nodes(conn, provider::current(), olog_id, &options, |done, total| {
+                eprintln!("Embedded {}/{} nodes", done, total);
+            })
+            .map_err(|e| CliError::context("Error re-embedding nodes", e))?;
+            println!(
+                "Embedded {} nodes with {}; {} were already up to date.",
+                report.embedded, options.model, report.up_to_date
+            );
+        },
+        Commands::ImportRdf { file, title } => {
+            let text = fs::read_to_string(&file).map_err(|e| CliError::context(&format!("Error reading {}", file.display()), e))?;
+            let triples = rdf::parse_turtle(&text).map_err(|e| CliError::context(&format!("Error reading {}", file.display()), e))?;
+            let name = file.file_name().map_or_else(|| file.display().to_string(), |name| name.to_string_lossy().into_owned());
+            let citation = Citation { id: Uuid::new_v4(), title: name, label: file.display().to_string(), text };
+            let citation_id = citation.id;
+            let olog = rdf::ontology_to_olog(&triples, title, citation);
+            if olog.nodes.is_empty() {
+                return Err(CliError::new(
+                    ErrorCategory::Usage,
+                    format!("No classes, properties or assertions found in {}", file.display()),
+                ));
+            }
+
+            write_olog_to_db(conn, &olog, Operation::Import).map_err(|e| CliError::context("Error writing Olog to database", e))?;
+            let origins: Vec<_> = olog.nodes.iter()
+                .map(|node| provenance::NodeProvenance { label: node.label.clone(), citation_id, pass: 1, section: None })
+                .collect();
+            provenance::record_provenance(conn, &olog, &origins)?;
+            println!(
+                "Imported Olog {}: {} ({} nodes, {} hyperedges from {} triples)",
+                olog.id, olog.title, olog.nodes.len(), olog.hyperedges.len(), triples.len()
+            );
+        },
+        Commands::ImportJson { file, citation, title } => {
+            let json = fs::read_to_string(&file).map_err(|e| CliError::context(&format!("Error reading {}", file.display()), e))?;
+            let cited = citation.as_ref().unwrap_or(&file);
+            let text = match &citation {
+                Some(path) => fs::read_to_string(path).map_err(|e| CliError::context(&format!("Error reading {}", path.display()), e))?,
+                None => json.clone(),
+            };
+            let name = cited.file_name().map_or_else(|| cited.display().to_string(), |name| name.to_string_lossy().into_owned());
+            let citation = Citation::new(name, cited.display().to_string(), text);
+            let mut olog = olog_from_json(&json, citation)
+                .map_err(|e| CliError::new(ErrorCategory::Schema, format!("Invalid olog JSON in {}: {}", file.display(), e)))?;
+            if let Some(title) = title {
+                olog.title = title;
+            }
+
+            write_olog_to_db(conn, &olog, Operation::Import).map_err(|e| CliError::context("Error writing Olog to database", e))?;
+            println!(
+                "Imported Olog {}: {} ({} nodes, {} hyperedges)",
+                olog.id, olog.title, olog.nodes.len(), olog.hyperedges.len()
+            );
+        },
+        Commands::Sync { other_db, resolve } => {
+            let report = sync::sync_databases(conn, &other_db, resolve)
+                .map_err(|e| CliError::context(&format!("Error syncing with {}", other_db.display()), e))?;
+
+            for (olog_id, title) in &report.imported {
+                println!("Imported Olog {}: {}", olog_id, title);
+            }
+            for olog_id in &report.merged {
+                println!("Merged divergent Olog {}", olog_id);
+            }
+            for (olog_id, copy_id) in &report.kept_both {
+                println!("Kept both versions of Olog {}; the other copy is now Olog {}", olog_id, copy_id);
+            }
+            for (olog_id, title) in &report.divergent {
+                println!("Olog {} ({}) differs between the databases", olog_id, title);
+            }
+            for olog_id in &report.elsewhere {
+                println!("Olog {} exists locally in another namespace; skipped", olog_id);
+            }
+            println!(
+                "{} imported, {} identical, {} merged, {} kept both, {} divergent",
+                report.imported.len(),
+                report.identical,
+                report.merged.len(),
+                report.kept_both.len(),
+                report.divergent.len()
+            );
+            if !report.divergent.is_empty() {
+                println!("Use --resolve merge or --resolve keep-both to reconcile divergent ologs.");
+            }
+        },
     }
+    Ok(())
 }
 
-fn merge_ologs(olog1: Olog, olog2: Olog) -> Olog {
-    let mut node_map = HashMap::new();
-    let mut hyperedge_map = HashMap::new();
+fn main() -> ExitCode {
+    // The subcommand's name tags the model usage it records
+    let parsed = Cli::command().try_get_matches().and_then(|matches| {
+        let operation = matches.subcommand_name().unwrap_or_default().to_string();
+        Cli::from_arg_matches(&matches).map(|cli| (cli, operation))
+    });
+    let (cli, operation) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            // Help and version output, or a human is reading: let clap print as usual
+            let json_requested = env::args().collect::<Vec<_>>().windows(2)
+                .any(|w| w[0] == "--error-format" && w[1] == "json")
+                || env::args().any(|arg| arg == "--error-format=json");
+            if !e.use_stderr() || !json_requested {
+                e.exit();
+            }
+            let rendered = e.render().to_string();
+            let message = rendered.lines().next().unwrap_or_default().trim_start_matches("error: ");
+            CliError::new(ErrorCategory::Usage, message).report(ErrorFormat::Json);
+            return ExitCode::from(ErrorCategory::Usage.exit_code());
+        },
+    };
 
-    // Merge nodes
-    for node in olog1.nodes.into_iter().chain(olog2.nodes.into_iter()) {
-        node_map.entry(node.label.clone()).or_insert(node);
+    if cli.prompt_dev {
+        prompts::enable_dev_mode();
+    } else {
+        let dir = prompts::template_dir(cli.prompt_dir).and_then(|dir| dir.map_or(Ok(()), prompts::set_template_dir));
+        if let Err(e) = dir {
+            let e = CliError::new(ErrorCategory::Config, e.to_string());
+            e.report(cli.error_format);
+            return ExitCode::from(e.category.exit_code());
+        }
     }
-
-    // Preparing merged nodes for hyperedge linking
-    let merged_nodes = node_map.values().cloned().collect::<Vec<Node>>();
-
-    // Helper to find node by label
-    let find_node_by_label = |label: &str| merged_nodes.iter().find(|n| n.label == label).cloned();
-
-    // Merge hyperedges
-    for hyperedge in olog1.hyperedges.into_iter().chain(olog2.hyperedges.into_iter()) {
-        let source_nodes = hyperedge.source.iter().filter_map(|node| find_node_by_label(&node.label)).collect::<Vec<Node>>();
-        let target_nodes = hyperedge.target.iter().filter_map(|node| find_node_by_label(&node.label)).collect::<Vec<Node>>();
-
-        // Key for identifying unique hyperedges
-        let hyperedge_key = (hyperedge.label.clone(), source_nodes.clone(), target_nodes.clone());
-        
-        hyperedge_map.entry(hyperedge_key).or_insert(Hyperedge {
-            id: Uuid::new_v4(), // Assign a new UUID for merged hyperedge
-            label: hyperedge.label,
-            source: source_nodes,
-            target: target_nodes,
-            citations: hyperedge.citations,
-        });
+    match config::max_attempts(cli.max_attempts, retry::DEFAULT_MAX_ATTEMPTS) {
+        Ok(attempts) => retry::set_max_attempts(attempts.value),
+        Err(e) => {
+            let e = CliError::new(ErrorCategory::Config, e.to_string());
+            e.report(cli.error_format);
+            return ExitCode::from(e.category.exit_code());
+        },
     }
-
-    Olog {
-        id: olog1.id,
-        title: olog1.title,
-        nodes: merged_nodes,
-        hyperedges: hyperedge_map.values().cloned().collect(),
+    if cli.yes {
+        confirm::assume_yes();
     }
-}
-
-fn generate_olog(text: String) -> Result<Olog, Box<dyn std::error::Error>> {
-    let prompt = include_str!("./res/olog.md").to_string();
-    let openai_response = get_openai_response_json(format!("{}\n{}", prompt, text))?;
-    let openai_title = get_openai_response(format!("{}\n{}", "What is the the title of this document? Respond with only the title and no additional text", text))?;
-    let openai_label = get_openai_response(format!("{}\n{}", "Create a label for this document. The label should be under 50 words long. Respond with only the label and no additional text", text))?;
-    let olog_schema: JsonOlogSchema = serde_json::from_str(&openai_response)?;
-    let olog_schema_uuid: JsonOlogSchema = replace_ids_with_uuids(olog_schema);
-    let citation: Citation = Citation {
-        id: Uuid::new_v4(),
-        title: openai_title,
-        label: openai_label,
-        text: text,
-    };
-    let olog: Olog = convert_json_olog_to_olog(olog_schema_uuid, citation);
-
-    Ok(olog)
-}
-
-fn main() {
-    let text = include_str!("./res/olog-pdf.md").to_string();
-
-    // Create database tables
-    if let Err(e) = create_olog_tables() {
-        eprintln!("Error creating tables: {}", e);
-        return;
+    if cli.no_llm_cache {
+        cache::disable_responses();
+    }
+    cache::set_response_ttl(cli.llm_cache_ttl);
+
+    let selected = config::provider(cli.provider)
+        .map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))
+        .and_then(|kind| match (kind.value, cli.model_path.clone()) {
+            (Provider::LocalGguf, None) => Err(CliError::new(ErrorCategory::Usage, "--provider local-gguf needs --model-path")),
+            (Provider::LocalGguf, path @ Some(_)) | (_, path @ None) => {
+                provider::select(kind.value, path).map_err(|e| CliError::new(ErrorCategory::Config, e.to_string()))
+            },
+            (_, Some(_)) => Err(CliError::new(ErrorCategory::Usage, "--model-path needs --provider local-gguf")),
+        });
+    if let Err(e) = selected {
+        e.report(cli.error_format);
+        return ExitCode::from(e.category.exit_code());
     }
 
-    // Generate two separate Ologs
-    let olog1 = match generate_olog(text.clone()) {
-        Ok(olog) => olog,
-        Err(e) => {
-            eprintln!("An error occurred in generating Olog1: {}", e);
-            return;
-        },
-    };
+    if let Commands::Config { command: ConfigCommand::Show { preset, format } } = &cli.command {
+        return match show_config(cli.db, cli.provider, cli.max_attempts, preset, *format) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                e.report(cli.error_format);
+                ExitCode::from(e.category.exit_code())
+            },
+        };
+    }
 
-    let olog2 = match generate_olog(text) {
-        Ok(olog) => olog,
+    let db_path = match database_path(cli.db) {
+        Ok(path) => path,
         Err(e) => {
-            eprintln!("An error occurred in generating Olog2: {}", e);
-            return;
+            let e = CliError::new(ErrorCategory::Config, e.to_string());
+            e.report(cli.error_format);
+            return ExitCode::from(e.category.exit_code());
         },
     };
 
-    // Merge the two Ologs
-    let merged_olog = merge_ologs(olog1, olog2);
-
-    // Write the merged Olog to the database
-    match write_olog_to_db(&merged_olog) {
-        Ok(_) => println!("Merged Olog written to database successfully."),
-        Err(e) => eprintln!("Error writing merged Olog to database: {}", e),
+    // Has to work even when the database can't be opened
+    if let Commands::Doctor { preset } = &cli.command {
+        return match doctor::run_checks(preset, &db_path, cli.model_path.as_deref()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                e.report(cli.error_format);
+                ExitCode::from(e.category.exit_code())
+            },
+        };
     }
 
-    // Optionally, read the merged Olog from the database and display it
-    match read_olog_from_db(merged_olog.id) {
-        Ok(olog_from_db) => println!("Read merged Olog from database: {:#?}", olog_from_db),
-        Err(e) => eprintln!("Error reading merged Olog from database: {}", e),
+    match run(cli.command, &operation, &cli.namespace, &db_path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            e.report(cli.error_format);
+            ExitCode::from(e.category.exit_code())
+        },
     }
 }
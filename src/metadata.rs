@@ -0,0 +1,269 @@
+use clap::ValueEnum;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config;
+use crate::db::document_key;
+use crate::error::OlogError;
+use crate::http;
+use crate::pdf;
+use crate::retry::with_retries;
+
+// A candidate at least this confident ends the chain; otherwise the most
+// confident candidate any extractor found is used
+pub const MIN_CONFIDENCE: f64 = 0.6;
+const CROSSREF_URL: &str = "https://api.crossref.org/works/";
+const CROSSREF_TIMEOUT: Duration = Duration::from_secs(10);
+// Headings this far into the document are section headings, not the title
+const TITLE_SEARCH_LINES: usize = 40;
+const LABEL_MAX_WORDS: usize = 50;
+
+const GENERIC_HEADING: &str = r"(?i)^(abstract|summary|introduction|contents|table of contents|preface|foreword|keywords|references)$";
+// Titles that word processors and print drivers fill in on their own
+const PLACEHOLDER_TITLE: &str = r"(?i)^(untitled|microsoft (word|powerpoint)\b|document\d*$|slide \d+|title$)|\.(pdf|docx?|tex|dvi|ps)$";
+const ABSTRACT_LINE: &str = r"(?i)^[#*_\s]*abstract[*_\s]*[:.\-—]?[*_\s]*(.*)$";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Extractor {
+    /// A Markdown heading at the top of the document, and its abstract
+    Heading,
+    /// The PDF's own title and subject fields
+    PdfMetadata,
+    /// Crossref's record for a DOI found in the front matter
+    Crossref,
+    /// Ask the label model
+    Model,
+}
+
+impl Extractor {
+    pub const ALL: [Extractor; 4] = [Extractor::Heading, Extractor::PdfMetadata, Extractor::Crossref, Extractor::Model];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Extractor::Heading => "heading",
+            Extractor::PdfMetadata => "pdf-metadata",
+            Extractor::Crossref => "crossref",
+            Extractor::Model => "model",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Extracted {
+    pub value: String,
+    pub extractor: Extractor,
+    // How far the extractor trusts its own answer, 0 to 1
+    pub confidence: f64,
+}
+
+/// Which extractors produced a citation's title and label
+#[derive(Debug, Clone)]
+pub struct CitationMetadata {
+    pub citation_id: Uuid,
+    pub title: Extracted,
+    pub label: Extracted,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossrefResponse {
+    message: CrossrefWork,
+}
+
+#[derive(Debug, Deserialize)]
+struct CrossrefWork {
+    #[serde(default)]
+    title: Vec<String>,
+    #[serde(rename = "abstract")]
+    summary: Option<String>,
+}
+
+// Runs the title and label chains over one document. The Crossref record is
+// fetched at most once and only if the chain gets that far; an offline
+// provider means nothing about the document should leave the machine, DOI
+// included.
+pub struct Lookup<'a> {
+    text: &'a str,
+    pdf: Option<&'a pdf::Metadata>,
+    offline: bool,
+    crossref: Option<Option<CrossrefWork>>,
+}
+
+impl<'a> Lookup<'a> {
+    pub fn new(text: &'a str, pdf: Option<&'a pdf::Metadata>, offline: bool) -> Self {
+        Lookup { text, pdf, offline, crossref: None }
+    }
+
+    pub fn title(
+        &mut self,
+        chain: &[Extractor],
+        model: impl FnOnce() -> Result<String, OlogError>,
+    ) -> Result<Extracted, OlogError> {
+        self.run("title", chain, model, |lookup, extractor| match extractor {
+            Extractor::Heading => heading_title(lookup.text),
+            Extractor::PdfMetadata => lookup.pdf.and_then(|pdf| pdf.title.as_deref()).map(|title| {
+                let placeholder = Regex::new(PLACEHOLDER_TITLE).unwrap().is_match(title) || title.chars().count() < 4;
+                (title.to_string(), if placeholder { 0.2 } else { 0.7 })
+            }),
+            Extractor::Crossref => lookup.crossref().and_then(|work| work.title.first().cloned()).map(|title| (title, 0.95)),
+            Extractor::Model => None,
+        })
+    }
+
+    pub fn label(
+        &mut self,
+        chain: &[Extractor],
+        model: impl FnOnce() -> Result<String, OlogError>,
+    ) -> Result<Extracted, OlogError> {
+        self.run("label", chain, model, |lookup, extractor| match extractor {
+            Extractor::Heading => abstract_label(lookup.text),
+            Extractor::PdfMetadata => lookup.pdf.and_then(|pdf| pdf.subject.as_deref()).map(|subject| (first_words(subject), 0.5)),
+            Extractor::Crossref => lookup.crossref()
+                .and_then(|work| work.summary.as_deref())
+                .map(|summary| (first_words(&strip_tags(summary)), 0.9)),
+            Extractor::Model => None,
+        })
+    }
+
+    fn run(
+        &mut self,
+        field: &str,
+        chain: &[Extractor],
+        model: impl FnOnce() -> Result<String, OlogError>,
+        heuristic: impl Fn(&mut Self, Extractor) -> Option<(String, f64)>,
+    ) -> Result<Extracted, OlogError> {
+        let mut model = Some(model);
+        let mut best: Option<Extracted> = None;
+        for &extractor in chain {
+            let found = match extractor {
+                // The model always answers, so its reply is trusted about as much as a clean heading
+                Extractor::Model => match model.take() {
+                    Some(model) => Some((model()?, 0.7)),
+                    None => None,
+                },
+                _ => heuristic(self, extractor),
+            };
+            let Some((value, confidence)) = found.filter(|(value, _)| !value.trim().is_empty()) else {
+                continue;
+            };
+            let candidate = Extracted { value: value.trim().to_string(), extractor, confidence };
+            if candidate.confidence >= MIN_CONFIDENCE {
+                return Ok(candidate);
+            }
+            if best.as_ref().is_none_or(|best| candidate.confidence > best.confidence) {
+                best = Some(candidate);
+            }
+        }
+        best.ok_or_else(|| {
+            let tried = chain.iter().map(|extractor| extractor.name()).collect::<Vec<_>>().join(", ");
+            OlogError::Usage(format!("No {} found by {}; add `model` to the extractors to fall back on the model", field, tried))
+        })
+    }
+
+    fn crossref(&mut self) -> Option<&CrossrefWork> {
+        if self.crossref.is_none() {
+            let doi = document_key(self.text).doi.filter(|_| !self.offline);
+            let work = doi.and_then(|doi| match fetch_crossref(&doi) {
+                Ok(work) => Some(work),
+                Err(e) => {
+                    eprintln!("Crossref lookup for {} failed: {}", doi, e);
+                    None
+                },
+            });
+            self.crossref = Some(work);
+        }
+        self.crossref.as_ref().and_then(Option::as_ref)
+    }
+}
+
+// The best title found without the network or the model, for {title} in
+// prompt templates
+pub fn title_hint(text: &str, pdf: Option<&pdf::Metadata>) -> Option<String> {
+    Lookup::new(text, pdf, true)
+        .title(&[Extractor::Heading, Extractor::PdfMetadata], || Err(OlogError::Other("not asked".to_string())))
+        .ok()
+        .map(|title| title.value)
+}
+
+fn fetch_crossref(doi: &str) -> Result<CrossrefWork, OlogError> {
+    let timeout = config::timeout(CROSSREF_TIMEOUT)?.value;
+    let url = format!("{}{}", CROSSREF_URL, doi);
+    let body = with_retries("Crossref lookup", || Ok(http::get(&url)?.timeout(timeout).call()?.into_string()?))?;
+    let response: CrossrefResponse = serde_json::from_str(&body).map_err(|e| OlogError::Schema(format!("Malformed Crossref record: {}", e)))?;
+    Ok(response.message)
+}
+
+// The first top-level heading, or failing that a lower one that isn't a
+// standard section name
+fn heading_title(text: &str) -> Option<(String, f64)> {
+    let generic = Regex::new(GENERIC_HEADING).unwrap();
+    let mut fallback = None;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()).take(TITLE_SEARCH_LINES) {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if level == 0 || !line[level..].starts_with(' ') {
+            continue;
+        }
+        let heading = line[level..].trim().trim_end_matches('#').trim().trim_matches(|c| c == '*' || c == '_').trim();
+        if heading.is_empty() || generic.is_match(heading) {
+            continue;
+        }
+        if level == 1 {
+            return Some((heading.to_string(), 0.85));
+        }
+        fallback.get_or_insert_with(|| (heading.to_string(), 0.4));
+    }
+    fallback
+}
+
+// The abstract's opening words, from an "Abstract" heading or line
+fn abstract_label(text: &str) -> Option<(String, f64)> {
+    let abstract_line = Regex::new(ABSTRACT_LINE).unwrap();
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(rest) = abstract_line.captures(line).map(|captures| captures[1].trim().to_string()) else {
+            continue;
+        };
+        let paragraph = if rest.is_empty() {
+            lines.by_ref().skip_while(|line| line.is_empty()).take_while(|line| !line.is_empty()).collect::<Vec<_>>().join(" ")
+        } else {
+            rest
+        };
+        let words = paragraph.split_whitespace().count();
+        // A line or two is more likely a stray mention than the abstract itself
+        return (words > 0).then(|| (first_words(&paragraph), if words >= 15 { 0.65 } else { 0.3 }));
+    }
+    None
+}
+
+fn first_words(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= LABEL_MAX_WORDS {
+        return words.join(" ");
+    }
+    format!("{}…", words[..LABEL_MAX_WORDS].join(" "))
+}
+
+// Crossref abstracts are JATS XML
+fn strip_tags(text: &str) -> String {
+    Regex::new(r"<[^>]*>").unwrap().replace_all(text, " ").to_string()
+}
+
+pub fn record_metadata(conn: &Connection, metadata: &[CitationMetadata]) -> rusqlite::Result<()> {
+    for entry in metadata {
+        conn.execute(
+            "INSERT OR REPLACE INTO Citation_Metadata (citation_id, title_extractor, title_confidence, label_extractor, label_confidence)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                entry.citation_id.to_string(),
+                entry.title.extractor.name(),
+                entry.title.confidence,
+                entry.label.extractor.name(),
+                entry.label.confidence
+            ],
+        )?;
+    }
+    Ok(())
+}
+
@@ -0,0 +1,96 @@
+use rusqlite::{Connection, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::db::has_table;
+
+const PREFIX: &str = "olog";
+
+// An optional (label name, label value) pair and the sample's value
+type Sample = (Option<(&'static str, String)>, f64);
+
+/// One Prometheus gauge: its samples share a name, help text and label name
+#[derive(Debug)]
+pub struct Gauge {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub samples: Vec<Sample>,
+}
+
+impl Gauge {
+    fn single(name: &'static str, help: &'static str, value: f64) -> Self {
+        Gauge { name, help, samples: vec![(None, value)] }
+    }
+}
+
+// Everything the database can tell about the work done so far. Counts span
+// every namespace; the olog count is also broken down by namespace.
+pub fn database_metrics(conn: &Connection) -> Result<Vec<Gauge>> {
+    let count = |table: &str| -> Result<f64> {
+        if !has_table(conn, table)? {
+            return Ok(0.0);
+        }
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0)).map(|n| n as f64)
+    };
+    let grouped = |sql: &str, label: &'static str| -> Result<Vec<Sample>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| Ok((Some((label, row.get::<_, String>(0)?)), row.get::<_, i64>(1)? as f64)))?;
+        rows.collect()
+    };
+
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let ocr_jobs = if has_table(conn, "Ocr_Cache")? {
+        grouped("SELECT ocr, COUNT(*) FROM Ocr_Cache GROUP BY ocr ORDER BY ocr", "ocr")?
+    } else {
+        Vec::new()
+    };
+
+    Ok(vec![
+        Gauge::single("database_bytes", "Size of the database file", (page_size * page_count) as f64),
+        Gauge {
+            name: "ologs",
+            help: "Stored ologs, books and chapters included",
+            samples: grouped("SELECT namespace, COUNT(*) FROM Ologs GROUP BY namespace ORDER BY namespace", "namespace")?,
+        },
+        Gauge::single("nodes", "Stored nodes", count("Nodes")?),
+        Gauge::single("hyperedges", "Stored hyperedges", count("Hyperedges")?),
+        Gauge::single("citations", "Stored citations, each shared by every olog that cites it", count("Citations")?),
+        Gauge::single("documents", "Distinct cited texts, each stored once", count("Documents")?),
+        Gauge::single("ingestions", "Documents ingested, counting each extend and new version", count("Ingestions")?),
+        Gauge { name: "ocr_documents", help: "PDFs whose extracted text is cached, by extractor", samples: ocr_jobs },
+        Gauge::single("debates", "Debates run over stored ologs", count("Debates")?),
+        Gauge::single("argument_maps", "Stored argument maps", count("Argument_Maps")?),
+        Gauge::single("merge_reviews_pending", "Undone semantic merges waiting for review", count("Merge_Reviews")?),
+    ])
+}
+
+/// The Prometheus text exposition format, for a textfile collector or any
+/// scraper that reads a file
+pub fn render(gauges: &[Gauge]) -> String {
+    let mut out = String::new();
+    for gauge in gauges {
+        let name = format!("{}_{}", PREFIX, gauge.name);
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, gauge.help, name));
+        for (label, value) in &gauge.samples {
+            match label {
+                Some((key, label)) => out.push_str(&format!("{}{{{}=\"{}\"}} {}\n", name, key, escape(label), value)),
+                None => out.push_str(&format!("{} {}\n", name, value)),
+            }
+        }
+    }
+    out
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Written aside and renamed, since a collector may read the file at any moment
+pub fn write_textfile(path: &Path, rendered: &str) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, rendered)?;
+    fs::rename(&partial, path)
+}
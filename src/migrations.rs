@@ -0,0 +1,511 @@
+use regex::Regex;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, Result};
+use std::path::Path;
+
+use crate::db::{add_column_if_missing, baseline_schema, citation_text, has_table, store_document, stored_citation_text};
+use crate::search::create_index;
+
+/// One ordered step of the schema's history
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every migration in order. New ones go at the end with the next version; a
+/// migration that has shipped is never edited, since databases record only
+/// that they applied it.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema: ologs, citations, ingestions, versions, books, provenance, aliases, debates and embeddings",
+        apply: baseline_schema,
+    },
+    Migration {
+        version: 2,
+        description: "merge reviews: semantic merges rolled back for review",
+        apply: merge_reviews,
+    },
+    Migration {
+        version: 3,
+        description: "edge verdicts: fact-check results for hyperedges against their citations",
+        apply: edge_verdicts,
+    },
+    Migration {
+        version: 4,
+        description: "compressed citations: long citation text stored zstd-compressed",
+        apply: compress_citations,
+    },
+    Migration {
+        version: 5,
+        description: "citation metadata: which extractor found each citation's title and label",
+        apply: citation_metadata,
+    },
+    Migration {
+        version: 6,
+        description: "cascading foreign keys: deleting a row deletes the rows that reference it",
+        apply: cascade_foreign_keys,
+    },
+    Migration {
+        version: 7,
+        description: "olog parents: the ologs a merge-ologs result was built from",
+        apply: olog_parents,
+    },
+    Migration {
+        version: 8,
+        description: "debate objections: typed objections raised by debate personas",
+        apply: debate_objections,
+    },
+    Migration {
+        version: 9,
+        description: "search index: full-text index over node labels, hyperedge labels and citation text",
+        apply: create_index,
+    },
+    Migration {
+        version: 10,
+        description: "ocr cache: text extracted from PDFs, keyed by content hash and source URL",
+        apply: ocr_cache,
+    },
+    Migration {
+        version: 11,
+        description: "lookup indexes: titles and labels for prefix matching, and the referencing side of every link",
+        apply: lookup_indexes,
+    },
+    Migration {
+        version: 12,
+        description: "argument maps: claims and premises, the support and attack relations between them, and their links to hyperedges",
+        apply: argument_maps,
+    },
+    Migration {
+        version: 13,
+        description: "redactions: the originals behind placeholders in redacted citation text",
+        apply: redactions,
+    },
+    Migration {
+        version: 14,
+        description: "olog kinds: whether each olog came from a document, a merge, an abstraction, a hand edit or an import",
+        apply: olog_kinds,
+    },
+    Migration {
+        version: 15,
+        description: "llm usage: tokens and estimated cost of every model call, by command and olog",
+        apply: llm_usage,
+    },
+    Migration {
+        version: 16,
+        description: "documents: cited text stored once, and the span of it each hyperedge quotes",
+        apply: documents,
+    },
+    Migration {
+        version: 17,
+        description: "link positions: the order of links written before positions were recorded",
+        apply: link_positions,
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Highest migration applied to the database; 0 before any was recorded
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    if !has_table(conn, "Schema_Migrations")? {
+        return Ok(0);
+    }
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM Schema_Migrations", [], |row| row.get(0))
+}
+
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    let current = current_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|migration| migration.version > current).collect())
+}
+
+fn merge_reviews(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Merge_Reviews (
+            olog_id TEXT NOT NULL,
+            alias TEXT NOT NULL,
+            label TEXT NOT NULL,
+            flagged_at TEXT NOT NULL,
+            PRIMARY KEY (olog_id, alias),
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn edge_verdicts(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Edge_Verdicts (
+            olog_id TEXT NOT NULL,
+            hyperedge_id TEXT NOT NULL,
+            citation_id TEXT NOT NULL,
+            verdict TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            reason TEXT NOT NULL,
+            model TEXT NOT NULL,
+            checked_at TEXT NOT NULL,
+            PRIMARY KEY (hyperedge_id, citation_id),
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// Rewrites the plain text already stored in the form new writes use
+fn compress_citations(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT citation_id, text FROM Citations WHERE typeof(text) = 'text'")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    let mut update = conn.prepare("UPDATE Citations SET text = ?2 WHERE citation_id = ?1")?;
+    for (citation_id, text) in rows {
+        if let Value::Blob(compressed) = stored_citation_text(&text)? {
+            update.execute(params![citation_id, compressed])?;
+        }
+    }
+    Ok(())
+}
+
+fn citation_metadata(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Citation_Metadata (
+            citation_id TEXT PRIMARY KEY,
+            title_extractor TEXT NOT NULL,
+            title_confidence REAL NOT NULL,
+            label_extractor TEXT NOT NULL,
+            label_confidence REAL NOT NULL,
+            FOREIGN KEY(citation_id) REFERENCES Citations(citation_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn olog_parents(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Olog_Parents (
+            olog_id TEXT NOT NULL,
+            parent_olog_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (olog_id, parent_olog_id),
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id) ON DELETE CASCADE,
+            FOREIGN KEY(parent_olog_id) REFERENCES Ologs(olog_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn debate_objections(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Debate_Objections (
+            debate_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            ordinal INTEGER NOT NULL,
+            persona TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            objection TEXT NOT NULL,
+            hyperedge_id TEXT,
+            PRIMARY KEY (debate_id, position, ordinal),
+            FOREIGN KEY(debate_id, position) REFERENCES Debate_Turns(debate_id, position) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn ocr_cache(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Ocr_Cache (
+            content_hash TEXT NOT NULL,
+            ocr TEXT NOT NULL,
+            url TEXT,
+            text NOT NULL,
+            cached_at TEXT NOT NULL,
+            PRIMARY KEY (content_hash, ocr)
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Ocr_Cache_Url ON Ocr_Cache(url, ocr)", [])?;
+    Ok(())
+}
+
+// NOCASE so that LIKE 'prefix%', which ignores ASCII case, can use them. The
+// rest index the column a cascading delete or reverse lookup searches by,
+// which the primary keys don't lead with.
+const LOOKUP_INDEXES: &[(&str, &str)] = &[
+    ("Ologs_Namespace_Title", "Ologs (namespace, title COLLATE NOCASE)"),
+    ("Nodes_Label", "Nodes (label COLLATE NOCASE)"),
+    ("Hyperedges_Label", "Hyperedges (label COLLATE NOCASE)"),
+    ("Citations_Title", "Citations (title COLLATE NOCASE)"),
+    ("Citation_Links_Citation", "Citation_Links (citation_id, hyperedge_id)"),
+    ("Hyperedge_Links_Node", "Hyperedge_Links (node_id, hyperedge_id)"),
+    ("Node_Provenance_Citation", "Node_Provenance (citation_id)"),
+    ("Ingestions_Olog", "Ingestions (olog_id)"),
+    ("Ingestions_Hash", "Ingestions (content_hash)"),
+    ("Ingestions_Doi", "Ingestions (doi)"),
+    ("Ingestions_Arxiv", "Ingestions (arxiv_id)"),
+    ("Olog_Versions_Parent", "Olog_Versions (parent_olog_id)"),
+    ("Olog_Parents_Parent", "Olog_Parents (parent_olog_id)"),
+    ("Book_Chapters_Chapter", "Book_Chapters (chapter_olog_id)"),
+    ("Debates_Olog", "Debates (olog_id)"),
+    ("Edge_Verdicts_Olog", "Edge_Verdicts (olog_id)"),
+];
+
+fn lookup_indexes(conn: &Connection) -> Result<()> {
+    for (name, on) in LOOKUP_INDEXES {
+        conn.execute(&format!("CREATE INDEX IF NOT EXISTS {} ON {}", name, on), [])?;
+    }
+    Ok(())
+}
+
+// Claim links keep hyperedges by id like debate evidence does, since a later
+// edit of the olog can leave some of them dangling
+fn argument_maps(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Argument_Maps (
+            argmap_id TEXT PRIMARY KEY,
+            namespace TEXT NOT NULL,
+            title TEXT NOT NULL,
+            olog_id TEXT,
+            model TEXT NOT NULL,
+            created_at TEXT,
+            FOREIGN KEY(olog_id) REFERENCES Ologs(olog_id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Claims (
+            claim_id TEXT PRIMARY KEY,
+            argmap_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            text TEXT NOT NULL,
+            FOREIGN KEY(argmap_id) REFERENCES Argument_Maps(argmap_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Claim_Relations (
+            source_claim_id TEXT NOT NULL,
+            target_claim_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            PRIMARY KEY (source_claim_id, target_claim_id, kind),
+            FOREIGN KEY(source_claim_id) REFERENCES Claims(claim_id) ON DELETE CASCADE,
+            FOREIGN KEY(target_claim_id) REFERENCES Claims(claim_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Claim_Links (
+            claim_id TEXT NOT NULL,
+            hyperedge_id TEXT NOT NULL,
+            PRIMARY KEY (claim_id, hyperedge_id),
+            FOREIGN KEY(claim_id) REFERENCES Claims(claim_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Argument_Maps_Olog ON Argument_Maps (olog_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Claims_Argmap ON Claims (argmap_id, position)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Claim_Relations_Target ON Claim_Relations (target_claim_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Claim_Links_Hyperedge ON Claim_Links (hyperedge_id)", [])?;
+    Ok(())
+}
+
+fn redactions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Redactions (
+            citation_id TEXT NOT NULL,
+            placeholder TEXT NOT NULL,
+            original TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            PRIMARY KEY (citation_id, placeholder),
+            FOREIGN KEY(citation_id) REFERENCES Citations(citation_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// Earlier ologs count as documents, except those with merge parents and books,
+// which hold the merged map of their chapters
+fn olog_kinds(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "Ologs", "kind", "TEXT NOT NULL DEFAULT 'document'")?;
+    conn.execute(
+        "UPDATE Ologs SET kind = 'corpus-merge'
+         WHERE olog_id IN (SELECT olog_id FROM Olog_Parents) OR olog_id IN (SELECT olog_id FROM Books)",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Ologs_Kind ON Ologs (namespace, kind)", [])?;
+    Ok(())
+}
+
+// olog_id has no foreign key: usage stays on record after its olog is deleted
+fn llm_usage(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Llm_Usage (
+            called_at TEXT NOT NULL,
+            namespace TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            olog_id TEXT,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            prompt_tokens INTEGER NOT NULL,
+            completion_tokens INTEGER NOT NULL,
+            cost REAL
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS Llm_Usage_Called ON Llm_Usage (namespace, called_at)", [])?;
+    Ok(())
+}
+
+// Citation text moves to Documents, keyed by its hash, so the citations each
+// pass and chunk made of the same text share one copy
+fn documents(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Documents (
+            document_id TEXT PRIMARY KEY,
+            text TEXT NOT NULL
+        )",
+        [],
+    )?;
+    add_column_if_missing(conn, "Citations", "document_id", "TEXT REFERENCES Documents(document_id)")?;
+    add_column_if_missing(conn, "Citation_Links", "span_start", "INTEGER")?;
+    add_column_if_missing(conn, "Citation_Links", "span_end", "INTEGER")?;
+
+    let mut stmt = conn.prepare("SELECT citation_id, text FROM Citations WHERE text IS NOT NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, citation_text(row, 1)?.unwrap_or_default())))?
+        .collect::<Result<Vec<_>>>()?;
+    let mut update = conn.prepare("UPDATE Citations SET document_id = ?2, text = NULL WHERE citation_id = ?1")?;
+    for (citation_id, text) in rows {
+        update.execute(params![citation_id, store_document(conn, &text)?])?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS Citations_Document ON Citations (document_id)", [])?;
+    Ok(())
+}
+
+// Links from before the position column have none, so the unique index on
+// (hyperedge_id, type, position) never covered them. They are numbered in
+// insertion order after any positioned link of the same hyperedge and side,
+// then any slot still claimed twice keeps its first link.
+fn link_positions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "UPDATE Hyperedge_Links SET position = (
+            SELECT numbered.position FROM (
+                SELECT rowid AS link,
+                       ROW_NUMBER() OVER (PARTITION BY hyperedge_id, type ORDER BY rowid) - 1
+                       + COALESCE((SELECT MAX(positioned.position) + 1 FROM Hyperedge_Links positioned
+                                   WHERE positioned.hyperedge_id = unpositioned.hyperedge_id
+                                   AND positioned.type = unpositioned.type), 0) AS position
+                FROM Hyperedge_Links unpositioned WHERE position IS NULL
+            ) numbered WHERE numbered.link = Hyperedge_Links.rowid
+        )
+        WHERE position IS NULL",
+        [],
+    )?;
+    conn.execute(
+        "DELETE FROM Hyperedge_Links WHERE rowid NOT IN
+         (SELECT MIN(rowid) FROM Hyperedge_Links GROUP BY hyperedge_id, type, position)",
+        [],
+    )?;
+    conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS Hyperedge_Links_Slot ON Hyperedge_Links (hyperedge_id, type, position)", [])?;
+    Ok(())
+}
+
+// SQLite can't alter a constraint, so every table with a foreign key is
+// rebuilt from its own definition with ON DELETE CASCADE added. Rows that
+// already dangle are copied as they are; `olog fsck` reports and repairs them.
+fn cascade_foreign_keys(conn: &Connection) -> Result<()> {
+    let references = Regex::new(r"(?i)REFERENCES\s+\w+\s*\([^)]*\)").unwrap();
+    let create = Regex::new(r"(?i)^CREATE TABLE\s+\w+").unwrap();
+    let mut stmt = conn.prepare("SELECT name, sql FROM sqlite_master WHERE type = 'table' AND sql LIKE '%REFERENCES%' ORDER BY name")?;
+    let tables = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    for (table, sql) in tables {
+        if sql.to_uppercase().contains("ON DELETE") {
+            continue;
+        }
+        let rebuilt = format!("{}_Rebuilt", table);
+        let sql = references.replace_all(&sql, "$0 ON DELETE CASCADE");
+        conn.execute(&create.replace(&sql, format!("CREATE TABLE {}", rebuilt)), [])?;
+
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<Result<Vec<_>>>()?.join(", ");
+        let mut stmt = conn.prepare("SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ?1 AND sql IS NOT NULL")?;
+        let indexes = stmt.query_map(params![table], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+
+        conn.execute(&format!("INSERT INTO {} ({}) SELECT {} FROM {}", rebuilt, columns, columns, table), [])?;
+        conn.execute(&format!("DROP TABLE {}", table), [])?;
+        conn.execute(&format!("ALTER TABLE {} RENAME TO {}", rebuilt, table), [])?;
+        for index in indexes {
+            conn.execute(&index, [])?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies pending migrations in order, each in its own transaction together
+/// with the row recording it, and returns the ones applied. Foreign keys are
+/// off meanwhile, since rebuilding a table drops the one other tables point at.
+pub fn migrate(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    let enforced: bool = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let applied = apply_pending(conn);
+    conn.pragma_update(None, "foreign_keys", enforced)?;
+    applied
+}
+
+fn apply_pending(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Schema_Migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    let pending = pending(conn)?;
+    for migration in &pending {
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx)?;
+        tx.execute(
+            "INSERT INTO Schema_Migrations (version, description, applied_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+            params![migration.version, migration.description],
+        )?;
+        tx.commit()?;
+    }
+    Ok(pending)
+}
+
+/// Why the database shouldn't be used as it is: it was written by a newer
+/// build, or it holds data and is behind this build. Empty databases are
+/// fine; creating the tables brings them up to date.
+pub fn schema_problem(conn: &Connection) -> Result<Option<String>> {
+    let (current, latest) = (current_version(conn)?, latest_version());
+    if current > latest {
+        return Ok(Some(format!(
+            "The database uses schema version {}, newer than the {} this build of olog knows; upgrade olog",
+            current, latest
+        )));
+    }
+    if current < latest && has_table(conn, "Ologs")? {
+        return Ok(Some(format!(
+            "The database is at schema version {} and this build needs {}; run `olog migrate` to upgrade it",
+            current, latest
+        )));
+    }
+    Ok(None)
+}
+
+/// Copies the database to `path` as it is now
+pub fn backup(conn: &Connection, path: &Path) -> Result<()> {
+    conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+    Ok(())
+}
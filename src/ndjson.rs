@@ -0,0 +1,123 @@
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use uuid::Uuid;
+
+use crate::db::{citation_text, span_columns};
+use crate::error::OlogError;
+
+// Every query below is restricted to the current namespace and, when given,
+// a single olog (?1)
+const OLOG_FILTER: &str = "Ologs.namespace = (SELECT namespace FROM temp.Session) AND (?1 IS NULL OR Ologs.olog_id = ?1)";
+
+// Writes one JSON object per line: ologs, then nodes, hyperedges and the
+// citations they use. Rows are streamed straight from SQLite cursors, so
+// memory use doesn't grow with the size of the database.
+pub fn write_ndjson(conn: &Connection, olog_id: Option<Uuid>, out: &mut impl Write) -> Result<(), OlogError> {
+    let filter = olog_id.map(|id| id.to_string());
+
+    let mut stmt = conn.prepare(&format!("SELECT olog_id, title, namespace FROM Ologs WHERE {}", OLOG_FILTER))?;
+    let mut rows = stmt.query(params![filter])?;
+    while let Some(row) = rows.next()? {
+        emit(out, json!({
+            "type": "olog",
+            "id": row.get::<_, String>(0)?,
+            "title": row.get::<_, String>(1)?,
+            "namespace": row.get::<_, String>(2)?,
+        }))?;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT Nodes.node_id, Nodes.label, Nodes.olog_id FROM Nodes JOIN Ologs ON Ologs.olog_id = Nodes.olog_id WHERE {}",
+        OLOG_FILTER
+    ))?;
+    let mut rows = stmt.query(params![filter])?;
+    while let Some(row) = rows.next()? {
+        emit(out, json!({
+            "type": "node",
+            "id": row.get::<_, String>(0)?,
+            "olog_id": row.get::<_, String>(2)?,
+            "label": row.get::<_, String>(1)?,
+        }))?;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT Hyperedges.hyperedge_id, Hyperedges.label, Hyperedges.olog_id,
+                Hyperedges.quantity_value, Hyperedges.quantity_unit, Hyperedges.quantity_uncertainty
+         FROM Hyperedges JOIN Ologs ON Ologs.olog_id = Hyperedges.olog_id WHERE {}",
+        OLOG_FILTER
+    ))?;
+    let mut links = conn.prepare("SELECT node_id, role FROM Hyperedge_Links WHERE hyperedge_id = ?1 AND type = ?2 ORDER BY position")?;
+    let mut citation_links = conn.prepare("SELECT citation_id, span_start, span_end FROM Citation_Links WHERE hyperedge_id = ?1")?;
+    let mut rows = stmt.query(params![filter])?;
+    while let Some(row) = rows.next()? {
+        let hyperedge_id: String = row.get(0)?;
+        let mut record = json!({
+            "type": "hyperedge",
+            "id": hyperedge_id,
+            "olog_id": row.get::<_, String>(2)?,
+            "label": row.get::<_, String>(1)?,
+        });
+
+        for (kind, ids_key, roles_key) in [("source", "sources", "source_roles"), ("target", "targets", "target_roles")] {
+            let endpoints = links
+                .query_map(params![hyperedge_id, kind], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            record[ids_key] = json!(endpoints.iter().map(|(id, _)| id).collect::<Vec<_>>());
+            // Roles are only written out when at least one of them is set, as in olog-json
+            if endpoints.iter().any(|(_, role)| role.is_some()) {
+                record[roles_key] = json!(endpoints.iter().map(|(_, role)| role.clone().unwrap_or_default()).collect::<Vec<_>>());
+            }
+        }
+
+        let citations = citation_links
+            .query_map(params![hyperedge_id], |row| Ok((row.get::<_, String>(0)?, span_columns(row, 1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        record["citations"] = json!(citations.iter().map(|(id, _)| id).collect::<Vec<_>>());
+        // Spans as in olog-json, though without the quote: the text is in the citation record
+        let spans: Vec<_> = citations.iter()
+            .filter_map(|(id, span)| span.map(|(start, end)| json!({"citation": id, "start": start, "end": end})))
+            .collect();
+        if !spans.is_empty() {
+            record["spans"] = json!(spans);
+        }
+
+        if let Some(value) = row.get::<_, Option<f64>>(3)? {
+            record["quantity"] = json!({
+                "value": value,
+                "unit": row.get::<_, Option<String>>(4)?,
+                "uncertainty": row.get::<_, Option<f64>>(5)?,
+            });
+        }
+        emit(out, record)?;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT Citations.citation_id, Citations.title, Citations.label, COALESCE(Citations.text, Documents.text)
+         FROM Citations
+         LEFT JOIN Documents ON Documents.document_id = Citations.document_id
+         JOIN Citation_Links ON Citation_Links.citation_id = Citations.citation_id
+         JOIN Hyperedges ON Hyperedges.hyperedge_id = Citation_Links.hyperedge_id
+         JOIN Ologs ON Ologs.olog_id = Hyperedges.olog_id
+         WHERE {}",
+        OLOG_FILTER
+    ))?;
+    let mut rows = stmt.query(params![filter])?;
+    while let Some(row) = rows.next()? {
+        emit(out, json!({
+            "type": "citation",
+            "id": row.get::<_, String>(0)?,
+            "title": row.get::<_, Option<String>>(1)?,
+            "label": row.get::<_, Option<String>>(2)?,
+            "text": citation_text(row, 3)?,
+        }))?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn emit(out: &mut impl Write, record: Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, &record)?;
+    out.write_all(b"\n")
+}
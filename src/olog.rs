@@ -0,0 +1,761 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::OlogError;
+
+
+/// Version 1 is what the model produces; version 2 adds citations for export/import
+pub const OLOG_JSON_SCHEMA_VERSION: u32 = 2;
+// Citation text this short is a quoted passage rather than a whole document
+const QUOTE_MAX_CHARS: usize = 2000;
+
+/// An olog as it appears in model output and in `olog-json` exports
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonOlogSchema {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    pub title: String,
+    pub nodes: Vec<JsonNodeSchema>,
+    pub hyperedges: Vec<JsonHyperedgeSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<JsonCitationSchema>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonNodeSchema {
+    pub id: String,
+    pub label: String,
+    /// Derived on export from the hyperedges touching the node; ignored on import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonHyperedgeSchema {
+    pub id: String,
+    pub label: String,
+    pub sources: Vec<String>,
+    pub targets: Vec<String>,
+    /// Optional argument roles, parallel to sources and targets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_roles: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_roles: Option<Vec<String>>,
+    /// Ids into the top-level citations section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<String>>,
+    /// Measured or reported value the relation states, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<JsonQuantitySchema>,
+    /// Passage of the document, copied word for word, that states the relation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    /// Where the quote sits in each cited text; written on export, and
+    /// preferred over searching for the quote on import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spans: Option<Vec<JsonSpanSchema>>,
+}
+
+/// Character offsets into a citation's text, end exclusive
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonSpanSchema {
+    pub citation: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonQuantitySchema {
+    pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncertainty: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonCitationSchema {
+    pub id: String,
+    pub title: String,
+    pub label: String,
+    pub text: String,
+}
+
+/// JSON Schema (draft 7) for [`JsonOlogSchema`], derived from the same types
+/// model output and `olog-json` imports are parsed with
+pub fn olog_json_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(JsonOlogSchema)).expect("a JSON Schema serializes")
+}
+
+/// A source document, or a part of one, that hyperedges were extracted from
+#[derive(Debug, Clone)]
+pub struct Citation {
+    pub id: Uuid,
+    pub title: String,
+    pub label: String,
+    pub text: String,
+}
+
+impl Citation {
+    /// A citation with a fresh id
+    pub fn new(title: impl Into<String>, label: impl Into<String>, text: impl Into<String>) -> Self {
+        Citation { id: Uuid::new_v4(), title: title.into(), label: label.into(), text: text.into() }
+    }
+}
+
+/// A relation from one or more source nodes to one or more target nodes
+#[derive(Debug, Clone)]
+pub struct Hyperedge {
+    pub id: Uuid,
+    pub label: String,
+    pub source: Vec<Node>,
+    pub target: Vec<Node>,
+    /// Argument role of each source/target, index-aligned with source and target
+    pub source_roles: Vec<Option<String>>,
+    pub target_roles: Vec<Option<String>>,
+    pub citations: Vec<Citation>,
+    pub quantity: Option<Quantity>,
+    /// Passages stating the relation, at most one per citation
+    pub spans: Vec<Span>,
+}
+
+impl Hyperedge {
+    /// The text of the first span whose citation the hyperedge still has
+    pub fn quote(&self) -> Option<String> {
+        self.spans.iter().find_map(|span| {
+            let citation = self.citations.iter().find(|citation| citation.id == span.citation_id)?;
+            span.text(&citation.text)
+        })
+    }
+}
+
+/// Where in a citation's text a hyperedge is stated, as character offsets
+/// (end exclusive). Offsets count chars rather than bytes so they survive
+/// any encoding the text is exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub citation_id: Uuid,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The spanned passage, if the span lies within `text`
+    pub fn text(&self, text: &str) -> Option<String> {
+        if self.start >= self.end || self.end > text.chars().count() {
+            return None;
+        }
+        Some(text.chars().skip(self.start).take(self.end - self.start).collect())
+    }
+}
+
+// Lowercased, with runs of whitespace as one space and typographic quotes
+// and dashes as ASCII, alongside the char offset each normalized char came
+// from. Models copying a passage rarely keep its line breaks or curly quotes.
+fn normalize_for_search(text: &str) -> (Vec<char>, Vec<usize>) {
+    let mut normalized = Vec::new();
+    let mut offsets = Vec::new();
+    let mut in_space = false;
+    for (offset, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            if !in_space && !normalized.is_empty() {
+                normalized.push(' ');
+                offsets.push(offset);
+            }
+            in_space = true;
+            continue;
+        }
+        in_space = false;
+        let c = match c {
+            '\u{2018}' | '\u{2019}' => '\'',
+            '\u{201C}' | '\u{201D}' => '"',
+            '\u{2013}' | '\u{2014}' => '-',
+            c => c,
+        };
+        for lower in c.to_lowercase() {
+            normalized.push(lower);
+            offsets.push(offset);
+        }
+    }
+    (normalized, offsets)
+}
+
+/// Finds `quote` in `text`, exactly or else ignoring case, line breaks and
+/// typographic punctuation, and returns its char offsets (end exclusive)
+pub fn locate_quote(text: &str, quote: &str) -> Option<(usize, usize)> {
+    let quote = quote.trim().trim_matches('"').trim();
+    if quote.is_empty() {
+        return None;
+    }
+    if let Some(byte_start) = text.find(quote) {
+        let start = text[..byte_start].chars().count();
+        return Some((start, start + quote.chars().count()));
+    }
+    let (haystack, offsets) = normalize_for_search(text);
+    let (needle, _) = normalize_for_search(quote);
+    let position = haystack.windows(needle.len()).position(|window| window == needle.as_slice())?;
+    Some((offsets[position], offsets[position + needle.len() - 1] + 1))
+}
+
+/// A reported value with optional unit and uncertainty
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Option<String>,
+    pub uncertainty: Option<f64>,
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value)?;
+        if let Some(uncertainty) = self.uncertainty {
+            write!(f, " ± {}", uncertainty)?;
+        }
+        if let Some(unit) = &self.unit {
+            write!(f, " {}", unit)?;
+        }
+        Ok(())
+    }
+}
+
+/// A concept in an olog
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Node {
+    pub id: Uuid,
+    pub label: String,
+}
+
+/// An ontology log: concepts linked by hyperedges, each backed by citations
+#[derive(Debug, Clone)]
+pub struct Olog {
+    pub id: Uuid,
+    pub title: String,
+    pub nodes: Vec<Node>,
+    pub hyperedges: Vec<Hyperedge>,
+}
+
+/// Parses olog JSON, rejecting schema versions newer than this build understands
+pub fn parse_olog_json(json_data: &str) -> Result<JsonOlogSchema, OlogError> {
+    let olog: JsonOlogSchema = serde_json::from_str(json_data)?;
+    let version = olog.schema_version.unwrap_or(1);
+    if version > OLOG_JSON_SCHEMA_VERSION {
+        return Err(OlogError::Schema(format!(
+            "Olog JSON schema version {} is newer than the supported version {}",
+            version, OLOG_JSON_SCHEMA_VERSION
+        )));
+    }
+    Ok(olog)
+}
+
+/// Strict validation: every hyperedge must connect known nodes on both sides,
+/// with at most one role per endpoint
+pub fn check_olog_references(olog: &JsonOlogSchema) -> Result<(), OlogError> {
+    let node_ids: Vec<&str> = olog.nodes.iter().map(|node| node.id.as_str()).collect();
+
+    for hyperedge in &olog.hyperedges {
+        if hyperedge.sources.is_empty() || hyperedge.targets.is_empty() {
+            return Err(OlogError::Schema(format!("Hyperedge {} ({}) is missing sources or targets", hyperedge.id, hyperedge.label)));
+        }
+        for node_id in hyperedge.sources.iter().chain(hyperedge.targets.iter()) {
+            if !node_ids.contains(&node_id.as_str()) {
+                return Err(OlogError::Schema(format!("Hyperedge {} ({}) references unknown node {}", hyperedge.id, hyperedge.label, node_id)));
+            }
+        }
+        let roles_fit = |roles: &Option<Vec<String>>, nodes: &[String]| roles.as_ref().is_none_or(|r| r.len() == nodes.len());
+        if !roles_fit(&hyperedge.source_roles, &hyperedge.sources) || !roles_fit(&hyperedge.target_roles, &hyperedge.targets) {
+            return Err(OlogError::Schema(format!("Hyperedge {} ({}) has a different number of roles than endpoints", hyperedge.id, hyperedge.label)));
+        }
+    }
+    Ok(())
+}
+
+/// Every citation used in the olog, in order of first appearance
+pub fn distinct_citations(olog: &Olog) -> Vec<&Citation> {
+    let mut citations: Vec<&Citation> = Vec::new();
+    for citation in olog.hyperedges.iter().flat_map(|hyperedge| &hyperedge.citations) {
+        if !citations.iter().any(|c| c.id == citation.id) {
+            citations.push(citation);
+        }
+    }
+    citations
+}
+
+/// Nodes carry no citations of their own; a node is supported by whatever
+/// documents cite the hyperedges it takes part in
+pub fn node_citations(olog: &Olog) -> HashMap<Uuid, Vec<&Citation>> {
+    let mut by_node: HashMap<Uuid, Vec<&Citation>> = HashMap::new();
+    for hyperedge in &olog.hyperedges {
+        for node in hyperedge.source.iter().chain(&hyperedge.target) {
+            let entry = by_node.entry(node.id).or_default();
+            for citation in &hyperedge.citations {
+                if !entry.iter().any(|c| c.id == citation.id) {
+                    entry.push(citation);
+                }
+            }
+        }
+    }
+    by_node
+}
+
+/// The `olog-json` representation, including citations
+pub fn olog_to_json(olog: &Olog) -> JsonOlogSchema {
+    let citations: Vec<JsonCitationSchema> = distinct_citations(olog).into_iter()
+        .map(|citation| JsonCitationSchema {
+            id: citation.id.to_string(),
+            title: citation.title.clone(),
+            label: citation.label.clone(),
+            text: citation.text.clone(),
+        })
+        .collect();
+    let by_node = node_citations(olog);
+
+    // Roles are only written out when at least one of them is set
+    let roles = |roles: &[Option<String>]| -> Option<Vec<String>> {
+        roles.iter().any(Option::is_some)
+            .then(|| roles.iter().map(|role| role.clone().unwrap_or_default()).collect())
+    };
+
+    JsonOlogSchema {
+        schema_version: Some(OLOG_JSON_SCHEMA_VERSION),
+        title: olog.title.clone(),
+        nodes: olog.nodes.iter()
+            .map(|node| JsonNodeSchema {
+                id: node.id.to_string(),
+                label: node.label.clone(),
+                citations: Some(by_node.get(&node.id).map_or_else(Vec::new, |citations| {
+                    citations.iter().map(|citation| citation.id.to_string()).collect()
+                })),
+            })
+            .collect(),
+        hyperedges: olog.hyperedges.iter().map(|hyperedge| JsonHyperedgeSchema {
+            id: hyperedge.id.to_string(),
+            label: hyperedge.label.clone(),
+            sources: hyperedge.source.iter().map(|node| node.id.to_string()).collect(),
+            targets: hyperedge.target.iter().map(|node| node.id.to_string()).collect(),
+            source_roles: roles(&hyperedge.source_roles),
+            target_roles: roles(&hyperedge.target_roles),
+            citations: Some(hyperedge.citations.iter().map(|citation| citation.id.to_string()).collect()),
+            quantity: hyperedge.quantity.as_ref().map(|quantity| JsonQuantitySchema {
+                value: quantity.value,
+                unit: quantity.unit.clone(),
+                uncertainty: quantity.uncertainty,
+            }),
+            quote: hyperedge.quote(),
+            spans: (!hyperedge.spans.is_empty()).then(|| hyperedge.spans.iter().map(|span| JsonSpanSchema {
+                citation: span.citation_id.to_string(),
+                start: span.start,
+                end: span.end,
+            }).collect()),
+        }).collect(),
+        citations: Some(citations),
+    }
+}
+
+/// Swaps the model's short ids (n1, e1, ...) for fresh UUIDs
+pub fn replace_ids_with_uuids(mut olog: JsonOlogSchema) -> JsonOlogSchema {
+    let mut id_map: HashMap<String, Uuid> = HashMap::new();
+
+    // Replace node ids
+    for node in olog.nodes.iter_mut() {
+        let uuid = *id_map.entry(node.id.clone()).or_insert_with(Uuid::new_v4);
+        node.id = uuid.to_string();
+    }
+
+    // Replace hyperedge ids and update sources and targets
+    for hyperedge in olog.hyperedges.iter_mut() {
+        let uuid = *id_map.entry(hyperedge.id.clone()).or_insert_with(Uuid::new_v4);
+        hyperedge.id = uuid.to_string();
+
+        for source_id in hyperedge.sources.iter_mut() {
+            let source_uuid = *id_map.entry(source_id.clone()).or_insert_with(Uuid::new_v4);
+            *source_id = source_uuid.to_string();
+        }
+
+        for target_id in hyperedge.targets.iter_mut() {
+            let target_uuid = *id_map.entry(target_id.clone()).or_insert_with(Uuid::new_v4);
+            *target_id = target_uuid.to_string();
+        }
+    }
+
+    olog
+}
+
+/// Reads `olog-json` output back into an olog, for an export that was edited
+/// by hand. Node, hyperedge and citation ids are all replaced with fresh ones,
+/// so the import is stored alongside the original rather than over it, and
+/// edited citation text isn't lost to the original's. Hyperedges that cite
+/// nothing are attributed to `citation`.
+pub fn olog_from_json(json_data: &str, citation: Citation) -> Result<Olog, OlogError> {
+    let mut json_olog = parse_olog_json(json_data)?;
+    check_olog_references(&json_olog)?;
+
+    let mut citation_ids: HashMap<String, String> = HashMap::new();
+    for json_citation in json_olog.citations.iter_mut().flatten() {
+        let fresh = Uuid::new_v4().to_string();
+        if citation_ids.insert(json_citation.id.clone(), fresh.clone()).is_some() {
+            return Err(OlogError::Schema(format!("Citation {} appears more than once", json_citation.id)));
+        }
+        json_citation.id = fresh;
+    }
+    for hyperedge in &mut json_olog.hyperedges {
+        for citation_id in hyperedge.citations.iter_mut().flatten() {
+            *citation_id = citation_ids.get(citation_id).cloned().ok_or_else(|| {
+                OlogError::Schema(format!("Hyperedge {} ({}) cites unknown citation {}", hyperedge.id, hyperedge.label, citation_id))
+            })?;
+        }
+        // A span into a citation that isn't exported is dropped with it
+        if let Some(spans) = &mut hyperedge.spans {
+            spans.retain_mut(|span| match citation_ids.get(&span.citation) {
+                Some(fresh) => {
+                    span.citation = fresh.clone();
+                    true
+                },
+                None => false,
+            });
+        }
+    }
+    // Node citations are derived on export and carry nothing to import
+    for node in &mut json_olog.nodes {
+        node.citations = None;
+    }
+
+    Ok(convert_json_olog_to_olog(replace_ids_with_uuids(json_olog), citation))
+}
+
+/// Builds an olog from parsed JSON; hyperedges without citations of their own
+/// are attributed to `citation`
+pub fn convert_json_olog_to_olog(json_olog: JsonOlogSchema, citation: Citation) -> Olog {
+    let mut id_map: HashMap<String, Uuid> = HashMap::new();
+    let mut node_map: HashMap<Uuid, Node> = HashMap::new();
+
+    // Citations exported alongside the olog keep their ids so evidence stays shared
+    let citation_map: HashMap<String, Citation> = json_olog.citations.unwrap_or_default()
+        .into_iter()
+        .map(|json_citation| {
+            let id = Uuid::parse_str(&json_citation.id).unwrap_or_else(|_| Uuid::new_v4());
+            (json_citation.id, Citation {
+                id,
+                title: json_citation.title,
+                label: json_citation.label,
+                text: json_citation.text,
+            })
+        })
+        .collect();
+
+    // Process nodes and build a map from string IDs to Node instances
+    for json_node in &json_olog.nodes {
+        let uuid = *id_map.entry(json_node.id.clone()).or_insert_with(Uuid::new_v4);
+        let node = Node { id: uuid, label: json_node.label.clone() };
+        node_map.insert(uuid, node);
+    }
+
+    // Convert nodes to Vec<Node>
+    let nodes: Vec<Node> = node_map.values().cloned().collect();
+
+    // Process hyperedges and convert sources and targets to Node instances
+    let hyperedges = json_olog.hyperedges.into_iter().map(|json_hyperedge| {
+        let hyperedge_id = *id_map.entry(json_hyperedge.id.clone()).or_insert_with(Uuid::new_v4);
+        let resolve = |ids: &[String], roles: &Option<Vec<String>>| -> (Vec<Node>, Vec<Option<String>>) {
+            ids.iter().enumerate()
+                .filter_map(|(i, node_id)| {
+                    let node = id_map.get(node_id)
+                        .and_then(|uuid| node_map.get(uuid).cloned())?;
+                    // Export writes unset roles as "" to keep the list parallel
+                    let role = roles.as_ref().and_then(|roles| roles.get(i)).filter(|role| !role.is_empty()).cloned();
+                    Some((node, role))
+                })
+                .unzip()
+        };
+        let (sources, source_roles) = resolve(&json_hyperedge.sources, &json_hyperedge.source_roles);
+        let (targets, target_roles) = resolve(&json_hyperedge.targets, &json_hyperedge.target_roles);
+
+        let mut citations: Vec<Citation> = json_hyperedge.citations.iter().flatten()
+            .filter_map(|citation_id| citation_map.get(citation_id).cloned())
+            .collect();
+        if citations.is_empty() {
+            citations.push(citation.clone());
+        }
+        let spans = resolve_spans(&json_hyperedge, &citations);
+
+        // Non-finite values can't be stored as REAL
+        let quantity = json_hyperedge.quantity
+            .filter(|quantity| quantity.value.is_finite())
+            .map(|quantity| Quantity {
+                value: quantity.value,
+                unit: quantity.unit.filter(|unit| !unit.trim().is_empty()),
+                uncertainty: quantity.uncertainty.filter(|uncertainty| uncertainty.is_finite()),
+            });
+
+        Hyperedge {
+            id: hyperedge_id,
+            label: json_hyperedge.label,
+            source: sources,
+            target: targets,
+            source_roles,
+            target_roles,
+            citations,
+            quantity,
+            spans,
+        }
+    }).collect();
+
+    Olog {
+        id: Uuid::new_v4(),
+        title: json_olog.title,
+        nodes,
+        hyperedges,
+    }
+}
+
+// Exported spans are kept where they still fit their citation's text; a
+// citation without one is searched for the quote
+fn resolve_spans(json_hyperedge: &JsonHyperedgeSchema, citations: &[Citation]) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    for json_span in json_hyperedge.spans.iter().flatten() {
+        let Some(citation) = citations.iter().find(|citation| citation.id.to_string() == json_span.citation) else {
+            continue;
+        };
+        let span = Span { citation_id: citation.id, start: json_span.start, end: json_span.end };
+        if span.text(&citation.text).is_some() && !spans.iter().any(|s| s.citation_id == citation.id) {
+            spans.push(span);
+        }
+    }
+    let Some(quote) = &json_hyperedge.quote else {
+        return spans;
+    };
+    for citation in citations {
+        if spans.iter().any(|span| span.citation_id == citation.id) {
+            continue;
+        }
+        if let Some((start, end)) = locate_quote(&citation.text, quote) {
+            spans.push(Span { citation_id: citation.id, start, end });
+        }
+    }
+    spans
+}
+
+/// Merges two ologs, joining nodes with the same label and hyperedges with the
+/// same label, endpoints and quantity. Of duplicate hyperedges the best
+/// evidenced one is kept, with its citations first and the others' after them
+/// as secondary citations. The result keeps the first olog's id and title.
+pub fn merge_ologs(olog1: Olog, olog2: Olog) -> Olog {
+    let mut node_map = HashMap::new();
+    let mut hyperedge_map: HashMap<_, Vec<Hyperedge>> = HashMap::new();
+
+    // Merge nodes
+    for node in olog1.nodes.into_iter().chain(olog2.nodes) {
+        node_map.entry(node.label.clone()).or_insert(node);
+    }
+
+    // Preparing merged nodes for hyperedge linking
+    let merged_nodes = node_map.values().cloned().collect::<Vec<Node>>();
+
+    // Helper to find node by label
+    let find_node_by_label = |label: &str| merged_nodes.iter().find(|n| n.label == label).cloned();
+
+    // Merge hyperedges
+    for hyperedge in olog1.hyperedges.into_iter().chain(olog2.hyperedges) {
+        let source_nodes = hyperedge.source.iter().filter_map(|node| find_node_by_label(&node.label)).collect::<Vec<Node>>();
+        let target_nodes = hyperedge.target.iter().filter_map(|node| find_node_by_label(&node.label)).collect::<Vec<Node>>();
+
+        // Key for identifying unique hyperedges; the same relation reported with
+        // different values stays separate
+        let quantity_key = hyperedge.quantity.as_ref().map(Quantity::to_string);
+        let hyperedge_key = (hyperedge.label.clone(), source_nodes.clone(), target_nodes.clone(), quantity_key);
+        hyperedge_map.entry(hyperedge_key).or_default().push(Hyperedge { source: source_nodes, target: target_nodes, ..hyperedge });
+    }
+
+    Olog {
+        id: olog1.id,
+        title: olog1.title,
+        nodes: merged_nodes,
+        hyperedges: hyperedge_map.into_values().map(merge_duplicates).collect(),
+    }
+}
+
+// A relation found in several documents keeps every document's citation,
+// the best evidenced variant's roles, and a new id
+fn merge_duplicates(variants: Vec<Hyperedge>) -> Hyperedge {
+    let mut variants = variants.into_iter();
+    let mut best = variants.next().expect("every key has a hyperedge");
+    let mut secondary = Vec::new();
+    let mut secondary_spans = Vec::new();
+    for mut variant in variants {
+        if evidence(&variant) > evidence(&best) {
+            std::mem::swap(&mut best, &mut variant);
+        }
+        secondary.append(&mut variant.citations);
+        secondary_spans.append(&mut variant.spans);
+    }
+
+    let mut citations: Vec<Citation> = Vec::new();
+    for citation in std::mem::take(&mut best.citations).into_iter().chain(secondary) {
+        if !citations.iter().any(|c| c.id == citation.id) {
+            citations.push(citation);
+        }
+    }
+    let mut spans: Vec<Span> = Vec::new();
+    for span in std::mem::take(&mut best.spans).into_iter().chain(secondary_spans) {
+        if !spans.iter().any(|s| s.citation_id == span.citation_id) {
+            spans.push(span);
+        }
+    }
+    Hyperedge { id: Uuid::new_v4(), citations, spans, ..best }
+}
+
+// More sources beat fewer, and a quoted passage, or a span locating one,
+// beats a whole document
+fn evidence(hyperedge: &Hyperedge) -> (usize, bool) {
+    let mut sources: Vec<Uuid> = hyperedge.citations.iter().map(|citation| citation.id).collect();
+    sources.sort();
+    sources.dedup();
+    let quoted = !hyperedge.spans.is_empty()
+        || hyperedge.citations.iter().any(|citation| citation.text.chars().count() <= QUOTE_MAX_CHARS);
+    (sources.len(), quoted)
+}
+
+/// Gives every node and hyperedge a fresh id so an olog built from stored
+/// pieces can be written alongside the ologs it came from
+pub fn reassign_ids(olog: Olog) -> Olog {
+    let id_map: HashMap<Uuid, Uuid> = olog.nodes.iter()
+        .map(|node| (node.id, Uuid::new_v4()))
+        .collect();
+    let remap = |node: &Node| Node {
+        id: id_map.get(&node.id).copied().unwrap_or_else(Uuid::new_v4),
+        label: node.label.clone(),
+    };
+
+    Olog {
+        id: Uuid::new_v4(),
+        title: olog.title,
+        nodes: olog.nodes.iter().map(remap).collect(),
+        hyperedges: olog.hyperedges.into_iter().map(|hyperedge| Hyperedge {
+            id: Uuid::new_v4(),
+            source: hyperedge.source.iter().map(remap).collect(),
+            target: hyperedge.target.iter().map(remap).collect(),
+            ..hyperedge
+        }).collect(),
+    }
+}
+
+#[derive(Debug)]
+struct PendingEdge {
+    label: String,
+    sources: Vec<String>,
+    targets: Vec<String>,
+    citations: Vec<Citation>,
+    quantity: Option<Quantity>,
+}
+
+/// Authors an olog in code, naming nodes by label:
+/// `OlogBuilder::new("Decay").node("a neutron").node("a proton").edge("decays into", &["a neutron"], &["a proton"])`.
+/// Nothing is checked until [`OlogBuilder::build`], so calls chain freely.
+#[derive(Debug, Default)]
+pub struct OlogBuilder {
+    title: String,
+    nodes: Vec<String>,
+    edges: Vec<PendingEdge>,
+    // Cited by hyperedges that have no citation of their own
+    default_citations: Vec<Citation>,
+    misplaced: Option<String>,
+}
+
+impl OlogBuilder {
+    pub fn new(title: impl Into<String>) -> Self {
+        OlogBuilder { title: title.into(), ..Default::default() }
+    }
+
+    pub fn node(mut self, label: impl Into<String>) -> Self {
+        self.nodes.push(label.into());
+        self
+    }
+
+    /// Adds a hyperedge between nodes already added with [`OlogBuilder::node`]
+    pub fn edge(mut self, label: impl Into<String>, sources: &[&str], targets: &[&str]) -> Self {
+        self.edges.push(PendingEdge {
+            label: label.into(),
+            sources: sources.iter().map(|source| source.to_string()).collect(),
+            targets: targets.iter().map(|target| target.to_string()).collect(),
+            citations: Vec::new(),
+            quantity: None,
+        });
+        self
+    }
+
+    /// Cites the most recent hyperedge. Given before any hyperedge, it is cited
+    /// by every hyperedge that ends up without a citation of its own.
+    pub fn citation(mut self, citation: Citation) -> Self {
+        match self.edges.last_mut() {
+            Some(edge) => edge.citations.push(citation),
+            None => self.default_citations.push(citation),
+        }
+        self
+    }
+
+    /// Attaches a reported value to the most recent hyperedge
+    pub fn quantity(mut self, quantity: Quantity) -> Self {
+        match self.edges.last_mut() {
+            Some(edge) => edge.quantity = Some(quantity),
+            None => {
+                self.misplaced.get_or_insert_with(|| format!("Quantity {} given before any hyperedge", quantity));
+            },
+        }
+        self
+    }
+
+    /// Checks that the title and labels are non-empty, node labels are unique,
+    /// and every hyperedge joins known nodes on both sides
+    pub fn build(self) -> Result<Olog, OlogError> {
+        if let Some(message) = self.misplaced {
+            return Err(OlogError::Schema(message));
+        }
+        if self.title.trim().is_empty() {
+            return Err(OlogError::Schema("Olog title is empty".to_string()));
+        }
+
+        let mut nodes: Vec<Node> = Vec::new();
+        for label in self.nodes {
+            if label.trim().is_empty() {
+                return Err(OlogError::Schema("Node label is empty".to_string()));
+            }
+            if nodes.iter().any(|node| node.label == label) {
+                return Err(OlogError::Schema(format!("Node \"{}\" is added more than once", label)));
+            }
+            nodes.push(Node { id: Uuid::new_v4(), label });
+        }
+
+        let mut hyperedges = Vec::new();
+        for edge in self.edges {
+            if edge.label.trim().is_empty() {
+                return Err(OlogError::Schema("Hyperedge label is empty".to_string()));
+            }
+            if edge.sources.is_empty() || edge.targets.is_empty() {
+                return Err(OlogError::Schema(format!("Hyperedge \"{}\" is missing sources or targets", edge.label)));
+            }
+            let resolve = |labels: &[String]| -> Result<Vec<Node>, OlogError> {
+                labels.iter()
+                    .map(|label| {
+                        nodes.iter().find(|node| node.label == *label).cloned()
+                            .ok_or_else(|| OlogError::Schema(format!("Hyperedge \"{}\" references unknown node \"{}\"", edge.label, label)))
+                    })
+                    .collect()
+            };
+            let (source, target) = (resolve(&edge.sources)?, resolve(&edge.targets)?);
+            hyperedges.push(Hyperedge {
+                id: Uuid::new_v4(),
+                source_roles: vec![None; source.len()],
+                target_roles: vec![None; target.len()],
+                source,
+                target,
+                citations: if edge.citations.is_empty() { self.default_citations.clone() } else { edge.citations },
+                quantity: edge.quantity,
+                spans: Vec::new(),
+                label: edge.label,
+            });
+        }
+
+        Ok(Olog { id: Uuid::new_v4(), title: self.title, nodes, hyperedges })
+    }
+}
@@ -0,0 +1,103 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{Citation, Olog};
+use crate::split::edge_sentence;
+
+// One hop along a hyperedge, from one of its sources to one of its targets
+#[derive(Debug, Clone)]
+pub struct Step {
+    // Index into the olog's hyperedges
+    pub hyperedge: usize,
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct NarrationResponse {
+    sentences: Vec<String>,
+}
+
+// A node given on the command line, by id or by exact label
+pub fn resolve_node(olog: &Olog, given: &str) -> Option<Uuid> {
+    if let Ok(id) = Uuid::parse_str(given) {
+        return olog.nodes.iter().find(|node| node.id == id).map(|node| node.id);
+    }
+    olog.nodes.iter().find(|node| node.label.eq_ignore_ascii_case(given.trim())).map(|node| node.id)
+}
+
+// Directed paths from `from` to `to` that visit no node twice, shortest
+// first. Breadth-first, so the search stops as soon as enough are found.
+pub fn find_paths(olog: &Olog, from: Uuid, to: Uuid, max_hops: usize, max_paths: usize) -> Vec<Vec<Step>> {
+    let mut paths = Vec::new();
+    let mut queue: VecDeque<(Uuid, Vec<Step>)> = VecDeque::from([(from, Vec::new())]);
+
+    while let Some((node, path)) = queue.pop_front() {
+        if paths.len() >= max_paths {
+            break;
+        }
+        if node == to && !path.is_empty() {
+            paths.push(path);
+            continue;
+        }
+        if path.len() >= max_hops {
+            continue;
+        }
+        let visited = |id: Uuid| id == from || path.iter().any(|step| step.to == id);
+        for (index, hyperedge) in olog.hyperedges.iter().enumerate() {
+            if !hyperedge.source.iter().any(|source| source.id == node) {
+                continue;
+            }
+            for target in &hyperedge.target {
+                if visited(target.id) && target.id != to {
+                    continue;
+                }
+                let mut next = path.clone();
+                next.push(Step { hyperedge: index, from: node, to: target.id });
+                queue.push_back((target.id, next));
+            }
+        }
+    }
+    paths
+}
+
+// Citations backing the path, in order of first use
+pub fn path_citations<'a>(olog: &'a Olog, path: &[Step]) -> Vec<&'a Citation> {
+    let mut citations: Vec<&Citation> = Vec::new();
+    for step in path {
+        for citation in &olog.hyperedges[step.hyperedge].citations {
+            if !citations.iter().any(|c| c.id == citation.id) {
+                citations.push(citation);
+            }
+        }
+    }
+    citations
+}
+
+// One sentence per step. The model only rephrases the hyperedges and links
+// them together; steps it skips keep the plain hyperedge sentence.
+pub fn narrate_path(provider: &dyn LlmProvider, olog: &Olog, path: &[Step], model: &str) -> Result<Vec<String>, OlogError> {
+    let plain: Vec<String> = path.iter().map(|step| edge_sentence(&olog.hyperedges[step.hyperedge])).collect();
+    let listing = plain.iter().enumerate()
+        .map(|(i, sentence)| format!("{}: {}", i + 1, sentence))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "The numbered facts below come from an ontology log about \"{}\" and form a chain of relations. Explain \
+         the chain in plain language with exactly one sentence per fact, in the same order, so that each sentence \
+         follows on from the previous one. Each sentence may only state what its fact states; do not add \
+         background knowledge. Respond with JSON of the form {{\"sentences\": [\"...\"]}}.\n\n{}",
+        olog.title, listing
+    );
+    let response: NarrationResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed path narration: {}", e)))?;
+
+    Ok(plain.into_iter()
+        .enumerate()
+        .map(|(i, sentence)| response.sentences.get(i).filter(|s| !s.trim().is_empty()).cloned().unwrap_or(sentence))
+        .collect())
+}
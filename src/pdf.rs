@@ -0,0 +1,84 @@
+use clap::ValueEnum;
+use std::panic;
+
+use crate::error::OlogError;
+use crate::events::{self, Event};
+
+// Fewer letters and digits than this per page on average means the pages are
+// scanned images without a text layer
+const MIN_CHARS_PER_PAGE: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Ocr {
+    /// Read the PDF's embedded text on this machine; scanned pages have none
+    Local,
+}
+
+impl Ocr {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ocr::Local => "local",
+        }
+    }
+}
+
+pub fn is_pdf(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"%PDF-")
+}
+
+pub fn extract_text(bytes: &[u8], ocr: Ocr) -> Result<String, OlogError> {
+    events::emit(Event::OcrStarted { ocr: ocr.name(), bytes: bytes.len() });
+    let text = extract_with(bytes, ocr)?;
+    events::emit(Event::OcrCompleted { chars: text.chars().count() });
+    Ok(text)
+}
+
+fn extract_with(bytes: &[u8], ocr: Ocr) -> Result<String, OlogError> {
+    #[cfg(feature = "testing")]
+    if let Some(text) = crate::testing::ocr_text(bytes) {
+        return text;
+    }
+    match ocr {
+        Ocr::Local => extract_local(bytes),
+    }
+}
+
+/// Title and subject from the PDF's document information dictionary
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+}
+
+// Missing or unreadable metadata is just absent; it never fails a run
+pub fn metadata(bytes: &[u8]) -> Metadata {
+    let read = || -> Option<Metadata> {
+        let document = pdf_extract::Document::load_mem(bytes).ok()?;
+        let (_, info) = document.dereference(document.trailer.get(b"Info").ok()?).ok()?;
+        let info = info.as_dict().ok()?;
+        let field = |key: &[u8]| {
+            info.get(key).ok()
+                .and_then(|value| pdf_extract::decode_text_string(value).ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Some(Metadata { title: field(b"Title"), subject: field(b"Subject") })
+    };
+    panic::catch_unwind(read).ok().flatten().unwrap_or_default()
+}
+
+fn extract_local(bytes: &[u8]) -> Result<String, OlogError> {
+    // pdf-extract panics on some malformed files instead of returning an error
+    let pages = panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| OlogError::Ocr("The PDF could not be parsed".to_string()))?
+        .map_err(|e| OlogError::Ocr(e.to_string()))?;
+    let chars = pages.iter().flat_map(|page| page.chars()).filter(|c| c.is_alphanumeric()).count();
+    if chars < MIN_CHARS_PER_PAGE * pages.len().max(1) {
+        return Err(OlogError::Ocr(format!(
+            "Found only {} characters of text in {} pages; the PDF looks scanned, and local extraction cannot read scanned pages",
+            chars,
+            pages.len()
+        )));
+    }
+    Ok(pages.join("\n\n"))
+}
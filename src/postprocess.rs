@@ -0,0 +1,48 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::error::OlogError;
+use crate::olog::{parse_olog_json, JsonOlogSchema};
+
+// Pipes the olog through each command in turn; every command reads olog JSON
+// on stdin and must print olog JSON on stdout.
+pub fn run_postprocessors(mut olog: JsonOlogSchema, commands: &[String]) -> Result<JsonOlogSchema, OlogError> {
+    for command in commands {
+        let input = serde_json::to_string(&olog)?;
+        let output = run_command(command, input)?;
+        olog = parse_olog_json(&output)
+            .map_err(|e| OlogError::Schema(format!("Postprocessor `{}` returned invalid olog JSON: {}", command, e)))?;
+    }
+    Ok(olog)
+}
+
+fn run_command(command: &str, input: String) -> Result<String, OlogError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from another thread so a command that writes before it has
+    // read everything can't deadlock on a full pipe
+    let mut stdin = child.stdin.take().ok_or_else(|| io::Error::other("Failed to open postprocessor stdin"))?;
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    writer.join().map_err(|_| io::Error::other("Postprocessor stdin writer panicked"))??;
+
+    if !output.status.success() {
+        return Err(OlogError::Io(io::Error::other(format!(
+            "Postprocessor `{}` exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    String::from_utf8(output.stdout).map_err(|e| {
+        OlogError::Io(io::Error::new(io::ErrorKind::InvalidData, format!("Postprocessor `{}` wrote invalid UTF-8: {}", command, e)))
+    })
+}
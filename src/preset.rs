@@ -0,0 +1,178 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+use crate::config::{self, Layered, Source};
+use crate::error::OlogError;
+
+pub const DEFAULT_PRESET: &str = "balanced";
+// Override the model of whichever preset is in use
+pub const MODEL_ENV: &str = "OLOG_MODEL";
+pub const LABEL_MODEL_ENV: &str = "OLOG_LABEL_MODEL";
+
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub model: String,
+    // Number of generations merged into the final olog
+    pub count: usize,
+    // Reject model output with dangling node references instead of dropping them
+    pub strict_validation: bool,
+    // Merge hyperedges with the same endpoints and equivalent labels
+    pub consolidate: bool,
+    // Model for the title and label calls; the main model when unset
+    pub label_model: Option<String>,
+}
+
+// Any field left out in olog.toml keeps the built-in value
+#[derive(Debug, Default, Deserialize)]
+struct PresetOverride {
+    model: Option<String>,
+    count: Option<usize>,
+    strict_validation: Option<bool>,
+    consolidate: Option<bool>,
+    label_model: Option<String>,
+}
+
+// Database file, below --db and OLOG_DB_PATH in precedence
+pub fn configured_database() -> Result<Option<PathBuf>, OlogError> {
+    config::project_setting("database")
+}
+
+// Default for --id-prefix on exports
+pub fn configured_id_prefix() -> Result<Option<String>, OlogError> {
+    config::project_setting("export_id_prefix")
+}
+
+// Directory of prompt templates, below --prompt-dir and OLOG_PROMPT_DIR
+pub fn configured_prompt_dir() -> Result<Option<PathBuf>, OlogError> {
+    config::project_setting("prompt_dir")
+}
+
+// Enforced unless set to false, to open legacy databases with dangling
+// references until `olog fsck --repair` has cleaned them up
+pub fn configured_foreign_keys() -> Result<bool, OlogError> {
+    Ok(config::project_setting("foreign_keys")?.unwrap_or(true))
+}
+
+fn project_presets() -> Result<HashMap<String, PresetOverride>, OlogError> {
+    Ok(config::project_setting("presets")?.unwrap_or_default())
+}
+
+/// The preset's main and label model with the layer each came from, for `config show`
+pub fn models(name: &str) -> Result<(Layered<String>, Layered<Option<String>>), OlogError> {
+    let preset = resolve(name)?;
+    let builtin = builtin(name);
+    let overrides = project_presets()?.remove(name).unwrap_or_default();
+    let source = |env_var: &str, builtin_value: Option<Option<&str>>, value: Option<&str>, overridden: bool| {
+        if env::var(env_var).ok().is_some_and(|value| !value.trim().is_empty()) {
+            Source::Env
+        } else if overridden {
+            Source::Project
+        } else if builtin_value == Some(value) {
+            Source::Default
+        } else {
+            Source::File
+        }
+    };
+    let model_source = source(MODEL_ENV, builtin.as_ref().map(|builtin| Some(builtin.model.as_str())), Some(&preset.model), overrides.model.is_some());
+    let label_source = source(
+        LABEL_MODEL_ENV,
+        builtin.as_ref().map(|builtin| builtin.label_model.as_deref()),
+        preset.label_model.as_deref(),
+        overrides.label_model.is_some(),
+    );
+    Ok((
+        Layered { value: preset.model, source: model_source },
+        Layered { value: preset.label_model, source: label_source },
+    ))
+}
+
+fn builtin(name: &str) -> Option<Preset> {
+    match name {
+        "quick" => Some(Preset {
+            model: "gpt-3.5-turbo-1106".to_string(),
+            count: 1,
+            strict_validation: false,
+            consolidate: false,
+            label_model: None,
+        }),
+        "balanced" => Some(Preset {
+            model: "gpt-4-1106-preview".to_string(),
+            count: 2,
+            strict_validation: false,
+            consolidate: true,
+            label_model: None,
+        }),
+        "thorough" => Some(Preset {
+            model: "gpt-4-1106-preview".to_string(),
+            count: 4,
+            strict_validation: true,
+            consolidate: true,
+            label_model: None,
+        }),
+        // For --provider ollama: a model small enough for a laptop, which
+        // gains little from a second pass
+        "offline" => Some(Preset {
+            model: "llama3.1:8b".to_string(),
+            count: 1,
+            strict_validation: false,
+            consolidate: true,
+            label_model: None,
+        }),
+        _ => None,
+    }
+}
+
+// Built-in presets can be tweaked, and new ones defined, under [presets.<name>] in olog.toml.
+// `model` and `label_model` in the user config stand in for the built-in models
+// below that, and OLOG_MODEL and OLOG_LABEL_MODEL take precedence over all of them.
+pub fn resolve(name: &str) -> Result<Preset, OlogError> {
+    let mut presets = project_presets()?;
+    let user = config::read_config()?;
+
+    let overrides = presets.remove(name);
+    let mut preset = match (builtin(name), &overrides) {
+        (Some(preset), _) => preset,
+        // Custom presets start from the default one
+        (None, Some(_)) => builtin(DEFAULT_PRESET).expect("default preset is built in"),
+        (None, None) => {
+            return Err(OlogError::Config(format!("Unknown preset `{}` (expected quick, balanced, thorough, offline or one defined in {})", name, config::PROJECT_FILE)))
+        }
+    };
+
+    if let Some(model) = user.model {
+        preset.model = model;
+    }
+    if let Some(label_model) = user.label_model {
+        preset.label_model = Some(label_model);
+    }
+    if let Some(overrides) = overrides {
+        if let Some(model) = overrides.model {
+            preset.model = model;
+        }
+        if let Some(count) = overrides.count {
+            preset.count = count;
+        }
+        if let Some(strict_validation) = overrides.strict_validation {
+            preset.strict_validation = strict_validation;
+        }
+        if let Some(consolidate) = overrides.consolidate {
+            preset.consolidate = consolidate;
+        }
+        if let Some(label_model) = overrides.label_model {
+            preset.label_model = Some(label_model);
+        }
+    }
+    if let Some(model) = env::var(MODEL_ENV).ok().filter(|model| !model.trim().is_empty()) {
+        preset.model = model;
+    }
+    if let Some(label_model) = env::var(LABEL_MODEL_ENV).ok().filter(|model| !model.trim().is_empty()) {
+        preset.label_model = Some(label_model);
+    }
+
+    if preset.count == 0 {
+        return Err(OlogError::Config(format!("Preset `{}` must generate at least one olog", name)));
+    }
+    Ok(preset)
+}
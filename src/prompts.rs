@@ -0,0 +1,199 @@
+use clap::ValueEnum;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::error::OlogError;
+use crate::preset;
+
+// Set by --prompt-dev; templates are then read from the source tree on every
+// call so edits show up without rebuilding
+static DEV_MODE: AtomicBool = AtomicBool::new(false);
+const DEV_PROMPT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/res");
+// Set by --prompt-dir; a template found there replaces the built-in one
+static TEMPLATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+pub const PROMPT_DIR_ENV: &str = "OLOG_PROMPT_DIR";
+
+// Placeholders a template may use. A template without {text} gets the
+// document appended after it, and one without {context} gets the
+// --context-olog concepts appended to the instructions.
+pub const TEXT_VAR: &str = "text";
+pub const TITLE_VAR: &str = "title";
+pub const CONTEXT_VAR: &str = "context";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Olog extraction prompt
+    Olog,
+    /// Document title prompt
+    Title,
+    /// Document label prompt
+    Label,
+    /// Section appended to the olog prompt when quantities are extracted
+    #[value(skip)]
+    Quantities,
+    /// Section appended to the olog prompt when supporting quotes are extracted
+    #[value(skip)]
+    Quotes,
+    /// Section appended to the olog prompt when the document is a debate transcript
+    #[value(skip)]
+    Debate,
+}
+
+impl Template {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Template::Olog => "olog.md",
+            Template::Title => "title.md",
+            Template::Label => "label.md",
+            Template::Quantities => "quantities.md",
+            Template::Quotes => "quotes.md",
+            Template::Debate => "debate.md",
+        }
+    }
+
+    fn bundled(self) -> &'static str {
+        match self {
+            Template::Olog => include_str!("./res/olog.md"),
+            Template::Title => include_str!("./res/title.md"),
+            Template::Label => include_str!("./res/label.md"),
+            Template::Quantities => include_str!("./res/quantities.md"),
+            Template::Quotes => include_str!("./res/quotes.md"),
+            Template::Debate => include_str!("./res/debate.md"),
+        }
+    }
+}
+
+// Reviewers a debate can call on, each described by its own prompt. More can
+// be added as personas/<name>.md in the prompt directory.
+pub const BUILTIN_PERSONAS: [&str; 3] = ["empiricist", "theorist", "editor"];
+const PERSONA_DIR: &str = "personas";
+
+fn bundled_persona(name: &str) -> Option<&'static str> {
+    match name {
+        "empiricist" => Some(include_str!("./res/personas/empiricist.md")),
+        "theorist" => Some(include_str!("./res/personas/theorist.md")),
+        "editor" => Some(include_str!("./res/personas/editor.md")),
+        _ => None,
+    }
+}
+
+pub fn enable_dev_mode() {
+    DEV_MODE.store(true, Ordering::Relaxed);
+}
+
+// The directory given with --prompt-dir, then OLOG_PROMPT_DIR, then
+// `prompt_dir` in olog.toml
+pub fn template_dir(flag: Option<PathBuf>) -> Result<Option<PathBuf>, OlogError> {
+    if flag.is_some() {
+        return Ok(flag);
+    }
+    if let Some(dir) = env::var_os(PROMPT_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Ok(Some(PathBuf::from(dir)));
+    }
+    preset::configured_prompt_dir()
+}
+
+pub fn set_template_dir(dir: PathBuf) -> Result<(), OlogError> {
+    if !dir.is_dir() {
+        return Err(OlogError::Config(format!("Prompt directory {} does not exist", dir.display())));
+    }
+    TEMPLATE_DIR.set(dir).map_err(|_| OlogError::Config("Prompt directory already set".to_string()))
+}
+
+// The file a template is read from, or None for the built-in copy
+fn override_path(template: Template) -> Option<PathBuf> {
+    if DEV_MODE.load(Ordering::Relaxed) {
+        return Some(Path::new(DEV_PROMPT_DIR).join(template.file_name()));
+    }
+    TEMPLATE_DIR.get()
+        .map(|dir| dir.join(template.file_name()))
+        .filter(|path| path.exists())
+}
+
+// Where a template is read from, for diagnostics
+pub fn location(template: Template) -> String {
+    match override_path(template) {
+        Some(path) => path.display().to_string(),
+        None => format!("{} (built in)", template.file_name()),
+    }
+}
+
+pub fn load(template: Template) -> Result<String, OlogError> {
+    let text = match override_path(template) {
+        Some(path) => fs::read_to_string(&path).map_err(|e| OlogError::from(e).context(format!("Error reading prompt {}", path.display())))?,
+        None => template.bundled().to_string(),
+    };
+    Ok(text.trim_end().to_string())
+}
+
+// Directory persona files are read from instead of the built-in copies
+fn persona_dir() -> Option<PathBuf> {
+    if DEV_MODE.load(Ordering::Relaxed) {
+        return Some(Path::new(DEV_PROMPT_DIR).join(PERSONA_DIR));
+    }
+    TEMPLATE_DIR.get().map(|dir| dir.join(PERSONA_DIR))
+}
+
+// Built-in personas followed by any others in the prompt directory
+pub fn persona_names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_PERSONAS.iter().map(|name| name.to_string()).collect();
+    let files = persona_dir().and_then(|dir| fs::read_dir(dir).ok()).into_iter().flatten().flatten();
+    let mut more: Vec<String> = files
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "md"))
+        .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string))
+        .filter(|name| !names.contains(name))
+        .collect();
+    more.sort();
+    names.extend(more);
+    names
+}
+
+pub fn load_persona(name: &str) -> Result<String, OlogError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(OlogError::Usage(format!("Persona names use letters, digits, - and _; got `{}`", name)));
+    }
+    let path = persona_dir().map(|dir| dir.join(format!("{}.md", name))).filter(|path| path.exists());
+    let text = match (path, bundled_persona(name)) {
+        (Some(path), _) => fs::read_to_string(&path).map_err(|e| OlogError::from(e).context(format!("Error reading persona {}", path.display())))?,
+        (None, Some(bundled)) => bundled.to_string(),
+        (None, None) => {
+            return Err(OlogError::Config(format!("Unknown persona `{}`; available: {}", name, persona_names().join(", "))));
+        },
+    };
+    Ok(text.trim_end().to_string())
+}
+
+// Fills `{name}` placeholders in one pass, so a value that itself contains a
+// placeholder is left as it is. Other braces, such as the JSON examples in
+// the olog prompt, are kept.
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let var = vars.iter().find(|(name, _)| {
+            rest[1..].strip_prefix(name).is_some_and(|after| after.starts_with('}'))
+        });
+        match var {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[name.len() + 2..];
+            },
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn has_var(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{}}}", name))
+}
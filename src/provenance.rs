@@ -0,0 +1,92 @@
+use rusqlite::{params, Connection, Result};
+use uuid::Uuid;
+
+use crate::db::{citation_text, id_column};
+use crate::olog::Olog;
+
+// Characters of document text shown around a node's label
+const EXCERPT_CONTEXT_CHARS: usize = 120;
+
+// Which document chunk (a citation) and which generation pass produced a
+// node. Nodes are matched by label, since merging and id reassignment keep
+// labels but not ids.
+#[derive(Debug, Clone)]
+pub struct NodeProvenance {
+    pub label: String,
+    pub citation_id: Uuid,
+    pub pass: usize,
+    // Book chapter or other part of the document the chunk came from
+    pub section: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct NodeOrigin {
+    pub pass: usize,
+    pub section: Option<String>,
+    pub citation_id: String,
+    pub document_title: String,
+    // Text around the first mention of the label, if it appears verbatim
+    pub excerpt: Option<String>,
+}
+
+pub fn record_provenance(conn: &Connection, olog: &Olog, provenance: &[NodeProvenance]) -> Result<()> {
+    for entry in provenance {
+        let Some(node) = olog.nodes.iter().find(|node| node.label == entry.label) else { continue };
+        conn.execute(
+            "INSERT OR IGNORE INTO Node_Provenance (node_id, citation_id, pass, section) VALUES (?1, ?2, ?3, ?4)",
+            params![node.id.to_string(), entry.citation_id.to_string(), entry.pass as i64, entry.section],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn read_provenance(conn: &Connection, olog_id: Uuid) -> Result<Vec<NodeProvenance>> {
+    let mut stmt = conn.prepare(
+        "SELECT Nodes.label, Node_Provenance.citation_id, Node_Provenance.pass, Node_Provenance.section
+         FROM Node_Provenance JOIN Nodes ON Nodes.node_id = Node_Provenance.node_id
+         WHERE Nodes.olog_id = ?1",
+    )?;
+    let rows = stmt.query_map(params![olog_id.to_string()], |row| {
+        Ok(NodeProvenance {
+            label: row.get(0)?,
+            citation_id: id_column(row, 1)?,
+            pass: row.get::<_, i64>(2)? as usize,
+            section: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn node_origins(conn: &Connection, node_id: Uuid, label: &str) -> Result<Vec<NodeOrigin>> {
+    let mut stmt = conn.prepare(
+        "SELECT Node_Provenance.pass, Node_Provenance.section, Citations.citation_id, Citations.title,
+                COALESCE(Citations.text, Documents.text)
+         FROM Node_Provenance JOIN Citations ON Citations.citation_id = Node_Provenance.citation_id
+         LEFT JOIN Documents ON Documents.document_id = Citations.document_id
+         WHERE Node_Provenance.node_id = ?1
+         ORDER BY Node_Provenance.pass",
+    )?;
+    let rows = stmt.query_map(params![node_id.to_string()], |row| {
+        let text = citation_text(row, 4)?;
+        Ok(NodeOrigin {
+            pass: row.get::<_, i64>(0)? as usize,
+            section: row.get(1)?,
+            citation_id: row.get(2)?,
+            document_title: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            excerpt: text.and_then(|text| excerpt(&text, label)),
+        })
+    })?;
+    rows.collect()
+}
+
+// Labels are often paraphrased, so fall back to the longest word of the label
+fn excerpt(text: &str, label: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    let longest_word = label.split_whitespace().max_by_key(|word| word.chars().count()).unwrap_or(label);
+    let start = lower.find(&label.to_lowercase()).or_else(|| lower.find(&longest_word.to_lowercase()))?;
+
+    // Lowercasing can shift byte offsets for some scripts; stay on char boundaries
+    let before: String = text.get(..start)?.chars().rev().take(EXCERPT_CONTEXT_CHARS).collect::<Vec<_>>().into_iter().rev().collect();
+    let after: String = text.get(start..)?.chars().take(EXCERPT_CONTEXT_CHARS + label.chars().count()).collect();
+    Some(format!("…{}{}…", before, after).split_whitespace().collect::<Vec<_>>().join(" "))
+}
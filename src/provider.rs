@@ -0,0 +1,333 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::config;
+use crate::embedding;
+use crate::error::OlogError;
+use crate::http;
+use crate::llama;
+use crate::retry;
+use crate::sanitize;
+use crate::usage;
+
+// A long document's olog can take minutes to write; a reply that takes
+// longer than this has stalled, and the call is retried
+pub const DEFAULT_MODEL_TIMEOUT: Duration = Duration::from_secs(600);
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// Anthropic requires a reply limit; an olog for a long document runs to a few thousand tokens
+const ANTHROPIC_MAX_TOKENS: u32 = 8192;
+// Ollama's default window is 2048 tokens, and it silently drops the start of
+// any longer prompt, document included
+const OLLAMA_CONTEXT_TOKENS: u32 = 32768;
+
+/// Where model calls are answered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provider {
+    /// OpenAI's API, or any OpenAI-compatible endpoint set by OPENAI_BASE_URL
+    Openai,
+    /// Anthropic's Messages API, authenticated with ANTHROPIC_API_KEY
+    Anthropic,
+    /// An Ollama server, at OLLAMA_HOST or http://localhost:11434
+    Ollama,
+    /// A GGUF model run on this machine by llama.cpp, so documents never leave it
+    LocalGguf,
+}
+
+impl Provider {
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Openai => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Ollama => "ollama",
+            Provider::LocalGguf => "local-gguf",
+        }
+    }
+}
+
+/// Answers prompts. Every request carries the guardrail as its system message
+/// so instructions hidden in document text are treated as data.
+pub trait LlmProvider: Send + Sync {
+    /// Tells apart cached replies for the same model name from different backends
+    fn name(&self) -> String;
+    fn complete(&self, model: &str, prompt: &str) -> Result<String, OlogError>;
+    /// Like `complete`, with the reply constrained to a JSON object
+    fn complete_json(&self, model: &str, prompt: &str) -> Result<String, OlogError>;
+    /// Whether replies may be kept in and replayed from the response cache
+    fn cacheable(&self) -> bool {
+        true
+    }
+    /// One vector per text, in the same order
+    fn embed(&self, _model: &str, _texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+        Err(OlogError::Config(format!("Embeddings need the OpenAI or Ollama provider; {} only generates text", self.name())))
+    }
+    /// Whether documents stay on this machine; other lookups, such as
+    /// Crossref, are skipped too
+    fn is_offline(&self) -> bool {
+        false
+    }
+}
+
+/// OpenAI's chat completions API, or a compatible server at `endpoint`
+#[derive(Debug, Default)]
+pub struct OpenAi {
+    pub endpoint: Option<String>,
+}
+
+impl OpenAi {
+    fn request(&self, model: &str, prompt: &str, json: bool) -> Result<String, OlogError> {
+        let api_key = config::require_api_key()?;
+        let timeout = config::model_timeout(DEFAULT_MODEL_TIMEOUT)?.value;
+        let mut body = json!({
+            "model": model,
+            "messages": [
+                { "role": "system", "content": sanitize::GUARDRAIL },
+                { "role": "user", "content": prompt },
+            ],
+        });
+        if json {
+            body["response_format"] = json!({ "type": "json_object" });
+        }
+        let url = format!("{}/chat/completions", self.endpoint.as_deref().unwrap_or(OPENAI_URL).trim_end_matches('/'));
+        let reply = retry::with_retries("Model request", || {
+            Ok(http::post(&url)?
+                .set("Authorization", &format!("Bearer {}", api_key))
+                .set("Content-Type", "application/json")
+                .timeout(timeout)
+                .send_string(&body.to_string())?
+                .into_string()?)
+        })?;
+        let reply: Value = serde_json::from_str(&reply).map_err(|e| OlogError::Llm(format!("Malformed response from OpenAI: {}", e)))?;
+        let tokens = |field: &str| reply["usage"][field].as_i64().unwrap_or_default();
+        usage::record(&self.name(), model, tokens("prompt_tokens"), tokens("completion_tokens"));
+        reply["choices"][0]["message"]["content"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| OlogError::Llm("No response from OpenAI".to_string()))
+    }
+}
+
+impl LlmProvider for OpenAi {
+    // The bare name for OpenAI itself keeps replies cached before endpoints were configurable
+    fn name(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) => format!("openai:{}", endpoint),
+            None => "openai".to_string(),
+        }
+    }
+
+    fn complete(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, false)
+    }
+
+    fn complete_json(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, true)
+    }
+
+    fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+        embedding::embed_openai(model, texts)
+    }
+}
+
+/// Anthropic's Messages API; the key is read when the first call is made
+#[derive(Debug, Default)]
+pub struct Anthropic;
+
+impl Anthropic {
+    fn request(&self, model: &str, prompt: &str, json: bool) -> Result<String, OlogError> {
+        let api_key = config::require_anthropic_api_key()?;
+        let timeout = config::model_timeout(DEFAULT_MODEL_TIMEOUT)?.value;
+        let mut messages = vec![json!({ "role": "user", "content": prompt })];
+        // There is no JSON mode; starting the reply with a brace holds it to an object
+        if json {
+            messages.push(json!({ "role": "assistant", "content": "{" }));
+        }
+        let body = json!({
+            "model": model,
+            "max_tokens": ANTHROPIC_MAX_TOKENS,
+            "system": sanitize::GUARDRAIL,
+            "messages": messages,
+        });
+        let reply = retry::with_retries("Model request", || {
+            Ok(http::post(ANTHROPIC_URL)?
+                .set("x-api-key", &api_key)
+                .set("anthropic-version", ANTHROPIC_VERSION)
+                .set("Content-Type", "application/json")
+                .timeout(timeout)
+                .send_string(&body.to_string())?
+                .into_string()?)
+        })?;
+        let reply: Value = serde_json::from_str(&reply).map_err(|e| OlogError::Llm(format!("Malformed response from Anthropic: {}", e)))?;
+        let tokens = |field: &str| reply["usage"][field].as_i64().unwrap_or_default();
+        usage::record(&self.name(), model, tokens("input_tokens"), tokens("output_tokens"));
+        let text: String = reply["content"].as_array().into_iter().flatten()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        if text.trim().is_empty() {
+            return Err(OlogError::Llm("No response from Anthropic".to_string()));
+        }
+        Ok(if json { format!("{{{}", text) } else { text })
+    }
+}
+
+impl LlmProvider for Anthropic {
+    fn name(&self) -> String {
+        "anthropic".to_string()
+    }
+
+    fn complete(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, false)
+    }
+
+    fn complete_json(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, true)
+    }
+}
+
+/// An Ollama server's chat API
+#[derive(Debug)]
+pub struct Ollama {
+    pub host: String,
+}
+
+impl Ollama {
+    fn request(&self, model: &str, prompt: &str, json: bool) -> Result<String, OlogError> {
+        let mut body = json!({
+            "model": model,
+            "messages": [
+                { "role": "system", "content": sanitize::GUARDRAIL },
+                { "role": "user", "content": prompt },
+            ],
+            "stream": false,
+            "options": { "num_ctx": OLLAMA_CONTEXT_TOKENS, "temperature": 0 },
+        });
+        if json {
+            body["format"] = json!("json");
+        }
+        let url = format!("{}/api/chat", self.host.trim_end_matches('/'));
+        let timeout = config::model_timeout(DEFAULT_MODEL_TIMEOUT)?.value;
+        let reply = retry::with_retries("Model request", || {
+            Ok(http::post(&url)?
+                .set("Content-Type", "application/json")
+                .timeout(timeout)
+                .send_string(&body.to_string())?
+                .into_string()?)
+        })?;
+        let reply: Value = serde_json::from_str(&reply).map_err(|e| OlogError::Llm(format!("Malformed response from Ollama: {}", e)))?;
+        let tokens = |field: &str| reply[field].as_i64().unwrap_or_default();
+        usage::record(&self.name(), model, tokens("prompt_eval_count"), tokens("eval_count"));
+        if reply["done_reason"] == "length" {
+            return Err(OlogError::Llm(format!(
+                "{} stopped before finishing its reply; the prompt may not fit its context, so try a smaller document or --chunk-size",
+                model
+            )));
+        }
+        let content = reply["message"]["content"].as_str()
+            .map(strip_thinking)
+            .filter(|content| !content.trim().is_empty())
+            .ok_or_else(|| OlogError::Llm(format!("No response from Ollama at {}", self.host)))?;
+        if !json {
+            return Ok(content.trim().to_string());
+        }
+        json_object(content).map(str::to_string).ok_or_else(|| {
+            OlogError::Llm(format!("{} did not reply with a JSON object; pick a model that supports JSON output", model))
+        })
+    }
+}
+
+// Reasoning models served by Ollama put their thinking in front of the answer
+fn strip_thinking(reply: &str) -> &str {
+    match reply.split_once("</think>") {
+        Some((thinking, answer)) if thinking.trim_start().starts_with("<think>") => answer,
+        _ => reply,
+    }
+}
+
+// Even in JSON mode some models wrap the object in a code fence or follow it
+// with a remark, so the reply is cut down to its outermost braces
+fn json_object(reply: &str) -> Option<&str> {
+    let start = reply.find('{')?;
+    let end = reply.rfind('}')?;
+    (start < end).then(|| &reply[start..=end])
+}
+
+impl LlmProvider for Ollama {
+    fn name(&self) -> String {
+        format!("ollama:{}", self.host)
+    }
+
+    fn complete(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, false)
+    }
+
+    fn complete_json(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.request(model, prompt, true)
+    }
+
+    fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+        embedding::embed_ollama(&self.host, model, texts)
+    }
+
+    fn is_offline(&self) -> bool {
+        true
+    }
+}
+
+/// A GGUF file run through llama.cpp; the model name of each call is ignored
+#[derive(Debug)]
+pub struct LocalGguf {
+    pub model_path: PathBuf,
+}
+
+impl LlmProvider for LocalGguf {
+    fn name(&self) -> String {
+        self.model_path.display().to_string()
+    }
+
+    fn complete(&self, _model: &str, prompt: &str) -> Result<String, OlogError> {
+        llama::generate(&self.model_path, prompt, false)
+    }
+
+    fn complete_json(&self, _model: &str, prompt: &str) -> Result<String, OlogError> {
+        llama::generate(&self.model_path, prompt, true)
+    }
+
+    fn is_offline(&self) -> bool {
+        true
+    }
+}
+
+static SELECTED: OnceLock<(Provider, Box<dyn LlmProvider>)> = OnceLock::new();
+
+// Sets the backend for every model call in this process
+pub fn select(kind: Provider, model_path: Option<PathBuf>) -> Result<(), OlogError> {
+    let provider: Box<dyn LlmProvider> = match kind {
+        Provider::Openai => Box::new(OpenAi { endpoint: config::openai_base_url()?.value }),
+        Provider::Anthropic => Box::new(Anthropic),
+        Provider::Ollama => Box::new(Ollama { host: config::ollama_host()?.value }),
+        Provider::LocalGguf => {
+            let model_path = model_path.ok_or_else(|| OlogError::Config("--provider local-gguf needs --model-path".to_string()))?;
+            if !model_path.is_file() {
+                return Err(OlogError::Config(format!("Model file {} does not exist", model_path.display())));
+            }
+            Box::new(LocalGguf { model_path })
+        },
+    };
+    SELECTED.set((kind, provider)).map_err(|_| OlogError::Other("The model provider is already selected".to_string()))
+}
+
+/// The provider `select` picked, or OpenAI if nothing was selected. The
+/// command line passes it to the library, which takes a provider explicitly.
+pub fn current() -> &'static dyn LlmProvider {
+    SELECTED.get_or_init(|| (Provider::Openai, Box::new(OpenAi::default()))).1.as_ref()
+}
+
+pub fn current_kind() -> Provider {
+    SELECTED.get().map_or(Provider::Openai, |(kind, _)| *kind)
+}
@@ -0,0 +1,773 @@
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::error::OlogError;
+use crate::olog::{distinct_citations, Citation, Hyperedge, Node, Olog};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+const OWL: &str = "http://www.w3.org/2002/07/owl#";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+const SKOS: &str = "http://www.w3.org/2004/02/skos/core#";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const DCTERMS: &str = "http://purl.org/dc/terms/";
+// Properties the export mints for hyperedge endpoints, roles and quantities
+const OLOG: &str = "https://github.com/cyber-phys/olog-debate/ns#";
+// Exported ids without an --id-prefix
+const DEFAULT_BASE: &str = "urn:uuid:";
+
+// Predicates from these vocabularies describe the ontology rather than
+// relate its concepts
+const VOCABULARIES: [&str; 7] = [RDF, RDFS, OWL, XSD, SKOS, DC, DCTERMS];
+
+const SUBCLASS_LABEL: &str = "is a";
+const INSTANCE_LABEL: &str = "is an instance of";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Term {
+    Iri(String),
+    Blank(String),
+    Literal { value: String, language: Option<String> },
+}
+
+impl Term {
+    fn iri(&self) -> Option<&str> {
+        match self {
+            Term::Iri(iri) => Some(iri),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Triple {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term,
+}
+
+struct TurtleParser {
+    chars: Vec<char>,
+    pos: usize,
+    prefixes: HashMap<String, String>,
+    base: String,
+    blank_nodes: usize,
+    triples: Vec<Triple>,
+}
+
+// Parses Turtle, which covers N-Triples and OWL ontologies saved as Turtle.
+// RDF/XML would need an XML parser and is rejected with a hint to convert.
+pub fn parse_turtle(text: &str) -> Result<Vec<Triple>, OlogError> {
+    let start = text.trim_start();
+    if start.starts_with("<?xml") || start.starts_with("<rdf:RDF") {
+        return Err(OlogError::Schema(
+            "RDF/XML is not supported; convert the ontology to Turtle first (e.g. `rapper -o turtle`)".to_string(),
+        ));
+    }
+    let mut parser = TurtleParser {
+        chars: text.chars().collect(),
+        pos: 0,
+        prefixes: HashMap::new(),
+        base: String::new(),
+        blank_nodes: 0,
+        triples: Vec::new(),
+    };
+    parser.document()?;
+    Ok(parser.triples)
+}
+
+impl TurtleParser {
+    fn error(&self, message: &str) -> OlogError {
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|c| **c == '\n').count() + 1;
+        OlogError::Schema(format!("Turtle syntax error on line {}: {}", line, message))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, token: &str) -> bool {
+        token.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    // Case-insensitive keyword followed by whitespace, as in SPARQL-style PREFIX
+    fn starts_with_keyword(&self, keyword: &str) -> bool {
+        keyword.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i).is_some_and(|d| d.eq_ignore_ascii_case(&c)))
+            && self.chars.get(self.pos + keyword.len()).is_some_and(|c| c.is_whitespace())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), OlogError> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn document(&mut self) -> Result<(), OlogError> {
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                return Ok(());
+            }
+            if self.starts_with("@prefix") || self.starts_with_keyword("prefix") {
+                let turtle_style = self.peek() == Some('@');
+                self.pos += if turtle_style { 7 } else { 6 };
+                self.skip_whitespace();
+                let name = self.read_while(|c| c != ':' && !c.is_whitespace());
+                self.expect(':')?;
+                self.skip_whitespace();
+                let iri = self.iri_ref()?;
+                self.prefixes.insert(name, iri);
+                if turtle_style {
+                    self.expect('.')?;
+                }
+            } else if self.starts_with("@base") || self.starts_with_keyword("base") {
+                let turtle_style = self.peek() == Some('@');
+                self.pos += if turtle_style { 5 } else { 4 };
+                self.skip_whitespace();
+                self.base = self.iri_ref()?;
+                if turtle_style {
+                    self.expect('.')?;
+                }
+            } else {
+                self.statement()?;
+                self.expect('.')?;
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), OlogError> {
+        if self.peek() == Some('[') {
+            let subject = self.blank_node_property_list()?;
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                self.predicate_object_list(&subject)?;
+            }
+            return Ok(());
+        }
+        let subject = self.term()?;
+        self.predicate_object_list(&subject)
+    }
+
+    fn predicate_object_list(&mut self, subject: &Term) -> Result<(), OlogError> {
+        loop {
+            self.skip_whitespace();
+            let predicate = self.verb()?;
+            loop {
+                self.skip_whitespace();
+                let object = self.object()?;
+                self.triples.push(Triple { subject: subject.clone(), predicate: predicate.clone(), object });
+                self.skip_whitespace();
+                if self.peek() != Some(',') {
+                    break;
+                }
+                self.pos += 1;
+            }
+            if self.peek() != Some(';') {
+                return Ok(());
+            }
+            while self.peek() == Some(';') {
+                self.pos += 1;
+                self.skip_whitespace();
+            }
+            if matches!(self.peek(), None | Some('.') | Some(']')) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn verb(&mut self) -> Result<Term, OlogError> {
+        if self.peek() == Some('a') && self.chars.get(self.pos + 1).is_none_or(|c| c.is_whitespace() || matches!(c, '<' | '[' | '"' | '_')) {
+            self.pos += 1;
+            return Ok(Term::Iri(format!("{}type", RDF)));
+        }
+        self.term()
+    }
+
+    fn object(&mut self) -> Result<Term, OlogError> {
+        match self.peek() {
+            Some('[') => self.blank_node_property_list(),
+            Some('(') => self.collection(),
+            Some('"') | Some('\'') => self.literal(),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => {
+                let mut value = self.read_while(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | 'e' | 'E'));
+                while self.peek() == Some('.') && self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                    value.push('.');
+                    value.push_str(&self.read_while(|c| c.is_ascii_digit() || matches!(c, 'e' | 'E' | '+' | '-')));
+                }
+                Ok(Term::Literal { value, language: None })
+            },
+            _ if self.starts_with("true") || self.starts_with("false") => {
+                let value = self.read_while(|c| c.is_ascii_alphabetic());
+                Ok(Term::Literal { value, language: None })
+            },
+            _ => self.term(),
+        }
+    }
+
+    // An IRI, prefixed name or labelled blank node
+    fn term(&mut self) -> Result<Term, OlogError> {
+        match self.peek() {
+            Some('<') => Ok(Term::Iri(self.iri_ref()?)),
+            None => Err(self.error("unexpected end of file")),
+            _ if self.starts_with("_:") => {
+                self.pos += 2;
+                Ok(Term::Blank(self.name()))
+            },
+            _ => {
+                let name = self.name();
+                let Some((prefix, local)) = name.split_once(':') else {
+                    return Err(self.error(&format!("expected an IRI or prefixed name, found \"{}\"", name)));
+                };
+                let namespace = self.prefixes.get(prefix)
+                    .ok_or_else(|| self.error(&format!("undeclared prefix \"{}:\"", prefix)))?;
+                Ok(Term::Iri(format!("{}{}", namespace, local.replace('\\', ""))))
+            },
+        }
+    }
+
+    // A prefixed name or blank node label; may contain dots, but not end in one
+    fn name(&mut self) -> String {
+        let mut name = self.read_while(|c| !c.is_whitespace() && !matches!(c, ';' | ',' | '(' | ')' | '[' | ']' | '<' | '"' | '#'));
+        while name.ends_with('.') {
+            name.pop();
+            self.pos -= 1;
+        }
+        name
+    }
+
+    fn iri_ref(&mut self) -> Result<String, OlogError> {
+        if self.peek() != Some('<') {
+            return Err(self.error("expected '<'"));
+        }
+        self.pos += 1;
+        let iri = self.read_while(|c| c != '>');
+        if self.peek() != Some('>') {
+            return Err(self.error("unterminated IRI"));
+        }
+        self.pos += 1;
+        // Relative IRIs resolve against @base by simple concatenation
+        if iri.contains(':') {
+            Ok(iri)
+        } else {
+            Ok(format!("{}{}", self.base, iri))
+        }
+    }
+
+    fn literal(&mut self) -> Result<Term, OlogError> {
+        let quote = self.peek().unwrap_or('"');
+        let long: String = std::iter::repeat_n(quote, 3).collect();
+        let long_form = self.starts_with(&long);
+        self.pos += if long_form { 3 } else { 1 };
+
+        let mut value = String::new();
+        loop {
+            if long_form && self.starts_with(&long) {
+                self.pos += 3;
+                break;
+            }
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote && !long_form => {
+                    self.pos += 1;
+                    break;
+                },
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    value.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        other => other,
+                    });
+                    self.pos += 1;
+                },
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+
+        let mut language = None;
+        if self.peek() == Some('@') {
+            self.pos += 1;
+            language = Some(self.read_while(|c| c.is_ascii_alphanumeric() || c == '-').to_lowercase());
+        } else if self.starts_with("^^") {
+            self.pos += 2;
+            self.term()?;
+        }
+        Ok(Term::Literal { value, language })
+    }
+
+    fn blank_node_property_list(&mut self) -> Result<Term, OlogError> {
+        self.pos += 1;
+        let node = self.fresh_blank_node();
+        self.skip_whitespace();
+        if self.peek() != Some(']') {
+            self.predicate_object_list(&node)?;
+        }
+        self.expect(']')?;
+        Ok(node)
+    }
+
+    fn collection(&mut self) -> Result<Term, OlogError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.pos += 1;
+                break;
+            }
+            items.push(self.object()?);
+        }
+
+        let mut list = Term::Iri(format!("{}nil", RDF));
+        for item in items.into_iter().rev() {
+            let cell = self.fresh_blank_node();
+            self.triples.push(Triple { subject: cell.clone(), predicate: Term::Iri(format!("{}first", RDF)), object: item });
+            self.triples.push(Triple { subject: cell.clone(), predicate: Term::Iri(format!("{}rest", RDF)), object: list });
+            list = cell;
+        }
+        Ok(list)
+    }
+
+    fn fresh_blank_node(&mut self) -> Term {
+        self.blank_nodes += 1;
+        Term::Blank(format!("anon{}", self.blank_nodes))
+    }
+
+    fn read_while(&mut self, keep: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&keep) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
+
+// "CellMembrane" or "has_part" to "cell membrane" and "has part"; acronyms
+// keep their case
+fn humanize(iri: &str) -> String {
+    let local = iri.rsplit(['#', '/', ':']).next().filter(|local| !local.is_empty()).unwrap_or(iri);
+    let chars: Vec<char> = local.chars().collect();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            continue;
+        }
+        let previous = i.checked_sub(1).map(|j| chars[j]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()
+                || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase())));
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    words.extend((!word.is_empty()).then_some(word));
+    words.into_iter()
+        .map(|word| if word.chars().skip(1).any(char::is_uppercase) { word } else { word.to_lowercase() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn in_vocabulary(iri: &str) -> bool {
+    VOCABULARIES.iter().any(|namespace| iri.starts_with(namespace))
+}
+
+// Classes become nodes, rdfs:subClassOf becomes "is a", object properties
+// with a domain and range become hyperedges between them, and assertions
+// between named resources become hyperedges labelled by their property.
+// Restrictions and other blank-node constructs are left out.
+pub fn ontology_to_olog(triples: &[Triple], title: Option<String>, citation: Citation) -> Olog {
+    let rdf_type = format!("{}type", RDF);
+    let subclass_of = format!("{}subClassOf", RDFS);
+    let domain = format!("{}domain", RDFS);
+    let range = format!("{}range", RDFS);
+    let is = |term: &Term, namespace: &str, local: &str| term.iri().is_some_and(|iri| iri.strip_prefix(namespace) == Some(local));
+
+    // rdfs:label beats skos:prefLabel; English or untagged beats other languages
+    let mut labels: HashMap<&str, (u8, String)> = HashMap::new();
+    for triple in triples {
+        let (Some(subject), Term::Literal { value, language }) = (triple.subject.iri(), &triple.object) else { continue };
+        let kind = if is(&triple.predicate, RDFS, "label") {
+            0
+        } else if is(&triple.predicate, SKOS, "prefLabel") {
+            2
+        } else {
+            continue;
+        };
+        let rank = kind + u8::from(language.as_deref().is_some_and(|language| !language.starts_with("en")));
+        if labels.get(subject).is_none_or(|(best, _)| rank < *best) {
+            labels.insert(subject, (rank, value.trim().to_string()));
+        }
+    }
+    let label_of = |iri: &str| labels.get(iri).map_or_else(|| humanize(iri), |(_, label)| label.clone());
+
+    let typed = |iri: &str, namespace: &str, kinds: &[&str]| {
+        triples.iter().any(|triple| {
+            triple.subject.iri() == Some(iri) && triple.predicate.iri() == Some(rdf_type.as_str())
+                && kinds.iter().any(|kind| is(&triple.object, namespace, kind))
+        })
+    };
+    let not_a_relation = |iri: &str| in_vocabulary(iri) || typed(iri, OWL, &["AnnotationProperty", "DatatypeProperty"]);
+
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut node_index: HashMap<String, usize> = HashMap::new();
+    let mut node = |iri: &str| -> Node {
+        let index = *node_index.entry(iri.to_string()).or_insert_with(|| {
+            nodes.push(Node { id: Uuid::new_v4(), label: label_of(iri) });
+            nodes.len() - 1
+        });
+        nodes[index].clone()
+    };
+
+    let mut seen: HashSet<(String, String, String)> = HashSet::new();
+    let mut hyperedges: Vec<Hyperedge> = Vec::new();
+    let mut relate = |source: Node, label: String, target: Node| {
+        if seen.insert((source.label.clone(), label.clone(), target.label.clone())) {
+            hyperedges.push(Hyperedge {
+                id: Uuid::new_v4(),
+                label,
+                source: vec![source],
+                target: vec![target],
+                source_roles: vec![None],
+                target_roles: vec![None],
+                citations: vec![citation.clone()],
+                quantity: None,
+                spans: Vec::new(),
+            });
+        }
+    };
+
+    for triple in triples {
+        let (Some(subject), Some(predicate)) = (triple.subject.iri(), triple.predicate.iri()) else { continue };
+        if predicate == rdf_type {
+            match triple.object.iri() {
+                Some(_) if is(&triple.object, OWL, "Class") || is(&triple.object, RDFS, "Class") => {
+                    node(subject);
+                },
+                Some(class) if !in_vocabulary(class) => {
+                    let (individual, class) = (node(subject), node(class));
+                    relate(individual, INSTANCE_LABEL.to_string(), class);
+                },
+                _ => {},
+            }
+        } else if predicate == subclass_of {
+            if let Some(parent) = triple.object.iri() {
+                let (child, parent) = (node(subject), node(parent));
+                relate(child, SUBCLASS_LABEL.to_string(), parent);
+            }
+        } else if predicate == domain {
+            let ranges = triples.iter().filter(|other| {
+                other.subject.iri() == Some(subject) && other.predicate.iri() == Some(range.as_str())
+            });
+            for range in ranges.filter_map(|other| other.object.iri()) {
+                let (Some(from), Some(to)) = (triple.object.iri(), (!in_vocabulary(range)).then_some(range)) else { continue };
+                let (from, to) = (node(from), node(to));
+                relate(from, label_of(subject), to);
+            }
+        } else if !not_a_relation(predicate) {
+            if let Some(object) = triple.object.iri() {
+                let (from, to) = (node(subject), node(object));
+                relate(from, label_of(predicate), to);
+            }
+        }
+    }
+
+    let ontology_title = triples.iter()
+        .filter(|triple| is(&triple.object, OWL, "Ontology") && triple.predicate.iri() == Some(rdf_type.as_str()))
+        .filter_map(|triple| triple.subject.iri())
+        .find_map(|ontology| {
+            triples.iter()
+                .filter(|triple| triple.subject.iri() == Some(ontology))
+                .filter(|triple| is(&triple.predicate, DC, "title") || is(&triple.predicate, DCTERMS, "title") || is(&triple.predicate, RDFS, "label"))
+                .find_map(|triple| match &triple.object {
+                    Term::Literal { value, .. } => Some(value.trim().to_string()),
+                    _ => None,
+                })
+        });
+
+    Olog {
+        id: Uuid::new_v4(),
+        title: title.or(ontology_title).unwrap_or_else(|| citation.title.clone()),
+        nodes,
+        hyperedges,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RdfFormat {
+    /// Turtle, which import-rdf reads back
+    Turtle,
+    /// RDF/XML, the OWL exchange syntax Protégé opens by default
+    RdfXml,
+}
+
+// Serializes an olog as an OWL ontology. `base` goes in front of every olog,
+// node, hyperedge and citation id; without one the ids become urn:uuid IRIs.
+pub fn export_rdf(olog: &Olog, format: RdfFormat, base: &str) -> String {
+    let triples = olog_to_triples(olog, if base.is_empty() { DEFAULT_BASE } else { base });
+    match format {
+        RdfFormat::Turtle => to_turtle(&triples),
+        RdfFormat::RdfXml => to_rdf_xml(&triples),
+    }
+}
+
+// Nodes become classes, or individuals where the olog says they are an
+// instance of something. "Is a" becomes rdfs:subClassOf. Other hyperedges
+// with one source and one target become object properties with that domain
+// and range, or assertions between individuals. Wider ones are reified as a
+// class with a restriction per endpoint, after the W3C pattern for n-ary
+// relations. Every hyperedge points at its citations with dcterms:source,
+// on an owl:Axiom where the hyperedge is a bare triple.
+fn olog_to_triples(olog: &Olog, base: &str) -> Vec<Triple> {
+    let iri = |id: Uuid| Term::Iri(format!("{}{}", base, id));
+    let term = |namespace: &str, local: &str| Term::Iri(format!("{}{}", namespace, local));
+    let literal = |value: &str| Term::Literal { value: value.to_string(), language: None };
+    let a = term(RDF, "type");
+    let label = term(RDFS, "label");
+    let source = term(DCTERMS, "source");
+
+    let individuals: HashSet<Uuid> = olog.hyperedges.iter()
+        .filter(|hyperedge| is_binary(hyperedge) && hyperedge.label.trim().eq_ignore_ascii_case(INSTANCE_LABEL))
+        .map(|hyperedge| hyperedge.source[0].id)
+        .collect();
+
+    let mut triples = Vec::new();
+    let mut blank_nodes = 0;
+    let mut add = |subject: &Term, predicate: &Term, object: Term| {
+        triples.push(Triple { subject: subject.clone(), predicate: predicate.clone(), object });
+    };
+
+    let ontology = iri(olog.id);
+    add(&ontology, &a, term(OWL, "Ontology"));
+    add(&ontology, &term(DCTERMS, "title"), literal(&olog.title));
+    let annotations = [
+        (DCTERMS, "source"),
+        (DCTERMS, "title"),
+        (OLOG, "role"),
+        (OLOG, "sourceRole"),
+        (OLOG, "targetRole"),
+        (OLOG, "quantity"),
+    ];
+    for (namespace, local) in annotations {
+        add(&term(namespace, local), &a, term(OWL, "AnnotationProperty"));
+    }
+    for local in ["source", "target"] {
+        add(&term(OLOG, local), &a, term(OWL, "ObjectProperty"));
+        add(&term(OLOG, local), &label, literal(local));
+    }
+
+    for node in &olog.nodes {
+        let kind = if individuals.contains(&node.id) { "NamedIndividual" } else { "Class" };
+        add(&iri(node.id), &a, term(OWL, kind));
+        add(&iri(node.id), &label, literal(&node.label));
+    }
+
+    for citation in distinct_citations(olog) {
+        add(&iri(citation.id), &a, term(OWL, "NamedIndividual"));
+        add(&iri(citation.id), &label, literal(citation.title.trim()));
+        add(&iri(citation.id), &term(DCTERMS, "title"), literal(citation.title.trim()));
+        if !citation.label.trim().is_empty() {
+            add(&iri(citation.id), &term(RDFS, "comment"), literal(citation.label.trim()));
+        }
+    }
+
+    for hyperedge in &olog.hyperedges {
+        let cited: Vec<Term> = hyperedge.citations.iter().map(|citation| iri(citation.id)).collect();
+        let relation = hyperedge.label.trim();
+        if is_binary(hyperedge) {
+            let (from, to) = (&hyperedge.source[0], &hyperedge.target[0]);
+            let (from_individual, to_individual) = (individuals.contains(&from.id), individuals.contains(&to.id));
+            let axiom = if relation.eq_ignore_ascii_case(SUBCLASS_LABEL) && !from_individual && !to_individual {
+                Some(term(RDFS, "subClassOf"))
+            } else if relation.eq_ignore_ascii_case(INSTANCE_LABEL) && !to_individual {
+                Some(a.clone())
+            } else {
+                None
+            };
+            if let Some(predicate) = axiom {
+                add(&iri(from.id), &predicate, iri(to.id));
+                blank_nodes += 1;
+                let annotation = Term::Blank(format!("b{}", blank_nodes));
+                add(&annotation, &a, term(OWL, "Axiom"));
+                add(&annotation, &term(OWL, "annotatedSource"), iri(from.id));
+                add(&annotation, &term(OWL, "annotatedProperty"), predicate);
+                add(&annotation, &term(OWL, "annotatedTarget"), iri(to.id));
+                for citation in &cited {
+                    add(&annotation, &source, citation.clone());
+                }
+                continue;
+            }
+
+            let property = iri(hyperedge.id);
+            add(&property, &a, term(OWL, "ObjectProperty"));
+            add(&property, &label, literal(relation));
+            if from_individual && to_individual {
+                add(&iri(from.id), &property, iri(to.id));
+            } else {
+                add(&property, &term(RDFS, "domain"), iri(from.id));
+                add(&property, &term(RDFS, "range"), iri(to.id));
+            }
+            for (role, predicate) in [(&hyperedge.source_roles, "sourceRole"), (&hyperedge.target_roles, "targetRole")] {
+                if let Some(Some(role)) = role.first() {
+                    add(&property, &term(OLOG, predicate), literal(role));
+                }
+            }
+        } else {
+            let class = iri(hyperedge.id);
+            add(&class, &a, term(OWL, "Class"));
+            add(&class, &label, literal(relation));
+            let endpoints = [
+                ("source", &hyperedge.source, &hyperedge.source_roles),
+                ("target", &hyperedge.target, &hyperedge.target_roles),
+            ];
+            for (end, nodes, roles) in endpoints {
+                for (i, node) in nodes.iter().enumerate() {
+                    blank_nodes += 1;
+                    let restriction = Term::Blank(format!("b{}", blank_nodes));
+                    add(&class, &term(RDFS, "subClassOf"), restriction.clone());
+                    add(&restriction, &a, term(OWL, "Restriction"));
+                    add(&restriction, &term(OWL, "onProperty"), term(OLOG, end));
+                    let filler = if individuals.contains(&node.id) { "hasValue" } else { "someValuesFrom" };
+                    add(&restriction, &term(OWL, filler), iri(node.id));
+                    if let Some(Some(role)) = roles.get(i) {
+                        add(&restriction, &term(OLOG, "role"), literal(role));
+                    }
+                }
+            }
+        }
+        let subject = iri(hyperedge.id);
+        if let Some(quantity) = &hyperedge.quantity {
+            add(&subject, &term(OLOG, "quantity"), literal(&quantity.to_string()));
+        }
+        for citation in cited {
+            add(&subject, &source, citation);
+        }
+    }
+    triples
+}
+
+fn is_binary(hyperedge: &Hyperedge) -> bool {
+    hyperedge.source.len() == 1 && hyperedge.target.len() == 1
+}
+
+const PREFIXES: [(&str, &str); 5] = [("rdf", RDF), ("rdfs", RDFS), ("owl", OWL), ("dcterms", DCTERMS), ("olog", OLOG)];
+
+// Triples under their subject, subjects in order of first appearance
+fn by_subject(triples: &[Triple]) -> Vec<(&Term, Vec<&Triple>)> {
+    let mut grouped: Vec<(&Term, Vec<&Triple>)> = Vec::new();
+    let mut index: HashMap<&Term, usize> = HashMap::new();
+    for triple in triples {
+        let position = *index.entry(&triple.subject).or_insert_with(|| {
+            grouped.push((&triple.subject, Vec::new()));
+            grouped.len() - 1
+        });
+        grouped[position].1.push(triple);
+    }
+    grouped
+}
+
+// prefix:local for IRIs in a declared namespace whose local part needs no escaping
+fn prefixed(iri: &str) -> Option<(&'static str, &str)> {
+    PREFIXES.iter().find_map(|(prefix, namespace)| {
+        let local = iri.strip_prefix(namespace)?;
+        let plain = local.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && local.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        plain.then_some((*prefix, local))
+    })
+}
+
+fn to_turtle(triples: &[Triple]) -> String {
+    let write_term = |term: &Term| match term {
+        Term::Iri(iri) => match prefixed(iri) {
+            Some((prefix, local)) => format!("{}:{}", prefix, local),
+            None => format!("<{}>", iri),
+        },
+        Term::Blank(name) => format!("_:{}", name),
+        Term::Literal { value, language } => {
+            let escaped = value.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+                .replace('\t', "\\t");
+            match language {
+                Some(language) => format!("\"{}\"@{}", escaped, language),
+                None => format!("\"{}\"", escaped),
+            }
+        },
+    };
+    let rdf_type = Term::Iri(format!("{}type", RDF));
+
+    let mut turtle: String = PREFIXES.iter()
+        .map(|(prefix, namespace)| format!("@prefix {}: <{}> .\n", prefix, namespace))
+        .collect();
+    for (subject, triples) in by_subject(triples) {
+        let statements: Vec<String> = triples.iter()
+            .map(|triple| {
+                let predicate = if triple.predicate == rdf_type { "a".to_string() } else { write_term(&triple.predicate) };
+                format!("{} {}", predicate, write_term(&triple.object))
+            })
+            .collect();
+        turtle.push_str(&format!("\n{}\n    {} .\n", write_term(subject), statements.join(" ;\n    ")));
+    }
+    turtle
+}
+
+fn to_rdf_xml(triples: &[Triple]) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rdf:RDF");
+    for (prefix, namespace) in PREFIXES {
+        xml.push_str(&format!("\n    xmlns:{}=\"{}\"", prefix, namespace));
+    }
+    xml.push_str(">\n");
+    for (subject, triples) in by_subject(triples) {
+        match subject {
+            Term::Blank(name) => xml.push_str(&format!("  <rdf:Description rdf:nodeID=\"{}\">\n", escape(name))),
+            Term::Iri(iri) => xml.push_str(&format!("  <rdf:Description rdf:about=\"{}\">\n", escape(iri))),
+            Term::Literal { .. } => continue,
+        }
+        for triple in triples {
+            let Some((prefix, local)) = triple.predicate.iri().and_then(prefixed) else { continue };
+            let element = format!("{}:{}", prefix, local);
+            match &triple.object {
+                Term::Iri(iri) => xml.push_str(&format!("    <{} rdf:resource=\"{}\"/>\n", element, escape(iri))),
+                Term::Blank(name) => xml.push_str(&format!("    <{} rdf:nodeID=\"{}\"/>\n", element, escape(name))),
+                Term::Literal { value, language: Some(language) } => {
+                    xml.push_str(&format!("    <{} xml:lang=\"{}\">{}</{}>\n", element, escape(language), escape(value), element));
+                },
+                Term::Literal { value, language: None } => {
+                    xml.push_str(&format!("    <{}>{}</{}>\n", element, escape(value), element));
+                },
+            }
+        }
+        xml.push_str("  </rdf:Description>\n");
+    }
+    xml.push_str("</rdf:RDF>\n");
+    xml
+}
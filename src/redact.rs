@@ -0,0 +1,233 @@
+use regex::{Captures, Regex};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::cache::content_hash;
+use crate::config;
+use crate::db::unknown_value;
+use crate::error::OlogError;
+use crate::olog::{distinct_citations, Olog};
+
+const EMAIL: &str = r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b";
+// Three or more digit groups, optionally with a country code
+const PHONE: &str = r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[ .-]\d{3,4}[ .-]\d{3,4}\b";
+const IDENTIFIERS: &[&str] = &[
+    // US social security numbers
+    r"\b\d{3}-\d{2}-\d{4}\b",
+    // IBANs, with or without the usual spacing
+    r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+];
+// A name after a title, and the names on header and signature lines
+const TITLED_NAME: &str = r"\b(?:Dr|Prof|Mr|Mrs|Ms|Mx|Sir|Dame)\.? +((?:[A-Z][\w'-]+)(?: +[A-Z][\w'-]+){0,2})";
+const NAME_LINE: &str = r"(?m)^[ \t]*(?:From|To|Cc|Bcc|Author|Authors|Prepared by|Reviewed by|Approved by|Signed|Contact)[ \t]*:[ \t]*(.+)$";
+const NAME: &str = r"^[A-Z][\w'-]+(?: +(?:[A-Z]\.|[A-Z][\w'-]+)){1,3}$";
+// Hex digits of the original's hash in a placeholder; the same detail gets
+// the same placeholder in every document
+const PLACEHOLDER_DIGITS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Email,
+    Phone,
+    Identifier,
+    Name,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Email => "EMAIL",
+            Kind::Phone => "PHONE",
+            Kind::Identifier => "ID",
+            Kind::Name => "NAME",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Kind> {
+        [Kind::Email, Kind::Phone, Kind::Identifier, Kind::Name].into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redaction {
+    pub placeholder: String,
+    pub original: String,
+    pub kind: Kind,
+}
+
+#[derive(Debug)]
+pub struct Redacted {
+    pub text: String,
+    pub redactions: Vec<Redaction>,
+}
+
+// Set under [redaction] in olog.toml: names to mask wherever they appear, and
+// patterns for in-house identifiers such as employee or case numbers
+#[derive(Debug, Default, Deserialize)]
+struct RedactionConfig {
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<String>,
+}
+
+/// What a redaction pass looks for besides emails, phone numbers and the
+/// built-in identifiers
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    pub names: Vec<String>,
+    pub patterns: Vec<Regex>,
+}
+
+// The rules in olog.toml
+pub fn rules() -> Result<Rules, OlogError> {
+    let redaction = config::project_setting::<RedactionConfig>("redaction")?.unwrap_or_default();
+    let patterns = redaction.patterns.iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| OlogError::Config(format!("Invalid redaction pattern `{}` in {}: {}", pattern, config::PROJECT_FILE, e)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Rules { names: redaction.names.into_iter().filter(|name| !name.trim().is_empty()).collect(), patterns })
+}
+
+struct Pass {
+    redactions: Vec<Redaction>,
+}
+
+impl Pass {
+    fn placeholder(&mut self, original: &str, kind: Kind) -> String {
+        let hash = content_hash(original.as_bytes());
+        let placeholder = format!("[{}_{}]", kind.name(), &hash[..PLACEHOLDER_DIGITS]);
+        if !self.redactions.iter().any(|redaction| redaction.placeholder == placeholder) {
+            self.redactions.push(Redaction { placeholder: placeholder.clone(), original: original.to_string(), kind });
+        }
+        placeholder
+    }
+
+    fn replace(&mut self, text: &str, pattern: &Regex, kind: Kind) -> String {
+        pattern.replace_all(text, |captures: &Captures| self.placeholder(&captures[0], kind)).into_owned()
+    }
+}
+
+// Masks emails, phone numbers, identifiers and personal names. Names are
+// found by rule: after a title such as "Dr.", on From:, Author: and similar
+// lines, and the names listed in the rules; a name found once is masked
+// everywhere in the text, and so is its surname.
+pub fn redact(text: &str, rules: &Rules) -> Redacted {
+    let (mut texts, redactions) = redact_all(&[text], rules);
+    Redacted { text: texts.remove(0), redactions }
+}
+
+// Redacts several texts about the same document together, so a name found in
+// one is masked in all of them
+pub fn redact_all(texts: &[&str], rules: &Rules) -> (Vec<String>, Vec<Redaction>) {
+    let mut pass = Pass { redactions: Vec::new() };
+    let mut patterns = vec![(Regex::new(EMAIL).unwrap(), Kind::Email)];
+    patterns.extend(IDENTIFIERS.iter().map(|pattern| (Regex::new(pattern).unwrap(), Kind::Identifier)));
+    patterns.extend(rules.patterns.iter().map(|pattern| (pattern.clone(), Kind::Identifier)));
+    patterns.push((Regex::new(PHONE).unwrap(), Kind::Phone));
+    let mut texts: Vec<String> = texts.iter()
+        .map(|text| patterns.iter().fold(text.to_string(), |text, (pattern, kind)| pass.replace(&text, pattern, *kind)))
+        .collect();
+
+    let name = Regex::new(NAME).unwrap();
+    let titled = Regex::new(TITLED_NAME).unwrap();
+    let name_line = Regex::new(NAME_LINE).unwrap();
+    let mut names: Vec<String> = rules.names.iter().map(|name| name.trim().to_string()).collect();
+    for text in &texts {
+        names.extend(titled.captures_iter(text).map(|captures| captures[1].to_string()));
+        for captures in name_line.captures_iter(text) {
+            names.extend(captures[1]
+                .split([',', ';', '&'])
+                .flat_map(|part| part.split(" and "))
+                .map(|part| part.trim().trim_end_matches('.').to_string())
+                .filter(|part| name.is_match(part)));
+        }
+    }
+    // A surname on its own ("Miller said") refers back to the full name
+    let surnames: Vec<String> = names.iter()
+        .filter_map(|name| name.rsplit_once(' ').map(|(_, surname)| surname.to_string()))
+        .filter(|surname| surname.chars().count() > 2)
+        .collect();
+    names.extend(surnames);
+    // Longest first, so a full name isn't left half masked by its first word
+    names.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then_with(|| a.cmp(b)));
+    names.dedup();
+    for found in names {
+        let pattern = Regex::new(&format!(r"\b{}\b", regex::escape(&found))).unwrap();
+        for text in &mut texts {
+            *text = pass.replace(text, &pattern, Kind::Name);
+        }
+    }
+
+    (texts, pass.redactions)
+}
+
+// Puts the originals back in place of their placeholders
+pub fn restore(text: &str, redactions: &[Redaction]) -> String {
+    let mut restored = text.to_string();
+    for redaction in redactions {
+        restored = restored.replace(&redaction.placeholder, &redaction.original);
+    }
+    restored
+}
+
+// Keeps the mapping for every citation of the olog whose text uses one of
+// the placeholders. Prompts, the search index and export-ndjson carry the
+// redacted text; local views and the other exports put the originals back
+// with `restore_olog`.
+pub fn record_redactions(conn: &Connection, olog: &Olog, redactions: &[Redaction]) -> rusqlite::Result<()> {
+    let mut insert = conn.prepare(
+        "INSERT OR IGNORE INTO Redactions (citation_id, placeholder, original, kind) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for citation in distinct_citations(olog) {
+        for redaction in redactions.iter().filter(|redaction| citation.text.contains(&redaction.placeholder)) {
+            insert.execute(params![citation.id.to_string(), redaction.placeholder, redaction.original, redaction.kind.name()])?;
+        }
+    }
+    Ok(())
+}
+
+// Every redaction recorded for the olog's citations
+pub fn read_redactions(conn: &Connection, olog: &Olog) -> rusqlite::Result<Vec<Redaction>> {
+    let mut stmt = conn.prepare("SELECT placeholder, original, kind FROM Redactions WHERE citation_id = ?1 ORDER BY rowid")?;
+    let mut redactions: HashMap<String, Redaction> = HashMap::new();
+    for citation in distinct_citations(olog) {
+        let rows = stmt.query_map(params![citation.id.to_string()], |row| {
+            let kind: String = row.get(2)?;
+            Ok(Redaction {
+                placeholder: row.get(0)?,
+                original: row.get(1)?,
+                kind: Kind::parse(&kind).ok_or_else(|| unknown_value(2, &kind))?,
+            })
+        })?;
+        for redaction in rows {
+            let redaction = redaction?;
+            redactions.entry(redaction.placeholder.clone()).or_insert(redaction);
+        }
+    }
+    Ok(redactions.into_values().collect())
+}
+
+// The olog as its sources wrote it, for showing locally: labels, citation
+// titles and citation text with the originals put back
+pub fn restore_olog(conn: &Connection, olog: &mut Olog) -> rusqlite::Result<()> {
+    let redactions = read_redactions(conn, olog)?;
+    let redactions = redactions.as_slice();
+    for node in &mut olog.nodes {
+        node.label = restore(&node.label, redactions);
+    }
+    for hyperedge in &mut olog.hyperedges {
+        hyperedge.label = restore(&hyperedge.label, redactions);
+        for node in hyperedge.source.iter_mut().chain(hyperedge.target.iter_mut()) {
+            node.label = restore(&node.label, redactions);
+        }
+        for citation in &mut hyperedge.citations {
+            citation.title = restore(&citation.title, redactions);
+            citation.label = restore(&citation.label, redactions);
+            citation.text = restore(&citation.text, redactions);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use rusqlite::{params, Connection};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::error::OlogError;
+use crate::provider::LlmProvider;
+
+#[derive(Debug, Clone)]
+pub struct ReembedOptions {
+    pub model: String,
+    pub batch_size: usize,
+    pub requests_per_minute: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ReembedReport {
+    pub embedded: usize,
+    // Nodes already embedded with this model under their current label
+    pub up_to_date: usize,
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn decode(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+}
+
+// Nodes in the namespace, or in one olog, whose stored embedding is missing,
+// from another model, or of a label that has since changed
+fn pending_nodes(conn: &Connection, olog_id: Option<Uuid>, model: &str) -> rusqlite::Result<(Vec<(String, String)>, usize)> {
+    let scope = "FROM Nodes
+         JOIN Ologs ON Ologs.olog_id = Nodes.olog_id
+         LEFT JOIN Embeddings ON Embeddings.node_id = Nodes.node_id
+         WHERE Ologs.namespace = (SELECT namespace FROM temp.Session)
+           AND (?2 IS NULL OR Nodes.olog_id = ?2)";
+    let olog_id = olog_id.map(|id| id.to_string());
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT Nodes.node_id, Nodes.label {}
+           AND (Embeddings.node_id IS NULL OR Embeddings.model != ?1 OR Embeddings.label != Nodes.label)
+         ORDER BY Nodes.node_id",
+        scope
+    ))?;
+    let pending = stmt
+        .query_map(params![model, olog_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let total: i64 = conn.query_row(&format!("SELECT COUNT(*) {}", scope), params![model, olog_id], |row| row.get(0))?;
+    let up_to_date = total as usize - pending.len();
+    Ok((pending, up_to_date))
+}
+
+// Embeds node labels in batches, committing each batch as it completes so an
+// interrupted job picks up where it stopped when run again. Requests are
+// spaced to stay under `requests_per_minute`; each is retried as configured
+// with retry::set_max_attempts. `on_batch` gets the number of
+// nodes embedded so far and the number that needed it.
+pub fn reembed_nodes(
+    conn: &Connection,
+    provider: &dyn LlmProvider,
+    olog_id: Option<Uuid>,
+    options: &ReembedOptions,
+    mut on_batch: impl FnMut(usize, usize),
+) -> Result<ReembedReport, OlogError> {
+    let (pending, up_to_date) = pending_nodes(conn, olog_id, &options.model)?;
+    let interval = Duration::from_secs(60) / options.requests_per_minute.max(1);
+    let mut report = ReembedReport { embedded: 0, up_to_date };
+    let mut last_request: Option<Instant> = None;
+
+    for batch in pending.chunks(options.batch_size.max(1)) {
+        if let Some(elapsed) = last_request.map(|at| at.elapsed()) {
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        last_request = Some(Instant::now());
+
+        let labels: Vec<String> = batch.iter().map(|(_, label)| label.clone()).collect();
+        let vectors = provider.embed(&options.model, &labels).map_err(|e| {
+            e.context(format!(
+                "Stopped after embedding {} of {} nodes (run the command again to resume)",
+                report.embedded, pending.len()
+            ))
+        })?;
+
+        let tx = conn.unchecked_transaction()?;
+        for ((node_id, label), vector) in batch.iter().zip(&vectors) {
+            tx.execute(
+                "INSERT INTO Embeddings (node_id, model, label, vector, embedded_at)
+                 VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+                 ON CONFLICT(node_id) DO UPDATE SET
+                     model = excluded.model, label = excluded.label, vector = excluded.vector, embedded_at = excluded.embedded_at",
+                params![node_id, options.model, label, encode(vector)],
+            )?;
+        }
+        tx.commit()?;
+
+        report.embedded += batch.len();
+        on_batch(report.embedded, pending.len());
+    }
+    Ok(report)
+}
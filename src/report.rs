@@ -0,0 +1,179 @@
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::db::{list_ologs, parse_id, read_olog_from_db};
+use crate::olog::Hyperedge;
+use crate::split::edge_sentence;
+use crate::unify::node_aliases;
+
+// Co-occurring concepts listed at the end of a report
+const RELATED_CONCEPTS: usize = 15;
+
+// A hyperedge involving the concept, with the olog it was found in
+#[derive(Debug)]
+pub struct Mention {
+    pub olog_id: Uuid,
+    pub olog_title: String,
+    pub hyperedge: Hyperedge,
+}
+
+#[derive(Debug)]
+pub struct ConceptReport {
+    // The first matching node label, or the query when nothing matched
+    pub label: String,
+    // Node labels and aliases that matched, as spelled in the ologs
+    pub names: Vec<String>,
+    // Every node the concept matched, in any olog
+    pub node_ids: Vec<Uuid>,
+    pub mentions: Vec<Mention>,
+}
+
+// Collects every hyperedge touching a node named `label`, or carrying it as an
+// alias, across the namespace. Ologs superseded by a newer version are left
+// out so the same document isn't counted twice.
+pub fn gather(conn: &Connection, label: &str) -> rusqlite::Result<ConceptReport> {
+    let wanted = normalize(label);
+    let mut names: Vec<String> = Vec::new();
+    let mut node_ids: Vec<Uuid> = Vec::new();
+    let mut mentions = Vec::new();
+    for summary in list_ologs(conn)? {
+        let olog_id = parse_id(0, &summary.id)?;
+        if superseded(conn, olog_id)? {
+            continue;
+        }
+        let olog = read_olog_from_db(conn, olog_id)?;
+        let first = node_ids.len();
+        for node in &olog.nodes {
+            let aliases = node_aliases(conn, node.id)?;
+            let matching: Vec<&String> = std::iter::once(&node.label).chain(&aliases)
+                .filter(|name| normalize(name) == wanted)
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+            node_ids.push(node.id);
+            for name in matching {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        for hyperedge in &olog.hyperedges {
+            if hyperedge.source.iter().chain(&hyperedge.target).any(|node| node_ids[first..].contains(&node.id)) {
+                mentions.push(Mention { olog_id, olog_title: olog.title.clone(), hyperedge: hyperedge.clone() });
+            }
+        }
+    }
+    let label = names.first().cloned().unwrap_or_else(|| label.split_whitespace().collect::<Vec<_>>().join(" "));
+    Ok(ConceptReport { label, names, node_ids, mentions })
+}
+
+fn superseded(conn: &Connection, olog_id: Uuid) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM Olog_Versions WHERE parent_olog_id = ?1",
+        params![olog_id.to_string()],
+        |row| row.get(0),
+    )
+}
+
+fn normalize(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// Markdown dossier: one section per relation, subdivided by the document each
+// hyperedge cites, then the documents and the concepts that most often appear
+// alongside this one
+pub fn to_markdown(report: &ConceptReport) -> String {
+    let mut out = format!("# {}\n\n", report.label);
+
+    let mut documents: BTreeMap<String, usize> = BTreeMap::new();
+    for mention in &report.mentions {
+        for title in document_titles(&mention.hyperedge) {
+            *documents.entry(title).or_default() += 1;
+        }
+    }
+    let ologs = {
+        let mut ids: Vec<Uuid> = report.mentions.iter().map(|mention| mention.olog_id).collect();
+        ids.sort();
+        ids.dedup();
+        ids.len()
+    };
+    out.push_str(&format!(
+        "Appears in {} hyperedge{} from {} document{} across {} olog{}.\n",
+        report.mentions.len(), plural(report.mentions.len()),
+        documents.len(), plural(documents.len()),
+        ologs, plural(ologs)
+    ));
+    let others: Vec<&str> = report.names.iter().map(String::as_str).filter(|name| *name != report.label).collect();
+    if !others.is_empty() {
+        out.push_str(&format!("Also written as: {}.\n", others.join(", ")));
+    }
+
+    // Relations keyed case-insensitively, in order of how often they occur
+    let mut relations: Vec<(String, Vec<&Mention>)> = Vec::new();
+    for mention in &report.mentions {
+        let key = normalize(&mention.hyperedge.label);
+        match relations.iter_mut().find(|(relation, _)| normalize(relation) == key) {
+            Some((_, mentions)) => mentions.push(mention),
+            None => relations.push((mention.hyperedge.label.trim().to_string(), vec![mention])),
+        }
+    }
+    relations.sort_by(|(a, a_mentions), (b, b_mentions)| b_mentions.len().cmp(&a_mentions.len()).then_with(|| a.cmp(b)));
+
+    for (relation, mentions) in &relations {
+        out.push_str(&format!("\n## {} ({})\n", relation, mentions.len()));
+        let mut by_document: BTreeMap<String, Vec<&Mention>> = BTreeMap::new();
+        for &mention in mentions {
+            for title in document_titles(&mention.hyperedge) {
+                by_document.entry(title).or_default().push(mention);
+            }
+        }
+        for (title, mentions) in by_document {
+            out.push_str(&format!("\n### {}\n\n", title));
+            for mention in mentions {
+                let quantity = mention.hyperedge.quantity.as_ref().map(|quantity| format!(" ({})", quantity)).unwrap_or_default();
+                out.push_str(&format!("- {}{} — *{}*\n", edge_sentence(&mention.hyperedge), quantity, mention.olog_title));
+            }
+        }
+    }
+
+    if !documents.is_empty() {
+        out.push_str("\n## Sources\n\n");
+        for (title, count) in &documents {
+            out.push_str(&format!("- {} ({} hyperedge{})\n", title, count, plural(*count)));
+        }
+    }
+
+    let mut related: Vec<(String, usize)> = Vec::new();
+    for mention in &report.mentions {
+        for node in mention.hyperedge.source.iter().chain(&mention.hyperedge.target).filter(|node| !report.node_ids.contains(&node.id)) {
+            match related.iter_mut().find(|(label, _)| normalize(label) == normalize(&node.label)) {
+                Some((_, count)) => *count += 1,
+                None => related.push((node.label.clone(), 1)),
+            }
+        }
+    }
+    related.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    if !related.is_empty() {
+        out.push_str("\n## Related concepts\n\n");
+        for (label, count) in related.iter().take(RELATED_CONCEPTS) {
+            out.push_str(&format!("- {} ({})\n", label, count));
+        }
+    }
+    out
+}
+
+fn document_titles(hyperedge: &Hyperedge) -> Vec<String> {
+    let mut titles: Vec<String> = hyperedge.citations.iter().map(|citation| citation.title.trim().to_string()).collect();
+    titles.sort();
+    titles.dedup();
+    if titles.is_empty() {
+        titles.push("Uncited".to_string());
+    }
+    titles
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
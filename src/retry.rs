@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::error::OlogError;
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+// First waits before a retry; each further retry doubles the wait up to MAX_DELAY
+const TRANSIENT_DELAY: Duration = Duration::from_secs(1);
+const RATE_LIMIT_DELAY: Duration = Duration::from_secs(5);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+static MAX_ATTEMPTS: AtomicU32 = AtomicU32::new(DEFAULT_MAX_ATTEMPTS);
+
+pub fn set_max_attempts(attempts: u32) {
+    MAX_ATTEMPTS.store(attempts.max(1), Ordering::Relaxed);
+}
+
+enum Failure {
+    // 429; the server may say how long to wait
+    RateLimited(Option<Duration>),
+    // 5xx, timeouts and dropped connections
+    Transient,
+    // Anything a retry won't fix: bad key, bad request, malformed reply
+    Permanent,
+}
+
+fn classify(error: &OlogError) -> Failure {
+    let OlogError::Network(error) = error else {
+        return Failure::Permanent;
+    };
+    match error.as_ref() {
+        ureq::Error::Status(429, response) => Failure::RateLimited(
+            response.header("retry-after").and_then(|seconds| seconds.trim().parse().ok()).map(Duration::from_secs),
+        ),
+        ureq::Error::Status(408 | 500..=599, _) | ureq::Error::Transport(_) => Failure::Transient,
+        _ => Failure::Permanent,
+    }
+}
+
+// Somewhere between half and all of the delay, so parallel runs that failed
+// together don't retry together
+fn jitter(delay: Duration) -> Duration {
+    let fraction = (Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+    delay.mul_f64(0.5 + fraction / 2.0)
+}
+
+// Runs `call` until it succeeds, fails in a way a retry won't fix, or has
+// been tried the configured number of times. `what` names the call in the
+// messages printed before each retry.
+pub fn with_retries<T>(what: &str, mut call: impl FnMut() -> Result<T, OlogError>) -> Result<T, OlogError> {
+    let max_attempts = MAX_ATTEMPTS.load(Ordering::Relaxed);
+    let mut attempt = 1;
+    loop {
+        let error = match call() {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        if attempt >= max_attempts {
+            return Err(error);
+        }
+        let (reason, delay) = match classify(&error) {
+            Failure::Permanent => return Err(error),
+            Failure::RateLimited(Some(wait)) => ("rate limited", wait.min(MAX_DELAY)),
+            Failure::RateLimited(None) => ("rate limited", jitter(backoff(RATE_LIMIT_DELAY, attempt))),
+            Failure::Transient => ("failed", jitter(backoff(TRANSIENT_DELAY, attempt))),
+        };
+        eprintln!(
+            "{} {} ({}); retrying in {:.1}s (attempt {}/{})",
+            what, reason, error, delay.as_secs_f64(), attempt + 1, max_attempts
+        );
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+fn backoff(first: Duration, attempt: u32) -> Duration {
+    first.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_DELAY)
+}
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::error::OlogError;
+use crate::olog::JsonOlogSchema;
+
+// Stops a script stuck in a loop; a pass over a large olog takes far fewer
+#[cfg(feature = "scripting")]
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+// Runs each Rhai script in turn over the generated olog. A script sees the
+// olog JSON as the map `olog` and whatever it leaves there is kept, so it can
+// rename labels, drop nodes or hyperedges, or fill in quantities. A hyperedge
+// whose sources or targets were all dropped goes with them.
+pub fn run_scripts(mut olog: JsonOlogSchema, scripts: &[PathBuf]) -> Result<JsonOlogSchema, OlogError> {
+    if scripts.is_empty() {
+        return Ok(olog);
+    }
+    for script in scripts {
+        olog = run_script(olog, script)?;
+    }
+    let node_ids: HashSet<&str> = olog.nodes.iter().map(|node| node.id.as_str()).collect();
+    let kept = |ids: &[String]| ids.iter().any(|id| node_ids.contains(id.as_str()));
+    let hyperedges = olog.hyperedges.into_iter()
+        .filter(|hyperedge| kept(&hyperedge.sources) && kept(&hyperedge.targets))
+        .collect();
+    Ok(JsonOlogSchema { hyperedges, ..olog })
+}
+
+#[cfg(feature = "scripting")]
+fn run_script(olog: JsonOlogSchema, script: &Path) -> Result<JsonOlogSchema, OlogError> {
+    use rhai::{Dynamic, Engine, Scope};
+
+    let failed = |e: Box<rhai::EvalAltResult>| OlogError::Other(format!("Script {} failed: {}", script.display(), e));
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    // Standard output may be carrying the olog itself
+    engine.on_print(|text| eprintln!("{}", text));
+
+    let ast = engine.compile_file(script.to_path_buf()).map_err(failed)?;
+    let mut scope = Scope::new();
+    scope.push("olog", rhai::serde::to_dynamic(&olog).map_err(failed)?);
+    engine.run_ast_with_scope(&mut scope, &ast).map_err(failed)?;
+    let olog = scope.get_value::<Dynamic>("olog")
+        .ok_or_else(|| OlogError::Other(format!("Script {} removed `olog`", script.display())))?;
+    rhai::serde::from_dynamic(&olog)
+        .map_err(|e| OlogError::Schema(format!("Script {} left an invalid olog: {}", script.display(), e)))
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_script(_olog: JsonOlogSchema, script: &Path) -> Result<JsonOlogSchema, OlogError> {
+    Err(OlogError::Config(format!(
+        "Cannot run {}: this olog was built without scripting (cargo build --features scripting)",
+        script.display()
+    )))
+}
@@ -0,0 +1,181 @@
+use rusqlite::{params, Connection, Result};
+use uuid::Uuid;
+
+use crate::db::{citation_text, has_table, parse_id, unknown_value};
+use crate::olog::{distinct_citations, Olog};
+
+// Words of context around each match in a snippet
+const SNIPPET_TOKENS: i64 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Node,
+    Hyperedge,
+    Citation,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Node => "node",
+            Kind::Hyperedge => "hyperedge",
+            Kind::Citation => "citation",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Kind> {
+        [Kind::Node, Kind::Hyperedge, Kind::Citation].into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Hit {
+    pub kind: Kind,
+    pub item_id: String,
+    pub snippet: String,
+}
+
+// Matches grouped under the olog they belong to, best olog first. A citation
+// shared by several ologs is listed under each.
+#[derive(Debug)]
+pub struct OlogHits {
+    pub olog_id: Uuid,
+    pub title: String,
+    pub hits: Vec<Hit>,
+}
+
+// Node and hyperedge rows carry their olog; citation rows are shared and are
+// tied to ologs through Citation_Links when searching
+pub(crate) fn create_index(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS Search_Index USING fts5(
+            text,
+            kind UNINDEXED,
+            item_id UNINDEXED,
+            olog_id UNINDEXED,
+            tokenize = 'porter unicode61'
+        )",
+        [],
+    )?;
+    reindex(conn).map(|_| ())
+}
+
+// Refreshes an olog's node and hyperedge rows and adds any citations not yet
+// indexed; called whenever an olog is written
+pub fn index_olog(conn: &Connection, olog: &Olog) -> Result<()> {
+    let olog_id = olog.id.to_string();
+    conn.execute("DELETE FROM Search_Index WHERE olog_id = ?1", params![olog_id])?;
+    let mut insert = conn.prepare("INSERT INTO Search_Index (text, kind, item_id, olog_id) VALUES (?1, ?2, ?3, ?4)")?;
+    for node in &olog.nodes {
+        insert.execute(params![node.label, Kind::Node.name(), node.id.to_string(), olog_id])?;
+    }
+    for hyperedge in &olog.hyperedges {
+        insert.execute(params![hyperedge.label, Kind::Hyperedge.name(), hyperedge.id.to_string(), olog_id])?;
+    }
+    for citation in distinct_citations(olog) {
+        let id = citation.id.to_string();
+        let indexed: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM Search_Index WHERE kind = ?1 AND item_id = ?2",
+            params![Kind::Citation.name(), id],
+            |row| row.get(0),
+        )?;
+        if !indexed {
+            let text = format!("{}\n{}", citation.title.trim(), citation.text);
+            insert.execute(params![text, Kind::Citation.name(), id, None::<String>])?;
+        }
+    }
+    Ok(())
+}
+
+// Drops an olog's rows and those of citations that no longer exist
+pub fn unindex_olog(conn: &Connection, olog_id: Uuid) -> Result<()> {
+    conn.execute("DELETE FROM Search_Index WHERE olog_id = ?1", params![olog_id.to_string()])?;
+    conn.execute(
+        "DELETE FROM Search_Index WHERE kind = ?1 AND item_id NOT IN (SELECT citation_id FROM Citations)",
+        params![Kind::Citation.name()],
+    )?;
+    Ok(())
+}
+
+// Rebuilds the whole index from the stored rows, for databases changed
+// outside olog, such as by loading a dump; returns the rows indexed
+pub fn reindex(conn: &Connection) -> Result<usize> {
+    conn.execute("DELETE FROM Search_Index", [])?;
+    let mut indexed = conn.execute(
+        "INSERT INTO Search_Index (text, kind, item_id, olog_id) SELECT label, ?1, node_id, olog_id FROM Nodes",
+        params![Kind::Node.name()],
+    )?;
+    indexed += conn.execute(
+        "INSERT INTO Search_Index (text, kind, item_id, olog_id) SELECT label, ?1, hyperedge_id, olog_id FROM Hyperedges",
+        params![Kind::Hyperedge.name()],
+    )?;
+    // Citation text may be compressed, so it goes through Rust. The migration
+    // that first builds the index runs before citation text moved to Documents.
+    let mut stmt = conn.prepare(if has_table(conn, "Documents")? {
+        "SELECT citation_id, title, COALESCE(Citations.text, Documents.text)
+         FROM Citations LEFT JOIN Documents ON Documents.document_id = Citations.document_id"
+    } else {
+        "SELECT citation_id, title, text FROM Citations"
+    })?;
+    let citations = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, citation_text(row, 2)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let mut insert = conn.prepare("INSERT INTO Search_Index (text, kind, item_id, olog_id) VALUES (?1, ?2, ?3, NULL)")?;
+    for (id, title, text) in citations {
+        let text = format!("{}\n{}", title.unwrap_or_default().trim(), text.unwrap_or_default());
+        indexed += insert.execute(params![text, Kind::Citation.name(), id])?;
+    }
+    Ok(indexed)
+}
+
+// Turns plain words into an FTS5 query that matches all of them, so
+// punctuation in the query can't be misread as FTS5 syntax
+pub fn plain_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Best matches first, at most `limit` of them, in the current namespace.
+// Matched words are wrapped in `open` and `close`.
+pub fn search(conn: &Connection, query: &str, limit: usize, open: &str, close: &str) -> Result<Vec<OlogHits>> {
+    let mut stmt = conn.prepare(
+        "SELECT Search_Index.kind, Search_Index.item_id, Ologs.olog_id, Ologs.title,
+                snippet(Search_Index, 0, ?2, ?3, '…', ?4)
+         FROM Search_Index
+         JOIN Ologs ON Ologs.olog_id = Search_Index.olog_id
+            OR (Search_Index.olog_id IS NULL AND Ologs.olog_id IN (
+                SELECT Hyperedges.olog_id FROM Citation_Links
+                JOIN Hyperedges ON Hyperedges.hyperedge_id = Citation_Links.hyperedge_id
+                WHERE Citation_Links.citation_id = Search_Index.item_id))
+         WHERE Search_Index MATCH ?1
+           AND Ologs.namespace = (SELECT namespace FROM temp.Session)
+         ORDER BY rank
+         LIMIT ?5",
+    )?;
+    let rows = stmt.query_map(params![query, open, close, SNIPPET_TOKENS, limit as i64], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+
+    let mut grouped: Vec<OlogHits> = Vec::new();
+    for row in rows {
+        let (kind, item_id, olog_id, title, snippet) = row?;
+        let kind = Kind::parse(&kind).ok_or_else(|| unknown_value(0, &kind))?;
+        let olog_id = parse_id(2, &olog_id)?;
+        let hit = Hit { kind, item_id, snippet: snippet.split_whitespace().collect::<Vec<_>>().join(" ") };
+        match grouped.iter_mut().find(|group| group.olog_id == olog_id) {
+            Some(group) => group.hits.push(hit),
+            None => grouped.push(OlogHits { olog_id, title, hits: vec![hit] }),
+        }
+    }
+    Ok(grouped)
+}
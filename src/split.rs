@@ -0,0 +1,179 @@
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::embedding::cosine_similarity;
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{Hyperedge, Node, Olog};
+
+// Nodes taking part in fewer hyperedges are never considered overloaded
+const MIN_EDGES: usize = 4;
+// Smaller groups are folded into the closest larger one
+const MIN_GROUP_EDGES: usize = 2;
+
+#[derive(Debug)]
+pub struct Overload {
+    pub node: Node,
+    // Indices into the olog's hyperedges, one list per concept, largest first
+    pub groups: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelResponse {
+    labels: Vec<String>,
+}
+
+// Clusters the hyperedges around each busy node by embedding similarity; nodes
+// whose hyperedges fall into two or more separate groups are reported. Groups
+// keep merging while their average similarity is at least `threshold`.
+pub fn find_overloaded(provider: &dyn LlmProvider, olog: &Olog, embedding_model: &str, threshold: f32, only: Option<Uuid>) -> Result<Vec<Overload>, OlogError> {
+    let mut incident: HashMap<Uuid, Vec<usize>> = HashMap::new();
+    for (index, hyperedge) in olog.hyperedges.iter().enumerate() {
+        let mut ids: Vec<Uuid> = hyperedge.source.iter().chain(&hyperedge.target).map(|node| node.id).collect();
+        ids.sort();
+        ids.dedup();
+        for id in ids {
+            incident.entry(id).or_default().push(index);
+        }
+    }
+
+    let candidates: Vec<&Node> = olog.nodes.iter()
+        .filter(|node| only.is_none_or(|id| id == node.id))
+        .filter(|node| incident.get(&node.id).map_or(0, Vec::len) >= MIN_EDGES)
+        .collect();
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Each hyperedge is embedded once, however many candidates it touches
+    let mut needed: Vec<usize> = candidates.iter().flat_map(|node| incident[&node.id].iter().copied()).collect();
+    needed.sort();
+    needed.dedup();
+    let texts: Vec<String> = needed.iter().map(|&index| edge_sentence(&olog.hyperedges[index])).collect();
+    let vectors: HashMap<usize, Vec<f32>> = needed.into_iter().zip(provider.embed(embedding_model, &texts)?).collect();
+
+    Ok(candidates.into_iter()
+        .filter_map(|node| {
+            let groups = cluster(&incident[&node.id], &vectors, threshold);
+            (groups.len() >= 2).then(|| Overload { node: node.clone(), groups })
+        })
+        .collect())
+}
+
+pub fn edge_sentence(hyperedge: &Hyperedge) -> String {
+    let join = |nodes: &[Node]| nodes.iter().map(|node| node.label.as_str()).collect::<Vec<_>>().join(" and ");
+    format!("{} {} {}", join(&hyperedge.source), hyperedge.label, join(&hyperedge.target))
+}
+
+// Average-linkage agglomerative clustering over cosine similarity
+fn cluster(edges: &[usize], vectors: &HashMap<usize, Vec<f32>>, threshold: f32) -> Vec<Vec<usize>> {
+    let similarity: Vec<Vec<f32>> = edges.iter()
+        .map(|a| edges.iter().map(|b| cosine_similarity(&vectors[a], &vectors[b])).collect())
+        .collect();
+    let linkage = |a: &[usize], b: &[usize]| {
+        let mut total = 0.0;
+        for &i in a {
+            for &j in b {
+                total += similarity[i][j];
+            }
+        }
+        total / (a.len() * b.len()) as f32
+    };
+
+    let mut groups: Vec<Vec<usize>> = (0..edges.len()).map(|i| vec![i]).collect();
+    loop {
+        let mut best: Option<(f32, usize, usize)> = None;
+        for a in 0..groups.len() {
+            for b in a + 1..groups.len() {
+                let score = linkage(&groups[a], &groups[b]);
+                if best.is_none_or(|(top, _, _)| score > top) {
+                    best = Some((score, a, b));
+                }
+            }
+        }
+        match best {
+            Some((score, a, b)) if score >= threshold => {
+                let merged = groups.swap_remove(b);
+                groups[a].extend(merged);
+            },
+            _ => break,
+        }
+    }
+
+    let (mut kept, strays): (Vec<_>, Vec<_>) = groups.into_iter().partition(|group| group.len() >= MIN_GROUP_EDGES);
+    if kept.len() < 2 {
+        return Vec::new();
+    }
+    for stray in strays.into_iter().flatten() {
+        let nearest = (0..kept.len())
+            .max_by(|&a, &b| linkage(&[stray], &kept[a]).total_cmp(&linkage(&[stray], &kept[b])))
+            .expect("at least two groups are kept");
+        kept[nearest].push(stray);
+    }
+
+    kept.sort_by_key(|group| Reverse(group.len()));
+    kept.into_iter()
+        .map(|group| {
+            let mut group: Vec<usize> = group.into_iter().map(|i| edges[i]).collect();
+            group.sort();
+            group
+        })
+        .collect()
+}
+
+// One label per group, in order
+pub fn name_concepts(provider: &dyn LlmProvider, olog: &Olog, overload: &Overload, model: &str) -> Result<Vec<String>, OlogError> {
+    let listing = overload.groups.iter().enumerate()
+        .map(|(i, group)| {
+            let sentences = group.iter()
+                .map(|&index| format!("  - {}", edge_sentence(&olog.hyperedges[index])))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("Group {}:\n{}", i, sentences)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = format!(
+        "In an ontology log, the node \"{}\" is used for several unrelated concepts. Each group below lists the \
+         relations one of those concepts takes part in. Give every group a short noun phrase that names its concept \
+         precisely and tells it apart from the others, written like an olog node label (for example \"a language \
+         model\" or \"a model of the data\"). Respond with JSON of the form {{\"labels\": [\"...\"]}}, one label per \
+         group, in order.\n\n{}",
+        overload.node.label, listing
+    );
+    let response: LabelResponse = serde_json::from_str(&complete(provider, model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed label response: {}", e)))?;
+    if response.labels.len() != overload.groups.len() {
+        return Err(OlogError::Llm(format!("Asked for {} labels, got {}", overload.groups.len(), response.labels.len())));
+    }
+    Ok(response.labels)
+}
+
+// The largest group keeps the original node id under its new label; each
+// other group gets a new node and its hyperedges are relinked to it
+pub fn split_node(mut olog: Olog, overload: &Overload, labels: &[String]) -> (Olog, Vec<Node>) {
+    let mut concepts = Vec::new();
+    for (position, (group, label)) in overload.groups.iter().zip(labels).enumerate() {
+        let id = if position == 0 { overload.node.id } else { Uuid::new_v4() };
+        let concept = Node { id, label: label.clone() };
+        for &index in group {
+            let hyperedge = &mut olog.hyperedges[index];
+            for node in hyperedge.source.iter_mut().chain(hyperedge.target.iter_mut()) {
+                if node.id == overload.node.id {
+                    *node = concept.clone();
+                }
+            }
+        }
+        concepts.push(concept);
+    }
+
+    if let Some(node) = olog.nodes.iter_mut().find(|node| node.id == overload.node.id) {
+        *node = concepts[0].clone();
+    }
+    olog.nodes.extend(concepts[1..].iter().cloned());
+    (olog, concepts)
+}
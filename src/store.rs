@@ -0,0 +1,204 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::db::{citation_text, id_column, parse_id, span_columns};
+use crate::olog::{Citation, Hyperedge, Node, Quantity, Span};
+
+/// Rows fetched per query unless [`Store::with_page_size`] says otherwise
+pub const DEFAULT_PAGE_SIZE: usize = 500;
+
+/// Read access to a stored olog that pages through its nodes and hyperedges
+/// in id order, holding one page in memory at a time instead of the whole graph
+pub struct Store<'c> {
+    conn: &'c Connection,
+    page_size: usize,
+}
+
+/// Iterator over one olog's rows, fetched a page at a time. Each page starts
+/// after the last id of the previous one, so rows written while iterating
+/// are neither repeated nor skipped if their ids sort later.
+pub struct Paged<'c, T> {
+    conn: &'c Connection,
+    olog_id: String,
+    after: String,
+    page: VecDeque<T>,
+    page_size: usize,
+    exhausted: bool,
+    fetch: fn(&Connection, &str, &str, usize) -> Result<Vec<T>>,
+    key: fn(&T) -> Uuid,
+}
+
+pub type NodesIter<'c> = Paged<'c, Node>;
+pub type HyperedgeStream<'c> = Paged<'c, Hyperedge>;
+
+impl<'c> Store<'c> {
+    pub fn new(conn: &'c Connection) -> Self {
+        Store { conn, page_size: DEFAULT_PAGE_SIZE }
+    }
+
+    pub fn with_page_size(self, page_size: usize) -> Self {
+        Store { page_size: page_size.max(1), ..self }
+    }
+
+    /// Nodes of the olog in id order
+    pub fn nodes_iter(&self, olog_id: Uuid) -> NodesIter<'c> {
+        self.paged(olog_id, fetch_nodes, |node| node.id)
+    }
+
+    /// Hyperedges of the olog in id order, each with its endpoints, roles,
+    /// citations and quantity. Citations come without their text, which can
+    /// be a whole paper shared by every hyperedge; [`Store::citation_text`]
+    /// loads it.
+    pub fn hyperedges_stream(&self, olog_id: Uuid) -> HyperedgeStream<'c> {
+        self.paged(olog_id, fetch_hyperedges, |hyperedge| hyperedge.id)
+    }
+
+    /// The text a citation quotes, or `None` for an unknown citation
+    pub fn citation_text(&self, citation_id: Uuid) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT COALESCE(c.text, d.text) FROM Citations AS c
+                 LEFT JOIN Documents AS d ON d.document_id = c.document_id
+                 WHERE c.citation_id = ?1",
+                params![citation_id.to_string()],
+                |row| citation_text(row, 0),
+            )
+            .optional()
+            .map(Option::flatten)
+    }
+
+    fn paged<T>(
+        &self,
+        olog_id: Uuid,
+        fetch: fn(&Connection, &str, &str, usize) -> Result<Vec<T>>,
+        key: fn(&T) -> Uuid,
+    ) -> Paged<'c, T> {
+        Paged {
+            conn: self.conn,
+            olog_id: olog_id.to_string(),
+            after: String::new(),
+            page: VecDeque::new(),
+            page_size: self.page_size,
+            exhausted: false,
+            fetch,
+            key,
+        }
+    }
+}
+
+impl<T> Iterator for Paged<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.exhausted {
+            match (self.fetch)(self.conn, &self.olog_id, &self.after, self.page_size) {
+                Ok(rows) => {
+                    self.exhausted = rows.len() < self.page_size;
+                    if let Some(last) = rows.last() {
+                        self.after = (self.key)(last).to_string();
+                    }
+                    self.page.extend(rows);
+                },
+                Err(e) => {
+                    self.exhausted = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+fn fetch_nodes(conn: &Connection, olog_id: &str, after: &str, limit: usize) -> Result<Vec<Node>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT node_id, label FROM Nodes WHERE olog_id = ?1 AND node_id > ?2 ORDER BY node_id LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(params![olog_id, after, limit as i64], |row| {
+        Ok(Node { id: id_column(row, 0)?, label: row.get(1)? })
+    })?;
+    rows.collect()
+}
+
+// Links and citations for the whole page come in one query each, over the
+// page's id range, rather than three queries per hyperedge
+fn fetch_hyperedges(conn: &Connection, olog_id: &str, after: &str, limit: usize) -> Result<Vec<Hyperedge>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT hyperedge_id, label, quantity_value, quantity_unit, quantity_uncertainty FROM Hyperedges
+         WHERE olog_id = ?1 AND hyperedge_id > ?2 ORDER BY hyperedge_id LIMIT ?3",
+    )?;
+    let rows = stmt
+        .query_map(params![olog_id, after, limit as i64], |row| {
+            let quantity = row.get::<_, Option<f64>>(2)?
+                .map(|value| -> Result<Quantity> { Ok(Quantity { value, unit: row.get(3)?, uncertainty: row.get(4)? }) })
+                .transpose()?;
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, quantity))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let Some((last, _, _)) = rows.last() else {
+        return Ok(Vec::new());
+    };
+    let range = params![olog_id, after, last];
+
+    type Endpoints = Vec<(Node, Option<String>)>;
+    let mut endpoints: HashMap<String, (Endpoints, Endpoints)> = HashMap::new();
+    let mut links = conn.prepare_cached(
+        "SELECT Hyperedge_Links.hyperedge_id, Hyperedge_Links.node_id, Nodes.label, Hyperedge_Links.role, Hyperedge_Links.type
+         FROM Hyperedge_Links
+         JOIN Hyperedges ON Hyperedges.hyperedge_id = Hyperedge_Links.hyperedge_id
+         JOIN Nodes ON Nodes.node_id = Hyperedge_Links.node_id
+         WHERE Hyperedges.olog_id = ?1 AND Hyperedges.hyperedge_id > ?2 AND Hyperedges.hyperedge_id <= ?3
+         ORDER BY Hyperedge_Links.position, Hyperedge_Links.rowid",
+    )?;
+    for row in links.query_map(range, |row| {
+        let node = Node { id: id_column(row, 1)?, label: row.get(2)? };
+        Ok((row.get::<_, String>(0)?, node, row.get::<_, Option<String>>(3)?, row.get::<_, String>(4)?))
+    })? {
+        let (hyperedge_id, node, role, kind) = row?;
+        let (sources, targets) = endpoints.entry(hyperedge_id).or_default();
+        match kind.as_str() {
+            "source" => sources.push((node, role)),
+            "target" => targets.push((node, role)),
+            _ => {},
+        }
+    }
+
+    let mut cited: HashMap<String, Vec<(Citation, Option<Span>)>> = HashMap::new();
+    let mut citations = conn.prepare_cached(
+        "SELECT cl.hyperedge_id, c.citation_id, c.title, c.label, cl.span_start, cl.span_end
+         FROM Citation_Links AS cl
+         JOIN Hyperedges ON Hyperedges.hyperedge_id = cl.hyperedge_id
+         JOIN Citations AS c ON c.citation_id = cl.citation_id
+         WHERE Hyperedges.olog_id = ?1 AND Hyperedges.hyperedge_id > ?2 AND Hyperedges.hyperedge_id <= ?3
+         ORDER BY cl.rowid",
+    )?;
+    for row in citations.query_map(range, |row| {
+        let citation = Citation { id: id_column(row, 1)?, title: row.get(2)?, label: row.get(3)?, text: String::new() };
+        let span = span_columns(row, 4)?.map(|(start, end)| Span { citation_id: citation.id, start, end });
+        Ok((row.get::<_, String>(0)?, citation, span))
+    })? {
+        let (hyperedge_id, citation, span) = row?;
+        cited.entry(hyperedge_id).or_default().push((citation, span));
+    }
+
+    rows.into_iter()
+        .map(|(hyperedge_id, label, quantity)| {
+            let (source, target) = endpoints.remove(&hyperedge_id).unwrap_or_default();
+            let (source, source_roles) = source.into_iter().unzip();
+            let (target, target_roles) = target.into_iter().unzip();
+            let (citations, spans): (Vec<Citation>, Vec<Option<Span>>) =
+                cited.remove(&hyperedge_id).unwrap_or_default().into_iter().unzip();
+            Ok(Hyperedge {
+                id: parse_id(0, &hyperedge_id)?,
+                label,
+                source,
+                target,
+                source_roles,
+                target_roles,
+                citations,
+                quantity,
+                spans: spans.into_iter().flatten().collect(),
+            })
+        })
+        .collect()
+}
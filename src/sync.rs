@@ -0,0 +1,183 @@
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::BTreeSet;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::db::{
+    current_namespace, has_column, has_table, olog_in_namespace, read_olog_from_db, replace_olog_in_db, write_olog_to_db,
+};
+use crate::error::OlogError;
+use crate::gitrepo::Operation;
+use crate::olog::{merge_ologs, reassign_ids, Olog};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Resolution {
+    /// Report divergent ologs and leave them alone
+    Skip,
+    /// Merge the other database's version into the local olog
+    Merge,
+    /// Import the other database's version as a separate olog
+    KeepBoth,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub imported: Vec<(Uuid, String)>,
+    pub identical: usize,
+    pub divergent: Vec<(Uuid, String)>,
+    pub merged: Vec<Uuid>,
+    // (local olog, newly imported copy)
+    pub kept_both: Vec<(Uuid, Uuid)>,
+    // Present locally, but in another namespace
+    pub elsewhere: Vec<Uuid>,
+}
+
+// Pulls ologs from another olog.db into the local one. The other database is
+// only ever opened read-only.
+pub fn sync_databases(local: &Connection, other_path: &Path, resolution: Resolution) -> Result<SyncReport, OlogError> {
+    let other = Connection::open_with_flags(other_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut report = SyncReport::default();
+
+    // Only the current namespace is synced; ologs from databases that predate
+    // namespaces all belong to the default one
+    let namespace = current_namespace(local)?;
+    let query = if has_column(&other, "Ologs", "namespace")? {
+        "SELECT olog_id FROM Ologs WHERE namespace = ?1"
+    } else {
+        "SELECT olog_id FROM Ologs WHERE ?1 = 'default'"
+    };
+    let mut stmt = other.prepare(query)?;
+    let other_ids = stmt
+        .query_map(params![namespace], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for id in other_ids {
+        let olog_id = Uuid::parse_str(&id).map_err(|e| OlogError::Schema(format!("Malformed olog id {}: {}", id, e)))?;
+        let remote = read_olog_from_db(&other, olog_id)?;
+
+        let local_olog = match read_olog_from_db(local, olog_id).optional()? {
+            Some(_) if !olog_in_namespace(local, olog_id)? => {
+                report.elsewhere.push(olog_id);
+                continue;
+            },
+            Some(local_olog) => local_olog,
+            None => {
+                write_olog_to_db(local, &remote, Operation::Import)?;
+                copy_provenance(local, &other, olog_id)?;
+                report.imported.push((olog_id, remote.title));
+                continue;
+            }
+        };
+
+        if fingerprint(&local_olog) == fingerprint(&remote) {
+            report.identical += 1;
+            continue;
+        }
+
+        match resolution {
+            Resolution::Skip => report.divergent.push((olog_id, local_olog.title)),
+            Resolution::Merge => {
+                // Both sides may use the same node id for differently labelled nodes
+                let merged = merge_ologs(local_olog, reassign_ids(remote));
+                replace_olog_in_db(local, &merged, Operation::Merge)?;
+                report.merged.push(olog_id);
+            }
+            Resolution::KeepBoth => {
+                let copy = reassign_ids(remote);
+                write_olog_to_db(local, &copy, Operation::Import)?;
+                report.kept_both.push((olog_id, copy.id));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+type EdgeKey = (String, Vec<String>, Vec<String>);
+
+// Ids differ between independently merged copies, so compare by labels
+fn fingerprint(olog: &Olog) -> (BTreeSet<String>, BTreeSet<EdgeKey>) {
+    let nodes = olog.nodes.iter().map(|node| node.label.clone()).collect();
+    let edges = olog.hyperedges.iter().map(|hyperedge| {
+        let mut sources: Vec<String> = hyperedge.source.iter().map(|node| node.label.clone()).collect();
+        let mut targets: Vec<String> = hyperedge.target.iter().map(|node| node.label.clone()).collect();
+        sources.sort();
+        targets.sort();
+        (hyperedge.label.clone(), sources, targets)
+    }).collect();
+    (nodes, edges)
+}
+
+// Brings along ingestion and version records so duplicate detection keeps
+// working for imported ologs
+fn copy_provenance(local: &Connection, other: &Connection, olog_id: Uuid) -> rusqlite::Result<()> {
+    let olog_id = olog_id.to_string();
+
+    if has_table(other, "Ingestions")? {
+        let mut stmt = other.prepare("SELECT content_hash, doi, arxiv_id, ingested_at FROM Ingestions WHERE olog_id = ?1")?;
+        let rows = stmt.query_map(params![olog_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (content_hash, doi, arxiv_id, ingested_at) = row?;
+            local.execute(
+                "INSERT INTO Ingestions (olog_id, content_hash, doi, arxiv_id, ingested_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![olog_id, content_hash, doi, arxiv_id, ingested_at],
+            )?;
+        }
+    }
+
+    if has_table(other, "Olog_Versions")? {
+        let version = other.query_row(
+            "SELECT parent_olog_id, version FROM Olog_Versions WHERE olog_id = ?1",
+            params![olog_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        ).optional()?;
+        if let Some((parent_olog_id, version)) = version {
+            local.execute(
+                "INSERT OR IGNORE INTO Olog_Versions (olog_id, parent_olog_id, version) VALUES (?1, ?2, ?3)",
+                params![olog_id, parent_olog_id, version],
+            )?;
+        }
+    }
+
+    // Imports keep node ids, so provenance rows carry over as they are
+    if has_table(other, "Node_Provenance")? {
+        let mut stmt = other.prepare(
+            "SELECT node_id, citation_id, pass, section FROM Node_Provenance
+             WHERE node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)",
+        )?;
+        let rows = stmt.query_map(params![olog_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (node_id, citation_id, pass, section) = row?;
+            local.execute(
+                "INSERT OR IGNORE INTO Node_Provenance (node_id, citation_id, pass, section) VALUES (?1, ?2, ?3, ?4)",
+                params![node_id, citation_id, pass, section],
+            )?;
+        }
+    }
+    if has_table(other, "Node_Aliases")? {
+        let mut stmt = other.prepare(
+            "SELECT node_id, alias FROM Node_Aliases WHERE node_id IN (SELECT node_id FROM Nodes WHERE olog_id = ?1)",
+        )?;
+        let rows = stmt.query_map(params![olog_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (node_id, alias) = row?;
+            local.execute("INSERT OR IGNORE INTO Node_Aliases (node_id, alias) VALUES (?1, ?2)", params![node_id, alias])?;
+        }
+    }
+    Ok(())
+}
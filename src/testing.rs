@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::OlogError;
+use crate::provider::LlmProvider;
+
+/// A model call as the mock sees it
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub model: String,
+    pub prompt: String,
+    /// Whether the call asked for a JSON object
+    pub json: bool,
+}
+
+type LlmHandler = Box<dyn FnMut(&MockRequest) -> Result<String, String> + Send>;
+type EmbedHandler = Box<dyn FnMut(&str) -> Vec<f32> + Send>;
+
+/// A model provider that answers from a handler instead of an API; pass it
+/// wherever the library takes a `&dyn LlmProvider`. No API key is needed and
+/// its replies are never cached.
+pub struct MockLlmProvider {
+    handler: Mutex<LlmHandler>,
+    embedder: Mutex<EmbedHandler>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+// A handler that panicked leaves the mock usable for the calls after it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl MockLlmProvider {
+    /// Replies by calling `handler`; an `Err` becomes the call's error
+    pub fn new(handler: impl FnMut(&MockRequest) -> Result<String, String> + Send + 'static) -> Self {
+        MockLlmProvider {
+            handler: Mutex::new(Box::new(handler)),
+            embedder: Mutex::new(Box::new(letter_counts)),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Embeds each text with `embedder` instead of by its letter counts
+    pub fn with_embeddings(self, embedder: impl FnMut(&str) -> Vec<f32> + Send + 'static) -> Self {
+        MockLlmProvider { embedder: Mutex::new(Box::new(embedder)), ..self }
+    }
+
+    /// Replies with `responses` in order, then fails every further call
+    pub fn scripted<S: Into<String>>(responses: impl IntoIterator<Item = S>) -> Self {
+        let mut queue: VecDeque<String> = responses.into_iter().map(Into::into).collect();
+        Self::new(move |request| {
+            queue.pop_front().ok_or_else(|| format!("Mock model has no scripted reply left for {}", request.model))
+        })
+    }
+
+    /// Replies to JSON calls with `olog_json` and to the title and label calls
+    /// with `title`, which is enough for any number of generation passes
+    pub fn generating(olog_json: impl Into<String>, title: impl Into<String>) -> Self {
+        let (olog_json, title) = (olog_json.into(), title.into());
+        Self::new(move |request| Ok(if request.json { olog_json.clone() } else { title.clone() }))
+    }
+
+    /// Calls received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        lock(&self.requests).clone()
+    }
+
+    fn answer(&self, model: &str, prompt: &str, json: bool) -> Result<String, OlogError> {
+        let request = MockRequest { model: model.to_string(), prompt: prompt.to_string(), json };
+        lock(&self.requests).push(request.clone());
+        (lock(&self.handler))(&request).map_err(OlogError::Llm)
+    }
+}
+
+impl LlmProvider for MockLlmProvider {
+    fn name(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn complete(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.answer(model, prompt, false)
+    }
+
+    fn complete_json(&self, model: &str, prompt: &str) -> Result<String, OlogError> {
+        self.answer(model, prompt, true)
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    fn embed(&self, _model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, OlogError> {
+        let mut embedder = lock(&self.embedder);
+        Ok(texts.iter().map(|text| embedder(text)).collect())
+    }
+}
+
+// Labels spelled alike embed close together, and the same label identically
+fn letter_counts(text: &str) -> Vec<f32> {
+    let mut counts = vec![0.0; 26];
+    for letter in text.to_ascii_lowercase().bytes().filter(u8::is_ascii_lowercase) {
+        counts[(letter - b'a') as usize] += 1.0;
+    }
+    counts
+}
+
+type OcrHandler = Box<dyn FnMut(&[u8]) -> Result<String, String>>;
+
+struct InstalledOcr {
+    handler: OcrHandler,
+    requests: usize,
+}
+
+thread_local! {
+    static OCR: RefCell<Option<InstalledOcr>> = const { RefCell::new(None) };
+}
+
+/// Stands in for PDF text extraction on this thread until dropped
+pub struct MockOcrProvider {
+    _thread: std::marker::PhantomData<*const ()>,
+}
+
+impl MockOcrProvider {
+    /// Extracts text by calling `handler` with the PDF's bytes
+    pub fn new(handler: impl FnMut(&[u8]) -> Result<String, String> + 'static) -> Self {
+        OCR.with(|ocr| *ocr.borrow_mut() = Some(InstalledOcr { handler: Box::new(handler), requests: 0 }));
+        MockOcrProvider { _thread: std::marker::PhantomData }
+    }
+
+    /// Returns `text` for every PDF
+    pub fn returning(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self::new(move |_| Ok(text.clone()))
+    }
+
+    /// Number of PDFs extracted so far
+    pub fn calls(&self) -> usize {
+        OCR.with(|ocr| ocr.borrow().as_ref().map_or(0, |installed| installed.requests))
+    }
+}
+
+impl Drop for MockOcrProvider {
+    fn drop(&mut self) {
+        OCR.with(|ocr| *ocr.borrow_mut() = None);
+    }
+}
+
+// The mocked extraction, if a MockOcrProvider is installed on this thread
+pub(crate) fn ocr_text(bytes: &[u8]) -> Option<Result<String, OlogError>> {
+    OCR.with(|ocr| {
+        let mut installed = ocr.borrow_mut();
+        let installed = installed.as_mut()?;
+        installed.requests += 1;
+        Some((installed.handler)(bytes).map_err(OlogError::Ocr))
+    })
+}
+
+/// Small, stable inputs for exercising the pipeline end to end
+pub mod fixtures {
+    use crate::boilerplate::Filter;
+    use crate::llm::GenerationOptions;
+    use crate::metadata::Extractor;
+    use crate::olog::{Citation, Olog, OlogBuilder, Quantity};
+
+    pub const CELL_BIOLOGY_TITLE: &str = "Cellular respiration";
+
+    /// A short document to generate from
+    pub const CELL_BIOLOGY_TEXT: &str = "# Cellular respiration\n\nMitochondria produce ATP by oxidative \
+        phosphorylation. ATP powers muscle contraction. A typical cell holds about 2000 mitochondria.\n";
+
+    /// The model's olog reply for `CELL_BIOLOGY_TEXT`, for use with
+    /// [`super::MockLlmProvider::generating`]
+    pub const CELL_BIOLOGY_JSON: &str = r#"{
+        "title": "Cellular respiration",
+        "nodes": [
+            {"id": "n1", "label": "a mitochondrion"},
+            {"id": "n2", "label": "ATP"},
+            {"id": "n3", "label": "muscle contraction"},
+            {"id": "n4", "label": "a cell"}
+        ],
+        "hyperedges": [
+            {"id": "e1", "sources": ["n1"], "targets": ["n2"], "label": "produces"},
+            {"id": "e2", "sources": ["n2"], "targets": ["n3"], "label": "powers"},
+            {"id": "e3", "sources": ["n4"], "targets": ["n1"], "label": "holds",
+             "quantity": {"value": 2000, "unit": "mitochondria"}}
+        ]
+    }"#;
+
+    /// The olog `CELL_BIOLOGY_JSON` describes, built directly
+    pub fn cell_biology() -> Olog {
+        OlogBuilder::new(CELL_BIOLOGY_TITLE)
+            .citation(Citation::new(CELL_BIOLOGY_TITLE, "cellular respiration", CELL_BIOLOGY_TEXT))
+            .node("a mitochondrion")
+            .node("ATP")
+            .node("muscle contraction")
+            .node("a cell")
+            .edge("produces", &["a mitochondrion"], &["ATP"])
+            .edge("powers", &["ATP"], &["muscle contraction"])
+            .edge("holds", &["a cell"], &["a mitochondrion"])
+            .quantity(Quantity { value: 2000.0, unit: Some("mitochondria".to_string()), uncertainty: None })
+            .build()
+            .expect("the fixture olog is valid")
+    }
+
+    /// One strict pass with every optional step off, so the only model calls
+    /// are the olog, title and label prompts
+    pub fn generation_options() -> GenerationOptions {
+        GenerationOptions {
+            model: "mock-model".to_string(),
+            label_model: "mock-model".to_string(),
+            count: 1,
+            reuse_labels: true,
+            strict_validation: true,
+            postprocessors: Vec::new(),
+            scripts: Vec::new(),
+            context: None,
+            sanitize: false,
+            consolidate: false,
+            quantities: false,
+            quotes: false,
+            debate_transcript: false,
+            filters: Filter::ALL.to_vec(),
+            semantic_merge: None,
+            chunking: None,
+            parallelism: 1,
+            extractors: vec![Extractor::Model],
+            source_metadata: None,
+            redaction: None,
+        }
+    }
+}
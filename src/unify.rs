@@ -0,0 +1,252 @@
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::embedding::cosine_similarity;
+use crate::error::OlogError;
+use crate::llm::complete;
+use crate::provider::LlmProvider;
+use crate::olog::{merge_ologs, Node, Olog};
+
+pub const DEFAULT_THRESHOLD: f32 = 0.9;
+pub const DEFAULT_CHECK_SAMPLE: usize = 10;
+pub const DEFAULT_MAX_ERROR_RATE: f32 = 0.2;
+
+// Collapse nodes whose labels embed close together, on top of the exact
+// label matching merge_ologs does
+#[derive(Debug, Clone)]
+pub struct SemanticMerge {
+    pub model: String,
+    // Minimum cosine similarity for two labels to name the same concept
+    pub threshold: f32,
+    pub check: Option<MergeCheck>,
+}
+
+// Spot check run after a semantic merge: the model grades a random sample of
+// the folded labels, and the merge is undone if too many look wrong
+#[derive(Debug, Clone)]
+pub struct MergeCheck {
+    pub model: String,
+    pub sample: usize,
+    pub max_error_rate: f32,
+}
+
+#[derive(Debug)]
+pub struct MergeGrade {
+    pub checked: usize,
+    // (alias, label) pairs the model says name different concepts
+    pub wrong: Vec<(String, String)>,
+}
+
+impl MergeGrade {
+    pub fn error_rate(&self) -> f32 {
+        if self.checked == 0 {
+            return 0.0;
+        }
+        self.wrong.len() as f32 / self.checked as f32
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GradeResponse {
+    same: Vec<bool>,
+}
+
+// Labels folded into the one that was kept
+#[derive(Debug, Clone)]
+pub struct Unification {
+    pub label: String,
+    pub aliases: Vec<String>,
+}
+
+// Relabels near-duplicate nodes to a single label and merges them. Within a
+// group the label used by the most hyperedges wins, then the shortest.
+pub fn unify_nodes(provider: &dyn LlmProvider, olog: Olog, merge: &SemanticMerge) -> Result<(Olog, Vec<Unification>), OlogError> {
+    let mut degree: HashMap<&str, usize> = olog.nodes.iter().map(|node| (node.label.as_str(), 0)).collect();
+    for hyperedge in &olog.hyperedges {
+        for node in hyperedge.source.iter().chain(&hyperedge.target) {
+            *degree.entry(node.label.as_str()).or_insert(0) += 1;
+        }
+    }
+    let mut labels: Vec<&str> = degree.keys().copied().collect();
+    labels.sort_by(|a, b| degree[b].cmp(&degree[a]).then(a.len().cmp(&b.len())).then(a.cmp(b)));
+    if labels.len() < 2 {
+        return Ok((olog, Vec::new()));
+    }
+
+    let texts: Vec<String> = labels.iter().map(|label| label.to_string()).collect();
+    let vectors = provider.embed(&merge.model, &texts)?;
+
+    // Greedy: each label joins the most similar kept label above the
+    // threshold, or is kept itself
+    let mut kept: Vec<usize> = Vec::new();
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..labels.len() {
+        let best = kept.iter()
+            .map(|&k| (k, cosine_similarity(&vectors[i], &vectors[k])))
+            .filter(|(_, similarity)| *similarity >= merge.threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((k, _)) => groups.entry(k).or_default().push(i),
+            None => kept.push(i),
+        }
+    }
+    if groups.is_empty() {
+        return Ok((olog, Vec::new()));
+    }
+
+    let unified: Vec<Unification> = kept.iter()
+        .filter_map(|k| groups.get(k).map(|members| Unification {
+            label: labels[*k].to_string(),
+            aliases: members.iter().map(|&i| labels[i].to_string()).collect(),
+        }))
+        .collect();
+    Ok((apply_unifications(olog, &unified), unified))
+}
+
+// Relabels every alias to its kept label and merges the nodes, and then the
+// hyperedges, that end up sharing a label
+pub fn apply_unifications(olog: Olog, unified: &[Unification]) -> Olog {
+    let canonical: HashMap<&str, &str> = unified.iter()
+        .flat_map(|unification| unification.aliases.iter().map(|alias| (alias.as_str(), unification.label.as_str())))
+        .collect();
+    let relabel = |node: &Node| Node {
+        id: node.id,
+        label: canonical.get(node.label.as_str()).map_or_else(|| node.label.clone(), |label| label.to_string()),
+    };
+    let relabeled = Olog {
+        id: olog.id,
+        title: olog.title.clone(),
+        nodes: olog.nodes.iter().map(relabel).collect(),
+        hyperedges: olog.hyperedges.iter().map(|hyperedge| {
+            let mut hyperedge = hyperedge.clone();
+            hyperedge.source = hyperedge.source.iter().map(relabel).collect();
+            hyperedge.target = hyperedge.target.iter().map(relabel).collect();
+            hyperedge
+        }).collect(),
+    };
+    // Merging with an empty olog folds nodes, and then hyperedges, that now
+    // share a label
+    let empty = Olog { id: olog.id, title: olog.title, nodes: Vec::new(), hyperedges: Vec::new() };
+    merge_ologs(relabeled, empty)
+}
+
+// Asks the model whether each of a random sample of folded labels names the
+// same concept as the label it was folded into
+pub fn grade_unifications(provider: &dyn LlmProvider, unified: &[Unification], check: &MergeCheck) -> Result<MergeGrade, OlogError> {
+    let mut pairs: Vec<(String, String)> = unified.iter()
+        .flat_map(|unification| unification.aliases.iter().map(|alias| (alias.clone(), unification.label.clone())))
+        .collect();
+    // Random v4 ids as sort keys shuffle the pairs without another dependency
+    pairs.sort_by_cached_key(|_| Uuid::new_v4().as_u128());
+    pairs.truncate(check.sample.max(1));
+    if pairs.is_empty() {
+        return Ok(MergeGrade { checked: 0, wrong: Vec::new() });
+    }
+
+    let listing = pairs.iter().enumerate()
+        .map(|(i, (alias, label))| format!("{}. \"{}\" / \"{}\"", i + 1, alias, label))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Each numbered line below pairs two node labels from an ontology log that were merged into one node because \
+         their wording is similar. For each pair, decide whether both labels name the same concept, so that merging \
+         them loses no meaning. Labels for related but different concepts (a part and its whole, a process and its \
+         product, opposite properties) are not the same. Respond with JSON of the form {{\"same\": [true, false, ...]}}, \
+         one answer per line in order.\n\n{}",
+        listing
+    );
+    let response: GradeResponse = serde_json::from_str(&complete(provider, &check.model, prompt, true)?)
+        .map_err(|e| OlogError::Schema(format!("Malformed merge grading response: {}", e)))?;
+    if response.same.len() != pairs.len() {
+        return Err(OlogError::Llm(format!("Merge grading returned {} answers for {} pairs", response.same.len(), pairs.len())));
+    }
+
+    let checked = pairs.len();
+    let wrong = pairs.into_iter().zip(response.same).filter(|(_, same)| !same).map(|(pair, _)| pair).collect();
+    Ok(MergeGrade { checked, wrong })
+}
+
+// Keeps a rolled-back merge so `review-merges` can offer it again
+pub fn flag_for_review(conn: &Connection, olog_id: Uuid, unified: &[Unification]) -> rusqlite::Result<()> {
+    for unification in unified {
+        for alias in &unification.aliases {
+            conn.execute(
+                "INSERT OR REPLACE INTO Merge_Reviews (olog_id, alias, label, flagged_at) VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)",
+                params![olog_id.to_string(), alias, unification.label],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Merges awaiting review for an olog, grouped under the label they would fold into
+pub fn flagged_merges(conn: &Connection, olog_id: Uuid) -> rusqlite::Result<Vec<Unification>> {
+    let mut stmt = conn.prepare("SELECT label, alias FROM Merge_Reviews WHERE olog_id = ?1 ORDER BY label, rowid")?;
+    let rows = stmt
+        .query_map(params![olog_id.to_string()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(group_by_label(rows))
+}
+
+pub fn clear_review(conn: &Connection, olog_id: Uuid, unification: &Unification) -> rusqlite::Result<()> {
+    for alias in &unification.aliases {
+        conn.execute(
+            "DELETE FROM Merge_Reviews WHERE olog_id = ?1 AND alias = ?2",
+            params![olog_id.to_string(), alias],
+        )?;
+    }
+    Ok(())
+}
+
+fn group_by_label(rows: Vec<(String, String)>) -> Vec<Unification> {
+    let mut unified: Vec<Unification> = Vec::new();
+    for (label, alias) in rows {
+        match unified.iter_mut().find(|unification| unification.label == label) {
+            Some(unification) => unification.aliases.push(alias),
+            None => unified.push(Unification { label, aliases: vec![alias] }),
+        }
+    }
+    unified
+}
+
+// The label a node ends up with after unification
+pub fn canonical_label<'a>(unified: &'a [Unification], label: &'a str) -> &'a str {
+    unified.iter()
+        .find(|unification| unification.aliases.iter().any(|alias| alias == label))
+        .map_or(label, |unification| unification.label.as_str())
+}
+
+pub fn record_aliases(conn: &Connection, olog: &Olog, unified: &[Unification]) -> rusqlite::Result<()> {
+    for unification in unified {
+        let Some(node) = olog.nodes.iter().find(|node| node.label == unification.label) else { continue };
+        for alias in &unification.aliases {
+            conn.execute(
+                "INSERT OR IGNORE INTO Node_Aliases (node_id, alias) VALUES (?1, ?2)",
+                params![node.id.to_string(), alias],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Aliases of every node in an olog, grouped under the node's label
+pub fn read_aliases(conn: &Connection, olog_id: Uuid) -> rusqlite::Result<Vec<Unification>> {
+    let mut stmt = conn.prepare(
+        "SELECT Nodes.label, Node_Aliases.alias
+         FROM Node_Aliases JOIN Nodes ON Nodes.node_id = Node_Aliases.node_id
+         WHERE Nodes.olog_id = ?1
+         ORDER BY Nodes.label, Node_Aliases.rowid",
+    )?;
+    let rows = stmt
+        .query_map(params![olog_id.to_string()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(group_by_label(rows))
+}
+
+pub fn node_aliases(conn: &Connection, node_id: Uuid) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT alias FROM Node_Aliases WHERE node_id = ?1 ORDER BY rowid")?;
+    let aliases = stmt.query_map(params![node_id.to_string()], |row| row.get(0))?;
+    aliases.collect()
+}
@@ -0,0 +1,139 @@
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+use crate::error::OlogError;
+use crate::estimate;
+
+/// One model or embedding call that was answered, as its provider counted it
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// The olog the call went into, once it is known
+    pub olog_id: Option<Uuid>,
+}
+
+// Calls made since the last `store_pending`, from any thread
+static PENDING: Mutex<Vec<Call>> = Mutex::new(Vec::new());
+
+fn pending() -> MutexGuard<'static, Vec<Call>> {
+    PENDING.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Cached replies cost nothing and aren't recorded
+pub(crate) fn record(provider: &str, model: &str, prompt_tokens: i64, completion_tokens: i64) {
+    pending().push(Call {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        prompt_tokens,
+        completion_tokens,
+        olog_id: None,
+    });
+}
+
+/// Charges every pending call not yet charged to an olog to `olog_id`; a
+/// pipeline calls it once the olog its calls built is stored
+pub fn attribute(olog_id: Uuid) {
+    for call in pending().iter_mut().filter(|call| call.olog_id.is_none()) {
+        call.olog_id = Some(olog_id);
+    }
+}
+
+// Writes the pending calls under `operation`, the command that made them.
+// Calls no pipeline attributed are charged to `olog_id`, if any. Rows outlive
+// the ologs they name, since the money was spent either way.
+pub fn store_pending(conn: &Connection, operation: &str, olog_id: Option<Uuid>) -> Result<usize, OlogError> {
+    let calls: Vec<Call> = pending().drain(..).collect();
+    let mut insert = conn.prepare(
+        "INSERT INTO Llm_Usage (called_at, namespace, operation, olog_id, provider, model, prompt_tokens, completion_tokens, cost)
+         VALUES (CURRENT_TIMESTAMP, (SELECT namespace FROM temp.Session), ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for call in &calls {
+        let cost = estimate::price(&call.model)?
+            .map(|(input, output)| (call.prompt_tokens as f64 * input + call.completion_tokens as f64 * output) / 1_000_000.0);
+        insert.execute(params![
+            operation,
+            call.olog_id.or(olog_id).map(|id| id.to_string()),
+            call.provider,
+            call.model,
+            call.prompt_tokens,
+            call.completion_tokens,
+            cost,
+        ])?;
+    }
+    Ok(calls.len())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Calendar day (UTC) the calls were made
+    Day,
+    Model,
+    /// The olog the calls went into; calls that built none are grouped together
+    Olog,
+    /// The command that made the calls
+    Operation,
+}
+
+impl GroupBy {
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupBy::Day => "day",
+            GroupBy::Model => "model",
+            GroupBy::Olog => "olog",
+            GroupBy::Operation => "operation",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UsageTotal {
+    /// The day, model, olog id or operation
+    pub key: Option<String>,
+    /// The olog's title when grouping by olog and the olog still exists
+    pub title: Option<String>,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost: f64,
+    /// Calls whose model had no known price, left out of `cost`
+    pub unpriced_calls: i64,
+}
+
+// Totals for the current namespace, newest day or costliest group first,
+// counting calls made on or after `since` (YYYY-MM-DD) if given
+pub fn totals(conn: &Connection, by: GroupBy, since: Option<&str>) -> rusqlite::Result<Vec<UsageTotal>> {
+    let (key, order) = match by {
+        GroupBy::Day => ("date(called_at)", "1 DESC"),
+        GroupBy::Model => ("model", "6 DESC, 1"),
+        GroupBy::Olog => ("Llm_Usage.olog_id", "6 DESC, 1"),
+        GroupBy::Operation => ("operation", "6 DESC, 1"),
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {key}, MAX(Ologs.title), COUNT(*), SUM(prompt_tokens), SUM(completion_tokens),
+                COALESCE(SUM(cost), 0), SUM(cost IS NULL)
+         FROM Llm_Usage LEFT JOIN Ologs ON Ologs.olog_id = Llm_Usage.olog_id
+         WHERE Llm_Usage.namespace = (SELECT namespace FROM temp.Session)
+           AND (?1 IS NULL OR date(called_at) >= date(?1))
+         GROUP BY 1
+         ORDER BY {order}",
+        key = key,
+        order = order,
+    ))?;
+    let totals = stmt.query_map(params![since], |row| {
+        Ok(UsageTotal {
+            key: row.get(0)?,
+            title: if by == GroupBy::Olog { row.get(1)? } else { None },
+            calls: row.get(2)?,
+            prompt_tokens: row.get(3)?,
+            completion_tokens: row.get(4)?,
+            cost: row.get(5)?,
+            unpriced_calls: row.get(6)?,
+        })
+    })?;
+    totals.collect()
+}
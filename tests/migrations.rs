@@ -0,0 +1,51 @@
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use olog::db::DEFAULT_NAMESPACE;
+use olog::{create_olog_tables, read_olog_from_db, use_namespace};
+
+const OLOG: &str = "00000000-0000-0000-0000-000000000001";
+const HYPEREDGE: &str = "00000000-0000-0000-0000-000000000002";
+const A: &str = "00000000-0000-0000-0000-00000000000a";
+const B: &str = "00000000-0000-0000-0000-00000000000b";
+const C: &str = "00000000-0000-0000-0000-00000000000c";
+
+// The tables as written before links had a role or a position, including a
+// link duplicated by a retried write
+fn legacy_database() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(&format!(
+        "CREATE TABLE Ologs (olog_id TEXT PRIMARY KEY, title TEXT NOT NULL);
+         CREATE TABLE Nodes (node_id TEXT PRIMARY KEY, label TEXT NOT NULL, olog_id TEXT NOT NULL);
+         CREATE TABLE Hyperedges (hyperedge_id TEXT PRIMARY KEY, label TEXT NOT NULL, olog_id TEXT NOT NULL);
+         CREATE TABLE Hyperedge_Links (hyperedge_id TEXT NOT NULL, node_id TEXT NOT NULL, type TEXT NOT NULL);
+         INSERT INTO Ologs VALUES ('{OLOG}', 'legacy');
+         INSERT INTO Nodes VALUES ('{A}', 'a', '{OLOG}'), ('{B}', 'b', '{OLOG}'), ('{C}', 'c', '{OLOG}');
+         INSERT INTO Hyperedges VALUES ('{HYPEREDGE}', 'combine into', '{OLOG}');
+         INSERT INTO Hyperedge_Links VALUES
+            ('{HYPEREDGE}', '{A}', 'source'),
+            ('{HYPEREDGE}', '{B}', 'source'),
+            ('{HYPEREDGE}', '{C}', 'target'),
+            ('{HYPEREDGE}', '{B}', 'source');"
+    ))
+    .unwrap();
+    conn
+}
+
+#[test]
+fn migrating_keeps_every_source_and_target_of_legacy_hyperedges() {
+    let conn = legacy_database();
+    use_namespace(&conn, DEFAULT_NAMESPACE).unwrap();
+    create_olog_tables(&conn).unwrap();
+
+    let olog = read_olog_from_db(&conn, Uuid::parse_str(OLOG).unwrap()).unwrap();
+    let hyperedge = &olog.hyperedges[0];
+    let labels = |nodes: &[olog::Node]| nodes.iter().map(|node| node.label.clone()).collect::<Vec<_>>();
+    assert_eq!(labels(&hyperedge.source), ["a", "b"]);
+    assert_eq!(labels(&hyperedge.target), ["c"]);
+
+    let unpositioned: i64 = conn
+        .query_row("SELECT COUNT(*) FROM Hyperedge_Links WHERE position IS NULL", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(unpositioned, 0);
+}
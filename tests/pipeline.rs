@@ -0,0 +1,85 @@
+use rusqlite::Connection;
+
+use olog::db::DEFAULT_NAMESPACE;
+use olog::testing::{fixtures, MockLlmProvider};
+use olog::{
+    create_olog_tables, delete_olog, generate_merged_olog, read_olog_from_db, use_namespace, write_olog_to_db, Operation,
+};
+
+fn database() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    use_namespace(&conn, DEFAULT_NAMESPACE).unwrap();
+    create_olog_tables(&conn).unwrap();
+    conn
+}
+
+#[test]
+fn generated_olog_survives_storage() {
+    let mock = MockLlmProvider::generating(fixtures::CELL_BIOLOGY_JSON, fixtures::CELL_BIOLOGY_TITLE);
+    let generated = generate_merged_olog(&mock, fixtures::CELL_BIOLOGY_TEXT, &fixtures::generation_options()).unwrap();
+
+    let conn = database();
+    write_olog_to_db(&conn, &generated.olog, Operation::Generate).unwrap();
+    let stored = read_olog_from_db(&conn, generated.olog.id).unwrap();
+
+    assert_eq!(stored.title, fixtures::CELL_BIOLOGY_TITLE);
+    let mut labels: Vec<&str> = stored.nodes.iter().map(|node| node.label.as_str()).collect();
+    labels.sort();
+    assert_eq!(labels, ["ATP", "a cell", "a mitochondrion", "muscle contraction"]);
+    assert_eq!(stored.hyperedges.len(), 3);
+
+    let holds = stored.hyperedges.iter().find(|hyperedge| hyperedge.label == "holds").unwrap();
+    let quantity = holds.quantity.as_ref().unwrap();
+    assert_eq!(quantity.value, 2000.0);
+    assert_eq!(quantity.unit.as_deref(), Some("mitochondria"));
+    assert_eq!(holds.source[0].label, "a cell");
+    assert_eq!(holds.target[0].label, "a mitochondrion");
+    assert_eq!(holds.citations[0].text, fixtures::CELL_BIOLOGY_TEXT);
+}
+
+#[test]
+fn deleting_an_olog_leaves_citations_it_never_linked_to() {
+    let conn = database();
+    let olog = fixtures::cell_biology();
+    write_olog_to_db(&conn, &olog, Operation::Generate).unwrap();
+    conn.execute("INSERT INTO Citations (citation_id, title, label, text) VALUES ('unlinked', 'Elsewhere', '', 'kept')", [])
+        .unwrap();
+
+    assert_eq!(delete_olog(&conn, olog.id).unwrap(), 1);
+    let remaining: Vec<String> = conn
+        .prepare("SELECT citation_id FROM Citations")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(remaining, ["unlinked"]);
+}
+
+#[test]
+fn independent_passes_reach_the_mock_from_worker_threads() {
+    let mock = MockLlmProvider::generating(fixtures::CELL_BIOLOGY_JSON, fixtures::CELL_BIOLOGY_TITLE);
+    let options = olog::GenerationOptions { count: 3, reuse_labels: false, parallelism: 3, ..fixtures::generation_options() };
+    let generated = generate_merged_olog(&mock, fixtures::CELL_BIOLOGY_TEXT, &options).unwrap();
+
+    assert_eq!(mock.requests().iter().filter(|request| request.json).count(), 3);
+    // The passes agree, so merging them leaves one copy of each node
+    assert_eq!(generated.olog.nodes.len(), 4);
+}
+
+#[test]
+fn semantic_merge_embeds_through_the_given_provider() {
+    // "a cell" and "a mitochondrion" share a vector, so they fold together
+    let mock = MockLlmProvider::generating(fixtures::CELL_BIOLOGY_JSON, fixtures::CELL_BIOLOGY_TITLE)
+        .with_embeddings(|label| match label {
+            "a cell" | "a mitochondrion" => vec![1.0, 0.0, 0.0],
+            "ATP" => vec![0.0, 1.0, 0.0],
+            _ => vec![0.0, 0.0, 1.0],
+        });
+    let merge = olog::unify::SemanticMerge { model: "mock-embedding".to_string(), threshold: 0.99, check: None };
+    let options = olog::GenerationOptions { semantic_merge: Some(merge), ..fixtures::generation_options() };
+    let generated = generate_merged_olog(&mock, fixtures::CELL_BIOLOGY_TEXT, &options).unwrap();
+
+    assert_eq!(generated.unified.len(), 1);
+    assert_eq!(generated.olog.nodes.len(), 3);
+}